@@ -1,27 +1,67 @@
-
-(def defmacro '(macro (name param-list &rest body)
-  (list 'def name (list 'quote (concat (list 'macro param-list) body)))))
-
-(defmacro defun (name param-list &rest body)
-  (list 'def name (list 'quote (concat (list 'fn param-list) body))))
-
-(defun second (lst)
-  (rest (first lst)))
-
-(defun is-unquote (expr)
-  (= 'unquote (first expr)))
-
-(defun is-empty (lst)
-  (= (length lst) 0))
-
-;; (defun map (fun lst)
-;;   (if (is-empty lst) lst
-;;       (cons (fun (first lst))
-;;             (map fun (rest lst)))))
-
-;; (defmacro quasiquote (expr)
-;;   (if (is-list expr)
-;;       (if (is-unquote)
-;;           (second expr)
-;;           (map 'quasiquote expr))
-;;       expr)
+
+(def defmacro (macro (name param-list &rest body)
+  (list 'def name (concat (list 'macro param-list) body))))
+
+(defmacro defun (name param-list &rest body)
+  (list 'def name (concat (list 'fn param-list) body)))
+
+;; Same as defun.
+(defmacro defn (name param-list &rest body)
+  (list 'def name (concat (list 'fn param-list) body)))
+
+;; A hygienic macro expanding calls by rules of a pattern and a template,
+;; see syntax-expand.
+(defmacro defsyntax (name &rest rules)
+  (list 'def name (list 'macro '(&rest form)
+                        (list 'syntax-expand (list 'quote name) (list 'quote rules) 'form))))
+
+(defun second (lst)
+  (rest (first lst)))
+
+(defun is-unquote (expr)
+  (= 'unquote (first expr)))
+
+(defun is-empty (lst)
+  (= (length lst) 0))
+
+;; (defun map (fun lst)
+;;   (if (is-empty lst) lst
+;;       (cons (fun (first lst))
+;;             (map fun (rest lst)))))
+
+;; A promise of the value of expr, which is evaluated when it is forced.
+(defmacro delay (expr)
+  (list 'make-promise (list 'fn '() expr)))
+
+;; A stream of head followed by the stream tail evaluates to, which is
+;; evaluated when the rest of the stream is first needed.
+(defmacro stream-cons (head tail)
+  (list 'make-stream head (list 'fn '() tail)))
+
+;; A generator running body up to the next (yield value) whenever next is
+;; called on it.
+(defmacro generator (&rest body)
+  (list 'make-generator (list 'fn '() (generator-body body))))
+
+;; Graphs are maps from every node to a map of its neighbors to the weights of
+;; the edges to them, see topo-sort and shortest-path. graph-add-edge returns
+;; graph with an edge from one node to another, of weight 1 by default, adding
+;; the nodes as needed.
+(defun graph-add-edge (graph from to &rest weight)
+  (let ((graph (assoc graph to (get graph to (make-map)))))
+    (assoc graph from (assoc (get graph from (make-map)) to
+                             (match weight (() 1) ((w) w))))))
+
+(defun neighbors (graph node)
+  (keys (get graph node (make-map))))
+
+;; (for ((x xs) (y ys) :when test) expr) is the list of the values of expr
+;; for every x in xs and y in ys for which test holds. Binders may be
+;; patterns, and :when filters apply to the bindings before them.
+(defmacro for (clauses expr)
+  (match clauses
+    (() `(list ,expr))
+    ((':when test &rest more) `(if ,test (for ,more ,expr) '()))
+    (((binder values) &rest more)
+     `(apply concat (map (fn (,binder) (for ,more ,expr)) ,values)))
+    (_ (error :syntax-error "for expects clauses (binder values) or :when test" clauses))))
@@ -0,0 +1,43 @@
+// Measures the peak heap usage of evaluating a long begin body whose forms
+// produce large intermediate values. Only the value of the last form has to
+// be kept alive, so peak usage should not grow with the number of forms.
+//
+// Run with `cargo bench --bench begin`.
+
+mod common;
+
+use std::time::Instant;
+
+use lisp::interpreter::Interpreter;
+
+#[global_allocator]
+static ALLOCATOR: common::Counting = common::Counting;
+
+fn setup(elems: usize) -> String {
+    format!("(def big (list {}))", (0..elems)
+            .map(|n| n.to_string())
+            .collect::<Vec<String>>()
+            .join(" "))
+}
+
+fn program(forms: usize) -> String {
+    format!("(begin {})", vec!["(concat big big big big)"; forms].join(" "))
+}
+
+fn main() {
+    let mut interpreter = Interpreter::new();
+    if interpreter.eval_str(&setup(1000)).is_err() {
+        panic!("setup failed");
+    }
+    for forms in &[1, 10, 100, 1000] {
+        let prog = program(*forms);
+        let base = common::reset_peak();
+        let start = Instant::now();
+        if interpreter.eval_str(&prog).is_err() {
+            panic!("evaluation failed");
+        }
+        let elapsed = start.elapsed();
+        println!("begin with {:4} forms: peak {:8} bytes, {:?}",
+                 forms, common::peak() - base, elapsed);
+    }
+}
@@ -0,0 +1,39 @@
+// Counts the allocations made while reading and evaluating a program working
+// on many small lists, as produced by quotes and short argument lists.
+//
+// Run with `cargo bench --bench lists`.
+
+mod common;
+
+use std::time::Instant;
+
+use lisp::interpreter::Interpreter;
+
+#[global_allocator]
+static ALLOCATOR: common::Counting = common::Counting;
+
+const PROGRAM: &str = "
+(def pair '(fn (a b) (list a b)))
+(def swap '(fn (p) (list (first (rest p)) (first p))))
+(def total '(fn (p) (+ (first p) (first (rest p)))))
+";
+
+const CALL: &str = "(total (swap (pair (length '(1 2 3)) (first '(4 5)))))";
+
+fn main() {
+    let mut interpreter = Interpreter::new();
+    if interpreter.eval_str(PROGRAM).is_err() {
+        panic!("setup failed");
+    }
+    for calls in &[1, 100, 10000] {
+        let prog = vec![CALL; *calls].join(" ");
+        let before = common::allocations();
+        let start = Instant::now();
+        if interpreter.eval_str(&prog).is_err() {
+            panic!("evaluation failed");
+        }
+        let elapsed = start.elapsed();
+        println!("{:5} calls: {:9} allocations, {:?}",
+                 calls, common::allocations() - before, elapsed);
+    }
+}
@@ -0,0 +1,51 @@
+// Measures the peak heap usage of loading a data file form by form with
+// load, against reading all of its forms before evaluating them like
+// eval_source does. Only the forms' values are retained, so the peak of load
+// should stay flat as the file grows while reading everything first grows
+// with it.
+//
+// Run with `cargo bench --bench load`.
+
+mod common;
+
+use std::fs;
+use std::time::Instant;
+
+use lisp::interpreter::Interpreter;
+
+#[global_allocator]
+static ALLOCATOR: common::Counting = common::Counting;
+
+// A file of forms each counting a literal list of 1000 numbers.
+fn data(forms: usize) -> String {
+    let row = (0..1000).map(|n| n.to_string()).collect::<Vec<String>>().join(" ");
+    format!("(length '({}))\n", row).repeat(forms)
+}
+
+fn main() {
+    let path = std::env::temp_dir().join("risp-bench-load.lisp");
+    for forms in &[10, 100, 1000] {
+        fs::write(&path, data(*forms)).expect("data file can be written");
+        let f = path.to_str().unwrap();
+
+        let mut interpreter = Interpreter::new();
+        let base = common::reset_peak();
+        let start = Instant::now();
+        if interpreter.load(f).is_err() {
+            panic!("load failed");
+        }
+        println!("load        {:4} forms: peak {:9} bytes, {:?}",
+                 forms, common::peak() - base, start.elapsed());
+
+        let mut interpreter = Interpreter::new();
+        let base = common::reset_peak();
+        let start = Instant::now();
+        let source = fs::read_to_string(f).expect("data file can be read");
+        if interpreter.eval_source(&source).is_err() {
+            panic!("evaluation failed");
+        }
+        println!("eval_source {:4} forms: peak {:9} bytes, {:?}",
+                 forms, common::peak() - base, start.elapsed());
+    }
+    fs::remove_file(&path).ok();
+}
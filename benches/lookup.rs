@@ -0,0 +1,30 @@
+// Measures variable lookup in a hot loop run by a closure nested in many
+// scopes, so that every reference of a global like + or = passes the whole
+// scope chain.
+//
+// Run with `cargo bench --bench lookup`.
+
+use std::time::Instant;
+
+use lisp::interpreter::Interpreter;
+
+fn program(depth: usize, iterations: usize) -> String {
+    let lets = (0..depth)
+        .map(|n| format!("(let ((v{} {}))", n, n))
+        .collect::<String>();
+    format!("{}(letrec ((count (fn (n acc) (if (= n 0) acc (count (- n 1) (+ acc 1))))))
+               (count {} 0)){}",
+            lets, iterations, ")".repeat(depth))
+}
+
+fn main() {
+    let mut interpreter = Interpreter::new();
+    for depth in &[0, 4, 16, 64] {
+        let prog = program(*depth, 100000);
+        let start = Instant::now();
+        if interpreter.eval_str(&prog).is_err() {
+            panic!("evaluation failed");
+        }
+        println!("lookup through {:2} scopes: {:?}", depth, start.elapsed());
+    }
+}
@@ -0,0 +1,21 @@
+// Measures the time to create an interpreter. The first one on a thread
+// evaluates the prelude, later ones clone the snapshot taken afterwards.
+//
+// Run with `cargo bench --bench startup`.
+
+use std::time::Instant;
+
+use lisp::interpreter::Interpreter;
+
+fn main() {
+    let start = Instant::now();
+    let _ = Interpreter::new();
+    println!("first interpreter:   {:?}", start.elapsed());
+
+    let runs = 1000;
+    let start = Instant::now();
+    for _ in 0..runs {
+        let _ = Interpreter::new();
+    }
+    println!("further interpreters: {:?} each", start.elapsed() / runs);
+}
@@ -0,0 +1,11 @@
+;; Association lists of (key value) entries and property lists of
+;; alternating keys and values. Lookups find the first entry of a key and
+;; return the default, or () without one, if there is none, like get.
+;; expect: (2 0 ((red 1) (green 20) (blue 3)) {:host "localhost" :port 80})
+
+(def colors '((red 1) (green 2) (blue 3)))
+
+(list (alist-get colors 'green)
+      (alist-get colors 'black 0)
+      (alist-set colors 'green 20)
+      (plist->map '(:host "localhost" :port 80 :host "example.org")))
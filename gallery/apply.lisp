@@ -0,0 +1,10 @@
+;; apply calls a function with the elements of a list as its arguments, for
+;; argument lists computed at runtime. Functions, natives and special forms
+;; evaluating all of their arguments can be applied.
+;; expect: (10 (1 2 3 4) 2)
+
+(def numbers '(1 2 3 4))
+
+(list (apply + numbers)
+      (apply list numbers)
+      (apply (fn (a b &rest more) b) numbers))
@@ -0,0 +1,6 @@
+;; Numbers and the arithmetic natives.
+;; expect: 25
+
+(def a (+ 1 2 3 4))
+(def b (* 2 (- a 5)))
+(+ a b 5)
@@ -0,0 +1,13 @@
+;; fn creates a closure, which keeps the scope it was created in.
+;; expect: (1 2 1 13)
+
+(defun make-counter ()
+  (let ((n 0))
+    (fn () (set n (+ n 1)))))
+
+(defun adder (x)
+  (fn (y) (+ x y)))
+
+(def a (make-counter))
+(def b (make-counter))
+(list (a) (a) (b) ((adder 10) 3))
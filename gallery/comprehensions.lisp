@@ -0,0 +1,10 @@
+;; for collects the value of its expression for every combination of the
+;; bound values, here pairs of a color and a size. :when skips the
+;; combinations before it for which its test fails.
+;; expect: ((red 1) (red 2) (blue 2))
+
+(for ((color '(red green blue))
+      :when (match color ('green #f) (_ #t))
+      (size '(1 2))
+      :when (match (list color size) (('blue 1) #f) (_ #t)))
+  (list color size))
@@ -0,0 +1,18 @@
+;; Errors carry a condition type, which catch clauses can select on. A
+;; clause catches the types it lists and the types deriving from them, so
+;; :program-error also catches :unbound-error. error takes an optional
+;; type of its own.
+;; expect: ((:type-error "Expected a number") :unbound-error (:overdrawn (5)))
+
+(defun withdraw (balance amount)
+  (if (= amount 0)
+      balance
+      (error :overdrawn "insufficient funds" amount)))
+
+(list (try (+ 1 "one")
+           (catch :io-error e 'unreachable)
+           (catch (:type-error :arity-error) e (list (get e :type) (get e :message))))
+      (try (undefined)
+           (catch :program-error e (get e :type)))
+      (try (withdraw 0 5)
+           (catch e (list (get e :type) (get e :data)))))
@@ -0,0 +1,9 @@
+;; defn defines a function like defun, but evaluates its fn form, so the
+;; function closes over the scope it is defined in. Error frames show the
+;; name a function was first def'd as.
+;; expect: (11 12)
+
+(let ((k 10))
+  (defn add-k (x) (+ x k)))
+
+(list (add-k 1) (add-k 2))
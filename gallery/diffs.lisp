@@ -0,0 +1,8 @@
+;; diff lists the places where two nested values differ, each as a map of the
+;; path of list indices and map keys leading to it and the expected and actual
+;; values there. A side missing an element has no entry for it.
+;; expect: (() ({"path" (1 0) "expected" 2 "actual" 3}) ({"path" (2) "expected" 4}))
+
+(list (diff '(1 (2)) '(1 (2)))
+      (diff '(1 (2)) '(1 (3)))
+      (diff '(1 2 4) '(1 2)))
@@ -0,0 +1,13 @@
+;; = compares atoms: numbers, symbols, keywords, strings and bools. equal?
+;; compares any objects by structure, eq? by identity: shared objects like
+;; strings, maps and functions are identical only to themselves.
+;; expect: (#t #t #f #t #f)
+
+(def greeting "hello")
+(def same greeting)
+
+(list (= "hello" "hello")
+      (equal? '(1 (2 "three")) (list 1 (list 2 "three")))
+      (equal? '(1 2) '(2 1))
+      (eq? greeting same)
+      (eq? (fn (x) x) (fn (x) x)))
@@ -0,0 +1,14 @@
+;; error signals an error with a message and data. try evaluates its body
+;; and, if it fails, the catch clause with the error bound to a map of its
+;; :message and :data.
+;; expect: (("negative" (-1)) 2 "Unbound symbol 'undefined'")
+
+(defun checked (n)
+  (if (= n -1) (error "negative" n) n))
+
+(defun describe (condition)
+  (list (get condition :message) (get condition :data)))
+
+(list (try (checked -1) (catch e (describe e)))
+      (try (checked 2) (catch e 'unreachable))
+      (try (undefined) (catch e (get e :message))))
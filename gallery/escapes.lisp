@@ -0,0 +1,12 @@
+;; call/ec calls a function with an escape continuation. Calling it returns
+;; its arguments from call/ec right away, skipping the rest of the function,
+;; e.g. to stop a map at the first element satisfying a predicate.
+;; expect: ("three" #f)
+
+(defun find (pred lst)
+  (call/ec (fn (return)
+    (map (fn (x) (if (pred x) (return x))) lst)
+    #f)))
+
+(list (find string? '(1 2 "three" 4))
+      (find string? '(1 2 3)))
@@ -0,0 +1,13 @@
+;; eval-when controls when its body is evaluated. With :expand it runs while
+;; the definition around it is expanded, so the forms after it can use the
+;; macros it defines. With :execute it runs when evaluated, like begin.
+;; expect: (12 nil 2)
+
+(defun quadruple (x)
+  (eval-when (:expand)
+    (defmacro twice (form) `(+ ,form ,form)))
+  (twice (twice x)))
+
+(list (quadruple 3)
+      (eval-when (:expand) 1)
+      (eval-when (:expand :execute) 2))
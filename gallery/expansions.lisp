@@ -0,0 +1,10 @@
+;; macroexpand-1 returns the form a macro call expands to, macroexpand keeps
+;; expanding while the result is a macro call. Forms nested in arguments are
+;; left as they are.
+;; expect: ((swap 3 3) (list 3 3))
+
+(defmacro swap (a b) `(list ,b ,a))
+(defmacro twice (x) `(swap ,x ,x))
+
+(list (macroexpand-1 '(twice 3))
+      (macroexpand '(twice 3)))
@@ -0,0 +1,10 @@
+;; Numbers, byte sizes and durations in milliseconds formatted for a report.
+;; format-number takes :decimals, :separator and :style, which is :plain,
+;; :percent or :engineering.
+;; expect: ("1,234,567.89" "42.5%" "47.0e3" "1.5 MB" "1h 1m 5s")
+
+(list (format-number 1234567.891)
+      (format-number 0.425 :style :percent :decimals 1)
+      (format-number 47000 :style :engineering :decimals 1)
+      (format-bytes 1572864)
+      (format-duration 3665000))
@@ -0,0 +1,7 @@
+;; Functions are lists starting with fn, rest parameters collect the
+;; remaining arguments.
+;; expect: 30
+
+(def square '(fn (x) (* x x)))
+(def sum-all '(fn (&rest xs) (reduce + xs)))
+(sum-all (square 1) (square 2) (square 3) (square 4))
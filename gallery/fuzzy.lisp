@@ -0,0 +1,8 @@
+;; Edit distances between strings, and the candidates close enough to a
+;; query to be likely typos of it, closest first. Symbols work as well as
+;; strings, so the bound symbols can be searched for a misspelled name.
+;; expect: (3 ("colour" "color") (length))
+
+(list (string-distance "kitten" "sitting")
+      (fuzzy-match "colour" (list "flavour" "color" "colour" "value"))
+      (fuzzy-match "lenght" (bound-symbols)))
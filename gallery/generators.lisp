@@ -0,0 +1,34 @@
+;; A generator runs its body up to the next yield each time next is called on
+;; it, and keeps its place in between. Once the body has finished, next
+;; returns its default argument.
+;; expect: ((0 1 1 2 3 5 8 13) (3 2 1 liftoff))
+
+(defn fibonacci ()
+  (generator
+    (loop ((a 0) (b 1))
+      (yield a)
+      (recur b (+ a b)))))
+
+(defn countdown (n)
+  (generator
+    (loop ((i n))
+      (if (< 0 i)
+          (begin (yield i)
+                 (recur (- i 1)))))
+    (yield 'liftoff)))
+
+(defn take (n g)
+  (loop ((i 0) (taken '()))
+    (if (< i n)
+        (recur (+ i 1) (concat taken (list (next g))))
+        taken)))
+
+(defn drain (g)
+  (loop ((drained '()))
+    (let ((value (next g #f)))
+      (if (bool? value)
+          drained
+          (recur (concat drained (list value)))))))
+
+(list (take 8 (fibonacci))
+      (drain (countdown 3)))
@@ -0,0 +1,15 @@
+;; Symbols a macro introduces can capture the variables of its caller, like
+;; tmp in naive-or. gensym returns a fresh symbol no user code can name, so
+;; macros can bind it without capturing anything.
+;; expect: (#f 5)
+
+(defmacro naive-or (a b)
+  `(let ((tmp ,a)) (if tmp tmp ,b)))
+
+(defmacro safe-or (a b)
+  (let ((tmp (gensym)))
+    `(let ((,tmp ,a)) (if ,tmp ,tmp ,b))))
+
+(let ((tmp 5))
+  (list (naive-or #f tmp)
+        (safe-or #f tmp)))
@@ -0,0 +1,17 @@
+;; Graphs are maps from every node to a map of its neighbors to edge weights.
+;; With edges from each garment to those worn over it, topo-sort gives an
+;; order to dress in; shortest-path finds the lightest route and its weight.
+;; expect: ((shirt pants socks tie belt shoes jacket) (a c f e) 20)
+
+(defn add-edges (graph edges)
+  (reduce (fn (graph edge) (apply graph-add-edge (concat (list graph) edge)))
+          (concat (list graph) edges)))
+
+(def clothes (add-edges (make-map) '((shirt tie) (tie jacket) (pants shoes) (pants belt)
+                                     (belt jacket) (shirt belt) (socks shoes))))
+
+(def roads (add-edges (make-map) '((a b 7) (a c 9) (a f 14) (b c 10) (b d 15)
+                                   (c d 11) (c f 2) (d e 6) (f e 9))))
+
+(let-values (((path distance) (shortest-path roads 'a 'e)))
+  (list (topo-sort clothes) path distance))
@@ -0,0 +1,31 @@
+;; A heap keeps its items in the order of a function that tells whether an
+;; item comes before another; heap-pop! removes the first. Used as priority
+;; queue, it finds the shortest path by always extending the shortest route.
+;; expect: (20 (a c f e))
+
+(def roads '((a ((b 7) (c 9) (f 14)))
+              (b ((a 7) (c 10) (d 15)))
+              (c ((a 9) (b 10) (d 11) (f 2)))
+              (d ((b 15) (c 11) (e 6)))
+              (e ((d 6) (f 9)))
+              (f ((a 14) (c 2) (e 9)))))
+
+(defn cheapest-route (from to)
+  (let ((routes (make-heap (fn (a b) (< (first a) (first b)))
+                           (list 0 (list from)))))
+    (loop ((visited (make-map)))
+      (let (((distance path) (heap-pop! routes)))
+        (let ((here (first path)))
+          (if (= here to)
+              (list distance (reduce (fn (acc x) (concat (list x) acc))
+                                     (concat '(()) path)))
+              (if (get visited here #f)
+                  (recur visited)
+                  (begin
+                    (map (fn (road)
+                           (heap-push! routes (list (+ distance (first (rest road)))
+                                                    (concat (list (first road)) path))))
+                         (alist-get roads here))
+                    (recur (assoc visited here #t))))))))))
+
+(cheapest-route 'a 'e)
@@ -0,0 +1,11 @@
+;; Natives and functions can be passed to map, filter, reduce and for-each.
+;; expect: ((5 7 9) (1 2) 10 6)
+
+(def inc '(fn (x) (+ x 1)))
+(def small '(fn (x) (< x 3)))
+(def total 0)
+(for-each (fn (x y) (set total (+ total x y))) '(1 2 3) '(0 0 0 99))
+(list (map + '(1 2 3) (map inc '(3 4 5)))
+      (filter small '(1 4 2 5))
+      (reduce + '(1 2 3 4))
+      total)
@@ -0,0 +1,13 @@
+;; With the option :record-history every value assigned to a global is
+;; recorded with the form assigning it, history-of lists them oldest first.
+;; expect: ((0 (def balance 0)) (100 (set balance (+ balance amount))) (70 (set balance (- balance amount))))
+
+(set-option! :record-history #t)
+(def balance 0)
+(defn deposit (amount) (set balance (+ balance amount)))
+(defn withdraw (amount) (set balance (- balance amount)))
+(deposit 100)
+(withdraw 30)
+(set-option! :record-history #f)
+(deposit 5)
+(history-of 'balance)
@@ -0,0 +1,10 @@
+;; Parameters after &key are passed by name as :key value pairs following
+;; the positional arguments, in any order. Keys not given are nil.
+;; expect: ((origin 1 2) (origin 1 nil) (p 0 5))
+
+(defun make-point (name &key x y)
+  (list name x y))
+
+(list (make-point 'origin :y 2 :x 1)
+      (make-point 'origin :x 1)
+      (make-point 'p :y (+ 2 3) :x 0))
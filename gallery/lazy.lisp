@@ -0,0 +1,17 @@
+;; stream-cons builds a stream whose rest is computed only when it is needed,
+;; so streams can be infinite. stream-map and stream-filter are lazy too, and
+;; stream-take computes as many elements as it takes. Printing a stream shows
+;; the elements computed so far.
+;; expect: ((0 1 1 2 3 5 8 13) (0 2 8 34 144) ~stream 0 1 1 2 3 5 8 13 21 34 55 89 144 ...~)
+
+(defn fibs (a b)
+  (stream-cons a (fibs b (+ a b))))
+
+(defn even? (n)
+  (if (= n 0) #t (if (= n 1) #f (even? (- n 2)))))
+
+(def fibonacci (fibs 0 1))
+
+(list (stream-take 8 fibonacci)
+      (stream-take 5 (stream-filter even? fibonacci))
+      fibonacci)
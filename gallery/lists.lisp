@@ -0,0 +1,8 @@
+;; Building, taking apart and combining lists.
+;; expect: (1 2 3 4 5)
+
+(def xs (list 1 2 3))
+(def ys (rest '(0 4 5)))
+(if (= (length (concat xs ys)) 5)
+    (concat xs ys)
+    else '())
@@ -0,0 +1,8 @@
+;; letrec binds its names before evaluating any value, so local functions can
+;; call themselves and each other.
+;; expect: (120 #t #f)
+
+(letrec ((fact (fn (n) (if (= n 0) 1 (* n (fact (- n 1))))))
+         (even? (fn (n) (if (= n 0) #t (odd? (- n 1)))))
+         (odd? (fn (n) (if (= n 0) #f (even? (- n 1))))))
+  (list (fact 5) (even? 10) (odd? 10)))
@@ -0,0 +1,13 @@
+;; loop binds variables like let, recur in tail position of the body
+;; evaluates it again with the variables rebound, without growing the stack.
+;; expect: (3628800 100000)
+
+(defun factorial (n)
+  (loop ((i n) (acc 1))
+    (if (= i 0)
+        acc
+        (recur (- i 1) (* acc i)))))
+
+(list (factorial 10)
+      (loop ((i 0))
+        (if (= i 100000) i (recur (+ i 1)))))
@@ -0,0 +1,5 @@
+;; Macros receive their arguments unevaluated and return a form to evaluate.
+;; expect: 7
+
+(def unless '(macro (test then alt) (list 'if test alt then)))
+(unless (= 1 2) 7 8)
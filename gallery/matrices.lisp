@@ -0,0 +1,23 @@
+;; Matrices hold numbers in one contiguous block instead of lists of lists.
+;; Raising ((1 1) (1 0)) to the nth power gives Fibonacci numbers, and a
+;; matrix times its transpose is symmetric.
+;; expect: (55 ((5 11) (11 25)))
+
+(def step (list->matrix '((1 1) (1 0))))
+
+(defn identity (n)
+  (loop ((m (make-matrix n n)) (i 0))
+    (if (< i n)
+        (recur (mat-set! m i i 1) (+ i 1))
+        m)))
+
+(defn mat-pow (m n)
+  (loop ((result (identity 2)) (i 0))
+    (if (< i n)
+        (recur (mat-mul result m) (+ i 1))
+        result)))
+
+(def m (list->matrix '((1 2) (3 4))))
+
+(list (mat-ref (mat-pow step 10) 0 1)
+      (matrix->list (mat-mul m (transpose m))))
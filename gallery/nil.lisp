@@ -0,0 +1,12 @@
+;; nil is the value of forms with nothing to return, like an if without else
+;; or get of a missing key. It is false in tests, but unlike #f and () it is
+;; not a bool or a list, so a stored () or #f can be told from a missing one.
+;; expect: (nil nil () skipped (#t #f))
+
+(def settings (make-map :tags '()))
+
+(list (if #f 'never)
+      (get settings :colour)
+      (get settings :tags)
+      (if (get settings :colour) 'used 'skipped)
+      (list (nil? (get settings :colour)) (nil? (get settings :tags))))
@@ -0,0 +1,17 @@
+;; match evaluates the first clause whose pattern matches a value. Symbols
+;; bind, _ matches anything, quoted data and literals must be equal.
+;; expect: (7 (1 + (2 * 3)))
+
+(defun evaluate (expr)
+  (match expr
+    (('+ a b) (+ (evaluate a) (evaluate b)))
+    (('* a b) (* (evaluate a) (evaluate b)))
+    (n n)))
+
+(defun infix (expr)
+  (match expr
+    ((op a b) (list (infix a) op (infix b)))
+    (_ expr)))
+
+(list (evaluate '(+ 1 (* 2 3)))
+      (infix '(+ 1 (* 2 3))))
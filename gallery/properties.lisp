@@ -0,0 +1,8 @@
+;; check-prop tests a property on generated values, failures are shrunk.
+;; expect: #t
+
+(defun reverse (lst)
+  (reduce (fn (acc x) (concat (list x) acc)) (concat '(()) lst)))
+
+(check-prop 50 (gen-list (gen-int -100 100))
+  (fn (lst) (assert-equal lst (reverse (reverse lst)))))
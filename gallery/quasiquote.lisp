@@ -0,0 +1,8 @@
+;; Quasiquoted templates evaluate unquoted forms and splice lists.
+;; expect: (when-positive 3 (1 2 3))
+
+(def args '(1 2 3))
+(defmacro when-positive (n &rest body)
+  `(if (= ,n 0) #f (begin ,@body)))
+
+(when-positive 1 `(when-positive ,(length args) ,args))
@@ -0,0 +1,19 @@
+;; Stacks and queues are persistent: pushing, popping, enqueuing and
+;; dequeuing return a new collection and leave the old one unchanged. Here a
+;; queue visits the nodes of a tree level by level, and a stack reverses them.
+;; expect: ((a b c d e f) (f e d c b a))
+
+(def tree '(a (b (d) (e)) (c (f))))
+
+(defn breadth-first (tree)
+  (loop ((queue (make-queue tree)) (visited (make-stack)))
+    (if (= (queue-length queue) 0)
+        visited
+        (let-values (((node more) (dequeue queue)))
+          (recur (reduce enqueue (concat (list more) (rest node)))
+                 (stack-push visited (first node)))))))
+
+(let ((visited (breadth-first tree)))
+  (list (reduce (fn (acc x) (concat (list x) acc))
+                (concat '(()) (stack->list visited)))
+        (stack->list visited)))
@@ -0,0 +1,12 @@
+;; read-string reads the forms in a string as data, returning several forms
+;; as multiple values. eval evaluates a form in the global environment, so
+;; together they evaluate code built or received at runtime.
+;; expect: ((+ 1 2) 3 (port 8080))
+
+(def source "(def port 8080) (list 'port port)")
+
+(list (read-string "(+ 1 2)")
+      (eval (read-string "(+ 1 2)"))
+      (let-values (((definition result) (read-string source)))
+        (eval definition)
+        (eval result)))
@@ -0,0 +1,8 @@
+;; Global definitions, let scopes, local bindings and set.
+;; expect: (11 2 3)
+
+(def counter 1)
+(let ((step 10))
+  (local double 2)
+  (set counter (+ counter step))
+  (list counter double (+ double 1)))
@@ -0,0 +1,9 @@
+;; let* binds one binding after the other, so the value of a binding can
+;; refer to the ones before it. let evaluates all values first and would not
+;; see width when computing area.
+;; expect: (3 4 12)
+
+(let* ((width 3)
+       (height (+ width 1))
+       (area (* width height)))
+  (list width height area))
@@ -0,0 +1,11 @@
+;; defspec states the predicates the arguments and the result of a function
+;; must satisfy. Calls by name are checked against them, a violation fails
+;; with a spec-error naming the argument and the offending value.
+;; expect: (3 (:spec-error "argument 1 of inc violates spec number?, got \"one\""))
+
+(defspec inc (number?) => number?)
+(defn inc (x) (+ x 1))
+
+(list (inc 2)
+      (try (inc "one")
+           (catch :spec-error e (list (get e :type) (get e :message)))))
@@ -0,0 +1,12 @@
+;; Descriptive statistics of a list of numbers. variance and stddev are
+;; those of a sample, or of the whole population with :population #t.
+;; histogram counts the numbers in bins of equal width.
+;; expect: (5 4.5 2 7.6 ((2 5.5 6) (5.5 9 2)))
+
+(def scores '(2 4 4 4 5 5 7 9))
+
+(list (mean scores)
+      (median scores)
+      (stddev scores :population #t)
+      (percentile scores 90)
+      (histogram scores 2))
@@ -0,0 +1,16 @@
+;; delay returns a promise of the value of an expression, which force
+;; evaluates once and remembers. A stream is a value and a promise of the
+;; rest of the stream, so it can be infinite as long as only a finite part
+;; is forced.
+;; expect: (1 2 3 4 5)
+
+(defn integers-from (n)
+  (list n (delay (integers-from (+ n 1)))))
+
+(defn take (k stream)
+  (if (= k 0)
+      '()
+      (concat (list (first stream))
+              (take (- k 1) (force (first (rest stream)))))))
+
+(take 5 (integers-from 1))
@@ -0,0 +1,17 @@
+;; defsyntax defines a hygienic macro by rules of a pattern and a template.
+;; Variables the template binds are renamed for every expansion, so swap!
+;; works even on a variable named tmp. `body ...` matches and splices the
+;; remaining arguments.
+;; expect: ((2 1) 6)
+
+(defsyntax swap!
+  ((_ a b) (let ((tmp a)) (set a b) (set b tmp))))
+
+(defsyntax sum
+  ((_) 0)
+  ((_ x rest ...) (+ x (sum rest ...))))
+
+(list (let ((tmp 1) (other 2))
+        (swap! tmp other)
+        (list tmp other))
+      (sum 1 2 3))
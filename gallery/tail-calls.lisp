@@ -0,0 +1,9 @@
+;; Calls in tail position reuse the caller's frame, so loops can recurse.
+;; expect: 100000
+
+(defun count-up (n acc)
+  (if (= n 0)
+      acc
+      (count-up (- n 1) (+ acc 1))))
+
+(count-up 100000 0)
@@ -0,0 +1,13 @@
+;; render-template fills {{name}} from a map of data. {{#each}} repeats its
+;; body for every element of a list, whose keys are then in scope too, and
+;; {{#if}} with an optional {{else}} chooses by the value of a key.
+;; expect: "Order for Ann: 2 x tea, 1 x cake (gift). Total 7"
+
+(def order (make-map :customer (make-map :name "Ann")
+                     :items (list (make-map :name "tea" :count 2)
+                                  (make-map :name "cake" :count 1 :gift #t))
+                     :total 7))
+
+(render-template
+ "Order for {{customer.name}}: {{#each items}}{{#unless @first}}, {{/unless}}{{count}} x {{name}}{{#if gift}} (gift){{/if}}{{/each}}. Total {{total}}"
+ order)
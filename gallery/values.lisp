@@ -0,0 +1,13 @@
+;; values returns several results without packing them into a list.
+;; let-values binds them with patterns, other contexts take the first value.
+;; expect: (1 (2 3) 3 (1 2))
+
+(defun uncons (l)
+  (values (first l) (rest l)))
+
+(defun swap (a b)
+  (if (= a b) (values a a) (values b a)))
+
+(let-values (((head tail) (uncons '(1 2 3)))
+             ((x y) (swap 2 1)))
+  (list head tail (+ 2 (uncons '(1 2))) (list x y)))
@@ -0,0 +1,9 @@
+;; postwalk replaces every node of a nested structure by a function applied
+;; to it, children first. prewalk replaces a node before walking into the
+;; replacement, here substituting a value for x before evaluating the form.
+;; expect: ((10 (20 30) 40) 15)
+
+(list (postwalk (fn (node) (if (number? node) (* node 10) node))
+                '(1 (2 3) 4))
+      (eval (prewalk (fn (node) (match node ('x 5) (_ node)))
+                     '(+ x (* x 2)))))
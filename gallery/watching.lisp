@@ -0,0 +1,14 @@
+;; watch reports every change of a binding by def and set, printing it or
+;; calling a handler with the symbol, the old and the new value.
+;; expect: ((count 0 1) (count 1 2) (count 2 0))
+
+(def changes '())
+(def count 0)
+(watch 'count (fn (sym old new) (set changes (concat changes (list (list sym old new))))))
+(defn bump () (set count (+ count 1)))
+(bump)
+(bump)
+(def count 0)
+(unwatch 'count)
+(bump)
+changes
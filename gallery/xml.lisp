@@ -0,0 +1,17 @@
+;; xml-parse reads XML or HTML into elements (tag attrs children) and text
+;; strings, select finds elements by CSS-like selectors and xml-text gets
+;; their text. xml-write turns nodes back into markup.
+;; expect: ((("Home" "/") ("Blog" "/blog")) "<ul><li><a href=\"/\">Home</a></li><li><a href=\"/blog\">Blog</a></li></ul>")
+
+(def page (xml-parse "<html><body><ul class='menu'><li><a href='/'>Home</a></li><li class=current><a href='/blog'>Blog</a></li></ul><p>More <a href='/about'>about us</a><br></body></html>"))
+
+(defn link (a)
+  (let (((tag attrs children) a))
+    (list (xml-text children) (get attrs :href))))
+
+(def links (map link (select page "ul.menu > li a")))
+
+(defn item ((text href))
+  (list "li" '() (list (list "a" (make-map :href href) (list text)))))
+
+(list links (xml-write (list "ul" '() (map item links))))
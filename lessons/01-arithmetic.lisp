@@ -0,0 +1,10 @@
+;; Lessons are lists of the form (lesson title text &rest exercises), where
+;; each exercise is (exercise prompt expected). An answer is accepted if it
+;; evaluates to the same value as expected.
+
+(lesson "Arithmetic"
+  "Lisp uses prefix notation: the operator comes first, followed by its arguments, all wrapped in parentheses. (+ 1 2) adds 1 and 2."
+  (exercise "Add the numbers 1, 2 and 3." (+ 1 2 3))
+  (exercise "Multiply 6 by 7." (* 6 7))
+  (exercise "Subtract 4 from 10." (- 10 4))
+  (exercise "Forms nest: compute (2 + 3) * 4." (* (+ 2 3) 4)))
@@ -0,0 +1,6 @@
+(lesson "Lists"
+  "Lists are built with list, and taken apart with first and rest. A quote in front of a form, like '(1 2 3), returns the form without evaluating it."
+  (exercise "Build a list containing 1, 2 and 3." (list 1 2 3))
+  (exercise "Get the first element of '(4 5 6)." (first '(4 5 6)))
+  (exercise "Get everything but the first element of '(4 5 6)." (rest '(4 5 6)))
+  (exercise "Compute the length of '(a b c d)." (length '(a b c d))))
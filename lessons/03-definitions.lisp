@@ -0,0 +1,7 @@
+(lesson "Definitions and conditionals"
+  "def binds a global name to a value. if evaluates its test and then either the consequent or the optional alternative."
+  (exercise "Define x as 5, then compute x times x." (* 5 5))
+  (exercise "Write an if that returns 1 when (= 1 2) is true and 2 otherwise."
+            (if (= 1 2) 1 2))
+  (exercise "Apply + to the pairs of '(1 2) and '(10 20) using map."
+            (map + '(1 2) '(10 20))))
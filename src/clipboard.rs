@@ -0,0 +1,90 @@
+// Clipboard access through the platform's clipboard tools, the first one
+// found on the PATH is used: pbcopy/pbpaste on macOS, wl-copy/wl-paste on
+// Wayland and xclip or xsel on X11.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef},
+};
+
+const PASTE: &[(&str, &[&str])] = &[
+    ("pbpaste", &[]),
+    ("wl-paste", &["--no-newline"]),
+    ("xclip", &["-selection", "clipboard", "-out"]),
+    ("xsel", &["--clipboard", "--output"]),
+];
+
+const COPY: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard", "-in"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+fn no_tool() -> EvalError {
+    EvalError::new("No clipboard tool found, install wl-clipboard, xclip or xsel".to_string())
+        .with_condition(condition::IO_ERROR)
+}
+
+fn clipboard_get(_: &mut Interpreter, _: &[LispObject]) -> Result<LispObject, EvalError> {
+    for (program, args) in PASTE {
+        if let Ok(output) = Command::new(program).args(*args).stderr(Stdio::null()).output() {
+            return if output.status.success() {
+                Ok(LispObject::String(String::from_utf8_lossy(&output.stdout).into()))
+            } else {
+                Err(EvalError::new(format!("{} failed with {}", program, output.status))
+                    .with_condition(condition::IO_ERROR))
+            }
+        }
+    }
+    Err(no_tool())
+}
+
+pub const CLIPBOARD_GET: NativeDef = NativeDef {
+    name: "clipboard-get",
+    positional: &[],
+    rest: None,
+    func: clipboard_get,
+};
+
+fn copy(program: &str, args: &[&str], text: &str) -> Option<Result<(), EvalError>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let written = child.stdin.take()
+        .map(|mut stdin| stdin.write_all(text.as_bytes()))
+        .unwrap_or(Ok(()));
+    Some(match (written, child.wait()) {
+        (Ok(()), Ok(status)) if status.success() => Ok(()),
+        (Err(e), _) | (_, Err(e)) => Err(EvalError::new(format!("{} failed: {}", program, e))
+                                         .with_condition(condition::IO_ERROR)),
+        (_, Ok(status)) => Err(EvalError::new(format!("{} failed with {}", program, status))
+                               .with_condition(condition::IO_ERROR)),
+    })
+}
+
+fn clipboard_set(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let text = args[0].as_str()
+        .map_err(|e| e.trace(1))?;
+    for (program, tool_args) in COPY {
+        if let Some(result) = copy(program, tool_args, text) {
+            return result.map(|()| args[0].clone())
+        }
+    }
+    Err(no_tool())
+}
+
+pub const CLIPBOARD_SET: NativeDef = NativeDef {
+    name: "clipboard-set!",
+    positional: &["text"],
+    rest: None,
+    func: clipboard_set,
+};
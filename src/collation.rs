@@ -0,0 +1,201 @@
+// Collation of strings for sorting them as readers expect rather than by
+// code points. Strings are compared letter by letter ignoring accents and
+// case first, then by accents, then by case, lower case first, like the
+// levels of the Unicode collation algorithm. Accents are known for the
+// Latin-1 and Latin Extended-A letters. Locales differing from these root
+// rules tailor some letters to sort after z, e.g. å, ä and ö in Swedish.
+
+use std::cmp::Ordering;
+
+use crate::{
+    condition,
+    exc,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef, SerializeSymbol},
+    lisp_object_util::keyword_args,
+};
+
+// Letters with accents as pairs of the letter and its base letter.
+const ACCENTED: &str = "àaáaâaãaäaåaāaăaąaçcćcĉcċcčcďdđdèeéeêeëeēeĕeėeęeěeĝgğgġgģgĥhħhìiíiîiïiĩiīiĭiįiıiĵjķkĺlļlľlŀlłlñnńnņnňnòoóoôoõoöoøoōoŏoőoŕrŗrřrśsŝsşsšsţtťtŧtùuúuûuüuũuūuŭuůuűuųuŵwýyÿyŷyźzżzžz";
+
+// Letters sorting after z, in order, by language.
+const TAILORINGS: &[(&str, &str)] = &[
+    ("da", "æøå"),
+    ("fi", "åäö"),
+    ("nb", "æøå"),
+    ("nn", "æøå"),
+    ("no", "æøå"),
+    ("sv", "åäö"),
+];
+
+// The base letter of an accented letter c and a weight distinguishing the
+// accents of the same letter.
+fn unaccented(c: char) -> Option<(char, usize)> {
+    let mut chars = ACCENTED.chars();
+    let mut accent = 0;
+    while let (Some(accented), Some(base)) = (chars.next(), chars.next()) {
+        accent += 1;
+        if accented == c {
+            return Some((base, accent))
+        }
+    }
+    None
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Strength {
+    Primary,
+    Secondary,
+    Tertiary,
+}
+
+struct Collation {
+    tailoring: &'static str,
+    strength: Strength,
+    numeric: bool,
+}
+
+// A unit of the primary level. Runs of digits compare by their value with
+// numeric collation and sort before letters.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Primary {
+    Number(usize, String),
+    Char(u32),
+}
+
+// The comparison levels of a string.
+struct Key {
+    primary: Vec<Primary>,
+    accents: Vec<usize>,
+    cases: Vec<bool>,
+}
+
+impl Collation {
+    fn key(&self, s: &str) -> Key {
+        let mut key = Key { primary: vec![], accents: vec![], cases: vec![] };
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            if self.numeric && c.is_ascii_digit() {
+                let mut digits = c.to_string();
+                while let Some(d) = chars.next_if(char::is_ascii_digit) {
+                    digits.push(d);
+                }
+                let digits = digits.trim_start_matches('0').to_string();
+                key.primary.push(Primary::Number(digits.len(), digits));
+                continue
+            }
+            let lower = c.to_lowercase().next().unwrap_or(c);
+            key.cases.push(lower != c);
+            if let Some(position) = self.tailoring.chars().position(|t| t == lower) {
+                key.primary.push(Primary::Char('z' as u32 + 1 + position as u32));
+                key.accents.push(0);
+                continue
+            }
+            match unaccented(lower) {
+                Some((base, accent)) => {
+                    key.primary.push(Primary::Char(base as u32));
+                    key.accents.push(accent);
+                },
+                None if lower == 'ß' => {
+                    key.primary.extend([Primary::Char('s' as u32), Primary::Char('s' as u32)]);
+                    key.accents.extend([1, 1]);
+                },
+                None => {
+                    key.primary.push(Primary::Char(lower as u32));
+                    key.accents.push(0);
+                },
+            }
+        }
+        key
+    }
+
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        let (a, b) = (self.key(a), self.key(b));
+        let mut ordering = a.primary.cmp(&b.primary);
+        if self.strength >= Strength::Secondary {
+            ordering = ordering.then_with(|| a.accents.cmp(&b.accents));
+        }
+        if self.strength >= Strength::Tertiary {
+            ordering = ordering.then_with(|| a.cases.cmp(&b.cases));
+        }
+        ordering
+    }
+}
+
+fn strength(interp: &Interpreter, value: &LispObject) -> Result<Strength, EvalError> {
+    let name = match value {
+        LispObject::Keyword(k) => interp.symbols().as_string(k),
+        _ => None,
+    };
+    match name {
+        Some("primary") => Ok(Strength::Primary),
+        Some("secondary") => Ok(Strength::Secondary),
+        Some("tertiary") => Ok(Strength::Tertiary),
+        _ => Err(EvalError::new(format!("Expected :primary, :secondary or :tertiary as strength, got {}",
+                                        interp.serialize(value)))
+                 .with_condition(condition::TYPE_ERROR)),
+    }
+}
+
+// Parse the keyword arguments :locale, :strength and :numeric in options,
+// which start at argument first.
+fn collation(interp: &Interpreter, options: &[LispObject], first: usize) -> Result<Collation, EvalError> {
+    let mut collation = Collation { tailoring: "", strength: Strength::Tertiary, numeric: false };
+    let args = keyword_args(interp.symbols(), options)
+        .map_err(|(e, index)| e.trace(index + first))?;
+    for ((name, value), index) in args.into_iter().zip((first..).step_by(2)) {
+        match name {
+            Some("locale") => {
+                let locale = value.as_str()
+                    .map_err(|e| e.trace(index + 1))?;
+                let language = locale.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+                collation.tailoring = TAILORINGS.iter()
+                    .find(|(tailored, _)| *tailored == language)
+                    .map_or("", |(_, letters)| letters);
+            },
+            Some("strength") => collation.strength = strength(interp, value)
+                .map_err(|e| e.trace(index + 1))?,
+            Some("numeric") => collation.numeric = value.as_bool()
+                .map_err(|e| e.trace(index + 1))?,
+            _ => return Err(exc::unknown_keyword(name).trace(index)),
+        }
+    }
+    Ok(collation)
+}
+
+// -1, 0 or 1 as a collates before, equal to or after b.
+fn string_compare(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let a = args[0].as_str()
+        .map_err(|e| e.trace(1))?;
+    let b = args[1].as_str()
+        .map_err(|e| e.trace(2))?;
+    let collation = collation(interp, args[2].as_slice()?, 3)?;
+    Ok(LispObject::Number(collation.compare(a, b) as i8 as f64))
+}
+
+pub const STRING_COMPARE: NativeDef = NativeDef {
+    name: "string-compare",
+    positional: &["a", "b"],
+    rest: Some("options"),
+    func: string_compare,
+};
+
+// The strings sorted by collation, stable for strings collating equal.
+fn collate(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let strings = args[0].as_slice()
+        .map_err(|e| e.trace(1))?;
+    let collation = collation(interp, args[1].as_slice()?, 2)?;
+    let mut keyed = strings.iter()
+        .map(|s| s.as_str().map(|text| (text, s.clone())))
+        .collect::<Result<Vec<(&str, LispObject)>, EvalError>>()
+        .map_err(|e| e.trace(1))?;
+    keyed.sort_by(|(a, _), (b, _)| collation.compare(a, b));
+    Ok(LispObject::List(keyed.into_iter().map(|(_, s)| s).collect()))
+}
+
+pub const COLLATE: NativeDef = NativeDef {
+    name: "collate",
+    positional: &["strings"],
+    rest: Some("options"),
+    func: collate,
+};
@@ -0,0 +1,405 @@
+// Persistent stacks and queues. Both are built from linked nodes shared
+// between versions, so pushing, popping and enqueuing return a new
+// collection in constant time and leave the old one unchanged. A queue keeps
+// its back in reverse and reverses it into the front when the front runs out,
+// so dequeuing takes constant time on average. Heaps, priority queues in an
+// order given by a function, are mutable instead.
+
+use std::rc::Rc;
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, Handle, LispObject, NativeDef},
+};
+
+const STACK: &str = "stack";
+const QUEUE: &str = "queue";
+
+struct Node {
+    value: LispObject,
+    next: Link,
+}
+
+type Link = Option<Rc<Node>>;
+
+// Drop the nodes no other version shares one by one, instead of recursively,
+// which overflows the stack for long collections.
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut next = self.next.take();
+        while let Some(node) = next {
+            next = match Rc::try_unwrap(node) {
+                Ok(mut node) => node.next.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+fn push(link: &Link, value: LispObject) -> Link {
+    Some(Rc::new(Node { value, next: link.clone() }))
+}
+
+fn to_vec(mut link: &Link) -> Vec<LispObject> {
+    let mut values = vec![];
+    while let Some(node) = link {
+        values.push(node.value.clone());
+        link = &node.next;
+    }
+    values
+}
+
+#[derive(Clone)]
+struct Stack {
+    top: Link,
+    len: usize,
+}
+
+#[derive(Clone)]
+struct Queue {
+    front: Link,
+    back: Link,
+    len: usize,
+}
+
+fn stack(object: &LispObject) -> Result<Stack, EvalError> {
+    object.as_handle(STACK)?.value.borrow().downcast_ref::<Stack>()
+        .cloned()
+        .ok_or_else(|| EvalError::new("Invalid stack handle".to_string()))
+}
+
+fn queue(object: &LispObject) -> Result<Queue, EvalError> {
+    object.as_handle(QUEUE)?.value.borrow().downcast_ref::<Queue>()
+        .cloned()
+        .ok_or_else(|| EvalError::new("Invalid queue handle".to_string()))
+}
+
+// The queue of object with its front filled. The handle is updated, so the
+// back is reversed only once even if the same version is dequeued again.
+fn normalized_queue(object: &LispObject) -> Result<Queue, EvalError> {
+    let q = queue(object)?;
+    if q.front.is_some() || q.back.is_none() {
+        return Ok(q)
+    }
+    let normalized = Queue {
+        front: to_vec(&q.back).into_iter().fold(None, |front, value| push(&front, value)),
+        back: None,
+        len: q.len,
+    };
+    *object.as_handle(QUEUE)?.value.borrow_mut() = Box::new(normalized.clone());
+    Ok(normalized)
+}
+
+fn empty(kind: &str) -> EvalError {
+    EvalError::new(format!("The {} is empty", kind))
+        .with_condition(condition::PROGRAM_ERROR)
+}
+
+// A stack of items, the first of them on top.
+fn make_stack(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let items = args[0].as_slice()?;
+    let top = items.iter().rev().fold(None, |top, item| push(&top, item.clone()));
+    Ok(Handle::object(STACK, Stack { top, len: items.len() }))
+}
+
+pub const MAKE_STACK: NativeDef = NativeDef {
+    name: "make-stack",
+    positional: &[],
+    rest: Some("items"),
+    func: make_stack,
+};
+
+fn stack_push(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let s = stack(&args[0])
+        .map_err(|e| e.trace(1))?;
+    Ok(Handle::object(STACK, Stack { top: push(&s.top, args[1].clone()), len: s.len + 1 }))
+}
+
+pub const STACK_PUSH: NativeDef = NativeDef {
+    name: "stack-push",
+    positional: &["stack", "item"],
+    rest: None,
+    func: stack_push,
+};
+
+// The top item and the stack below it as values.
+fn stack_pop(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let s = stack(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let top = s.top.ok_or_else(|| empty(STACK).trace(1))?;
+    let rest = Handle::object(STACK, Stack { top: top.next.clone(), len: s.len - 1 });
+    Ok(LispObject::Values(Rc::new(vec![top.value.clone(), rest])))
+}
+
+pub const STACK_POP: NativeDef = NativeDef {
+    name: "stack-pop",
+    positional: &["stack"],
+    rest: None,
+    func: stack_pop,
+};
+
+fn stack_peek(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let s = stack(&args[0])
+        .map_err(|e| e.trace(1))?;
+    s.top.map(|top| top.value.clone())
+        .ok_or_else(|| empty(STACK).trace(1))
+}
+
+pub const STACK_PEEK: NativeDef = NativeDef {
+    name: "stack-peek",
+    positional: &["stack"],
+    rest: None,
+    func: stack_peek,
+};
+
+fn stack_length(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let s = stack(&args[0])
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::Number(s.len as f64))
+}
+
+pub const STACK_LENGTH: NativeDef = NativeDef {
+    name: "stack-length",
+    positional: &["stack"],
+    rest: None,
+    func: stack_length,
+};
+
+// The items from the top down.
+fn stack_to_list(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let s = stack(&args[0])
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::List(to_vec(&s.top)))
+}
+
+pub const STACK_TO_LIST: NativeDef = NativeDef {
+    name: "stack->list",
+    positional: &["stack"],
+    rest: None,
+    func: stack_to_list,
+};
+
+// A queue of items, the first of them in front.
+fn make_queue(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let items = args[0].as_slice()?;
+    let front = items.iter().rev().fold(None, |front, item| push(&front, item.clone()));
+    Ok(Handle::object(QUEUE, Queue { front, back: None, len: items.len() }))
+}
+
+pub const MAKE_QUEUE: NativeDef = NativeDef {
+    name: "make-queue",
+    positional: &[],
+    rest: Some("items"),
+    func: make_queue,
+};
+
+fn enqueue(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let q = queue(&args[0])
+        .map_err(|e| e.trace(1))?;
+    Ok(Handle::object(QUEUE, Queue {
+        front: q.front,
+        back: push(&q.back, args[1].clone()),
+        len: q.len + 1,
+    }))
+}
+
+pub const ENQUEUE: NativeDef = NativeDef {
+    name: "enqueue",
+    positional: &["queue", "item"],
+    rest: None,
+    func: enqueue,
+};
+
+// The front item and the queue behind it as values.
+fn dequeue(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let q = normalized_queue(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let front = q.front.ok_or_else(|| empty(QUEUE).trace(1))?;
+    let rest = Handle::object(QUEUE, Queue { front: front.next.clone(), back: q.back, len: q.len - 1 });
+    Ok(LispObject::Values(Rc::new(vec![front.value.clone(), rest])))
+}
+
+pub const DEQUEUE: NativeDef = NativeDef {
+    name: "dequeue",
+    positional: &["queue"],
+    rest: None,
+    func: dequeue,
+};
+
+fn queue_peek(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let q = normalized_queue(&args[0])
+        .map_err(|e| e.trace(1))?;
+    q.front.map(|front| front.value.clone())
+        .ok_or_else(|| empty(QUEUE).trace(1))
+}
+
+pub const QUEUE_PEEK: NativeDef = NativeDef {
+    name: "queue-peek",
+    positional: &["queue"],
+    rest: None,
+    func: queue_peek,
+};
+
+fn queue_length(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let q = queue(&args[0])
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::Number(q.len as f64))
+}
+
+pub const QUEUE_LENGTH: NativeDef = NativeDef {
+    name: "queue-length",
+    positional: &["queue"],
+    rest: None,
+    func: queue_length,
+};
+
+// The items from front to back.
+fn queue_to_list(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let q = queue(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let mut items = to_vec(&q.front);
+    items.extend(to_vec(&q.back).into_iter().rev());
+    Ok(LispObject::List(items))
+}
+
+pub const QUEUE_TO_LIST: NativeDef = NativeDef {
+    name: "queue->list",
+    positional: &["queue"],
+    rest: None,
+    func: queue_to_list,
+};
+
+const HEAP: &str = "heap";
+
+// A binary heap ordered by a function of two items, true if the first comes
+// before the second. Unlike stacks and queues, heaps are modified in place.
+struct Heap {
+    before: LispObject,
+    items: Vec<LispObject>,
+}
+
+impl Heap {
+    fn before(&self, interp: &mut Interpreter, a: usize, b: usize) -> Result<bool, EvalError> {
+        let before = interp.apply(&self.before, &[self.items[a].clone(), self.items[b].clone()])?;
+        interp.is_true(&before)
+    }
+
+    fn sift_up(&mut self, interp: &mut Interpreter, mut index: usize) -> Result<(), EvalError> {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if !self.before(interp, index, parent)? {
+                break
+            }
+            self.items.swap(index, parent);
+            index = parent;
+        }
+        Ok(())
+    }
+
+    fn sift_down(&mut self, interp: &mut Interpreter, mut index: usize) -> Result<(), EvalError> {
+        loop {
+            let mut first = index;
+            for child in [2 * index + 1, 2 * index + 2] {
+                if child < self.items.len() && self.before(interp, child, first)? {
+                    first = child;
+                }
+            }
+            if first == index {
+                return Ok(())
+            }
+            self.items.swap(index, first);
+            index = first;
+        }
+    }
+}
+
+// Apply f to the heap of object. The heap is taken out of its handle while
+// the ordering function runs, so the function may not use the heap itself.
+fn with_heap<T>(interp: &mut Interpreter, object: &LispObject,
+                f: impl FnOnce(&mut Heap, &mut Interpreter) -> Result<T, EvalError>)
+                -> Result<T, EvalError> {
+    let handle = object.as_handle(HEAP)?;
+    let mut heap = handle.value.replace(Box::new(()))
+        .downcast::<Heap>()
+        .map_err(|_| EvalError::new("The heap is in use by its ordering function".to_string())
+                 .with_condition(condition::PROGRAM_ERROR))?;
+    let result = f(&mut heap, interp);
+    handle.value.replace(heap);
+    result
+}
+
+fn make_heap(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let mut heap = Heap { before: args[0].clone(), items: vec![] };
+    for (index, item) in args[1].as_slice()?.iter().enumerate() {
+        heap.items.push(item.clone());
+        heap.sift_up(interp, index)
+            .map_err(|e| e.trace(1))?;
+    }
+    Ok(Handle::object(HEAP, heap))
+}
+
+pub const MAKE_HEAP: NativeDef = NativeDef {
+    name: "make-heap",
+    positional: &["before"],
+    rest: Some("items"),
+    func: make_heap,
+};
+
+fn heap_push(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    with_heap(interp, &args[0], |heap, interp| {
+        heap.items.push(args[1].clone());
+        heap.sift_up(interp, heap.items.len() - 1)
+    }).map_err(|e| e.trace(1))?;
+    Ok(args[0].clone())
+}
+
+pub const HEAP_PUSH: NativeDef = NativeDef {
+    name: "heap-push!",
+    positional: &["heap", "item"],
+    rest: None,
+    func: heap_push,
+};
+
+// Remove and return the first item.
+fn heap_pop(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    with_heap(interp, &args[0], |heap, interp| {
+        if heap.items.is_empty() {
+            return Err(empty(HEAP))
+        }
+        let first = heap.items.swap_remove(0);
+        heap.sift_down(interp, 0)?;
+        Ok(first)
+    }).map_err(|e| e.trace(1))
+}
+
+pub const HEAP_POP: NativeDef = NativeDef {
+    name: "heap-pop!",
+    positional: &["heap"],
+    rest: None,
+    func: heap_pop,
+};
+
+fn heap_peek(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    with_heap(interp, &args[0], |heap, _| heap.items.first().cloned().ok_or_else(|| empty(HEAP)))
+        .map_err(|e| e.trace(1))
+}
+
+pub const HEAP_PEEK: NativeDef = NativeDef {
+    name: "heap-peek",
+    positional: &["heap"],
+    rest: None,
+    func: heap_peek,
+};
+
+fn heap_length(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    with_heap(interp, &args[0], |heap, _| Ok(LispObject::Number(heap.items.len() as f64)))
+        .map_err(|e| e.trace(1))
+}
+
+pub const HEAP_LENGTH: NativeDef = NativeDef {
+    name: "heap-length",
+    positional: &["heap"],
+    rest: None,
+    func: heap_length,
+};
@@ -0,0 +1,48 @@
+// Condition types classify errors, so try can catch them selectively. A
+// type also matches the types deriving from it, and every type derives from
+// error. Types signalled by `error` that are not listed derive from error
+// directly.
+pub const ERROR: &str = "error";
+pub const PROGRAM_ERROR: &str = "program-error";
+pub const SYNTAX_ERROR: &str = "syntax-error";
+pub const ARITY_ERROR: &str = "arity-error";
+pub const UNBOUND_ERROR: &str = "unbound-error";
+pub const TYPE_ERROR: &str = "type-error";
+pub const PATTERN_ERROR: &str = "pattern-error";
+pub const SPEC_ERROR: &str = "spec-error";
+pub const IO_ERROR: &str = "io-error";
+pub const STACK_OVERFLOW: &str = "stack-overflow";
+pub const OUT_OF_FUEL: &str = "out-of-fuel";
+
+const PARENTS: &[(&str, &str)] = &[
+    (PROGRAM_ERROR, ERROR),
+    (SYNTAX_ERROR, PROGRAM_ERROR),
+    (ARITY_ERROR, PROGRAM_ERROR),
+    (UNBOUND_ERROR, PROGRAM_ERROR),
+    (TYPE_ERROR, ERROR),
+    (PATTERN_ERROR, ERROR),
+    (SPEC_ERROR, ERROR),
+    (IO_ERROR, ERROR),
+    (STACK_OVERFLOW, ERROR),
+    (OUT_OF_FUEL, ERROR),
+];
+
+fn parent(condition: &str) -> Option<&'static str> {
+    match PARENTS.iter().find(|(name, _)| *name == condition) {
+        Some((_, parent)) => Some(parent),
+        None if condition == ERROR => None,
+        None => Some(ERROR),
+    }
+}
+
+// Whether condition is ancestor or derives from it.
+pub fn is_a(condition: &str, ancestor: &str) -> bool {
+    let mut condition = Some(condition);
+    while let Some(c) = condition {
+        if c == ancestor {
+            return true
+        }
+        condition = parent(c);
+    }
+    false
+}
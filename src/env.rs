@@ -1,211 +1,943 @@
-use std::collections::HashMap;
-use std::collections::hash_map::Entry;
-
-use crate::{
-    lisp_object::{
-        ParamList,
-        LispObject,
-        NativeDef,
-        SpecialForm,
-        Symbol,
-        SerializeSymbol,
-    },
-    native
-};
-
-
-pub struct Symbols {
-    registry: HashMap<String, Symbol>,
-    reverse: HashMap<Symbol, String>,
-    next_id: Symbol,
-
-    pub sym_fn: Symbol,
-    pub sym_macro: Symbol,
-    pub sym_quote: Symbol,
-    pub sym_quasiquote: Symbol,
-    pub sym_unquote: Symbol,
-    pub sym_unquote_splice: Symbol,
-    pub sym_rest: Symbol,
-}
-
-impl Symbols {
-    pub fn new() -> Symbols {
-        let mut symbols = Symbols {
-            registry: HashMap::new(),
-            reverse: HashMap::new(),
-            next_id: 0,
-
-            sym_fn: 0,
-            sym_macro: 0,
-            sym_quote: 0,
-            sym_quasiquote: 0,
-            sym_unquote: 0,
-            sym_unquote_splice: 0,
-            sym_rest: 0,
-        };
-        symbols.sym_fn = symbols.intern("fn");
-        symbols.sym_macro = symbols.intern("macro");
-        symbols.sym_quote = symbols.intern("quote");
-        symbols.sym_quasiquote = symbols.intern("quasiquote");
-        symbols.sym_unquote = symbols.intern("unquote");
-        symbols.sym_unquote_splice = symbols.intern("unquote-splice");
-        symbols.sym_rest = symbols.intern("&rest");
-        symbols
-    }
-
-    pub fn intern(&mut self, name: &str) -> Symbol {
-        match self.registry.entry(name.to_string()) {
-            Entry::Occupied(e) => *e.get(),
-            Entry::Vacant(_) => {
-                self.next_id += 1;
-                self.registry.insert(name.to_string(), self.next_id);
-                self.reverse.insert(self.next_id, name.to_string());
-                self.next_id
-            }
-        }
-    }
-
-    pub fn symbol(&mut self, name: &str) -> LispObject {
-        LispObject::Symbol(self.intern(name))
-    }
-
-    pub fn quote(&mut self, obj: LispObject) -> LispObject {
-        LispObject::List(vec![LispObject::Symbol(self.sym_quote), obj])
-    }
-
-    pub fn quasi_quote(&mut self, obj: LispObject) -> LispObject {
-        LispObject::List(vec![LispObject::Symbol(self.sym_quasiquote), obj])
-    }
-
-    pub fn unquote(&mut self, obj: LispObject) -> LispObject {
-        LispObject::List(vec![LispObject::Symbol(self.sym_unquote), obj])
-    }
-
-    pub fn unquote_splice(&mut self, obj: LispObject) -> LispObject {
-        LispObject::List(vec![LispObject::Symbol(self.sym_unquote_splice), obj])
-    }
-
-    fn form_to_string(&self, l: &Vec<LispObject>) -> String {
-        l.iter()
-            .map(|o| self.serialize_object(o))
-            .collect::<Vec<String>>()
-            .join(" ")
-    }
-
-    pub fn serialize_param_list(&self, lst: &ParamList) -> String {
-        let (pos, rest) = lst;
-        let pos_str = pos.iter()
-            .map(|o| self.as_string(o).unwrap_or("~~uninterned~~"))
-            .collect::<Vec<&str>>()
-            .join(" ");
-
-        let rest_str = match rest {
-            Some(s) => format!(" &rest {}", self.as_string(&s)
-                               .unwrap_or("~~uninterned~~")),
-            None => "".to_string(),
-        };
-
-        format!("({}{})", pos_str, rest_str)
-    }
-
-    pub fn serialize_object(&self, obj: &LispObject) -> String {
-        match obj {
-            LispObject::Symbol(s) =>
-                format!("{}", self.as_string(s)
-                        .unwrap_or("~~uninterned~~")),
-            LispObject::List(l) =>
-                format!("({})", self.form_to_string(l)),
-            LispObject::Bool(true) =>
-                "#t".to_string(),
-            LispObject::Bool(false) =>
-                "#f".to_string(),
-            LispObject::SpecialForm(sf) =>
-                format!("~special:{}~", sf),
-            LispObject::String(s) =>
-                format!("\"{}\"", s),
-            LispObject::Number(n) =>
-                format!("{}", n.to_string()),
-            LispObject::Native(ps, _) =>
-                format!("(~native~{}~)",
-                        self.serialize_param_list(&ps)),
-        }
-    }
-}
-
-impl SerializeSymbol for Symbols {
-    fn as_string(&self, sym: &Symbol) -> Option<&str> {
-        self.reverse.get(sym).map(|s| &s[..])
-    }
-}
-
-pub struct Env {
-    vars: Vec<HashMap<Symbol, LispObject>>,
-}
-
-impl Env {
-    pub fn new() -> Env {
-        Env {
-            vars: vec![HashMap::new()],
-        }
-    }
-
-    pub fn push_scope(&mut self) {
-        self.vars.push(HashMap::new());
-    }
-
-    pub fn pop_scope(&mut self) {
-        self.vars.pop();
-    }
-
-    pub fn set(&mut self, key: Symbol, value: LispObject) {
-        self.vars.last_mut().and_then(|v| v.insert(key, value));
-    }
-
-    pub fn global(&mut self, key: Symbol, value: LispObject) {
-        self.vars.first_mut().and_then(|v| v.insert(key, value));
-    }
-
-    pub fn resolve(&self, key: &Symbol) -> Option<&LispObject> {
-        match self.vars.iter().rev()
-            .find(|scope| scope.contains_key(key)) {
-                Some(scope) => scope.get(key),
-                None => None,
-            }
-    }
-}
-
-fn set_native(sym: &mut Symbols, env: &mut Env, def: NativeDef) {
-    // Intern Arguments
-    let pos_args = def.positional.iter()
-        .map(|s| sym.intern(s))
-        .collect::<Vec<Symbol>>();
-    let rest_arg = def.rest.map(|s| sym.intern(s));
-    env.global(sym.intern(def.name),
-               LispObject::Native((pos_args, rest_arg), def.func));
-}
-
-fn set_special(sym: &mut Symbols, env: &mut Env, sf: SpecialForm) {
-    env.global(sym.intern(&sf.to_string()),
-               LispObject::SpecialForm(sf));
-}
-
-pub fn create_root(symbols: &mut Symbols) -> Env {
-    let mut root = Env::new();
-    set_special(symbols, &mut root, SpecialForm::Def);
-    set_special(symbols, &mut root, SpecialForm::Set);
-    set_special(symbols, &mut root, SpecialForm::If);
-    set_special(symbols, &mut root, SpecialForm::Let);
-    set_special(symbols, &mut root, SpecialForm::Begin);
-    set_special(symbols, &mut root, SpecialForm::Quote);
-    set_native (symbols, &mut root, native::ADD);
-    set_native (symbols, &mut root, native::MULTIPLY);
-    set_native (symbols, &mut root, native::SUBTRACT);
-    set_native (symbols, &mut root, native::EQUAL);
-    set_native (symbols, &mut root, native::FIRST);
-    set_native (symbols, &mut root, native::REST);
-    set_native (symbols, &mut root, native::LIST);
-    set_native (symbols, &mut root, native::CONCAT);
-    set_native (symbols, &mut root, native::IS_LIST);
-    set_native (symbols, &mut root, native::LENGTH);
-    root
-}
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::collections::hash_map::Entry;
+
+use crate::{
+    lisp_object::{
+        ParamList,
+        LispObject,
+        NativeDef,
+        SpecialForm,
+        StreamTail,
+        Symbol,
+        SerializeSymbol,
+    },
+    collections,
+    format,
+    fuzzy,
+    generator,
+    graph,
+    lexer::{print_string, print_symbol},
+    matrix,
+    native,
+    notify,
+    promise,
+    prop,
+    stats,
+    stream,
+    syntax,
+    template,
+    timer,
+    watch,
+    xml,
+};
+#[cfg(feature = "sqlite")]
+use crate::sqlite;
+#[cfg(feature = "net")]
+use crate::net;
+#[cfg(feature = "desktop")]
+use crate::clipboard;
+#[cfg(feature = "collation")]
+use crate::collation;
+#[cfg(feature = "markdown")]
+use crate::markdown;
+#[cfg(feature = "graphics")]
+use crate::graphics;
+
+// Customizes how values are displayed, e.g. the handles of an embedder, or
+// colored or HTML output for other frontends. See Interpreter::set_printer.
+pub trait Printer {
+    // The rendering of object, None to render it as usual. Called for every
+    // value displayed, including the elements of lists and maps.
+    fn print(&self, symbols: &Symbols, object: &LispObject) -> Option<String>;
+}
+
+#[derive(Clone)]
+pub struct Symbols {
+    registry: HashMap<String, Symbol>,
+    reverse: HashMap<Symbol, String>,
+    next_id: Symbol,
+    strings: HashSet<Rc<str>>,
+
+    pub sym_fn: Symbol,
+    pub sym_macro: Symbol,
+    pub sym_quote: Symbol,
+    pub sym_quasiquote: Symbol,
+    pub sym_unquote: Symbol,
+    pub sym_unquote_splice: Symbol,
+    pub sym_rest: Symbol,
+    pub sym_key: Symbol,
+    pub sym_else: Symbol,
+}
+
+impl Default for Symbols {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Symbols {
+    pub fn new() -> Symbols {
+        let mut symbols = Symbols {
+            registry: HashMap::new(),
+            reverse: HashMap::new(),
+            next_id: 0,
+            strings: HashSet::new(),
+
+            sym_fn: 0,
+            sym_macro: 0,
+            sym_quote: 0,
+            sym_quasiquote: 0,
+            sym_unquote: 0,
+            sym_unquote_splice: 0,
+            sym_rest: 0,
+            sym_key: 0,
+            sym_else: 0,
+        };
+        symbols.sym_fn = symbols.intern("fn");
+        symbols.sym_macro = symbols.intern("macro");
+        symbols.sym_quote = symbols.intern("quote");
+        symbols.sym_quasiquote = symbols.intern("quasiquote");
+        symbols.sym_unquote = symbols.intern("unquote");
+        symbols.sym_unquote_splice = symbols.intern("unquote-splice");
+        symbols.sym_rest = symbols.intern("&rest");
+        symbols.sym_key = symbols.intern("&key");
+        symbols.sym_else = symbols.intern("else");
+        symbols
+    }
+
+    // Number of interned symbol names.
+    pub fn len(&self) -> usize {
+        self.registry.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.registry.is_empty()
+    }
+
+    // Drop the names of symbols not in live, and string literals no longer
+    // referenced. Ids are never reused, but interning a dropped name again
+    // yields a new id, so live must contain every symbol still in use, see
+    // mark_symbols.
+    pub fn compact(&mut self, live: &HashSet<Symbol>) {
+        let builtin = [self.sym_fn, self.sym_macro, self.sym_quote, self.sym_quasiquote,
+                       self.sym_unquote, self.sym_unquote_splice, self.sym_rest, self.sym_key,
+                       self.sym_else];
+        let keep = |id: &Symbol| live.contains(id) || builtin.contains(id);
+        self.registry.retain(|_, id| keep(id));
+        self.reverse.retain(|id, _| keep(id));
+        self.strings.retain(|s| Rc::strong_count(s) > 1);
+    }
+
+    // The interned name closest to name by edit distance among the symbols
+    // accepted by candidate, if one is close enough to be a likely typo.
+    pub fn similar(&self, name: &str, candidate: impl Fn(Symbol) -> bool) -> Option<&str> {
+        let limit = similarity_limit(name);
+        self.registry.iter()
+            .filter(|(other, id)| other.as_str() != name && candidate(**id))
+            .map(|(other, _)| (edit_distance(name, other), other.as_str()))
+            .filter(|(distance, _)| *distance <= limit)
+            .min()
+            .map(|(_, other)| other)
+    }
+
+    // The symbol named name if it was interned, without interning it.
+    pub fn find(&self, name: &str) -> Option<Symbol> {
+        self.registry.get(name).copied()
+    }
+
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        match self.registry.entry(name.to_string()) {
+            Entry::Occupied(e) => *e.get(),
+            Entry::Vacant(_) => {
+                self.next_id += 1;
+                self.registry.insert(name.to_string(), self.next_id);
+                self.reverse.insert(self.next_id, name.to_string());
+                self.next_id
+            }
+        }
+    }
+
+    // A symbol distinct from every other, named prefix followed by its id.
+    // The name is only used for printing, reading it interns a different
+    // symbol, so macros can bind gensyms without capturing user variables.
+    pub fn gensym(&mut self, prefix: &str) -> Symbol {
+        self.next_id += 1;
+        self.reverse.insert(self.next_id, format!("{}{}", prefix, self.next_id));
+        self.next_id
+    }
+
+    // String literals are shared like symbols, so equal literals point to the
+    // same allocation and cloning them is cheap.
+    pub fn string(&mut self, s: &str) -> Rc<str> {
+        match self.strings.get(s) {
+            Some(rc) => rc.clone(),
+            None => {
+                let rc: Rc<str> = Rc::from(s);
+                self.strings.insert(rc.clone());
+                rc
+            }
+        }
+    }
+
+    pub fn symbol(&mut self, name: &str) -> LispObject {
+        LispObject::Symbol(self.intern(name))
+    }
+
+    pub fn quote(&mut self, obj: LispObject) -> LispObject {
+        LispObject::List(vec![LispObject::Symbol(self.sym_quote), obj])
+    }
+
+    pub fn quasi_quote(&mut self, obj: LispObject) -> LispObject {
+        LispObject::List(vec![LispObject::Symbol(self.sym_quasiquote), obj])
+    }
+
+    pub fn unquote(&mut self, obj: LispObject) -> LispObject {
+        LispObject::List(vec![LispObject::Symbol(self.sym_unquote), obj])
+    }
+
+    pub fn unquote_splice(&mut self, obj: LispObject) -> LispObject {
+        LispObject::List(vec![LispObject::Symbol(self.sym_unquote_splice), obj])
+    }
+
+    fn form_to_string(&self, l: &[LispObject]) -> String {
+        self.elements_to_string(l, DisplayOptions::default(), None)
+    }
+
+    // Elements of a list separated by spaces, eliding the ones after the
+    // first length ones of this and nested lists with "...".
+    fn elements_to_string(&self, l: &[LispObject], options: DisplayOptions,
+                          printer: Option<&dyn Printer>) -> String {
+        let mut elements = l.iter()
+            .take(options.length.unwrap_or(l.len()))
+            .map(|o| self.render(o, options, printer))
+            .collect::<Vec<String>>();
+        if elements.len() < l.len() {
+            elements.push("...".to_string());
+        }
+        elements.join(" ")
+    }
+
+    pub fn serialize_call(&self, head: &str, tail: &[LispObject]) -> String {
+        if tail.is_empty() {
+            format!("({})", head)
+        } else {
+            format!("({} {})", head, self.form_to_string(tail))
+        }
+    }
+
+    pub fn serialize_param_list(&self, lst: &ParamList) -> String {
+        let (pos, rest) = lst;
+        let pos_str = pos.iter()
+            .map(|o| self.as_string(o).unwrap_or("~~uninterned~~"))
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        let rest_str = match rest {
+            Some(s) => format!(" &rest {}", self.as_string(s)
+                               .unwrap_or("~~uninterned~~")),
+            None => "".to_string(),
+        };
+
+        format!("({}{})", pos_str, rest_str)
+    }
+
+    pub fn serialize_object(&self, obj: &LispObject) -> String {
+        self.serialize_limited(obj, DisplayOptions::default())
+    }
+
+    // Serialize obj limited by options, e.g. printing at most length
+    // elements of each list.
+    pub fn serialize_limited(&self, obj: &LispObject, options: DisplayOptions) -> String {
+        self.render(obj, options, None)
+    }
+
+    // Like serialize_limited, but values printer renders are shown its way.
+    pub fn serialize_with(&self, obj: &LispObject, options: DisplayOptions, printer: &dyn Printer)
+                          -> String {
+        self.render(obj, options, Some(printer))
+    }
+
+    fn render(&self, obj: &LispObject, options: DisplayOptions, printer: Option<&dyn Printer>)
+              -> String {
+        if let Some(rendered) = printer.and_then(|printer| printer.print(self, obj)) {
+            return rendered
+        }
+        match obj {
+            LispObject::Symbol(s) => match self.as_string(s) {
+                Some(name) => print_symbol(name),
+                None => "~~uninterned~~".to_string(),
+            },
+            LispObject::Keyword(s) =>
+                format!(":{}", self.as_string(s).unwrap_or("~~uninterned~~")),
+            LispObject::List(l) =>
+                format!("({})", self.elements_to_string(l, options, printer)),
+            LispObject::Values(l) if l.is_empty() =>
+                "(values)".to_string(),
+            LispObject::Values(l) =>
+                format!("(values {})", self.elements_to_string(l, options, printer)),
+            LispObject::Nil =>
+                "nil".to_string(),
+            LispObject::Bool(true) =>
+                "#t".to_string(),
+            LispObject::Bool(false) =>
+                "#f".to_string(),
+            LispObject::SpecialForm(sf) =>
+                format!("~special:{}~", sf),
+            LispObject::String(s) =>
+                print_string(s),
+            LispObject::Number(n) => match options.precision {
+                Some(precision) if n.fract() != 0.0 => format!("{:.*}", precision, n),
+                _ => format!("{}", n),
+            },
+            LispObject::Native(ps, _) =>
+                format!("(~native~{}~)",
+                        self.serialize_param_list(ps)),
+            LispObject::Lambda(l) =>
+                format!("({})", self.form_to_string(&l.source)),
+            LispObject::Map(m) =>
+                format!("{{{}}}", m.iter()
+                        .map(|(k, v)| format!("{} {}", self.render(k, options, printer),
+                                              self.render(v, options, printer)))
+                        .collect::<Vec<String>>()
+                        .join(" ")),
+            LispObject::Handle(h) =>
+                format!("~{}~", h.kind),
+            LispObject::Stream(s) => {
+                let (cells, ends) = s.forced();
+                let heads = cells.iter().map(|cell| cell.head.clone()).collect::<Vec<LispObject>>();
+                let mut elements = self.elements_to_string(&heads, options, printer);
+                if !ends && heads.len() <= options.length.unwrap_or(heads.len()) {
+                    elements.push_str(" ...");
+                }
+                format!("~stream {}~", elements)
+            },
+        }
+    }
+}
+
+impl SerializeSymbol for Symbols {
+    fn as_string(&self, sym: &Symbol) -> Option<&str> {
+        self.reverse.get(sym).map(|s| &s[..])
+    }
+}
+
+// Limits on rendering values for display.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DisplayOptions {
+    // Elements of each list printed, the rest is elided
+    pub length: Option<usize>,
+    // Digits printed after the decimal point of numbers with a fraction
+    pub precision: Option<usize>,
+}
+
+// A local scope created by let or a function call. Scopes are shared, so
+// closures created in a scope keep it alive and see later changes to it.
+pub struct Scope {
+    // Scopes hold few bindings, mostly the parameters of a call, which are
+    // moved in as they are bound instead of being hashed into a map. Later
+    // bindings of the same key take precedence.
+    vars: RefCell<Vec<(Symbol, LispObject)>>,
+    // Bit key % 64 is set for every key in vars, lookups skip scopes that
+    // cannot contain a key without searching them.
+    mask: Cell<u64>,
+    parent: Option<Rc<Scope>>,
+}
+
+// A global definition, constants are defined by defconst and cannot be
+// modified afterwards.
+#[derive(Clone)]
+struct Binding {
+    value: LispObject,
+    constant: bool,
+}
+
+// Global definitions and the chain of local scopes currently evaluated in.
+// Cloning an Env copies the globals, so clones can be modified independently.
+// Scopes are shared, including those captured by closures.
+#[derive(Clone)]
+pub struct Env {
+    globals: HashMap<Symbol, Binding>,
+    scope: Option<Rc<Scope>>,
+    // Watched keys with their handlers, None to print changes
+    watched: HashMap<Symbol, Option<LispObject>>,
+    // Record changes of all bindings, not only watched ones
+    recording: bool,
+    // Changes not yet reported
+    changes: Vec<Change>,
+}
+
+// A change of a binding by set or a definition. old is None if key was
+// unbound.
+#[derive(Clone)]
+pub struct Change {
+    pub key: Symbol,
+    pub old: Option<LispObject>,
+    pub new: LispObject,
+    // Whether a global binding changed
+    pub global: bool,
+}
+
+// Outcome of modifying a binding.
+#[derive(PartialEq)]
+pub enum Assign {
+    Done,
+    Unbound,
+    Constant,
+}
+
+impl Scope {
+    fn mask_bit(key: Symbol) -> u64 {
+        1 << (key % 64)
+    }
+
+    fn may_contain(&self, key: Symbol) -> bool {
+        self.mask.get() & Scope::mask_bit(key) != 0
+    }
+
+    fn position(&self, key: Symbol) -> Option<usize> {
+        if !self.may_contain(key) {
+            return None
+        }
+        self.vars.borrow().iter().rposition(|(k, _)| *k == key)
+    }
+
+    fn mark_symbols(&self, live: &mut HashSet<Symbol>) {
+        for (key, value) in self.vars.borrow().iter() {
+            live.insert(*key);
+            mark_symbols(value, live);
+        }
+        if let Some(parent) = &self.parent {
+            parent.mark_symbols(live);
+        }
+    }
+}
+
+impl Default for Env {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Env {
+    pub fn new() -> Env {
+        Env {
+            globals: HashMap::new(),
+            scope: None,
+            watched: HashMap::new(),
+            recording: false,
+            changes: vec![],
+        }
+    }
+
+    // Enter a new scope binding the pairs of binding.
+    pub fn push_scope(&mut self, binding: Vec<(Symbol, LispObject)>) {
+        let mask = binding.iter().fold(0, |mask, (key, _)| mask | Scope::mask_bit(*key));
+        self.scope = Some(Rc::new(Scope {
+            vars: RefCell::new(binding),
+            mask: Cell::new(mask),
+            parent: self.scope.take(),
+        }));
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.scope = self.scope.take().and_then(|scope| scope.parent.clone());
+    }
+
+    // The current chain of local scopes, captured by closures.
+    pub fn scope(&self) -> Option<Rc<Scope>> {
+        self.scope.clone()
+    }
+
+    // Continue evaluation in scope, returns the scope that was replaced.
+    pub fn enter(&mut self, scope: Option<Rc<Scope>>) -> Option<Rc<Scope>> {
+        std::mem::replace(&mut self.scope, scope)
+    }
+
+    // Bind key in the innermost scope, which are the globals outside of any
+    // let or function.
+    pub fn local(&mut self, key: Symbol, value: LispObject) -> Assign {
+        match &self.scope {
+            Some(scope) => {
+                match scope.position(key) {
+                    Some(index) => scope.vars.borrow_mut()[index].1 = value,
+                    None => {
+                        scope.vars.borrow_mut().push((key, value));
+                        scope.mask.set(scope.mask.get() | Scope::mask_bit(key));
+                    },
+                }
+                Assign::Done
+            },
+            None => self.global(key, value),
+        }
+    }
+
+    // Update the nearest existing binding of key.
+    pub fn set(&mut self, key: Symbol, value: LispObject) -> Assign {
+        let mut scope = self.scope.as_ref();
+        while let Some(s) = scope {
+            if let Some(index) = s.position(key) {
+                let old = std::mem::replace(&mut s.vars.borrow_mut()[index].1, value.clone());
+                self.record(key, Some(old), value, false);
+                return Assign::Done
+            }
+            scope = s.parent.as_ref();
+        }
+        match self.globals.get_mut(&key) {
+            Some(binding) if binding.constant => Assign::Constant,
+            Some(binding) => {
+                let old = std::mem::replace(&mut binding.value, value.clone());
+                self.record(key, Some(old), value, true);
+                Assign::Done
+            },
+            None => Assign::Unbound,
+        }
+    }
+
+    pub fn global(&mut self, key: Symbol, value: LispObject) -> Assign {
+        self.define(key, value, false)
+    }
+
+    // Remove the global binding of key.
+    pub fn unbind(&mut self, key: Symbol) {
+        self.globals.remove(&key);
+    }
+
+    pub fn constant(&mut self, key: Symbol, value: LispObject) -> Assign {
+        self.define(key, value, true)
+    }
+
+    fn define(&mut self, key: Symbol, value: LispObject, constant: bool) -> Assign {
+        match self.globals.get(&key) {
+            Some(binding) if binding.constant => Assign::Constant,
+            _ => {
+                let old = self.globals.insert(key, Binding { value: value.clone(), constant });
+                self.record(key, old.map(|binding| binding.value), value, true);
+                Assign::Done
+            },
+        }
+    }
+
+    // Report changes of key by set and definitions, to handler or printed if
+    // None, replacing a previous watch.
+    pub fn watch(&mut self, key: Symbol, handler: Option<LispObject>) {
+        self.watched.insert(key, handler);
+    }
+
+    // Stop watching key, returns whether it was watched.
+    pub fn unwatch(&mut self, key: Symbol) -> bool {
+        self.watched.remove(&key).is_some()
+    }
+
+    // The handler of a watched key, None to print its changes.
+    pub fn watcher(&self, key: Symbol) -> Option<Option<LispObject>> {
+        self.watched.get(&key).cloned()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    // Record the changes of all bindings, not only the watched ones.
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
+    }
+
+    fn record(&mut self, key: Symbol, old: Option<LispObject>, new: LispObject, global: bool) {
+        if self.recording || self.watched.contains_key(&key) {
+            self.changes.push(Change { key, old, new, global });
+        }
+    }
+
+    // The changes of watched bindings, or all while recording, since the
+    // last call.
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        std::mem::take(&mut self.changes)
+    }
+
+    // Global definitions as (symbol, value, constant).
+    pub fn globals(&self) -> impl Iterator<Item = (Symbol, &LispObject, bool)> {
+        self.globals.iter().map(|(key, binding)| (*key, &binding.value, binding.constant))
+    }
+
+    // Add the symbols of all bindings to live.
+    pub fn mark_symbols(&self, live: &mut HashSet<Symbol>) {
+        for (key, binding) in self.globals.iter() {
+            live.insert(*key);
+            mark_symbols(&binding.value, live);
+        }
+        for (key, handler) in self.watched.iter() {
+            live.insert(*key);
+            if let Some(handler) = handler {
+                mark_symbols(handler, live);
+            }
+        }
+        if let Some(scope) = &self.scope {
+            scope.mark_symbols(live);
+        }
+    }
+
+    pub fn resolve(&self, key: &Symbol) -> Option<LispObject> {
+        let mut scope = self.scope.as_ref();
+        while let Some(s) = scope {
+            if let Some(index) = s.position(*key) {
+                return Some(s.vars.borrow()[index].1.clone())
+            }
+            scope = s.parent.as_ref();
+        }
+        self.globals.get(key).map(|binding| binding.value.clone())
+    }
+}
+
+// Read-only view of the bindings visible in an Env by name, for tools like
+// completion and embedders inspecting an interpreter.
+pub struct EnvView<'a> {
+    env: &'a Env,
+    symbols: &'a Symbols,
+}
+
+impl<'a> EnvView<'a> {
+    pub fn new(env: &'a Env, symbols: &'a Symbols) -> EnvView<'a> {
+        EnvView { env, symbols }
+    }
+
+    // Visible bindings as (name, value), local ones innermost first, then
+    // the globals sorted by name. Shadowed bindings are skipped.
+    pub fn iter_bindings(&self) -> impl Iterator<Item = (&'a str, LispObject)> + 'a {
+        let symbols = self.symbols;
+        self.bindings().into_iter()
+            .filter_map(move |(key, value)| Some((symbols.as_string(&key)?, value)))
+    }
+
+    // The symbols of all visible bindings, sorted by name.
+    pub fn bound_symbols(&self) -> Vec<Symbol> {
+        let mut keys = self.bindings().into_iter()
+            .map(|(key, _)| key)
+            .collect::<Vec<Symbol>>();
+        keys.sort_by_key(|key| self.symbols.as_string(key));
+        keys
+    }
+
+    fn bindings(&self) -> Vec<(Symbol, LispObject)> {
+        let mut seen = HashSet::new();
+        let mut bindings = vec![];
+        let mut scope = self.env.scope.as_ref();
+        while let Some(s) = scope {
+            for (key, value) in s.vars.borrow().iter().rev() {
+                if seen.insert(*key) {
+                    bindings.push((*key, value.clone()));
+                }
+            }
+            scope = s.parent.as_ref();
+        }
+        let mut globals = self.env.globals.iter()
+            .filter(|(key, _)| !seen.contains(*key))
+            .map(|(key, binding)| (*key, binding.value.clone()))
+            .collect::<Vec<(Symbol, LispObject)>>();
+        globals.sort_by_key(|(key, _)| self.symbols.as_string(key));
+        bindings.extend(globals);
+        bindings
+    }
+
+    pub fn lookup_str(&self, name: &str) -> Option<LispObject> {
+        self.env.resolve(&self.symbols.find(name)?)
+    }
+}
+
+// The largest edit distance of a string similar enough to name to be a likely
+// typo. Names shorter than three characters are close to too many others.
+pub(crate) fn similarity_limit(name: &str) -> usize {
+    (name.chars().count() / 3).min(3)
+}
+
+// Levenshtein distance between a and b.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut row = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn set_native(sym: &mut Symbols, env: &mut Env, def: NativeDef) {
+    // Intern Arguments
+    let pos_args = def.positional.iter()
+        .map(|s| sym.intern(s))
+        .collect::<Vec<Symbol>>();
+    let rest_arg = def.rest.map(|s| sym.intern(s));
+    env.global(sym.intern(def.name),
+               LispObject::Native((pos_args, rest_arg), def.func));
+}
+
+fn set_special(sym: &mut Symbols, env: &mut Env, sf: SpecialForm) {
+    env.global(sym.intern(&sf.to_string()),
+               LispObject::SpecialForm(sf));
+}
+
+// Groups of natives with effects outside of the interpreter, on files, time,
+// databases, the network or the desktop, by name. InterpreterConfig::features
+// selects the groups bound in an interpreter.
+pub fn features() -> Vec<(&'static str, Vec<NativeDef>)> {
+    #[allow(unused_mut)]
+    let mut desktop = vec![notify::BEEP];
+    #[cfg(feature = "desktop")]
+    desktop.extend([clipboard::CLIPBOARD_GET, clipboard::CLIPBOARD_SET, notify::NOTIFY]);
+    #[allow(unused_mut)]
+    let mut features = vec![
+        ("files", vec![native::LOAD, watch::WATCH_PATH]),
+        ("timers", vec![timer::AFTER, timer::EVERY, timer::CANCEL, timer::SLEEP]),
+        ("desktop", desktop),
+    ];
+    #[cfg(feature = "sqlite")]
+    features.push(("sqlite", vec![sqlite::DB_OPEN, sqlite::DB_QUERY, sqlite::DB_EXEC]));
+    #[cfg(feature = "net")]
+    features.push(("net", vec![net::HTTP_SERVE, net::WS_CONNECT, net::WS_SEND, net::WS_RECV]));
+    features
+}
+
+// Bind the natives of the features named in enabled, or all if None, and
+// unbind the natives of the others.
+pub fn select_features(symbols: &mut Symbols, env: &mut Env, enabled: Option<&[String]>) {
+    for (name, natives) in features() {
+        let bound = enabled.is_none_or(|enabled| enabled.iter().any(|e| e == name));
+        for def in natives {
+            if bound {
+                set_native(symbols, env, def);
+            } else {
+                env.unbind(symbols.intern(def.name));
+            }
+        }
+    }
+}
+
+// Add the symbols object refers to to live. Values held by handles are
+// opaque and not visited.
+pub fn mark_symbols(object: &LispObject, live: &mut HashSet<Symbol>) {
+    match object {
+        LispObject::Symbol(s) | LispObject::Keyword(s) => {
+            live.insert(*s);
+        },
+        LispObject::List(l) => l.iter().for_each(|o| mark_symbols(o, live)),
+        LispObject::Values(l) => l.iter().for_each(|o| mark_symbols(o, live)),
+        LispObject::Stream(s) => {
+            let (cells, _) = s.forced();
+            for cell in cells {
+                mark_symbols(&cell.head, live);
+                match &*cell.tail.borrow() {
+                    StreamTail::Delayed(thunk) => mark_symbols(thunk, live),
+                    StreamTail::Map(f, source) | StreamTail::Filter(f, source) => {
+                        mark_symbols(f, live);
+                        mark_symbols(&LispObject::Stream(source.clone()), live);
+                    },
+                    StreamTail::Forced(rest) if !matches!(rest, LispObject::Stream(_)) =>
+                        mark_symbols(rest, live),
+                    StreamTail::Forced(_) => (),
+                }
+            }
+        },
+        LispObject::Native((positional, rest), _) => {
+            live.extend(positional.iter().chain(rest.iter()));
+        },
+        LispObject::Lambda(lambda) => {
+            live.extend(lambda.params.0.iter().chain(lambda.params.1.iter()).chain(lambda.keys.iter()));
+            lambda.body.iter().chain(lambda.source.iter()).for_each(|o| mark_symbols(o, live));
+            if let Some(scope) = &lambda.scope {
+                scope.mark_symbols(live);
+            }
+        },
+        LispObject::Map(m) => m.iter().for_each(|(k, v)| {
+            mark_symbols(k, live);
+            mark_symbols(v, live);
+        }),
+        _ => (),
+    }
+}
+
+pub fn create_root(symbols: &mut Symbols) -> Env {
+    let mut root = Env::new();
+    set_special(symbols, &mut root, SpecialForm::Def);
+    set_special(symbols, &mut root, SpecialForm::Set);
+    set_special(symbols, &mut root, SpecialForm::Local);
+    set_special(symbols, &mut root, SpecialForm::If);
+    set_special(symbols, &mut root, SpecialForm::Let);
+    set_special(symbols, &mut root, SpecialForm::LetStar);
+    set_special(symbols, &mut root, SpecialForm::Letrec);
+    set_special(symbols, &mut root, SpecialForm::LetValues);
+    set_special(symbols, &mut root, SpecialForm::Begin);
+    set_special(symbols, &mut root, SpecialForm::Quote);
+    set_special(symbols, &mut root, SpecialForm::Quasiquote);
+    set_special(symbols, &mut root, SpecialForm::Fn);
+    set_special(symbols, &mut root, SpecialForm::Macro);
+    set_special(symbols, &mut root, SpecialForm::Defspec);
+    set_special(symbols, &mut root, SpecialForm::Defconst);
+    set_special(symbols, &mut root, SpecialForm::And);
+    set_special(symbols, &mut root, SpecialForm::Or);
+    set_special(symbols, &mut root, SpecialForm::Loop);
+    set_special(symbols, &mut root, SpecialForm::Recur);
+    set_special(symbols, &mut root, SpecialForm::Match);
+    set_special(symbols, &mut root, SpecialForm::EvalWhen);
+    set_special(symbols, &mut root, SpecialForm::UnwindProtect);
+    set_special(symbols, &mut root, SpecialForm::Try);
+    set_native (symbols, &mut root, native::ADD);
+    set_native (symbols, &mut root, native::MULTIPLY);
+    set_native (symbols, &mut root, native::SUBTRACT);
+    set_native (symbols, &mut root, native::EQUAL);
+    set_native (symbols, &mut root, native::LESS);
+    set_native (symbols, &mut root, native::EQUAL_P);
+    set_native (symbols, &mut root, native::EQ_P);
+    set_native (symbols, &mut root, native::NOT);
+    set_native (symbols, &mut root, native::FIRST);
+    set_native (symbols, &mut root, native::REST);
+    set_native (symbols, &mut root, native::LIST);
+    set_native (symbols, &mut root, native::VALUES);
+    set_native (symbols, &mut root, native::CONCAT);
+    set_native (symbols, &mut root, native::IS_LIST);
+    set_native (symbols, &mut root, native::LIST_P);
+    set_native (symbols, &mut root, native::NUMBER_P);
+    set_native (symbols, &mut root, native::STRING_P);
+    set_native (symbols, &mut root, native::SYMBOL_P);
+    set_native (symbols, &mut root, native::KEYWORD_P);
+    set_native (symbols, &mut root, native::BOOL_P);
+    set_native (symbols, &mut root, native::NIL_P);
+    set_native (symbols, &mut root, native::MAP_P);
+    set_native (symbols, &mut root, native::FN_P);
+    set_native (symbols, &mut root, native::LENGTH);
+    set_native (symbols, &mut root, native::APPLY);
+    set_native (symbols, &mut root, native::MAP);
+    set_native (symbols, &mut root, native::REDUCE);
+    set_native (symbols, &mut root, native::FILTER);
+    set_native (symbols, &mut root, native::FOR_EACH);
+    set_native (symbols, &mut root, native::POSTWALK);
+    set_native (symbols, &mut root, native::PREWALK);
+    set_native (symbols, &mut root, native::MAKE_MAP);
+    set_native (symbols, &mut root, native::GET);
+    set_native (symbols, &mut root, native::ASSOC);
+    set_native (symbols, &mut root, native::KEYS);
+    set_native (symbols, &mut root, native::ALIST_GET);
+    set_native (symbols, &mut root, native::ALIST_SET);
+    set_native (symbols, &mut root, native::ALIST_TO_MAP);
+    set_native (symbols, &mut root, native::PLIST_GET);
+    set_native (symbols, &mut root, native::PLIST_TO_MAP);
+    set_native (symbols, &mut root, native::DIFF);
+    set_native (symbols, &mut root, native::ASSERT_EQUAL);
+    set_native (symbols, &mut root, native::ERROR);
+    set_native (symbols, &mut root, native::CHECK_SPECS);
+    set_native (symbols, &mut root, native::SET_OPTION);
+    set_native (symbols, &mut root, native::GENSYM);
+    set_native (symbols, &mut root, native::BOUND_SYMBOLS);
+    set_native (symbols, &mut root, native::READ_STRING);
+    set_native (symbols, &mut root, syntax::SYNTAX_EXPAND);
+    set_native (symbols, &mut root, native::EVAL);
+    set_native (symbols, &mut root, native::CALL_EC);
+    set_native (symbols, &mut root, native::MACROEXPAND_1);
+    set_native (symbols, &mut root, native::MACROEXPAND);
+    set_native (symbols, &mut root, native::HISTORY);
+    set_native (symbols, &mut root, native::WATCH);
+    set_native (symbols, &mut root, native::UNWATCH);
+    set_native (symbols, &mut root, native::HISTORY_OF);
+    set_native (symbols, &mut root, promise::MAKE_PROMISE);
+    set_native (symbols, &mut root, promise::FORCE);
+    set_native (symbols, &mut root, promise::PROMISE_P);
+    set_native (symbols, &mut root, stream::MAKE_STREAM);
+    set_native (symbols, &mut root, stream::STREAM_FIRST);
+    set_native (symbols, &mut root, stream::STREAM_REST);
+    set_native (symbols, &mut root, stream::STREAM_P);
+    set_native (symbols, &mut root, stream::STREAM_MAP);
+    set_native (symbols, &mut root, stream::STREAM_FILTER);
+    set_native (symbols, &mut root, stream::STREAM_TAKE);
+    set_native (symbols, &mut root, generator::GENERATOR_BODY);
+    set_native (symbols, &mut root, generator::MAKE_GENERATOR);
+    set_native (symbols, &mut root, generator::NEXT);
+    set_native (symbols, &mut root, generator::GENERATOR_P);
+    set_native (symbols, &mut root, generator::YIELD);
+    set_native (symbols, &mut root, matrix::MAKE_MATRIX);
+    set_native (symbols, &mut root, matrix::LIST_TO_MATRIX);
+    set_native (symbols, &mut root, matrix::MATRIX_TO_LIST);
+    set_native (symbols, &mut root, matrix::MATRIX_P);
+    set_native (symbols, &mut root, matrix::MAT_SHAPE);
+    set_native (symbols, &mut root, matrix::MAT_REF);
+    set_native (symbols, &mut root, matrix::MAT_SET);
+    set_native (symbols, &mut root, matrix::MAT_MUL);
+    set_native (symbols, &mut root, matrix::TRANSPOSE);
+    set_native (symbols, &mut root, matrix::MAT_ADD);
+    set_native (symbols, &mut root, matrix::MAT_SUB);
+    set_native (symbols, &mut root, matrix::MAT_MUL_ELEMENTS);
+    set_native (symbols, &mut root, matrix::MAT_SCALE);
+    set_native (symbols, &mut root, matrix::MAT_MAP);
+    set_native (symbols, &mut root, matrix::DOT);
+    set_native (symbols, &mut root, stats::MEAN);
+    set_native (symbols, &mut root, stats::MEDIAN);
+    set_native (symbols, &mut root, stats::VARIANCE);
+    set_native (symbols, &mut root, stats::STDDEV);
+    set_native (symbols, &mut root, stats::PERCENTILE);
+    set_native (symbols, &mut root, stats::HISTOGRAM);
+    set_native (symbols, &mut root, graph::TOPO_SORT);
+    set_native (symbols, &mut root, graph::SHORTEST_PATH);
+    set_native (symbols, &mut root, template::RENDER_TEMPLATE);
+    set_native (symbols, &mut root, xml::XML_PARSE);
+    set_native (symbols, &mut root, xml::XML_WRITE);
+    set_native (symbols, &mut root, xml::XML_TEXT);
+    set_native (symbols, &mut root, xml::SELECT);
+    set_native (symbols, &mut root, collections::MAKE_STACK);
+    set_native (symbols, &mut root, collections::STACK_PUSH);
+    set_native (symbols, &mut root, collections::STACK_POP);
+    set_native (symbols, &mut root, collections::STACK_PEEK);
+    set_native (symbols, &mut root, collections::STACK_LENGTH);
+    set_native (symbols, &mut root, collections::STACK_TO_LIST);
+    set_native (symbols, &mut root, collections::MAKE_QUEUE);
+    set_native (symbols, &mut root, collections::ENQUEUE);
+    set_native (symbols, &mut root, collections::DEQUEUE);
+    set_native (symbols, &mut root, collections::QUEUE_PEEK);
+    set_native (symbols, &mut root, collections::QUEUE_LENGTH);
+    set_native (symbols, &mut root, collections::QUEUE_TO_LIST);
+    set_native (symbols, &mut root, collections::MAKE_HEAP);
+    set_native (symbols, &mut root, collections::HEAP_PUSH);
+    set_native (symbols, &mut root, collections::HEAP_POP);
+    set_native (symbols, &mut root, collections::HEAP_PEEK);
+    set_native (symbols, &mut root, collections::HEAP_LENGTH);
+    set_native (symbols, &mut root, format::FORMAT_NUMBER);
+    set_native (symbols, &mut root, format::FORMAT_BYTES);
+    set_native (symbols, &mut root, format::FORMAT_DURATION);
+    set_native (symbols, &mut root, fuzzy::STRING_DISTANCE);
+    set_native (symbols, &mut root, fuzzy::STRING_SIMILAR_P);
+    set_native (symbols, &mut root, fuzzy::FUZZY_MATCH);
+    set_native (symbols, &mut root, prop::GEN_INT);
+    set_native (symbols, &mut root, prop::GEN_BOOL);
+    set_native (symbols, &mut root, prop::GEN_LIST);
+    set_native (symbols, &mut root, prop::GEN_STRING);
+    set_native (symbols, &mut root, prop::GEN_ELEMENTS);
+    set_native (symbols, &mut root, prop::GEN_ONE_OF);
+    set_native (symbols, &mut root, prop::GEN_TUPLE);
+    set_native (symbols, &mut root, prop::GEN_MAP);
+    set_native (symbols, &mut root, prop::GEN_SAMPLE);
+    set_native (symbols, &mut root, prop::CHECK_PROP);
+    // The natives with effects outside of the interpreter are bound by feature
+    select_features(symbols, &mut root, None);
+    #[cfg(feature = "collation")]
+    {
+        set_native (symbols, &mut root, collation::STRING_COMPARE);
+        set_native (symbols, &mut root, collation::COLLATE);
+    }
+    #[cfg(feature = "markdown")]
+    set_native (symbols, &mut root, markdown::MARKDOWN_TO_HTML);
+    #[cfg(feature = "graphics")]
+    {
+        set_native (symbols, &mut root, graphics::MAKE_CANVAS);
+        set_native (symbols, &mut root, graphics::DRAW_LINE);
+        set_native (symbols, &mut root, graphics::DRAW_CIRCLE);
+        set_native (symbols, &mut root, graphics::DRAW_RECT);
+        set_native (symbols, &mut root, graphics::RENDER_SVG);
+        set_native (symbols, &mut root, graphics::TURTLE_FORWARD);
+        set_native (symbols, &mut root, graphics::TURTLE_TURN);
+        set_native (symbols, &mut root, graphics::TURTLE_PEN);
+    }
+    root
+}
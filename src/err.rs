@@ -1,91 +1,154 @@
-use ansi_term::Colour::{Blue, Red};
-use std::fmt;
-use crate::{
-    env::Symbols,
-    reader::ReadError,
-    lisp_object::{LispObject, EvalError},
-};
-
-fn print_underline(start: usize, end: usize, max_len: Option<usize>) {
-    eprintln!(" {:indent$} {} {}{}",
-              "",
-              Blue.paint("|"), " ".repeat(start),
-              Red.paint("^".repeat(end - start)),
-              indent=max_len.unwrap_or(0));
-}
-
-fn print_range(input: &str, start: usize, end: usize, place: Option<String>, max_len: Option<usize>) {
-    eprintln!(" {:indent$} {} {}",
-              place.unwrap_or("".to_string()), Blue.paint("|"), input,
-              indent=max_len.unwrap_or(0));
-    print_underline(start, end, max_len);
-}
-
-pub fn print_message(displayable: &dyn fmt::Display) {
-    eprintln!("{}: {}", Red.paint("Error"), displayable);
-}
-
-pub fn handle_read_error(input: &str, e: ReadError) -> Result<(), ReadError> {
-    match e {
-        ReadError::UnknownCharacter((start, end)) => {
-            print_message(&e);
-            print_range(input, start, end, None, None);
-        },
-        ReadError::UnexpectedRbrace((start, end)) => {
-            print_message(&e);
-            print_range(input, start, end, None, None);
-        },
-        ReadError::UnexpectedEndOfString =>
-            print_message(&e),
-        ReadError::InternalError =>
-            return Err(ReadError::InternalError),
-    }
-    Ok(())
-}
-
-fn handle_failed_form(sym: &Symbols, form: &LispObject, stack: &[usize])
-                      -> (String, usize, usize) {
-    if stack.len() == 0 {
-        let string = sym.serialize_object(form);
-        let len = string.len();
-        (string, 0, len)
-    } else {
-        match form {
-            LispObject::List(l) => {
-                let stack_len = stack.len() - 1;
-                let offset = stack[stack_len];
-                let mut start = 0;
-                let mut end = 0;
-                let mut string = "(".to_string();
-                for (index, object) in l.iter().enumerate() {
-                    if index == offset {
-                        let (s, off0, off1) = handle_failed_form(sym, object, &stack[0..stack_len]);
-                        start = off0 + string.len();
-                        end = off1 + string.len();
-                        string.push_str(&s);
-                    } else {
-                        string.push_str(&sym.serialize_object(object));
-                    }
-                    if index == l.len() - 1 {
-                        string.push_str(")");
-                    } else {
-                        string.push_str(" ");
-                    }
-                }
-                (string, start, end)
-            },
-            _ => handle_failed_form(sym, form, &stack[0..0])
-        }
-    }
-}
-
-pub fn handle_eval_error(sym: &Symbols, error: EvalError) {
-    print_message(&error);
-    let place_len = error.frames.iter()
-        .map(|(_, _, place)| place.as_ref().map(|p| p.len()).unwrap_or(0))
-        .max();
-    for (form, trace, place) in error.frames {
-        let (string, start, end) = handle_failed_form(sym, &form, &trace);
-        print_range(&string, start, end, place, place_len);
-    }
-}
+use ansi_term::Colour::{self, Blue, Red, Yellow};
+use std::fmt;
+use crate::{
+    env::Symbols,
+    reader::ReadError,
+    lisp_object::{LispObject, EvalError},
+};
+
+// text in colour, or plain if color is off.
+pub(crate) fn paint(color: bool, colour: Colour, text: &str) -> String {
+    if color {
+        colour.paint(text).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+fn print_underline(color: bool, start: usize, end: usize, max_len: Option<usize>) {
+    eprintln!(" {:indent$} {} {}{}",
+              "",
+              paint(color, Blue, "|"), " ".repeat(start),
+              paint(color, Red, &"^".repeat(end - start)),
+              indent=max_len.unwrap_or(0));
+}
+
+fn print_range(color: bool, input: &str, start: usize, end: usize, place: Option<String>,
+               max_len: Option<usize>) {
+    eprintln!(" {:indent$} {} {}",
+              place.unwrap_or("".to_string()), paint(color, Blue, "|"), input,
+              indent=max_len.unwrap_or(0));
+    print_underline(color, start, end, max_len);
+}
+
+pub fn print_message(color: bool, displayable: &dyn fmt::Display) {
+    eprintln!("{}: {}", paint(color, Red, "Error"), displayable);
+}
+
+pub fn print_warning(color: bool, displayable: &dyn fmt::Display) {
+    eprintln!("{}: {}", paint(color, Yellow, "Warning"), displayable);
+}
+
+pub fn handle_read_error(color: bool, input: &str, e: ReadError) -> Result<(), ReadError> {
+    match e {
+        ReadError::UnknownCharacter((start, end)) => {
+            print_message(color, &e);
+            print_range(color, input, start, end, None, None);
+        },
+        ReadError::UnexpectedRbrace((start, end)) => {
+            print_message(color, &e);
+            print_range(color, input, start, end, None, None);
+        },
+        ReadError::UnexpectedEndOfString | ReadError::UnexpectedEndOfInput =>
+            print_message(color, &e),
+        ReadError::InternalError =>
+            return Err(ReadError::InternalError),
+    }
+    Ok(())
+}
+
+// Frames wider than this are rendered with the forms around the failing one
+// elided.
+const FRAME_WIDTH: usize = 80;
+const ELLIPSIS: &str = "...";
+
+// Render form with the sub-form at the position stack leads to underlined,
+// returning the rendering and the range of the sub-form in it.
+fn handle_failed_form(sym: &Symbols, form: &LispObject, stack: &[usize])
+                      -> (String, usize, usize) {
+    let rendered = render_failed_form(sym, form, stack, false);
+    if rendered.0.len() <= FRAME_WIDTH {
+        rendered
+    } else {
+        render_failed_form(sym, form, stack, true)
+    }
+}
+
+// If elide is set, only the forms on the way to the failing sub-form are
+// rendered, along with atoms in head position so their forms remain
+// recognizable, e.g. (defun f ... (if ... (bad-call here) ...)). The form
+// directly containing the sub-form is kept whole if it is short enough.
+fn render_failed_form(sym: &Symbols, form: &LispObject, stack: &[usize], elide: bool)
+                      -> (String, usize, usize) {
+    match (form, stack.split_last()) {
+        (LispObject::List(l), Some((offset, stack))) => {
+            let mut parts: Vec<String> = vec![];
+            let mut start = 0;
+            let mut end = 0;
+            let elide = elide && (!stack.is_empty() ||
+                                  sym.serialize_object(form).len() > FRAME_WIDTH / 2);
+            for (index, object) in l.iter().enumerate() {
+                let is_head_atom = index == 0 && !matches!(object, LispObject::List(_));
+                if index == *offset {
+                    let (s, off0, off1) = render_failed_form(sym, object, stack, elide);
+                    let position = 1 + parts.iter().map(|part| part.len() + 1).sum::<usize>();
+                    start = position + off0;
+                    end = position + off1;
+                    parts.push(s);
+                } else if elide && !is_head_atom {
+                    if parts.last().map(String::as_str) != Some(ELLIPSIS) {
+                        parts.push(ELLIPSIS.to_string());
+                    }
+                } else {
+                    parts.push(sym.serialize_object(object));
+                }
+            }
+            (format!("({})", parts.join(" ")), start, end)
+        },
+        _ => {
+            let string = sym.serialize_object(form);
+            let len = string.len();
+            (string, 0, len)
+        },
+    }
+}
+
+// Number of frames printed for an error, the innermost and outermost half
+// of them are shown and the frames in between elided.
+pub const FRAME_LIMIT: usize = 10;
+
+pub fn handle_eval_error(color: bool, sym: &Symbols, error: &EvalError) {
+    print_frames(color, sym, error, Some(FRAME_LIMIT));
+}
+
+// Print error with at most limit of its frames.
+pub fn print_frames(color: bool, sym: &Symbols, error: &EvalError, limit: Option<usize>) {
+    print_message(color, error);
+    let place_len = error.frames.iter()
+        .map(|(_, _, place)| place.as_ref().map(|p| p.len()).unwrap_or(0))
+        .max();
+    let (inner, outer) = match limit {
+        Some(limit) if error.frames.len() > limit =>
+            (limit - limit / 2, limit / 2),
+        _ => (error.frames.len(), 0),
+    };
+    for (form, trace, place) in &error.frames[..inner] {
+        let (string, start, end) = handle_failed_form(sym, form, trace);
+        print_range(color, &string, start, end, place.clone(), place_len);
+    }
+    if outer > 0 || inner < error.frames.len() {
+        eprintln!(" {:indent$} {} … {} frames elided, use :backtrace full to show them …",
+                  "", paint(color, Blue, "|"), error.frames.len() - inner - outer,
+                  indent=place_len.unwrap_or(0));
+        for (form, trace, place) in &error.frames[error.frames.len() - outer..] {
+            let (string, start, end) = handle_failed_form(sym, form, trace);
+            print_range(color, &string, start, end, place.clone(), place_len);
+        }
+    }
+}
+
+pub fn print_span(color: bool, sym: &Symbols, form: &LispObject, trace: &[usize], place: Option<String>) {
+    let (string, start, end) = handle_failed_form(sym, form, trace);
+    let place_len = place.as_ref().map(|p| p.len());
+    print_range(color, &string, start, end, place, place_len);
+}
@@ -0,0 +1,87 @@
+use crate::{
+    interpreter::{ExecError, Interpreter},
+    lisp_object::LispObject,
+};
+
+// Example programs bundled with the binary. The first comment lines describe
+// the example, a line `;; expect: <value>` states the value of the last form.
+pub struct Example {
+    pub name: &'static str,
+    pub source: &'static str,
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example { name: "arithmetic", source: include_str!("../gallery/arithmetic.lisp") },
+    Example { name: "lists", source: include_str!("../gallery/lists.lisp") },
+    Example { name: "scopes", source: include_str!("../gallery/scopes.lisp") },
+    Example { name: "functions", source: include_str!("../gallery/functions.lisp") },
+    Example { name: "macros", source: include_str!("../gallery/macros.lisp") },
+    Example { name: "higher-order", source: include_str!("../gallery/higher-order.lisp") },
+    Example { name: "closures", source: include_str!("../gallery/closures.lisp") },
+    Example { name: "quasiquote", source: include_str!("../gallery/quasiquote.lisp") },
+    Example { name: "tail-calls", source: include_str!("../gallery/tail-calls.lisp") },
+    Example { name: "properties", source: include_str!("../gallery/properties.lisp") },
+    Example { name: "loops", source: include_str!("../gallery/loops.lisp") },
+    Example { name: "patterns", source: include_str!("../gallery/patterns.lisp") },
+    Example { name: "keywords", source: include_str!("../gallery/keywords.lisp") },
+    Example { name: "values", source: include_str!("../gallery/values.lisp") },
+    Example { name: "eval-when", source: include_str!("../gallery/eval-when.lisp") },
+    Example { name: "errors", source: include_str!("../gallery/errors.lisp") },
+    Example { name: "conditions", source: include_str!("../gallery/conditions.lisp") },
+    Example { name: "escapes", source: include_str!("../gallery/escapes.lisp") },
+    Example { name: "apply", source: include_str!("../gallery/apply.lisp") },
+    Example { name: "reading", source: include_str!("../gallery/reading.lisp") },
+    Example { name: "gensym", source: include_str!("../gallery/gensym.lisp") },
+    Example { name: "syntax", source: include_str!("../gallery/syntax.lisp") },
+    Example { name: "defn", source: include_str!("../gallery/defn.lisp") },
+    Example { name: "formatting", source: include_str!("../gallery/formatting.lisp") },
+    Example { name: "fuzzy", source: include_str!("../gallery/fuzzy.lisp") },
+    Example { name: "walk", source: include_str!("../gallery/walk.lisp") },
+    Example { name: "alists", source: include_str!("../gallery/alists.lisp") },
+    Example { name: "comprehensions", source: include_str!("../gallery/comprehensions.lisp") },
+    Example { name: "streams", source: include_str!("../gallery/streams.lisp") },
+    Example { name: "queues", source: include_str!("../gallery/queues.lisp") },
+    Example { name: "lazy", source: include_str!("../gallery/lazy.lisp") },
+    Example { name: "heaps", source: include_str!("../gallery/heaps.lisp") },
+    Example { name: "generators", source: include_str!("../gallery/generators.lisp") },
+    Example { name: "matrices", source: include_str!("../gallery/matrices.lisp") },
+    Example { name: "equality", source: include_str!("../gallery/equality.lisp") },
+    Example { name: "statistics", source: include_str!("../gallery/statistics.lisp") },
+    Example { name: "graphs", source: include_str!("../gallery/graphs.lisp") },
+    Example { name: "templates", source: include_str!("../gallery/templates.lisp") },
+    Example { name: "nil", source: include_str!("../gallery/nil.lisp") },
+    Example { name: "xml", source: include_str!("../gallery/xml.lisp") },
+    Example { name: "watching", source: include_str!("../gallery/watching.lisp") },
+    Example { name: "history", source: include_str!("../gallery/history.lisp") },
+    Example { name: "sequential-bindings", source: include_str!("../gallery/sequential-bindings.lisp") },
+    Example { name: "local-recursion", source: include_str!("../gallery/local-recursion.lisp") },
+    Example { name: "specs", source: include_str!("../gallery/specs.lisp") },
+    Example { name: "diffs", source: include_str!("../gallery/diffs.lisp") },
+    Example { name: "expansions", source: include_str!("../gallery/expansions.lisp") },
+];
+
+const EXPECT: &str = ";; expect:";
+
+impl Example {
+    pub fn description(&self) -> String {
+        self.source.lines()
+            .take_while(|line| line.starts_with(";;") && !line.starts_with(EXPECT))
+            .map(|line| line.trim_start_matches(';').trim())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    pub fn expected(&self) -> Option<&'static str> {
+        self.source.lines()
+            .find(|line| line.starts_with(EXPECT))
+            .map(|line| line[EXPECT.len()..].trim())
+    }
+
+    pub fn run(&self, interpreter: &mut Interpreter) -> Result<LispObject, ExecError> {
+        interpreter.eval_source(self.source)
+    }
+}
+
+pub fn find(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|example| example.name == name)
+}
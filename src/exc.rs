@@ -1,20 +1,191 @@
-
-use crate::lisp_object::{EvalError};
-
-pub fn apply_unimpl() -> EvalError {
-    EvalError::new("apply only implemented for Native, Lambda and Special Form".to_string())
-}
-
-pub fn apply_empty() -> EvalError {
-    EvalError::new("apply received empty form".to_string())
-}
-
-pub fn unbound_symbol(sym: Option<&str>) -> EvalError {
-    EvalError::new(format!("Unbound symbol '{}'",
-                           sym.unwrap_or("~~uninterned~~")))
-}
-
-pub fn unexpected_special_form() -> EvalError {
-    EvalError::new(
-        format!("Unexpected special form. You are maybe missing a quote."))
-}
+
+use crate::{
+    condition,
+    lisp_object::{ErrorKind, EvalError, SpecialForm},
+};
+
+pub fn apply_unimpl() -> EvalError {
+    EvalError::new("apply only implemented for Native, Lambda and Special Form".to_string())
+        .with_condition(condition::TYPE_ERROR)
+}
+
+pub fn apply_macro() -> EvalError {
+    EvalError::new("macros cannot be applied to evaluated arguments".to_string())
+        .with_condition(condition::TYPE_ERROR)
+}
+
+pub fn apply_special_form(sf: &SpecialForm) -> EvalError {
+    EvalError::new(format!("special form {} cannot be applied to evaluated arguments", sf))
+        .with_condition(condition::TYPE_ERROR)
+}
+
+pub fn apply_empty() -> EvalError {
+    EvalError::new("apply received empty form".to_string())
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn unbound_symbol(sym: Option<&str>, similar: Option<&str>) -> EvalError {
+    let name = sym.unwrap_or("~~uninterned~~");
+    let message = match similar {
+        Some(similar) => format!("Unbound symbol '{}', did you mean `{}`?", name, similar),
+        None => format!("Unbound symbol '{}'", name),
+    };
+    EvalError::new(message)
+        .with_condition(condition::UNBOUND_ERROR)
+}
+
+pub fn unexpected_special_form() -> EvalError {
+    EvalError::new(
+        "Unexpected special form. You are maybe missing a quote.".to_string())
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn set_unbound(sym: Option<&str>) -> EvalError {
+    EvalError::new(format!("Cannot set unbound symbol '{}', use def or local to introduce it",
+                           sym.unwrap_or("~~uninterned~~")))
+        .with_condition(condition::UNBOUND_ERROR)
+}
+
+pub fn duplicate_param(sym: Option<&str>) -> EvalError {
+    EvalError::new(format!("Duplicate parameter '{}' in parameter list",
+                           sym.unwrap_or("~~uninterned~~")))
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn duplicate_rest() -> EvalError {
+    EvalError::new("&rest may only appear once in parameter list".to_string())
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn key_with_rest() -> EvalError {
+    EvalError::new("&key cannot be combined with &rest in parameter list".to_string())
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn keyword_expected(actual: String) -> EvalError {
+    EvalError::new(format!("Expected a keyword argument, got {}", actual))
+        .with_condition(condition::ARITY_ERROR)
+}
+
+pub fn unknown_keyword(name: Option<&str>) -> EvalError {
+    EvalError::new(format!("Unknown keyword argument :{}", name.unwrap_or("~~uninterned~~")))
+        .with_condition(condition::ARITY_ERROR)
+}
+
+pub fn duplicate_keyword(name: Option<&str>) -> EvalError {
+    EvalError::new(format!("Keyword argument :{} given twice", name.unwrap_or("~~uninterned~~")))
+        .with_condition(condition::ARITY_ERROR)
+}
+
+pub fn keyword_without_value(name: Option<&str>) -> EvalError {
+    EvalError::new(format!("Keyword argument :{} is missing its value", name.unwrap_or("~~uninterned~~")))
+        .with_condition(condition::ARITY_ERROR)
+}
+
+pub fn unknown_situation(situation: String) -> EvalError {
+    EvalError::new(format!("Expected :expand or :execute as eval-when situation, got {}", situation))
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn try_without_catch() -> EvalError {
+    EvalError::new("special form try expects its body to be followed by (catch [types] sym &rest handler) clauses"
+                   .to_string())
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn error_without_message() -> EvalError {
+    EvalError::new("error expects a message after the condition type".to_string())
+        .with_condition(condition::ARITY_ERROR)
+}
+
+pub fn catch_type_expected(actual: String) -> EvalError {
+    EvalError::new(format!("Expected a keyword or a list of keywords as catch types, got {}", actual))
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn circular_load(chain: &[&str]) -> EvalError {
+    EvalError::new(format!("Circular load of {}", chain.join(" -> ")))
+        .with_condition(condition::PROGRAM_ERROR)
+}
+
+pub fn unknown_option(option: &str) -> EvalError {
+    EvalError::new(format!("Unknown option :{}, expected :legacy-if, :strict-predicates, :check-specs, \
+                            :step-limit, :max-depth, :print-length, :float-precision, :color, :fuel \
+                            or :record-history",
+                           option))
+        .with_condition(condition::TYPE_ERROR)
+}
+
+pub fn invalid_option(option: &str, actual: String) -> EvalError {
+    EvalError::new(format!("Option :{} expects a {} integer or #f, got {}", option,
+                           if option == "float-precision" { "non-negative" } else { "positive" },
+                           actual))
+        .with_condition(condition::TYPE_ERROR)
+}
+
+pub fn incomplete_input() -> EvalError {
+    EvalError::new("Unexpected end of input, a list is missing its closing brace".to_string())
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn unquote_splice_outside_list() -> EvalError {
+    EvalError::new("unquote-splice must appear inside a list".to_string())
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn assign_constant(sym: Option<&str>) -> EvalError {
+    EvalError::new(format!("Cannot modify constant '{}'",
+                           sym.unwrap_or("~~uninterned~~")))
+}
+
+pub fn recur_outside_loop() -> EvalError {
+    EvalError::new("recur must appear inside a loop".to_string())
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn recur_not_in_tail_position() -> EvalError {
+    EvalError::new("recur must be in tail position of its loop".to_string())
+        .with_condition(condition::SYNTAX_ERROR)
+}
+
+pub fn recur_arity(expected: usize, actual: usize) -> EvalError {
+    EvalError::new(format!("recur expected {} values for the loop bindings, got {}", expected, actual))
+        .with_condition(condition::ARITY_ERROR)
+}
+
+pub fn match_no_clause(value: &str) -> EvalError {
+    EvalError::new(format!("match found no clause for {}", value))
+        .with_condition(condition::PATTERN_ERROR)
+}
+
+pub fn argument_mismatch(index: usize, description: String) -> EvalError {
+    EvalError::new(format!("Argument {} does not match its parameter: {}", index + 1, description))
+        .with_condition(condition::PATTERN_ERROR)
+}
+
+pub fn step_limit(steps: u64) -> EvalError {
+    EvalError::new(format!("Evaluation stopped after {} steps", steps))
+}
+
+pub fn out_of_fuel(fuel: u64) -> EvalError {
+    EvalError::new(format!("Out of fuel, evaluation took more than {} steps", fuel))
+        .with_condition(condition::OUT_OF_FUEL)
+}
+
+pub fn stack_overflow(max_depth: usize) -> EvalError {
+    EvalError::new(format!("Stack overflow, evaluation nested deeper than {} levels", max_depth))
+        .with_condition(condition::STACK_OVERFLOW)
+}
+
+pub fn stack_exhausted(depth: usize) -> EvalError {
+    EvalError::new(format!("Stack overflow, the thread's stack ran out after {} nested evaluations",
+                           depth))
+        .with_condition(condition::STACK_OVERFLOW)
+}
+
+pub fn cancelled() -> EvalError {
+    EvalError {
+        kind: ErrorKind::Cancelled,
+        ..EvalError::new("Evaluation cancelled".to_string())
+    }
+}
@@ -0,0 +1,202 @@
+// Formatting of numbers, byte sizes and durations for human readers, e.g. in
+// generated reports. The options of format-number are keyword arguments.
+
+use crate::{
+    condition,
+    exc,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef, SerializeSymbol},
+    lisp_object_util::keyword_args,
+};
+
+const MAX_DECIMALS: f64 = 20.0;
+const BYTE_UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB", "PB"];
+
+enum Style {
+    Plain,
+    Percent,
+    Engineering,
+}
+
+struct NumberFormat {
+    decimals: Option<usize>,
+    separator: String,
+    style: Style,
+}
+
+fn decimals(value: &LispObject) -> Result<usize, EvalError> {
+    match value.as_number()? {
+        n if (0.0..=MAX_DECIMALS).contains(&n) && n.fract() == 0.0 => Ok(n as usize),
+        n => Err(EvalError::new(format!("Expected an integer from 0 to {} as decimals, got {}",
+                                        MAX_DECIMALS, n))
+                 .with_condition(condition::TYPE_ERROR)),
+    }
+}
+
+fn style(interp: &Interpreter, value: &LispObject) -> Result<Style, EvalError> {
+    let name = match value {
+        LispObject::Keyword(k) => interp.symbols().as_string(k),
+        _ => None,
+    };
+    match name {
+        Some("plain") => Ok(Style::Plain),
+        Some("percent") => Ok(Style::Percent),
+        Some("engineering") => Ok(Style::Engineering),
+        _ => Err(EvalError::new(format!("Expected :plain, :percent or :engineering as style, got {}",
+                                        interp.serialize(value)))
+                 .with_condition(condition::TYPE_ERROR)),
+    }
+}
+
+// Parse the keyword arguments of format-number, which follow the number.
+fn number_format(interp: &Interpreter, options: &[LispObject]) -> Result<NumberFormat, EvalError> {
+    let mut format = NumberFormat { decimals: None, separator: ",".to_string(), style: Style::Plain };
+    let args = keyword_args(interp.symbols(), options)
+        .map_err(|(e, index)| e.trace(index + 2))?;
+    for ((name, value), index) in args.into_iter().zip((2..).step_by(2)) {
+        match name {
+            Some("decimals") => format.decimals = Some(decimals(value)
+                                                       .map_err(|e| e.trace(index + 1))?),
+            Some("separator") => format.separator = value.as_str()
+                .map_err(|e| e.trace(index + 1))?
+                .to_string(),
+            Some("style") => format.style = style(interp, value)
+                .map_err(|e| e.trace(index + 1))?,
+            _ => return Err(exc::unknown_keyword(name).trace(index)),
+        }
+    }
+    Ok(format)
+}
+
+// Insert separator between groups of three digits, from the right.
+fn group(digits: &str, separator: &str) -> String {
+    let mut result = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            result.push_str(separator);
+        }
+        result.push(digit);
+    }
+    result
+}
+
+// n with decimals places and its integer part grouped by separator. Values
+// rounding to zero have no sign.
+fn fixed(n: f64, decimals: usize, separator: &str) -> String {
+    let digits = format!("{:.*}", decimals, n.abs());
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits.as_str(), None),
+    };
+    let negative = n < 0.0 && digits.chars().any(|c| c.is_ascii_digit() && c != '0');
+    let mut result = String::from(if negative { "-" } else { "" });
+    result.push_str(&group(integer, separator));
+    if let Some(fraction) = fraction {
+        result.push('.');
+        result.push_str(fraction);
+    }
+    result
+}
+
+fn default_decimals(n: f64) -> usize {
+    if n.fract() == 0.0 { 0 } else { 2 }
+}
+
+// n as mantissa from 1 to 1000 and an exponent divisible by 3.
+fn engineering(n: f64, decimals: Option<usize>) -> String {
+    let mut exponent = if n == 0.0 { 0 } else { (n.abs().log10() / 3.0).floor() as i32 * 3 };
+    let mut mantissa = n / 10f64.powi(exponent);
+    let decimals = decimals.unwrap_or_else(|| default_decimals(mantissa));
+    // Rounding may carry the mantissa over to 1000
+    if format!("{:.*}", decimals, mantissa.abs()).starts_with("1000") {
+        mantissa /= 1000.0;
+        exponent += 3;
+    }
+    format!("{}e{}", fixed(mantissa, decimals, ""), exponent)
+}
+
+fn format_number(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let n = args[0].as_number()
+        .map_err(|e| e.trace(1))?;
+    let format = number_format(interp, args[1].as_slice()?)?;
+    let formatted = if !n.is_finite() {
+        n.to_string()
+    } else {
+        match format.style {
+            Style::Plain =>
+                fixed(n, format.decimals.unwrap_or_else(|| default_decimals(n)), &format.separator),
+            Style::Percent => {
+                let percent = n * 100.0;
+                let decimals = format.decimals.unwrap_or_else(|| default_decimals(percent));
+                format!("{}%", fixed(percent, decimals, &format.separator))
+            },
+            Style::Engineering => engineering(n, format.decimals),
+        }
+    };
+    Ok(LispObject::String(formatted.into()))
+}
+
+pub const FORMAT_NUMBER: NativeDef = NativeDef {
+    name: "format-number",
+    positional: &["n"],
+    rest: Some("options"),
+    func: format_number,
+};
+
+// A number of bytes in the largest unit of 1024 bytes it reaches.
+fn format_bytes(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let bytes = args[0].as_number()
+        .map_err(|e| e.trace(1))?;
+    let mut size = bytes.abs();
+    let mut unit = 0;
+    while size >= 1024.0 && unit < BYTE_UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    let sign = if bytes < 0.0 { "-" } else { "" };
+    let formatted = match unit {
+        0 => format!("{}{} {}", sign, size, BYTE_UNITS[0]),
+        _ => format!("{}{:.1} {}", sign, size, BYTE_UNITS[unit]),
+    };
+    Ok(LispObject::String(formatted.into()))
+}
+
+pub const FORMAT_BYTES: NativeDef = NativeDef {
+    name: "format-bytes",
+    positional: &["bytes"],
+    rest: None,
+    func: format_bytes,
+};
+
+// A duration in milliseconds, like the timer natives take. Durations below a
+// minute keep a decimal of their seconds, longer ones are rounded to seconds
+// and shown in days, hours, minutes and seconds.
+fn format_duration(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let ms = args[0].as_number()
+        .map_err(|e| e.trace(1))?;
+    let sign = if ms < 0.0 { "-" } else { "" };
+    let formatted = match ms.abs() {
+        ms if ms < 1000.0 => format!("{}ms", ms.round()),
+        ms if ms < 60_000.0 => format!("{:.1}", ms / 1000.0)
+            .trim_end_matches(".0")
+            .to_string() + "s",
+        ms => {
+            let seconds = (ms / 1000.0).round() as u64;
+            let parts = [(seconds / 86400, "d"), (seconds / 3600 % 24, "h"),
+                         (seconds / 60 % 60, "m"), (seconds % 60, "s")];
+            parts.iter()
+                .filter(|(value, _)| *value > 0)
+                .map(|(value, unit)| format!("{}{}", value, unit))
+                .collect::<Vec<String>>()
+                .join(" ")
+        },
+    };
+    Ok(LispObject::String(format!("{}{}", sign, formatted).into()))
+}
+
+pub const FORMAT_DURATION: NativeDef = NativeDef {
+    name: "format-duration",
+    positional: &["ms"],
+    rest: None,
+    func: format_duration,
+};
@@ -0,0 +1,78 @@
+// Fuzzy string matching by Levenshtein distance, the same measure the
+// did-you-mean suggestions for unbound symbols use. Candidates of fuzzy-match
+// may be strings or symbols, so (bound-symbols) can be searched directly.
+
+use crate::{
+    condition,
+    env::{edit_distance, similarity_limit},
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef, SerializeSymbol},
+};
+
+// The text of a string or the name of a symbol.
+fn text<'a>(interp: &'a Interpreter, object: &'a LispObject) -> Result<&'a str, EvalError> {
+    match object {
+        LispObject::String(s) => Ok(s),
+        LispObject::Symbol(s) => Ok(interp.symbols().as_string(s).unwrap_or_default()),
+        _ => Err(EvalError::new(format!("Expected a string or symbol, got {}", interp.serialize(object)))
+                 .with_condition(condition::TYPE_ERROR)),
+    }
+}
+
+fn string_distance(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let a = text(interp, &args[0])
+        .map_err(|e| e.trace(1))?;
+    let b = text(interp, &args[1])
+        .map_err(|e| e.trace(2))?;
+    Ok(LispObject::Number(edit_distance(a, b) as f64))
+}
+
+pub const STRING_DISTANCE: NativeDef = NativeDef {
+    name: "string-distance",
+    positional: &["a", "b"],
+    rest: None,
+    func: string_distance,
+};
+
+// Whether other is close enough to name to be a likely typo of it.
+fn is_string_similar(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let name = text(interp, &args[0])
+        .map_err(|e| e.trace(1))?;
+    let other = text(interp, &args[1])
+        .map_err(|e| e.trace(2))?;
+    Ok(LispObject::Bool(edit_distance(name, other) <= similarity_limit(name)))
+}
+
+pub const STRING_SIMILAR_P: NativeDef = NativeDef {
+    name: "string-similar?",
+    positional: &["name", "other"],
+    rest: None,
+    func: is_string_similar,
+};
+
+// The candidates similar to query, closest first. Candidates at the same
+// distance keep their order.
+fn fuzzy_match(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let query = text(interp, &args[0])
+        .map_err(|e| e.trace(1))?;
+    let limit = similarity_limit(query);
+    let candidates = args[1].as_slice()
+        .map_err(|e| e.trace(2))?;
+    let mut matches = vec![];
+    for candidate in candidates {
+        let distance = edit_distance(query, text(interp, candidate)
+                                     .map_err(|e| e.trace(2))?);
+        if distance <= limit {
+            matches.push((distance, candidate.clone()));
+        }
+    }
+    matches.sort_by_key(|(distance, _)| *distance);
+    Ok(LispObject::List(matches.into_iter().map(|(_, candidate)| candidate).collect()))
+}
+
+pub const FUZZY_MATCH: NativeDef = NativeDef {
+    name: "fuzzy-match",
+    positional: &["query", "candidates"],
+    rest: None,
+    func: fuzzy_match,
+};
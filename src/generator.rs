@@ -0,0 +1,298 @@
+// Generators, objects computing a sequence of values on demand.
+// (generator body...), a prelude macro, rewrites body with generator-body so
+// that every (yield value) returns the value together with a thunk running
+// the rest of the body, and wraps it in make-generator. next runs the body to
+// the next yield. The rewrite follows yields through begin, if, let, let*
+// and loop forms, and macros expanding to them. Yields elsewhere, e.g. in
+// the arguments of a call, are rejected when the generator is expanded.
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, Handle, LispObject, NativeDef, SerializeSymbol, Symbol},
+};
+
+const GENERATOR: &str = "generator";
+const STEP: &str = "generator step";
+
+enum Generator {
+    Suspended(LispObject),
+    Running,
+    Done,
+}
+
+// What running a generator to its next yield returned, the value and the
+// thunk resuming the generator, or None if the body finished.
+struct Step(Option<(LispObject, LispObject)>);
+
+fn yield_step(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(Handle::object(STEP, Step(Some((args[0].clone(), args[1].clone())))))
+}
+
+fn done_step(_: &mut Interpreter, _: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(Handle::object(STEP, Step(None)))
+}
+
+fn syntax_error(message: String) -> EvalError {
+    EvalError::new(message).with_condition(condition::SYNTAX_ERROR)
+}
+
+struct Rewrite<'a> {
+    interp: &'a mut Interpreter,
+    yield_step: LispObject,
+    done_step: LispObject,
+    // The function replacing the innermost loop containing yields
+    recur_to: Option<Symbol>,
+}
+
+impl Rewrite<'_> {
+    fn symbol(&mut self, name: &str) -> LispObject {
+        LispObject::Symbol(self.interp.symbols_mut().intern(name))
+    }
+
+    fn head_name(&self, form: &LispObject) -> Option<String> {
+        match form {
+            LispObject::List(l) => match l.first() {
+                Some(LispObject::Symbol(s)) => self.interp.symbols().as_string(s).map(str::to_string),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // Whether form has to be rewritten, because it yields or recurs to a loop
+    // containing yields. Nested functions and quoted forms are not searched.
+    fn suspends(&self, form: &LispObject, recurs: bool) -> bool {
+        let l = match form {
+            LispObject::List(l) => l,
+            _ => return false,
+        };
+        match self.head_name(form).as_deref() {
+            Some("yield") => true,
+            Some("recur") if recurs => true,
+            Some("quote") | Some("fn") => false,
+            // The recurs of a nested loop go to that loop
+            Some("loop") => l[1..].iter().any(|f| self.suspends(f, false)),
+            _ => l.iter().any(|f| self.suspends(f, recurs)),
+        }
+    }
+
+    fn check_plain(&self, form: &LispObject) -> Result<(), EvalError> {
+        if self.suspends(form, self.recur_to.is_some()) {
+            return Err(syntax_error(format!("yield and recur must be in statement position in a generator, not in {}",
+                                            self.interp.serialize(form))))
+        }
+        Ok(())
+    }
+
+    // A thunk calling rest, bound to a fresh symbol around body, so that rest
+    // isn't copied into the branches of an if or evaluated in the scope of a
+    // let. body is given the call of the thunk.
+    fn continuation(&mut self, rest: LispObject,
+                    body: impl FnOnce(&mut Self, LispObject) -> Result<LispObject, EvalError>)
+                    -> Result<LispObject, EvalError> {
+        if matches!(&rest, LispObject::List(l) if l.len() == 1) {
+            return body(self, rest)
+        }
+        let k = LispObject::Symbol(self.interp.symbols_mut().gensym("k"));
+        let body = body(self, LispObject::List(vec![k.clone()]))?;
+        Ok(LispObject::List(vec![
+            self.symbol("let"),
+            LispObject::List(vec![LispObject::List(vec![k, self.thunk(rest)])]),
+            body,
+        ]))
+    }
+
+    fn thunk(&mut self, body: LispObject) -> LispObject {
+        LispObject::List(vec![self.symbol("fn"), LispObject::List(vec![]), body])
+    }
+
+    fn sequence(&mut self, forms: &[LispObject], rest: LispObject) -> Result<LispObject, EvalError> {
+        forms.iter().rev().try_fold(rest, |rest, form| self.rewrite(form, rest))
+    }
+
+    // Code evaluating form and then rest, which returns the next step.
+    fn rewrite(&mut self, form: &LispObject, rest: LispObject) -> Result<LispObject, EvalError> {
+        if !self.suspends(form, self.recur_to.is_some()) {
+            return Ok(LispObject::List(vec![self.symbol("begin"), form.clone(), rest]))
+        }
+        let l = form.as_slice()?;
+        match self.head_name(form).as_deref() {
+            Some("yield") => {
+                let value = match &l[1..] {
+                    [] => LispObject::List(vec![]),
+                    [value] => value.clone(),
+                    _ => return Err(syntax_error("yield takes at most one value".to_string())),
+                };
+                self.check_plain(&value)?;
+                Ok(LispObject::List(vec![self.yield_step.clone(), value, self.thunk(rest)]))
+            },
+            Some("begin") => self.sequence(&l[1..], rest),
+            Some("if") => {
+                let (test, then, alt) = match &l[1..] {
+                    [test, then] => (test, then, None),
+                    [test, then, alt] => (test, then, Some(alt)),
+                    [test, then, LispObject::Symbol(e), alt] if *e == self.interp.symbols().sym_else =>
+                        (test, then, Some(alt)),
+                    _ => return Err(syntax_error("Malformed if in generator".to_string())),
+                };
+                self.check_plain(test)?;
+                self.continuation(rest, |this, k| {
+                    let then = this.rewrite(then, k.clone())?;
+                    let alt = match alt {
+                        Some(alt) => this.rewrite(alt, k)?,
+                        None => k,
+                    };
+                    Ok(LispObject::List(vec![this.symbol("if"), test.clone(), then, alt]))
+                })
+            },
+            Some(name @ ("let" | "let*")) if l.len() >= 2 => {
+                let name = name.to_string();
+                l[1].as_slice()?.iter().try_for_each(|binding| self.check_plain(binding))?;
+                self.continuation(rest, |this, k| {
+                    let body = this.sequence(&l[2..], k)?;
+                    Ok(LispObject::List(vec![this.symbol(&name), l[1].clone(), body]))
+                })
+            },
+            // The loop becomes a local function, recur calls it
+            Some("loop") if l.len() >= 2 => {
+                let bindings = l[1].as_slice()?;
+                let mut binders = vec![];
+                let mut inits = vec![];
+                for binding in bindings {
+                    self.check_plain(binding)?;
+                    match binding.as_slice()? {
+                        [binder, init] => {
+                            binders.push(binder.clone());
+                            inits.push(init.clone());
+                        },
+                        _ => return Err(syntax_error("Malformed loop binding in generator".to_string())),
+                    }
+                }
+                let f = self.interp.symbols_mut().gensym("loop");
+                self.continuation(rest, |this, k| {
+                    let outer = this.recur_to.replace(f);
+                    let body = this.sequence(&l[2..], k);
+                    this.recur_to = outer;
+                    let lambda = LispObject::List(vec![this.symbol("fn"), LispObject::List(binders), body?]);
+                    let mut call = vec![LispObject::Symbol(f)];
+                    call.extend(inits);
+                    Ok(LispObject::List(vec![
+                        this.symbol("letrec"),
+                        LispObject::List(vec![LispObject::List(vec![LispObject::Symbol(f), lambda])]),
+                        LispObject::List(call),
+                    ]))
+                })
+            },
+            Some("recur") if self.recur_to.is_some() => {
+                l[1..].iter().try_for_each(|arg| self.check_plain(arg))?;
+                let mut call = vec![LispObject::Symbol(self.recur_to.unwrap_or_default())];
+                call.extend(l[1..].iter().cloned());
+                Ok(LispObject::List(call))
+            },
+            _ => match self.interp.macroexpand_1(form)? {
+                (expansion, true) => self.rewrite(&expansion, rest),
+                _ => Err(syntax_error(format!("yield and recur must be in statement position in a generator, not in {}",
+                                              self.interp.serialize(form)))),
+            },
+        }
+    }
+}
+
+// The body of a generator's thunk for the forms of body.
+fn generator_body(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let native = |interp: &mut Interpreter, positional: &[&str], func| {
+        let positional = positional.iter().map(|name| interp.symbols_mut().intern(name)).collect();
+        LispObject::Native((positional, None), func)
+    };
+    let yield_step = native(interp, &["value", "thunk"], yield_step);
+    let done_step = native(interp, &[], done_step);
+    let mut rewrite = Rewrite { interp, yield_step, done_step, recur_to: None };
+    let done = LispObject::List(vec![rewrite.done_step.clone()]);
+    rewrite.sequence(args[0].as_slice()?, done)
+        .map_err(|e| e.trace(1))
+}
+
+pub const GENERATOR_BODY: NativeDef = NativeDef {
+    name: "generator-body",
+    positional: &["body"],
+    rest: None,
+    func: generator_body,
+};
+
+fn make_generator(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(Handle::object(GENERATOR, Generator::Suspended(args[0].clone())))
+}
+
+pub const MAKE_GENERATOR: NativeDef = NativeDef {
+    name: "make-generator",
+    positional: &["thunk"],
+    rest: None,
+    func: make_generator,
+};
+
+// The next value of a generator. Once it is exhausted, the default is
+// returned if there is one. A generator failing with an error is exhausted.
+fn next(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let handle = args[0].as_handle(GENERATOR)
+        .map_err(|e| e.trace(1))?;
+    let state = match handle.value.borrow_mut().downcast_mut::<Generator>() {
+        Some(state) => std::mem::replace(state, Generator::Running),
+        None => return Err(EvalError::new("Invalid generator handle".to_string()).trace(1)),
+    };
+    let step = match state {
+        Generator::Suspended(thunk) => interp.apply(&thunk, &[])
+            .and_then(|step| {
+                let step = step.as_handle(STEP)?.value.borrow();
+                match step.downcast_ref::<Step>() {
+                    Some(Step(step)) => Ok(step.clone()),
+                    None => Err(EvalError::new("Invalid generator step".to_string())),
+                }
+            }),
+        Generator::Running => return Err(EvalError::new("The generator is already running".to_string())
+                                         .with_condition(condition::PROGRAM_ERROR)
+                                         .trace(1)),
+        Generator::Done => Ok(None),
+    };
+    let (state, value) = match step {
+        Ok(Some((value, thunk))) => (Generator::Suspended(thunk), Ok(value)),
+        Ok(None) => (Generator::Done, match args[1].as_slice()? {
+            [default, ..] => Ok(default.clone()),
+            [] => Err(EvalError::new("The generator is exhausted".to_string())
+                      .with_condition(condition::PROGRAM_ERROR)
+                      .trace(1)),
+        }),
+        Err(err) => (Generator::Done, Err(err)),
+    };
+    *handle.value.borrow_mut() = Box::new(state);
+    value
+}
+
+pub const NEXT: NativeDef = NativeDef {
+    name: "next",
+    positional: &["generator"],
+    rest: Some("default"),
+    func: next,
+};
+
+fn is_generator(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(args[0].as_handle(GENERATOR).is_ok()))
+}
+
+pub const GENERATOR_P: NativeDef = NativeDef {
+    name: "generator?",
+    positional: &["obj"],
+    rest: None,
+    func: is_generator,
+};
+
+fn yield_outside(_: &mut Interpreter, _: &[LispObject]) -> Result<LispObject, EvalError> {
+    Err(syntax_error("yield must be used in the body of a generator".to_string()))
+}
+
+pub const YIELD: NativeDef = NativeDef {
+    name: "yield",
+    positional: &[],
+    rest: Some("value"),
+    func: yield_outside,
+};
@@ -0,0 +1,168 @@
+// Algorithms on graphs given as adjacency maps, which map every node to a map
+// of its neighbors to the weights of the edges to them. The prelude builds
+// them with graph-add-edge and lists neighbors.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef},
+};
+
+// A graph with its nodes numbered in the order they appear in the map, first
+// as keys, then as neighbors only.
+struct Graph {
+    nodes: Vec<LispObject>,
+    edges: Vec<Vec<(usize, f64)>>,
+}
+
+impl Graph {
+    fn new(object: &LispObject) -> Result<Graph, EvalError> {
+        let mut graph = Graph { nodes: vec![], edges: vec![] };
+        let map = object.as_map()?;
+        for (node, _) in map.iter() {
+            graph.index(node);
+        }
+        for (node, neighbors) in map.iter() {
+            let from = graph.index(node);
+            for (neighbor, weight) in neighbors.as_map()?.iter() {
+                let to = graph.index(neighbor);
+                graph.edges[from].push((to, weight.as_number()?));
+            }
+        }
+        Ok(graph)
+    }
+
+    fn index(&mut self, node: &LispObject) -> usize {
+        match self.find(node) {
+            Some(index) => index,
+            None => {
+                self.nodes.push(node.clone());
+                self.edges.push(vec![]);
+                self.nodes.len() - 1
+            },
+        }
+    }
+
+    fn find(&self, node: &LispObject) -> Option<usize> {
+        self.nodes.iter().position(|n| n.equals(node))
+    }
+}
+
+// The nodes in an order in which every edge leads from an earlier node to a
+// later one, e.g. for edges from tasks to the tasks depending on them, an
+// order to run them in. Nodes keep their order in the map where possible.
+fn topo_sort(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let graph = Graph::new(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let mut incoming = vec![0; graph.nodes.len()];
+    for edges in &graph.edges {
+        for (to, _) in edges {
+            incoming[*to] += 1;
+        }
+    }
+    let mut order = vec![];
+    let mut ready = (0..graph.nodes.len()).filter(|n| incoming[*n] == 0).collect::<Vec<usize>>();
+    while !ready.is_empty() {
+        let node = ready.remove(0);
+        order.push(graph.nodes[node].clone());
+        for (to, _) in &graph.edges[node] {
+            incoming[*to] -= 1;
+            if incoming[*to] == 0 {
+                ready.push(*to);
+            }
+        }
+    }
+    match incoming.iter().position(|n| *n > 0) {
+        Some(node) => Err(EvalError::new(format!("The graph has a cycle through {}",
+                                                 interp.serialize(&graph.nodes[node])))
+                          .with_condition(condition::PROGRAM_ERROR)
+                          .trace(1)),
+        None => Ok(LispObject::List(order)),
+    }
+}
+
+pub const TOPO_SORT: NativeDef = NativeDef {
+    name: "topo-sort",
+    positional: &["graph"],
+    rest: None,
+    func: topo_sort,
+};
+
+// A node reached at a distance, ordered so that BinaryHeap pops the closest.
+struct Reached(f64, usize);
+
+impl PartialEq for Reached {
+    fn eq(&self, other: &Reached) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Reached {}
+
+impl PartialOrd for Reached {
+    fn partial_cmp(&self, other: &Reached) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Reached {
+    fn cmp(&self, other: &Reached) -> Ordering {
+        other.0.total_cmp(&self.0).then_with(|| other.1.cmp(&self.1))
+    }
+}
+
+// The path with the least total weight from one node to another and its
+// weight as second value, by Dijkstra's algorithm. The empty list if there is
+// no path.
+fn shortest_path(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let graph = Graph::new(&args[0])
+        .map_err(|e| e.trace(1))?;
+    if let Some((_, weight)) = graph.edges.iter().flatten().find(|(_, weight)| *weight < 0.0) {
+        return Err(EvalError::new(format!("Expected edges of non-negative weight, got {}", weight))
+                   .with_condition(condition::PROGRAM_ERROR)
+                   .trace(1))
+    }
+    let not_found = |index| EvalError::new(format!("{} is not a node of the graph",
+                                                   interp.serialize(&args[index])))
+        .with_condition(condition::PROGRAM_ERROR)
+        .trace(index + 1);
+    let from = graph.find(&args[1]).ok_or_else(|| not_found(1))?;
+    let to = graph.find(&args[2]).ok_or_else(|| not_found(2))?;
+    let mut distances = vec![f64::INFINITY; graph.nodes.len()];
+    let mut previous: Vec<Option<usize>> = vec![None; graph.nodes.len()];
+    let mut frontier = BinaryHeap::from([Reached(0.0, from)]);
+    distances[from] = 0.0;
+    while let Some(Reached(distance, node)) = frontier.pop() {
+        if node == to {
+            let mut path = vec![graph.nodes[to].clone()];
+            let mut node = to;
+            while let Some(before) = previous[node] {
+                path.push(graph.nodes[before].clone());
+                node = before;
+            }
+            path.reverse();
+            return Ok(LispObject::Values(vec![LispObject::List(path), LispObject::Number(distance)].into()))
+        }
+        if distance > distances[node] {
+            continue
+        }
+        for (neighbor, weight) in &graph.edges[node] {
+            if distance + weight < distances[*neighbor] {
+                distances[*neighbor] = distance + weight;
+                previous[*neighbor] = Some(node);
+                frontier.push(Reached(distance + weight, *neighbor));
+            }
+        }
+    }
+    Ok(LispObject::List(vec![]))
+}
+
+pub const SHORTEST_PATH: NativeDef = NativeDef {
+    name: "shortest-path",
+    positional: &["graph", "from", "to"],
+    rest: None,
+    func: shortest_path,
+};
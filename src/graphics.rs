@@ -0,0 +1,232 @@
+// Drawing on canvases rendered as SVG, for teaching and visual examples
+// without a windowing system. Shapes take the options :stroke and :fill, CSS
+// colors, and :width, the width of the stroke. Every canvas has a turtle,
+// starting in its center heading up, which draws lines as it moves forward
+// while its pen is down. Drawing changes the canvas and returns it.
+
+use crate::{
+    condition,
+    exc,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, Handle, LispObject, NativeDef},
+    lisp_object_util::keyword_args,
+    xml::escape,
+};
+
+const CANVAS: &str = "canvas";
+
+struct Turtle {
+    x: f64,
+    y: f64,
+    // Degrees clockwise from up
+    heading: f64,
+    pen: bool,
+}
+
+struct Canvas {
+    width: f64,
+    height: f64,
+    // SVG elements of the shapes drawn so far
+    elements: Vec<String>,
+    turtle: Turtle,
+}
+
+struct Style {
+    stroke: String,
+    fill: String,
+    width: f64,
+}
+
+// Coordinates rounded to thousandths, so turtle paths don't print as
+// 99.99999999999999.
+fn number(n: f64) -> String {
+    let rounded = (n * 1000.0).round() / 1000.0;
+    format!("{}", if rounded == 0.0 { 0.0 } else { rounded })
+}
+
+impl Style {
+    fn attributes(&self) -> String {
+        format!("fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"",
+                escape(&self.fill, true), escape(&self.stroke, true), number(self.width))
+    }
+}
+
+// The style given by the options after the first positional arguments,
+// with fill none unless given.
+fn style(interp: &Interpreter, args: &[LispObject], positional: usize) -> Result<Style, EvalError> {
+    let mut style = Style { stroke: "black".to_string(), fill: "none".to_string(), width: 1.0 };
+    let options = keyword_args(interp.symbols(), args[positional].as_slice()?)
+        .map_err(|(e, index)| e.trace(index + positional + 1))?;
+    for ((name, value), index) in options.into_iter().zip((positional + 1..).step_by(2)) {
+        match name {
+            Some("stroke") => style.stroke = value.as_str()
+                .map_err(|e| e.trace(index + 1))?.to_string(),
+            Some("fill") => style.fill = value.as_str()
+                .map_err(|e| e.trace(index + 1))?.to_string(),
+            Some("width") => style.width = value.as_number()
+                .map_err(|e| e.trace(index + 1))?,
+            _ => return Err(exc::unknown_keyword(name).trace(index)),
+        }
+    }
+    Ok(style)
+}
+
+fn numbers(args: &[LispObject], range: std::ops::Range<usize>) -> Result<Vec<f64>, EvalError> {
+    range.map(|index| args[index].as_number().map_err(|e| e.trace(index + 1)))
+        .collect()
+}
+
+// Run f on the canvas of args[0] and return it.
+fn with_canvas(args: &[LispObject], f: impl FnOnce(&mut Canvas)) -> Result<LispObject, EvalError> {
+    let handle = args[0].as_handle(CANVAS)
+        .map_err(|e| e.trace(1))?;
+    let mut canvas = handle.value.borrow_mut();
+    let canvas = canvas.downcast_mut::<Canvas>()
+        .ok_or_else(|| EvalError::new("Invalid canvas handle".to_string()).trace(1))?;
+    f(canvas);
+    Ok(args[0].clone())
+}
+
+fn line(x1: f64, y1: f64, x2: f64, y2: f64, style: &Style) -> String {
+    format!("<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}/>",
+            number(x1), number(y1), number(x2), number(y2), style.attributes())
+}
+
+fn make_canvas(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let size = numbers(args, 0..2)?;
+    if let Some(index) = size.iter().position(|n| *n <= 0.0) {
+        return Err(EvalError::new(format!("Expected a positive size, got {}", size[index]))
+                   .with_condition(condition::TYPE_ERROR)
+                   .trace(index + 1))
+    }
+    let turtle = Turtle { x: size[0] / 2.0, y: size[1] / 2.0, heading: 0.0, pen: true };
+    Ok(Handle::object(CANVAS, Canvas { width: size[0], height: size[1], elements: vec![], turtle }))
+}
+
+pub const MAKE_CANVAS: NativeDef = NativeDef {
+    name: "make-canvas",
+    positional: &["width", "height"],
+    rest: None,
+    func: make_canvas,
+};
+
+fn draw_line(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let points = numbers(args, 1..5)?;
+    let style = style(interp, args, 5)?;
+    with_canvas(args, |canvas| {
+        canvas.elements.push(line(points[0], points[1], points[2], points[3], &style));
+    })
+}
+
+pub const DRAW_LINE: NativeDef = NativeDef {
+    name: "draw-line",
+    positional: &["canvas", "x1", "y1", "x2", "y2"],
+    rest: Some("options"),
+    func: draw_line,
+};
+
+fn draw_circle(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let circle = numbers(args, 1..4)?;
+    let style = style(interp, args, 4)?;
+    with_canvas(args, |canvas| {
+        canvas.elements.push(format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
+                                     number(circle[0]), number(circle[1]), number(circle[2]),
+                                     style.attributes()));
+    })
+}
+
+pub const DRAW_CIRCLE: NativeDef = NativeDef {
+    name: "draw-circle",
+    positional: &["canvas", "cx", "cy", "r"],
+    rest: Some("options"),
+    func: draw_circle,
+};
+
+fn draw_rect(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let rect = numbers(args, 1..5)?;
+    let style = style(interp, args, 5)?;
+    with_canvas(args, |canvas| {
+        canvas.elements.push(format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
+                                     number(rect[0]), number(rect[1]), number(rect[2]), number(rect[3]),
+                                     style.attributes()));
+    })
+}
+
+pub const DRAW_RECT: NativeDef = NativeDef {
+    name: "draw-rect",
+    positional: &["canvas", "x", "y", "width", "height"],
+    rest: Some("options"),
+    func: draw_rect,
+};
+
+fn render_svg(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let mut svg = String::new();
+    with_canvas(args, |canvas| {
+        svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+                       viewBox=\"0 0 {w} {h}\">\n",
+                      w = number(canvas.width), h = number(canvas.height));
+        for element in &canvas.elements {
+            svg.push_str(element);
+            svg.push('\n');
+        }
+        svg.push_str("</svg>\n");
+    })?;
+    Ok(LispObject::String(svg.into()))
+}
+
+pub const RENDER_SVG: NativeDef = NativeDef {
+    name: "render-svg",
+    positional: &["canvas"],
+    rest: None,
+    func: render_svg,
+};
+
+// Move the turtle, drawing a line if its pen is down.
+fn turtle_forward(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let distance = numbers(args, 1..2)?[0];
+    let style = style(interp, args, 2)?;
+    with_canvas(args, |canvas| {
+        let turtle = &mut canvas.turtle;
+        let (x, y) = (turtle.x, turtle.y);
+        turtle.x += distance * turtle.heading.to_radians().sin();
+        turtle.y -= distance * turtle.heading.to_radians().cos();
+        if turtle.pen {
+            let line = line(x, y, turtle.x, turtle.y, &style);
+            canvas.elements.push(line);
+        }
+    })
+}
+
+pub const TURTLE_FORWARD: NativeDef = NativeDef {
+    name: "turtle-forward",
+    positional: &["canvas", "distance"],
+    rest: Some("options"),
+    func: turtle_forward,
+};
+
+// Turn the turtle clockwise by degrees, counterclockwise if negative.
+fn turtle_turn(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let degrees = numbers(args, 1..2)?[0];
+    with_canvas(args, |canvas| canvas.turtle.heading = (canvas.turtle.heading + degrees).rem_euclid(360.0))
+}
+
+pub const TURTLE_TURN: NativeDef = NativeDef {
+    name: "turtle-turn",
+    positional: &["canvas", "degrees"],
+    rest: None,
+    func: turtle_turn,
+};
+
+// Put the turtle's pen down on the canvas if down is true, else lift it.
+fn turtle_pen(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let down = args[1].as_bool()
+        .map_err(|e| e.trace(2))?;
+    with_canvas(args, |canvas| canvas.turtle.pen = down)
+}
+
+pub const TURTLE_PEN: NativeDef = NativeDef {
+    name: "turtle-pen",
+    positional: &["canvas", "down"],
+    rest: None,
+    func: turtle_pen,
+};
@@ -0,0 +1,164 @@
+// A navigable view of nested lists and maps for the REPL's :inspect command.
+
+use std::collections::HashSet;
+
+use ansi_term::Colour::{Blue, Green};
+use rustyline::{error::ReadlineError, Editor};
+
+use crate::{
+    env::Symbols,
+    err::paint,
+    lisp_object::LispObject,
+};
+
+const SUMMARY_WIDTH: usize = 60;
+
+const HELP: &str = "\
+  N [M ...]  jump into element N (then M ...)
+  +N / -N    expand / collapse element N, + / - for all elements
+  u          go up, t goes to the top
+  p          print the full value
+  q          quit the inspector";
+
+struct Frame {
+    label: String,
+    value: LispObject,
+    expanded: HashSet<usize>,
+}
+
+fn type_name(object: &LispObject) -> String {
+    match object {
+        LispObject::Nil => "nil".to_string(),
+        LispObject::Bool(_) => "bool".to_string(),
+        LispObject::SpecialForm(_) => "special-form".to_string(),
+        LispObject::Symbol(_) => "symbol".to_string(),
+        LispObject::Keyword(_) => "keyword".to_string(),
+        LispObject::String(_) => "string".to_string(),
+        LispObject::Number(_) => "number".to_string(),
+        LispObject::List(_) => "list".to_string(),
+        LispObject::Values(_) => "values".to_string(),
+        LispObject::Native(_, _) => "native".to_string(),
+        LispObject::Lambda(l) if l.is_macro => "macro".to_string(),
+        LispObject::Lambda(_) => "lambda".to_string(),
+        LispObject::Map(_) => "map".to_string(),
+        LispObject::Handle(h) => format!("handle {}", h.kind),
+        LispObject::Stream(_) => "stream".to_string(),
+    }
+}
+
+// The elements of a list or the entries of a map, labelled by index or key.
+fn children(symbols: &Symbols, object: &LispObject) -> Vec<(String, LispObject)> {
+    match object {
+        LispObject::List(l) => l.iter()
+            .enumerate()
+            .map(|(i, o)| (i.to_string(), o.clone()))
+            .collect(),
+        LispObject::Map(m) => m.iter()
+            .map(|(k, v)| (symbols.serialize_object(k), v.clone()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn describe(symbols: &Symbols, object: &LispObject) -> String {
+    match object {
+        LispObject::List(l) => format!("list, {} elements", l.len()),
+        LispObject::Map(m) => format!("map, {} entries", m.len()),
+        _ => format!("{} {}", type_name(object), symbols.serialize_object(object)),
+    }
+}
+
+fn summary(symbols: &Symbols, object: &LispObject) -> String {
+    let s = symbols.serialize_object(object);
+    match s.char_indices().nth(SUMMARY_WIDTH) {
+        Some((end, _)) => format!("{}...", &s[..end]),
+        None => s,
+    }
+}
+
+fn print_children(color: bool, symbols: &Symbols, object: &LispObject,
+                  expanded: Option<&HashSet<usize>>, depth: usize) {
+    let indent = "  ".repeat(depth + 1);
+    for (index, (label, child)) in children(symbols, object).iter().enumerate() {
+        let open = expanded.is_some_and(|e| e.contains(&index));
+        let mark = match child {
+            LispObject::List(_) | LispObject::Map(_) if open => "-",
+            LispObject::List(_) | LispObject::Map(_) => "+",
+            _ => " ",
+        };
+        println!("{}{} [{}] {} {}", indent, mark, paint(color, Blue, &index.to_string()),
+                 paint(color, Green, &type_name(child)),
+                 if label == &index.to_string() { summary(symbols, child) }
+                 else { format!("{} => {}", label, summary(symbols, child)) });
+        if open {
+            print_children(color, symbols, child, None, depth + 1);
+        }
+    }
+}
+
+fn print_frame(color: bool, symbols: &Symbols, stack: &[Frame]) {
+    let frame = stack.last().unwrap();
+    let path = stack.iter()
+        .map(|f| f.label.as_str())
+        .collect::<Vec<&str>>()
+        .join(" > ");
+    println!("{}  {}", path, describe(symbols, &frame.value));
+    print_children(color, symbols, &frame.value, Some(&frame.expanded), 0);
+}
+
+fn parse_index(s: &str, len: usize) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(index) if index < len => Ok(index),
+        _ => Err(format!("No element {}", s)),
+    }
+}
+
+// Execute command on the stack, returns false if the inspector should quit.
+fn command(symbols: &Symbols, stack: &mut Vec<Frame>, command: &str) -> Result<bool, String> {
+    let depth = stack.len();
+    let frame = stack.last_mut().unwrap();
+    let len = children(symbols, &frame.value).len();
+    match command {
+        "q" => return Ok(false),
+        "?" | "h" => println!("{}", HELP),
+        "p" => println!("{}", symbols.serialize_object(&frame.value)),
+        "u" | ".." if depth > 1 => { stack.pop(); },
+        "u" | ".." => return Err("Already at the top".to_string()),
+        "t" => stack.truncate(1),
+        "+" => frame.expanded = (0..len).collect(),
+        "-" => frame.expanded.clear(),
+        _ if command.starts_with('+') => {
+            frame.expanded.insert(parse_index(&command[1..], len)?);
+        },
+        _ if command.starts_with('-') => {
+            frame.expanded.remove(&parse_index(&command[1..], len)?);
+        },
+        _ => for step in command.split_whitespace() {
+            let frame = stack.last().unwrap();
+            let mut elements = children(symbols, &frame.value);
+            let index = parse_index(step, elements.len())?;
+            let (label, value) = elements.swap_remove(index);
+            stack.push(Frame { label, value, expanded: HashSet::new() });
+        },
+    }
+    Ok(true)
+}
+
+pub fn run(color: bool, symbols: &Symbols, rl: &mut Editor<()>, label: &str, value: LispObject)
+           -> Result<(), ReadlineError> {
+    let mut stack = vec![Frame { label: label.to_string(), value, expanded: HashSet::new() }];
+    println!("Inspecting {}, type ? for help.", label);
+    loop {
+        print_frame(color, symbols, &stack);
+        let line = match rl.readline("inspect> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Eof) | Err(ReadlineError::Interrupted) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        match command(symbols, &mut stack, line.trim()) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
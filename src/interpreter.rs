@@ -1,427 +1,2611 @@
-use rustyline::{error::ReadlineError, Editor};
-use rustyline;
-use std::iter;
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
-
-use crate::{
-    lisp_object::{
-        Symbol,
-        ParamList,
-        EvalError,
-        LispObject,
-        SpecialForm,
-        SerializeSymbol,
-    },
-    lisp_object_util::{
-        Match,
-        assert_args,
-        as_symbols,
-    },
-    reader::{Reader, ReadError},
-    env::{Env, Symbols, create_root},
-    err::{handle_eval_error, handle_read_error, print_message},
-    exc
-};
-
-pub enum ExecError {
-    Read(ReadError),
-    Eval(EvalError),
-}
-
-pub struct FunctionDef<'a> {
-    params: ParamList,
-    forms: &'a [LispObject],
-    is_macro: bool,
-}
-
-pub struct Interpreter {
-    symbols: Symbols,
-    env: Env,
-}
-
-impl Interpreter {
-    pub fn new() -> Interpreter {
-        let mut symbols = Symbols::new();
-        let env = create_root(&mut symbols);
-
-        Interpreter {
-            symbols: symbols,
-            env: env,
-        }
-    }
-
-    pub fn read_file(&mut self, f: &str) -> Result<(), String> {
-        let mut reader = Reader::new();
-        let mut prog: Vec<LispObject> = vec![];
-
-        let file = File::open(f).map_err(|e| e.to_string())?;
-        let fin = BufReader::new(file);
-
-        for line in fin.lines() {
-            let line = line.map_err(|e| e.to_string())?;
-            let input = line.splitn(2, ';').next().unwrap();
-            reader.partial(&mut self.symbols, &mut prog, &input)
-                .or_else(|e| handle_read_error(&line, e))
-                .map_err(|e| e.to_string())?;
-        }
-
-        for object in prog {
-            if let Err(e) = self.eval(&object) {
-                handle_eval_error(&self.symbols, e);
-                return Err(format!("Evaluation of {} failed.", f));
-            }
-        }
-
-        Ok(())
-    }
-
-    pub fn interactive(&mut self) {
-        let mut rl = Editor::<()>::new();
-        let mut reader = Reader::new();
-
-        loop {
-            let reader_stack = reader.len();
-            let prompt = match reader_stack {
-                0 => "? ".to_string(),
-                _ => format!("> {}", "  ".repeat(reader_stack)),
-            };
-
-            match rl.readline(&prompt[..]) {
-                Ok(line) => {
-                    let result = self.handle_line(&mut reader, &line);
-                    let result = self.handle_exec_error(&line, result);
-                    if result.is_err() {
-                        break result;
-                    }
-
-                    if line.trim().len() > 0 {
-                        rl.add_history_entry(line);
-                    }
-                },
-                Err(ReadlineError::Eof)         => break Ok(()),
-                Err(ReadlineError::Interrupted) => break Ok(()),
-                Err(e) => break Err(e.to_string()),
-            }
-        }.unwrap_or_else(|err| print_message(&err));
-    }
-
-    fn handle_line(&mut self, reader: &mut Reader, line: &String)
-                   -> Result<(), ExecError> {
-        let mut prog: Vec<LispObject> = vec![];
-        reader.partial(&mut self.symbols, &mut prog, line)
-            .map_err(ExecError::Read)?;
-        for obj in prog {
-            let result = self.eval(&obj)
-                .map_err(|e| ExecError::Eval(e.frame(obj, Some(":in:".to_string()))))?;
-            println!("{}", self.symbols.serialize_object(&result));
-        }
-        Ok(())
-    }
-
-    pub fn handle_exec_error(&self, line: &String, e: Result<(), ExecError>)
-                             -> Result<(), String> {
-        match e {
-            Err(ExecError::Eval(e)) => handle_eval_error(&self.symbols, e),
-            Err(ExecError::Read(e)) => {
-                if let Err(e) = handle_read_error(line, e) {
-                    return Err(e.to_string())
-                }
-            },
-            _ => (),
-        }
-        Ok(())
-    }
-
-    // fn expand_macros(&mut self, object: LispObject) -> Result<LispObject, EvalError> {
-    //     match object {
-    //         LispObject::List(l) => {
-    //             if let Some((params, forms)) = self.as_macro_call(&l) {
-    //                 self.eval_lambda(params, &forms, &l[1..], true)
-    //             } else {
-    //                 Ok(LispObject::List(
-    //                     l.into_iter()
-    //                         .map(|object| self.expand_macros(object))
-    //                         .collect::<Result<Vec<LispObject>, EvalError>>()?
-    //                 ))
-    //             }
-    //         },
-    //         obj => Ok(obj),
-    //     }
-    // }
-
-    // fn as_macro_call(&self, lst: &Vec<LispObject>) -> Option<(ParamList, Vec<LispObject>)> {
-    //     if lst.len() == 0 {
-    //         return None
-    //     }
-
-    //     let resolved_head = lst[0].as_symbol().ok()
-    //         .and_then(|sym| self.env.resolve(&sym));
-
-    //     match resolved_head {
-    //         Some(LispObject::Macro(ps, fs)) => Some((ps.clone(), fs.clone())),
-    //         _ => None,
-    //     }
-    // }
-
-    fn eval(&mut self, object: &LispObject) -> Result<LispObject, EvalError> {
-        match object {
-            LispObject::List(l) => {
-                if l.len() == 0 {
-                    return Err(exc::apply_empty())
-                }
-
-                let tail = &l[1..];
-                let head = self.eval(&l[0])
-                    .map_err(|e| e.trace(0))?;
-
-                match head {
-                    LispObject::SpecialForm(sf)
-                        => self.eval_special_form(sf, tail),
-                    LispObject::Native(params, func) => {
-                        let args = self.bind_param_list(&params, tail, true)?
-                            .into_iter().map(|(_, arg)| arg)
-                            .collect::<Vec<LispObject>>();
-                        func(&args[..])
-                    }
-                    LispObject::List(lst) => {
-                        self.eval_form(&lst, tail,
-                                       l[0].as_symbol().ok())
-                    }
-                    _ => Err(exc::apply_unimpl()
-                             .def_frame(&self.symbols, head, l[0].as_symbol().ok())
-                             .trace(0))
-                }
-            },
-            LispObject::Symbol(s) => match self.env.resolve(s) {
-                Some(object) => Ok(object.clone()),
-                None => Err(exc::unbound_symbol(self.symbols.as_string(s)))
-            }
-            LispObject::String(s) => Ok(LispObject::String(s.to_string())),
-            LispObject::Number(n) => Ok(LispObject::Number(*n)),
-            LispObject::Bool(b)   => Ok(LispObject::Bool(*b)),
-            LispObject::Native((p, r), f) => Ok(LispObject::Native((p.clone(), *r), *f)),
-            LispObject::SpecialForm(_)
-                => Err(exc::unexpected_special_form())
-        }
-    }
-
-    fn eval_form(&mut self, lst: &[LispObject], tail: &[LispObject], sym: Option<Symbol>)
-                 -> Result<LispObject, EvalError> {
-        let fn_def = self.parse_function_def(lst)
-            .map_err(|e| e.def_frame(&self.symbols, LispObject::List(lst.to_vec()), sym)
-                          .trace(0))?;
-        if fn_def.is_macro {
-            self.eval_macro(fn_def.params, &fn_def.forms, tail)
-        } else {
-            self.eval_lambda(fn_def.params, &fn_def.forms, tail, false)
-        }
-    }
-
-    fn eval_lambda(&mut self, params: ParamList, forms: &[LispObject], tail: &[LispObject], as_macro: bool)
-                   -> Result<LispObject, EvalError> {
-        let binding = self.bind_param_list(&params, tail, !as_macro)?;
-        self.eval_body(Some(binding), forms)
-            .map_err(|(err, index)| err.trace(index).frame(LispObject::List(forms.to_vec()), None))
-    }
-
-    fn eval_macro(&mut self, params: ParamList, forms: &[LispObject], tail: &[LispObject])
-                  -> Result<LispObject, EvalError> {
-        let expansion = self.eval_lambda(params, forms, tail, true)?;
-        self.eval(&expansion)
-            .map_err(|e| e.frame(expansion, None).trace(0))
-    }
-
-    fn eval_special_form(&mut self, sf: SpecialForm, tail: &[LispObject])
-                         -> Result<LispObject, EvalError> {
-        match sf {
-            SpecialForm::Quote => {
-                assert_args(Match::Exact, tail, 1, || "special form quote".to_string())?;
-                Ok(tail[0].clone())
-            }
-            SpecialForm::Begin => {
-                assert_args(Match::Min, tail, 1, || "special form begin".to_string())?;
-                let result = tail.iter().enumerate()
-                    .map(|(index, object)| self.eval(object)
-                         .map_err(|e| e.trace(index + 1)))
-                    .collect::<Result<Vec<LispObject>, EvalError>>()?;
-                Ok(result[result.len() -1].clone())
-            }
-            SpecialForm::Def => {
-                assert_args(Match::Exact, tail, 2, || "special form def".to_string())?;
-                match tail[0] {
-                    LispObject::Symbol(s) => {
-                        let value = self.eval(&tail[1])
-                            .map_err(|e| e.trace(2))?;
-                        self.env.global(s, value.clone());
-                        Ok(value)
-                    },
-                    _ => Err(EvalError::new("special form def must have a symbol in 1st place"
-                                            .to_string())
-                             .trace(1))
-                }
-            },
-            SpecialForm::Set => {
-                assert_args(Match::Exact, tail, 2, || "special form set".to_string())?;
-                match tail[0] {
-                    LispObject::Symbol(s) => {
-                        let value = self.eval(&tail[1])
-                            .map_err(|e| e.trace(2))?;
-                        self.env.set(s, value.clone());
-                        Ok(value)
-                    },
-                    _ => Err(EvalError::new("special form set must have a symbol in 1st place"
-                                            .to_string())
-                             .trace(1))
-                }
-            },
-            SpecialForm::If => {
-                // TODO allow only lispobject instead of vec<lispobject> as second param
-                assert_args(Match::Min, tail, 2, || "special form if".to_string())?;
-                let predicate = self.eval(&tail[0])
-                    .and_then(|object| object.as_bool())
-                    .map_err(|e| e.trace(1))?;
-                if predicate {
-                    self.eval(&tail[1])
-                        .map_err(|e| e.trace(2))
-                } else if tail.len() == 2 {
-                    Ok(LispObject::Bool(false))
-                } else {
-                    let result = tail[2..].iter().enumerate()
-                        .map(|(index, object)| self.eval(object)
-                             .map_err(|e| e.trace(3 + index)))
-                        .collect::<Result<Vec<LispObject>, EvalError>>()?;
-                    Ok(result[result.len() -1].clone())
-                }
-            },
-            SpecialForm::Let => {
-                assert_args(Match::Min, tail, 2, || "special form let".to_string())?;
-                let binding_forms = tail[0].as_list()
-                    .map_err(|e| e.trace(1))?;
-
-                let binding = binding_forms.iter().enumerate()
-                    .map(|(index, b)| {
-                        let b = b.as_list()
-                            .map_err(|e| e.trace(index).trace(1))?;
-                        let s = b[0].as_symbol()
-                            .map_err(|e| e.trace(0).trace(index).trace(1))?;
-                        let v = self.eval(&b[1])
-                            .map_err(|e| e.trace(1).trace(index).trace(1))?;
-                        Ok((s, v))
-                    })
-                    .collect::<Result<Vec<(Symbol, LispObject)>, EvalError>>()?;
-
-                let forms = &tail[1..];
-                self.eval_body(Some(binding), forms)
-                    .map_err(|(err, index)| err.trace(index + 2))
-            },
-        }
-    }
-
-    fn eval_body(&mut self, binding: Option<Vec<(Symbol,LispObject)>>, forms: &[LispObject])
-                 -> Result<LispObject, (EvalError, usize)> {
-        self.env.push_scope();
-        binding.map_or(
-            (), |b| b.into_iter().for_each(
-                |(sym, value)| self.env.set(sym, value)));
-
-        let result = forms.iter().enumerate()
-            .map(|(index, object)| self.eval(object)
-                 .map_err(|e| (e, index)))
-            .collect::<Result<Vec<LispObject>, (EvalError, usize)>>()
-            .map(|mut v| v.pop().unwrap_or_else(|| LispObject::List(vec![])));
-
-        self.env.pop_scope();
-        result
-    }
-
-    fn parse_function_def<'a>(&mut self, lst: &'a [LispObject])
-                              -> Result<FunctionDef<'a>, EvalError> {
-        assert_args(Match::Min, &lst, 2, || "fn definition".to_string())?;
-
-        let is_macro = match lst[0] {
-            LispObject::Symbol(x) if x == self.symbols.sym_fn =>
-                Ok(false),
-            LispObject::Symbol(x) if x == self.symbols.sym_macro =>
-                Ok(true),
-            _ => Err(EvalError::new(format!("Expected `fn` or `macro` symbol, got `{}`",
-                                            self.symbols.serialize_object(&lst[0])))
-                     .trace(0))
-        }?;
-
-        // TODO mention param-list in err message
-        let param_list = lst[1].as_list()
-            .map_err(|e| e.trace(1))?;
-        let params = self.parse_param_list(param_list)
-            .map_err(|e| e.trace(1))?;
-        let forms = &lst[2..];
-
-        Ok(FunctionDef {
-            params: params,
-            forms: forms,
-            is_macro: is_macro,
-        })
-    }
-
-    fn parse_param_list(&mut self, lst: Vec<LispObject>) -> Result<ParamList, EvalError> {
-        let mut params = as_symbols(&lst)
-            .map_err(|(e, index)| e.trace(index))?;
-        let rest_index = params.iter().enumerate()
-            .find(|(_, sym)| **sym == self.symbols.sym_rest)
-            .map(|(index, _)| index);
-        let rest = split_param_list(&mut params, rest_index)?;
-        Ok((params, rest))
-    }
-
-    fn bind_param_list<'a>(&mut self, params: &ParamList, tail: &[LispObject], eval_args: bool)
-                   -> Result<Vec<(Symbol, LispObject)>, EvalError> {
-        // Check Validity of Arguments
-        let m = match params.1 {
-            None    => Match::Exact,
-            Some(_) => Match::Min,
-        };
-        assert_args(m, tail, params.0.len(),
-                    || format!("param list {}", self.symbols.serialize_param_list(&params)))?;
-
-        // Evaluate Arguments
-        let mut args = if eval_args {
-            tail.iter().enumerate()
-                .map(|(index, object)| self.eval(object)
-                     // TODO this assumes param list always at position 1
-                     // Return index and error and process in caller
-                     .map_err(|e| e.trace(index + 1)))
-                .collect::<Result<Vec<LispObject>, EvalError>>()?
-        } else {
-            tail.to_vec()
-        };
-
-        // Return Binding
-        let symbols = params.0.clone().into_iter();
-        if let Some(rest_sym) = params.1 {
-            let rest_args = args.split_off(params.0.len());
-            args.push(LispObject::List(rest_args));
-            Ok(symbols
-               .chain(iter::once(rest_sym))
-               .zip(args.into_iter())
-               .collect::<Vec<(Symbol, LispObject)>>())
-        } else {
-            Ok(symbols
-               .zip(args.into_iter())
-               .collect::<Vec<(Symbol, LispObject)>>())
-        }
-    }
-}
-
-fn split_param_list(lst: &mut Vec<Symbol>, rest_index: Option<usize>)
-                    -> Result<Option<Symbol>, EvalError> {
-    match rest_index {
-        None => Ok(None),
-        Some(rest_index) => if rest_index == lst.len() - 2 {
-            let rest = lst.split_off(rest_index)[1];
-            Ok(Some(rest))
-        } else {
-            Err(EvalError::new("&rest must be second to last in parameter list".to_string())
-                .trace(rest_index))
-        }
-    }
-}
+use rustyline::{error::ReadlineError, Editor};
+use rustyline;
+use std::cell::{OnceCell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::iter;
+use std::num::NonZeroU64;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::fs::{self, File};
+use std::io::{self, prelude::*, BufReader};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+use crate::{
+    condition,
+    lisp_object::{
+        Symbol,
+        ParamList,
+        ErrorKind,
+        EvalError,
+        Lambda,
+        LispObject,
+        Sexpr,
+        SpecialForm,
+        SerializeSymbol,
+        SourceMap,
+        Trace,
+    },
+    lisp_object_util::{
+        Match,
+        assert_args,
+        catch_handler,
+    },
+    reader::{Reader, ReadError},
+    env::{Assign, DisplayOptions, Env, EnvView, Printer, Symbols, create_root, mark_symbols, select_features},
+    err::{handle_eval_error, handle_read_error, print_frames, print_message, FRAME_LIMIT},
+    exc,
+    inspect,
+    interrupt,
+    native,
+    pattern::{self, Pattern},
+    persist,
+    stack,
+    timer::Timers,
+    trace::{self, Kind},
+};
+
+// Definitions available in every interpreter created by Interpreter::new.
+const PRELUDE: &str = include_str!("../base.lisp");
+
+// Nested evaluations allowed by default and the stack size they need. On
+// threads with smaller stacks, evaluations fail with a stack-overflow error
+// when the stack runs low, see stack.rs.
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+thread_local! {
+    // Symbols and root environment after evaluating the prelude. Computed by
+    // the first Interpreter::new on a thread and cloned by later ones.
+    static SNAPSHOT: RefCell<Option<(Symbols, Env)>> = const { RefCell::new(None) };
+}
+
+pub enum ExecError {
+    Read(ReadError),
+    Eval(EvalError),
+    Io(String),
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::Read(e) => write!(f, "{}", e),
+            ExecError::Eval(e) => write!(f, "{}", e),
+            ExecError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+// The result of evaluating a form up to its tail position, see eval_tail.
+enum Step {
+    Value(LispObject),
+    // Continue with the element at index of the current form
+    Descend(usize),
+    // Continue with the element at the second index of the clause at the
+    // first index of the current form
+    DescendClause(usize, usize),
+    // Continue with the body form at index of a called lambda
+    Call(Rc<Lambda>, usize),
+    // Continue with the body form at index of a called fn list
+    CallForm(Rc<LispObject>, usize),
+    Expand(Rc<LispObject>),
+}
+
+// The form evaluation in tail position continues in, reached from here by a
+// path of indices. For Lambda the first index selects the body form.
+enum Root<'a> {
+    Form(&'a LispObject),
+    Owned(Rc<LispObject>),
+    Lambda(Rc<Lambda>),
+}
+
+impl Root<'_> {
+    fn node(&self, path: &[usize]) -> &LispObject {
+        let (mut node, path) = match self {
+            Root::Form(form) => (*form, path),
+            Root::Owned(form) => (form.as_ref(), path),
+            Root::Lambda(lambda) => (&lambda.body[path[0]], &path[1..]),
+        };
+        for index in path {
+            node = match node {
+                LispObject::List(l) => &l[*index],
+                _ => unreachable!("tail positions are elements of lists"),
+            };
+        }
+        node
+    }
+}
+
+// Context errors in tail position would have received from the nested calls
+// eval_tail replaces.
+enum Context {
+    Trace(usize),
+    Frame(Rc<LispObject>),
+    Body(Rc<LispObject>),
+    Lambda(Rc<Lambda>),
+}
+
+fn annotate(err: EvalError, contexts: &[Context]) -> EvalError {
+    contexts.iter().rev().fold(err, |err, context| match context {
+        Context::Trace(index) => err.trace(*index),
+        Context::Frame(form) => err.frame(form.as_ref().clone(), None),
+        Context::Body(form) => err.frame(
+            LispObject::List(form.as_slice().map(|l| l[2..].to_vec()).unwrap_or_default()), None),
+        Context::Lambda(lambda) => lambda.frame(err),
+    })
+}
+
+// Positional and rest parameters, patterns by index and keyword parameters.
+type ParsedParams = (ParamList, Vec<(usize, Pattern)>, Vec<Symbol>);
+
+pub struct FunctionDef<'a> {
+    params: ParamList,
+    patterns: Vec<(usize, Pattern)>,
+    keys: Vec<Symbol>,
+    forms: &'a [LispObject],
+    is_macro: bool,
+}
+
+#[derive(Clone)]
+pub struct Interpreter {
+    symbols: Symbols,
+    env: Env,
+    legacy_if: bool,   // Evaluate everything after the consequent as else body
+    strict_predicates: bool,
+    timers: Timers,
+    specs: HashMap<Symbol, Rc<Spec>>,
+    check_specs: bool,
+    step_guard: Option<StepGuard>,
+    inputs: Vec<String>,
+    // The last error reported, with all of its frames
+    last_error: Option<Rc<EvalError>>,
+    // Number of nested evaluations and the limit on them
+    depth: usize,
+    max_depth: Option<usize>,
+    // Address the stack of the thread may not grow beyond, if known.
+    // Interpreters are not Send, so this is the thread that created it.
+    stack_limit: Option<usize>,
+    // Number of loops being evaluated and the values of a pending recur
+    loops: usize,
+    recur: Option<Vec<LispObject>>,
+    cancel: CancellationToken,
+    load_hook: Option<Rc<RefCell<dyn LoadHook>>>,
+    // Files being loaded, outermost first, by canonical path and as named
+    loading: Vec<(PathBuf, String)>,
+    // Number of escape continuations created, identifying the next one
+    escapes: u64,
+    print_length: Option<usize>,
+    float_precision: Option<usize>,
+    color: bool,
+    // Steps each top-level evaluation may take and the steps of the current
+    // one
+    fuel: Option<u64>,
+    steps: u64,
+    features: Option<Vec<String>>,
+    printer: Option<Rc<dyn Printer>>,
+    // Values assigned to globals while recording, with the forms assigning
+    // them, oldest first
+    assignments: HashMap<Symbol, Vec<(LispObject, LispObject)>>,
+}
+
+// Lines a top-level form of a file spans, counting from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+// Observes the top-level forms of files evaluated by read_file and load,
+// e.g. to report progress or audit loaded code.
+pub trait LoadHook {
+    // Called before form is evaluated, returning false skips it.
+    fn before(&mut self, _file: &str, _form: &LispObject, _span: Span) -> bool {
+        true
+    }
+
+    fn after(&mut self, _file: &str, _form: &LispObject, _span: Span,
+             _result: Result<&LispObject, &EvalError>) {}
+}
+
+// Aborts the evaluation running in an interpreter from another thread. The
+// interpreter stops at the next step with an error whose kind is
+// ErrorKind::Cancelled, after which the token can be used again.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    // Consume a pending cancellation.
+    fn take(&self) -> bool {
+        self.0.load(Ordering::Relaxed) && self.0.swap(false, Ordering::Relaxed)
+    }
+}
+
+// REPL guard against runaway evaluations, asking whether to continue every
+// limit steps of evaluating an entered form.
+#[derive(Clone)]
+struct StepGuard {
+    limit: NonZeroU64,
+    steps: u64,
+}
+
+// Contract of a function set by defspec, checked when it is called by name.
+// Predicates are kept with the expressions they were written as.
+struct Spec {
+    params: Vec<(LispObject, LispObject)>,
+    result: (LispObject, LispObject),
+}
+
+// Settings of an interpreter, see Interpreter::new_with. All of them can
+// also be changed at runtime, from Lisp with set-option!.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InterpreterConfig {
+    // Evaluate everything after the consequent of if as else body
+    pub legacy_if: bool,
+    // Require bools as tests of if, and, or and predicates, otherwise every
+    // value but #f and () counts as true
+    pub strict_predicates: bool,
+    // Check calls of functions with a spec against it
+    pub check_specs: bool,
+    // Ask whether to continue every step_limit steps of a REPL evaluation
+    pub step_limit: Option<NonZeroU64>,
+    // Nested evaluations, e.g. of non-tail calls, allowed before failing
+    // with a stack-overflow error. The default fits into a stack of
+    // STACK_SIZE bytes, deeper limits need a thread with a larger one. On
+    // smaller stacks, evaluations fail the same way when the stack runs low.
+    pub max_depth: Option<usize>,
+    // Elements of each list printed by the REPL, the rest is elided
+    pub print_length: Option<usize>,
+    // Digits after the decimal point of numbers printed by the REPL, None
+    // prints all significant ones
+    pub float_precision: Option<usize>,
+    // Color error messages with ANSI escape sequences
+    pub color: bool,
+    // Steps each top-level evaluation may take before failing with an
+    // out-of-fuel error, e.g. to bound the time untrusted code runs
+    pub fuel: Option<u64>,
+    // Groups of natives with effects outside of the interpreter bound in it,
+    // by the names in env::features, None binds all of them. Not adjustable
+    // from Lisp, so code cannot grant itself access.
+    pub features: Option<Vec<String>>,
+    // Record every value assigned to a global with the form assigning it,
+    // see history-of
+    pub record_history: bool,
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        InterpreterConfig {
+            legacy_if: false,
+            strict_predicates: true,
+            check_specs: true,
+            step_limit: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            print_length: None,
+            float_precision: None,
+            color: true,
+            fuel: None,
+            features: None,
+            record_history: false,
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        if let Some((symbols, env)) = SNAPSHOT.with(|snapshot| snapshot.borrow().clone()) {
+            return Interpreter::from_parts(symbols, env)
+        }
+
+        let mut interpreter = Interpreter::without_prelude();
+        if interpreter.eval_source(PRELUDE).is_err() {
+            panic!("Evaluation of prelude failed.");
+        }
+        SNAPSHOT.with(|snapshot| snapshot.replace(
+            Some((interpreter.symbols.clone(), interpreter.env.clone()))));
+        interpreter
+    }
+
+    // Interpreter with only special forms and natives defined.
+    pub fn without_prelude() -> Interpreter {
+        let mut symbols = Symbols::new();
+        let env = create_root(&mut symbols);
+        Interpreter::from_parts(symbols, env)
+    }
+
+    fn from_parts(symbols: Symbols, env: Env) -> Interpreter {
+        Interpreter {
+            symbols,
+            env,
+            legacy_if: false,
+            strict_predicates: true,
+            timers: Timers::new(),
+            specs: HashMap::new(),
+            check_specs: true,
+            step_guard: None,
+            inputs: vec![],
+            last_error: None,
+            depth: 0,
+            max_depth: InterpreterConfig::default().max_depth,
+            stack_limit: stack::limit(),
+            loops: 0,
+            recur: None,
+            cancel: CancellationToken::default(),
+            load_hook: None,
+            loading: vec![],
+            escapes: 0,
+            print_length: None,
+            float_precision: None,
+            color: true,
+            fuel: None,
+            steps: 0,
+            features: None,
+            printer: None,
+            assignments: HashMap::new(),
+        }
+    }
+
+    // The last error reported by read_file or handle_exec_error. Its frames
+    // are complete, printing only elides them.
+    pub fn last_error(&self) -> Option<&EvalError> {
+        self.last_error.as_deref()
+    }
+
+    pub fn timers(&mut self) -> &mut Timers {
+        &mut self.timers
+    }
+
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    // Inputs entered at the REPL so far, input n is at index n - 1.
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    pub fn new_with(config: InterpreterConfig) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_config(config);
+        interpreter
+    }
+
+    pub fn config(&self) -> InterpreterConfig {
+        InterpreterConfig {
+            legacy_if: self.legacy_if,
+            strict_predicates: self.strict_predicates,
+            check_specs: self.check_specs,
+            step_limit: self.step_guard.as_ref().map(|guard| guard.limit),
+            max_depth: self.max_depth,
+            print_length: self.print_length,
+            float_precision: self.float_precision,
+            color: self.color,
+            fuel: self.fuel,
+            features: self.features.clone(),
+            record_history: self.env.is_recording(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: InterpreterConfig) {
+        self.set_legacy_if(config.legacy_if);
+        self.strict_predicates = config.strict_predicates;
+        self.set_check_specs(config.check_specs);
+        self.set_step_limit(config.step_limit);
+        self.max_depth = config.max_depth;
+        self.print_length = config.print_length;
+        self.float_precision = config.float_precision;
+        self.color = config.color;
+        self.fuel = config.fuel;
+        if config.features != self.features {
+            select_features(&mut self.symbols, &mut self.env, config.features.as_deref());
+            self.features = config.features;
+        }
+        self.env.set_recording(config.record_history);
+    }
+
+    // Change the config field named like option, with dashes instead of
+    // underscores. Returns the previous value, #f for no limit. Errors carry
+    // whether the option (0) or the value (1) is invalid.
+    pub(crate) fn set_option(&mut self, option: &str, value: &LispObject)
+                             -> Result<LispObject, (EvalError, usize)> {
+        let limit = || match value {
+            LispObject::Bool(false) => Ok(None),
+            LispObject::Number(n) if *n >= 1.0 && n.fract() == 0.0 => Ok(Some(*n as u64)),
+            _ => Err((exc::invalid_option(option, self.serialize(value)), 1)),
+        };
+        let flag = || value.as_bool().map_err(|e| (e, 1));
+        let from_limit = |limit: Option<u64>| limit
+            .map_or(LispObject::Bool(false), |limit| LispObject::Number(limit as f64));
+        let mut config = self.config();
+        let previous = match option {
+            "legacy-if" => LispObject::Bool(std::mem::replace(&mut config.legacy_if, flag()?)),
+            "strict-predicates" =>
+                LispObject::Bool(std::mem::replace(&mut config.strict_predicates, flag()?)),
+            "check-specs" => LispObject::Bool(std::mem::replace(&mut config.check_specs, flag()?)),
+            "step-limit" => {
+                let limit = limit()?.and_then(NonZeroU64::new);
+                from_limit(std::mem::replace(&mut config.step_limit, limit).map(NonZeroU64::get))
+            },
+            "max-depth" => {
+                let depth = limit()?.map(|n| n as usize);
+                from_limit(std::mem::replace(&mut config.max_depth, depth).map(|n| n as u64))
+            },
+            "print-length" => {
+                let length = limit()?.map(|n| n as usize);
+                from_limit(std::mem::replace(&mut config.print_length, length).map(|n| n as u64))
+            },
+            "float-precision" => {
+                let digits = match value {
+                    LispObject::Bool(false) => None,
+                    LispObject::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+                    _ => return Err((exc::invalid_option(option, self.serialize(value)), 1)),
+                };
+                std::mem::replace(&mut config.float_precision, digits)
+                    .map_or(LispObject::Bool(false), |digits| LispObject::Number(digits as f64))
+            },
+            "color" => LispObject::Bool(std::mem::replace(&mut config.color, flag()?)),
+            "fuel" => from_limit(std::mem::replace(&mut config.fuel, limit()?)),
+            "record-history" =>
+                LispObject::Bool(std::mem::replace(&mut config.record_history, flag()?)),
+            _ => return Err((exc::unknown_option(option), 0)),
+        };
+        self.set_config(config);
+        Ok(previous)
+    }
+
+    // Whether object counts as true where a test is expected. With strict
+    // predicates it must be a bool or nil.
+    pub(crate) fn is_true(&self, object: &LispObject) -> Result<bool, EvalError> {
+        match object {
+            LispObject::Nil => Ok(false),
+            _ if self.strict_predicates => object.as_bool(),
+            LispObject::Bool(b) => Ok(*b),
+            LispObject::List(l) => Ok(!l.is_empty()),
+            _ => Ok(true),
+        }
+    }
+
+    // Fail with a cancelled error if the cancellation token was triggered.
+    pub(crate) fn check_cancelled(&self) -> Result<(), EvalError> {
+        if self.cancel.take() {
+            return Err(exc::cancelled())
+        }
+        Ok(())
+    }
+
+    // Ask whether to continue every limit steps of evaluating a form entered
+    // at the REPL, None evaluates forms without asking.
+    pub fn set_step_limit(&mut self, limit: Option<NonZeroU64>) {
+        self.step_guard = limit.map(|limit| StepGuard { limit, steps: 0 });
+    }
+
+    // Enable or disable checking calls against specs, returns the previous
+    // setting.
+    pub fn set_check_specs(&mut self, enabled: bool) -> bool {
+        std::mem::replace(&mut self.check_specs, enabled)
+    }
+
+    pub fn set_legacy_if(&mut self, legacy_if: bool) {
+        self.legacy_if = legacy_if;
+    }
+
+    // Set the hook notified of top-level forms loaded from files, returning
+    // the previous one.
+    pub fn set_load_hook(&mut self, hook: Option<Rc<RefCell<dyn LoadHook>>>)
+                         -> Option<Rc<RefCell<dyn LoadHook>>> {
+        std::mem::replace(&mut self.load_hook, hook)
+    }
+
+    // Define globals by name, e.g. configuration injected by a host. Fails
+    // at the first name that is a constant, the ones before stay defined.
+    pub fn define_globals(&mut self, bindings: &[(&str, LispObject)]) -> Result<(), EvalError> {
+        for (name, value) in bindings {
+            let sym = self.symbols.intern(name);
+            if self.env.global(sym, value.clone()) == Assign::Constant {
+                return Err(exc::assign_constant(Some(name)))
+            }
+        }
+        Ok(())
+    }
+
+    // Call f with bindings in a local scope, e.g. request-specific values
+    // for one evaluation. The scope is left when f returns, whether its
+    // evaluations failed or not, so the bindings only stay visible to
+    // closures created within. Definitions made by f are global as usual.
+    pub fn with_scope<T>(&mut self, bindings: &[(&str, LispObject)],
+                         f: impl FnOnce(&mut Interpreter) -> T) -> T {
+        let binding = bindings.iter()
+            .map(|(name, value)| (self.symbols.intern(name), value.clone()))
+            .collect();
+        let outer = self.env.scope();
+        self.env.push_scope(binding);
+        let result = f(self);
+        self.env.enter(outer);
+        result
+    }
+
+    pub fn read_file(&mut self, f: &str) -> Result<(), String> {
+        if let Err(e) = self.enter_file(f) {
+            handle_eval_error(self.color, &self.symbols, &e);
+            self.last_error = Some(Rc::new(e));
+            return Err(format!("Evaluation of {} failed.", f));
+        }
+        let result = self.read_forms(f);
+        self.loading.pop();
+        result
+    }
+
+    // Mark f as being loaded, failing if it already is, which would load it
+    // forever.
+    fn enter_file(&mut self, f: &str) -> Result<(), EvalError> {
+        let path = fs::canonicalize(f).unwrap_or_else(|_| PathBuf::from(f));
+        if let Some(index) = self.loading.iter().position(|(p, _)| *p == path) {
+            let chain = self.loading[index..].iter()
+                .map(|(_, name)| name.as_str())
+                .chain(iter::once(f))
+                .collect::<Vec<&str>>();
+            return Err(exc::circular_load(&chain))
+        }
+        self.loading.push((path, f.to_string()));
+        Ok(())
+    }
+
+    fn read_forms(&mut self, f: &str) -> Result<(), String> {
+        let mut reader = Reader::new();
+        let mut prog: Vec<LispObject> = vec![];
+        let mut spans = vec![];
+        let mut start = 1;
+        let color = self.color;
+
+        let file = File::open(f).map_err(|e| e.to_string())?;
+        let fin = BufReader::new(file);
+
+        {
+            let _span = trace::span(Kind::Read, || f.to_string());
+            for (index, line) in fin.lines().enumerate() {
+                let line = line.map_err(|e| e.to_string())?;
+                if reader.is_empty() {
+                    start = index + 1;
+                }
+                reader.partial(&mut self.symbols, &mut prog, &line)
+                    .or_else(|e| handle_read_error(color, &line, e))
+                    .map_err(|e| e.to_string())?;
+                if prog.len() > spans.len() {
+                    spans.resize(prog.len(), Span { start, end: index + 1 });
+                    start = index + 1;
+                }
+            }
+        }
+
+        for (object, span) in prog.into_iter().zip(spans) {
+            if let Err(e) = self.eval_top_level(f, &object, span).and_then(|_| self.run_timers()) {
+                handle_eval_error(self.color, &self.symbols, &e);
+                self.last_error = Some(Rc::new(e));
+                return Err(format!("Evaluation of {} failed.", f));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Drop the names of symbols no longer referenced by the environment,
+    // specs or pending timers, for embedders interning a lot of input. Values
+    // the embedder keeps, and values only reachable through handles, must be
+    // passed in roots, otherwise their symbols lose their names.
+    pub fn compact_symbols(&mut self, roots: &[LispObject]) {
+        let mut live = HashSet::new();
+        self.env.mark_symbols(&mut live);
+        for (sym, spec) in self.specs.iter() {
+            live.insert(*sym);
+            spec.params.iter().chain(iter::once(&spec.result))
+                .for_each(|(src, pred)| {
+                    mark_symbols(src, &mut live);
+                    mark_symbols(pred, &mut live);
+                });
+        }
+        for (sym, assignments) in self.assignments.iter() {
+            live.insert(*sym);
+            assignments.iter()
+                .for_each(|(value, form)| {
+                    mark_symbols(value, &mut live);
+                    mark_symbols(form, &mut live);
+                });
+        }
+        self.timers.thunks()
+            .chain(self.recur.iter().flatten())
+            .chain(roots)
+            .for_each(|object| mark_symbols(object, &mut live));
+        self.symbols.compact(&live);
+    }
+
+    pub fn symbols(&self) -> &Symbols {
+        &self.symbols
+    }
+
+    pub(crate) fn symbols_mut(&mut self) -> &mut Symbols {
+        &mut self.symbols
+    }
+
+    pub fn env_view(&self) -> EnvView<'_> {
+        EnvView::new(&self.env, &self.symbols)
+    }
+
+    // Write the globals defined after the prelude to f, to be restored with
+    // read_file. Returns the names of globals whose values cannot be written.
+    pub fn save_globals(&self, f: &str) -> Result<Vec<String>, String> {
+        let prelude = Interpreter::new();
+        let bindings = self.env.globals()
+            .filter(|(sym, value, _)| !prelude.env.resolve(sym).is_some_and(|v| v.equals(value)))
+            // Results remembered by the REPL
+            .filter(|(sym, _, _)| !matches!(self.symbols.as_string(sym), Some("*1" | "*2" | "*3")))
+            .map(|(sym, value, constant)| (sym, value.clone(), constant))
+            .collect();
+        persist::save(&self.symbols, f, bindings)
+    }
+
+    // Evaluate the forms in file f as soon as each of them is read. A parsed
+    // form is dropped after its evaluation, so only values it retained stay
+    // alive and peak memory is bounded by the largest form, not the file.
+    pub fn load(&mut self, f: &str) -> Result<LispObject, ExecError> {
+        self.enter_file(f).map_err(ExecError::Eval)?;
+        let result = self.load_forms(f);
+        self.loading.pop();
+        result
+    }
+
+    fn load_forms(&mut self, f: &str) -> Result<LispObject, ExecError> {
+        let mut reader = Reader::new();
+        let mut prog: Vec<LispObject> = vec![];
+        let mut result = LispObject::Nil;
+        let mut start = 1;
+
+        let file = File::open(f).map_err(|e| ExecError::Io(format!("{}: {}", f, e)))?;
+        let fin = BufReader::new(file);
+        let _span = trace::span(Kind::Load, || f.to_string());
+
+        for (index, line) in fin.lines().enumerate() {
+            let line = line.map_err(|e| ExecError::Io(format!("{}: {}", f, e)))?;
+            if reader.is_empty() {
+                start = index + 1;
+            }
+            reader.partial(&mut self.symbols, &mut prog, &line)
+                .map_err(ExecError::Read)?;
+            let span = Span { start, end: index + 1 };
+            if !prog.is_empty() {
+                start = index + 1;
+            }
+            for obj in prog.drain(..) {
+                if let Some(value) = self.eval_top_level(f, &obj, span)
+                    .map_err(|e| ExecError::Eval(e.frame(obj, Some(f.to_string()))))? {
+                    result = value;
+                }
+                self.run_timers().map_err(ExecError::Eval)?;
+            }
+        }
+        if !reader.is_empty() {
+            return Err(ExecError::Read(ReadError::UnexpectedEndOfInput))
+        }
+        Ok(result)
+    }
+
+    // Evaluate a top-level form of file f spanning span, None if the load
+    // hook skipped it.
+    fn eval_top_level(&mut self, f: &str, object: &LispObject, span: Span)
+                      -> Result<Option<LispObject>, EvalError> {
+        let hook = self.load_hook.clone();
+        if let Some(hook) = &hook {
+            if !hook.borrow_mut().before(f, object, span) {
+                return Ok(None)
+            }
+        }
+        let result = self.eval(object);
+        if let Some(hook) = &hook {
+            hook.borrow_mut().after(f, object, span, result.as_ref());
+        }
+        result.map(Some)
+    }
+
+    // Read all forms in input and evaluate them, returning the value of the
+    // last one. Errors are returned to the caller instead of being printed.
+    pub fn eval_str(&mut self, input: &str) -> Result<LispObject, ExecError> {
+        let mut reader = Reader::new();
+        let mut prog: Vec<LispObject> = vec![];
+        {
+            let _span = trace::span(Kind::Read, || "<string>".to_string());
+            reader.partial(&mut self.symbols, &mut prog, input)
+                .map_err(ExecError::Read)?;
+        }
+        let mut result = LispObject::Nil;
+        for obj in prog {
+            result = self.eval(&obj)
+                .map_err(|e| ExecError::Eval(e.frame(obj, None)))?;
+        }
+        Ok(result)
+    }
+
+    // Like eval_str, but input may span multiple lines containing comments.
+    pub fn eval_source(&mut self, source: &str) -> Result<LispObject, ExecError> {
+        let (prog, _) = self.read_source(source)
+            .map_err(ExecError::Read)?;
+        let mut result = LispObject::Nil;
+        for obj in prog {
+            result = self.eval(&obj)
+                .map_err(|e| ExecError::Eval(e.frame(obj, None)))?;
+        }
+        Ok(result)
+    }
+
+    // Read the forms in source, which may span multiple lines containing
+    // comments. Returns whether the last form was complete.
+    pub fn read_source(&mut self, source: &str) -> Result<(Vec<LispObject>, bool), ReadError> {
+        let mut reader = Reader::new();
+        let mut prog: Vec<LispObject> = vec![];
+        let _span = trace::span(Kind::Read, || "<source>".to_string());
+        for line in source.lines() {
+            reader.partial(&mut self.symbols, &mut prog, line)?;
+        }
+        Ok((prog, reader.is_empty()))
+    }
+
+    // Evaluate object in the global environment, for eval.
+    pub(crate) fn eval_global(&mut self, object: &LispObject) -> Result<LispObject, EvalError> {
+        let scope = self.env.enter(None);
+        let result = self.eval(object);
+        self.env.enter(scope);
+        result
+    }
+
+    pub fn serialize(&self, object: &LispObject) -> String {
+        self.symbols.serialize_object(object)
+    }
+
+    // How the REPL shows object, with the print length and printer.
+    pub fn display(&self, object: &LispObject) -> String {
+        let options = DisplayOptions { length: self.print_length, precision: self.float_precision };
+        match &self.printer {
+            Some(printer) => self.symbols.serialize_with(object, options, printer.as_ref()),
+            None => self.symbols.serialize_limited(object, options),
+        }
+    }
+
+    // Set the printer customizing how values are displayed, returning the
+    // previous one.
+    pub fn set_printer(&mut self, printer: Option<Rc<dyn Printer>>) -> Option<Rc<dyn Printer>> {
+        std::mem::replace(&mut self.printer, printer)
+    }
+
+    pub fn interactive(&mut self) {
+        let _interrupt = interrupt::guard(self.cancellation_token());
+        let mut rl = Editor::<()>::new();
+        let mut reader = Reader::new();
+        // Lines of the input currently being read
+        let mut input = String::new();
+
+        loop {
+            let reader_stack = reader.len();
+            let prompt = match reader_stack {
+                0 => format!("{}? ", self.inputs.len() + 1),
+                _ => format!("> {}", "  ".repeat(reader_stack)),
+            };
+
+            match rl.readline(&prompt[..]) {
+                Ok(line) => {
+                    // Ctrl-C while no evaluation was running
+                    self.cancel.take();
+                    let result = match line.trim() {
+                        command if reader_stack == 0 && command.starts_with(':') =>
+                            self.handle_command(&mut rl, command),
+                        _ => {
+                            input.push_str(line.trim_end_matches(['\r', '\n']));
+                            input.push('\n');
+                            let result = self.handle_line(&mut reader, &line);
+                            if reader.is_empty() {
+                                if !input.trim().is_empty() {
+                                    self.inputs.push(input.trim_end().to_string());
+                                }
+                                input.clear();
+                            }
+                            result
+                        },
+                    };
+                    let result = self.handle_exec_error(&line, result);
+                    if result.is_err() {
+                        break result;
+                    }
+
+                    if !line.trim().is_empty() {
+                        rl.add_history_entry(line);
+                    }
+                },
+                Err(ReadlineError::Eof)         => break Ok(()),
+                Err(ReadlineError::Interrupted) => break Ok(()),
+                Err(e) => break Err(e.to_string()),
+            }
+        }.unwrap_or_else(|err| print_message(self.color, &err));
+    }
+
+    fn handle_command(&mut self, rl: &mut Editor<()>, command: &str) -> Result<(), ExecError> {
+        let (name, arg) = command.split_once(' ').unwrap_or((command, ""));
+        match (name, arg.trim()) {
+            (":inspect", expr) => self.inspect(rl, expr),
+            (":steps", "off") => {
+                self.set_step_limit(None);
+                Ok(())
+            },
+            (":steps", limit) => match limit.parse::<NonZeroU64>() {
+                Ok(limit) => {
+                    self.set_step_limit(Some(limit));
+                    Ok(())
+                },
+                _ => Err(ExecError::Io(format!("Expected :steps followed by a positive number or off, got {}",
+                                               command))),
+            },
+            (":redo", n) => self.redo(n),
+            (":env", prefix) => {
+                let mut bindings = self.env_view().iter_bindings()
+                    .filter(|(name, _)| name.starts_with(prefix))
+                    .map(|(name, value)| format!("{} = {}", name, self.serialize(&value)))
+                    .collect::<Vec<String>>();
+                bindings.sort();
+                bindings.iter().for_each(|binding| println!("{}", binding));
+                Ok(())
+            },
+            (":backtrace", limit @ ("" | "full")) => match &self.last_error {
+                Some(error) => {
+                    let limit = if limit == "full" { None } else { Some(FRAME_LIMIT) };
+                    print_frames(self.color, &self.symbols, error, limit);
+                    Ok(())
+                },
+                None => Err(ExecError::Io("No error to show".to_string())),
+            },
+            (":backtrace", _) => Err(ExecError::Io(format!("Expected :backtrace or :backtrace full, got {}",
+                                                           command))),
+            _ => Err(ExecError::Io(format!("Unknown command {}, use :backtrace, :env, :inspect, :redo or :steps",
+                                           name))),
+        }
+    }
+
+    // Evaluate input number n again, recording it as a new input.
+    fn redo(&mut self, n: &str) -> Result<(), ExecError> {
+        let input = n.parse::<usize>().ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| self.inputs.get(i))
+            .cloned()
+            .ok_or_else(|| ExecError::Io(format!("No input {}, expected a number from 1 to {}",
+                                                 n, self.inputs.len())))?;
+        println!("{}", input);
+        self.inputs.push(input.clone());
+        let mut reader = Reader::new();
+        for line in input.lines() {
+            self.handle_line(&mut reader, line)?;
+        }
+        Ok(())
+    }
+
+    fn handle_line(&mut self, reader: &mut Reader, line: &str)
+                   -> Result<(), ExecError> {
+        let mut prog: Vec<LispObject> = vec![];
+        reader.partial(&mut self.symbols, &mut prog, line)
+            .map_err(ExecError::Read)?;
+        for obj in prog {
+            if let Some(guard) = &mut self.step_guard {
+                guard.steps = 0;
+            }
+            let result = self.eval(&obj)
+                .map_err(|e| ExecError::Eval(e.frame(obj, Some(":in:".to_string()))))?;
+            println!("{}", self.display(&result));
+            self.remember(result);
+            self.run_timers().map_err(ExecError::Eval)?;
+        }
+        Ok(())
+    }
+
+    // Bind the last three results of the REPL to *1, *2 and *3.
+    fn remember(&mut self, result: LispObject) {
+        let history = ["*1", "*2", "*3"].map(|name| self.symbols.intern(name));
+        for i in (1..history.len()).rev() {
+            if let Some(previous) = self.env.resolve(&history[i - 1]) {
+                self.env.global(history[i], previous);
+            }
+        }
+        self.env.global(history[0], result);
+    }
+
+    fn inspect(&mut self, rl: &mut Editor<()>, expr: &str) -> Result<(), ExecError> {
+        let expr = if expr.is_empty() { "*1" } else { expr };
+        let value = self.eval_str(expr)?;
+        inspect::run(self.color, &self.symbols, rl, expr, value)
+            .map_err(|e| ExecError::Io(e.to_string()))
+    }
+
+    pub fn handle_exec_error(&mut self, line: &str, e: Result<(), ExecError>)
+                             -> Result<(), String> {
+        match e {
+            Err(ExecError::Eval(e)) => {
+                handle_eval_error(self.color, &self.symbols, &e);
+                self.last_error = Some(Rc::new(e));
+            },
+            Err(ExecError::Read(e)) => {
+                if let Err(e) = handle_read_error(self.color, line, e) {
+                    return Err(e.to_string())
+                }
+            },
+            Err(ExecError::Io(e)) => print_message(self.color, &e),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // Expand the macro calls in the body of a function definition, but not
+    // in its parameter list, once when it is defined so calls don't pay for
+    // expansion. locals are the names bound around it.
+    fn expand_definition(&mut self, object: LispObject, locals: &mut Vec<Symbol>)
+                         -> Result<LispObject, EvalError> {
+        match object {
+            LispObject::List(mut l) if self.is_function_def(&l) => {
+                let outer = locals.len();
+                binder_symbols(&l[1], locals);
+                let body = l.split_off(2);
+                for (index, form) in body.into_iter().enumerate() {
+                    l.push(self.expand_macros(form, locals)
+                           .map_err(|e| e.trace(index + 2))?);
+                }
+                locals.truncate(outer);
+                Ok(LispObject::List(l))
+            },
+            obj => Ok(obj),
+        }
+    }
+
+    // Expand the macro calls in object. locals are the names bound by the
+    // enclosing forms, calls through them are not macro calls.
+    fn expand_macros(&mut self, object: LispObject, locals: &mut Vec<Symbol>)
+                        -> Result<LispObject, EvalError> {
+        match object {
+            LispObject::List(l) => {
+                let shadowed = l.first().and_then(|o| o.as_symbol().ok())
+                    .is_some_and(|s| locals.contains(&s));
+                if shadowed {
+                    self.expand_elements(l, locals)
+                } else if let Some(mac) = self.as_macro_call(&l) {
+                    let expansion = self.expand_macro(mac, &l[1..], l[0].as_symbol().ok())?;
+                    self.expand_macros(expansion, locals)
+                } else if self.is_function_def(&l) {
+                    self.expand_definition(LispObject::List(l), locals)
+                } else if l.first().and_then(|o| o.as_symbol().ok())
+                    .is_some_and(|s| s == self.symbols.sym_quote || s == self.symbols.sym_quasiquote) {
+                    Ok(LispObject::List(l))
+                } else if self.is_match_form(&l) {
+                    self.expand_match_form(l, locals)
+                } else if self.is_special_form(&l, SpecialForm::EvalWhen) {
+                    self.expand_eval_when(l, locals)
+                } else if let Some(sf) = self.as_binding_form(&l) {
+                    self.expand_binding_form(l, sf, locals)
+                } else {
+                    self.expand_elements(l, locals)
+                }
+            },
+            obj => Ok(obj),
+        }
+    }
+
+    fn expand_elements(&mut self, l: Vec<LispObject>, locals: &mut Vec<Symbol>)
+                       -> Result<LispObject, EvalError> {
+        Ok(LispObject::List(
+            l.into_iter().enumerate()
+                .map(|(index, object)| self.expand_macros(object, locals)
+                     .map_err(|e| e.trace(index)))
+                .collect::<Result<Vec<LispObject>, EvalError>>()?
+        ))
+    }
+
+    // Expand the value and clause bodies of a match form, but not the patterns.
+    fn expand_match_form(&mut self, l: Vec<LispObject>, locals: &mut Vec<Symbol>)
+                         -> Result<LispObject, EvalError> {
+        let mut expanded = Vec::with_capacity(l.len());
+        for (index, object) in l.into_iter().enumerate() {
+            expanded.push(match object {
+                LispObject::List(mut clause) if index > 1 && !clause.is_empty() => {
+                    let outer = locals.len();
+                    binder_symbols(&clause[0], locals);
+                    let body = clause.split_off(1);
+                    for (i, form) in body.into_iter().enumerate() {
+                        clause.push(self.expand_macros(form, locals)
+                                    .map_err(|e| e.trace(i + 1).trace(index))?);
+                    }
+                    locals.truncate(outer);
+                    LispObject::List(clause)
+                },
+                object if index == 1 => self.expand_macros(object, locals)
+                    .map_err(|e| e.trace(index))?,
+                object => object,
+            });
+        }
+        Ok(LispObject::List(expanded))
+    }
+
+    // The special form binding names in the list following its head that l
+    // is, if any.
+    fn as_binding_form(&self, l: &[LispObject]) -> Option<SpecialForm> {
+        [SpecialForm::Let, SpecialForm::LetStar, SpecialForm::Letrec, SpecialForm::LetValues,
+         SpecialForm::Loop].iter()
+            .find(|sf| self.is_special_form(l, (*sf).clone()))
+            .cloned()
+    }
+
+    // Expand the value forms and body of a binding form sf, but not its
+    // binders. The values of let* see the previous bindings, those of letrec
+    // all of them.
+    fn expand_binding_form(&mut self, l: Vec<LispObject>, sf: SpecialForm, locals: &mut Vec<Symbol>)
+                           -> Result<LispObject, EvalError> {
+        let outer = locals.len();
+        let mut expanded = Vec::with_capacity(l.len());
+        let mut forms = l.into_iter();
+        expanded.extend(forms.next());
+        match forms.next() {
+            Some(LispObject::List(bindings)) => {
+                let mut bound = vec![];
+                if let SpecialForm::Letrec = sf {
+                    for binding in &bindings {
+                        if let Ok([binder, _]) = binding.as_slice() {
+                            binder_symbols(binder, locals);
+                        }
+                    }
+                }
+                let mut expanded_bindings = Vec::with_capacity(bindings.len());
+                for (index, binding) in bindings.into_iter().enumerate() {
+                    expanded_bindings.push(match binding {
+                        LispObject::List(mut b) if b.len() == 2 => {
+                            let value = b.pop().unwrap_or(LispObject::Nil);
+                            b.push(self.expand_macros(value, locals)
+                                   .map_err(|e| e.trace(1).trace(index).trace(1))?);
+                            match sf {
+                                SpecialForm::LetStar => binder_symbols(&b[0], locals),
+                                SpecialForm::Letrec => (),
+                                _ => binder_symbols(&b[0], &mut bound),
+                            }
+                            LispObject::List(b)
+                        },
+                        binding => binding,
+                    });
+                }
+                locals.extend(bound);
+                expanded.push(LispObject::List(expanded_bindings));
+            },
+            Some(bindings) => expanded.push(bindings),
+            None => (),
+        }
+        for (index, form) in forms.enumerate() {
+            expanded.push(self.expand_macros(form, locals)
+                          .map_err(|e| e.trace(index + 2))?);
+        }
+        locals.truncate(outer);
+        Ok(LispObject::List(expanded))
+    }
+
+    fn is_match_form(&self, l: &[LispObject]) -> bool {
+        self.is_special_form(l, SpecialForm::Match)
+    }
+
+    fn is_special_form(&self, l: &[LispObject], sf: SpecialForm) -> bool {
+        let head = match l.first() {
+            Some(LispObject::Symbol(s)) => self.env.resolve(s),
+            head => head.cloned(),
+        };
+        matches!(head, Some(LispObject::SpecialForm(head)) if head.to_string() == sf.to_string())
+    }
+
+    // The body of an eval-when form with the :expand situation is evaluated
+    // as it is expanded, so macros and functions it defines are available to
+    // the expansion of the forms following it. Its body is expanded after.
+    fn expand_eval_when(&mut self, mut l: Vec<LispObject>, locals: &mut Vec<Symbol>)
+                        -> Result<LispObject, EvalError> {
+        let (expand, _) = self.eval_when_situations(&l[1..])?;
+        if expand {
+            self.eval_sequence(&l[2..])
+                .map_err(|(err, index)| err.trace(index + 2))?;
+        }
+        let body = l.split_off(2);
+        for (index, form) in body.into_iter().enumerate() {
+            l.push(self.expand_macros(form, locals)
+                   .map_err(|e| e.trace(index + 2))?);
+        }
+        Ok(LispObject::List(l))
+    }
+
+    // Whether the situations of an eval-when form with tail include :expand
+    // and :execute.
+    fn eval_when_situations(&self, tail: &[LispObject]) -> Result<(bool, bool), EvalError> {
+        assert_args(Match::Min, tail, 1, || "special form (eval-when situations &rest body)".to_string(),
+                    || self.symbols.serialize_call("eval-when", tail))?;
+        let mut situations = (false, false);
+        for (index, situation) in tail[0].as_slice().map_err(|e| e.trace(1))?.iter().enumerate() {
+            match situation {
+                LispObject::Keyword(k) if self.symbols.as_string(k) == Some("expand") => situations.0 = true,
+                LispObject::Keyword(k) if self.symbols.as_string(k) == Some("execute") => situations.1 = true,
+                other => return Err(exc::unknown_situation(self.serialize(other)).trace(index).trace(1)),
+            }
+        }
+        Ok(situations)
+    }
+
+    // Apply f to an escape continuation, a function that makes this call
+    // return the values it is called with. It unwinds like an error, running
+    // unwind-protect cleanups on the way, and fails once this call returned.
+    pub(crate) fn call_with_escape(&mut self, f: &LispObject) -> Result<LispObject, EvalError> {
+        self.escapes += 1;
+        let id = LispObject::Number(self.escapes as f64);
+        let values = LispObject::Symbol(self.symbols.intern("values"));
+        let escape = LispObject::Native((vec![self.symbols.intern("id"), self.symbols.intern("values")], None),
+                                        native::escape);
+        let continuation = self.eval(&LispObject::List(vec![
+            LispObject::SpecialForm(SpecialForm::Fn),
+            LispObject::List(vec![LispObject::Symbol(self.symbols.sym_rest), values.clone()]),
+            LispObject::List(vec![escape, id.clone(), values]),
+        ]))?;
+        match self.apply(f, &[continuation]) {
+            Err(err) if err.kind == ErrorKind::Escape && err.data[0].equals(&id) =>
+                match err.data[1].as_slice()? {
+                    [value] => Ok(value.clone()),
+                    values => Ok(LispObject::Values(Rc::new(values.to_vec()))),
+                },
+            result => result,
+        }
+    }
+
+    // Expand form once if it is a call to a macro, returns whether it was.
+    pub fn macroexpand_1(&mut self, form: &LispObject) -> Result<(LispObject, bool), EvalError> {
+        match form {
+            LispObject::List(l) => match self.as_macro_call(l) {
+                Some(mac) => Ok((self.expand_macro(mac, &l[1..], l[0].as_symbol().ok())?, true)),
+                None => Ok((form.clone(), false)),
+            },
+            _ => Ok((form.clone(), false)),
+        }
+    }
+
+    fn as_macro_call(&self, lst: &[LispObject]) -> Option<LispObject> {
+        let resolved_head = lst.first()
+            .and_then(|head| head.as_symbol().ok())
+            .and_then(|sym| self.env.resolve(&sym));
+
+        match resolved_head {
+            Some(LispObject::List(l)) if l.first()
+                .and_then(|o| o.as_symbol().ok()) == Some(self.symbols.sym_macro) =>
+                Some(LispObject::List(l)),
+            Some(LispObject::Lambda(l)) if l.is_macro =>
+                Some(LispObject::Lambda(l)),
+            _ => None,
+        }
+    }
+
+    // Compute the expansion of a call to macro mac with arguments tail.
+    fn expand_macro(&mut self, mac: LispObject, tail: &[LispObject], sym: Option<Symbol>)
+                    -> Result<LispObject, EvalError> {
+        match mac {
+            LispObject::Lambda(lambda) => self.call_lambda(&lambda, tail, false, sym),
+            LispObject::List(lst) => {
+                let fn_def = self.parse_function_def(&lst)
+                    .map_err(|e| e.def_frame(&self.symbols, LispObject::List(lst.clone()), sym)
+                             .trace(0))?;
+                self.eval_lambda(&fn_def, tail, false, sym)
+            },
+            _ => Err(exc::apply_unimpl()),
+        }
+    }
+
+    // Compile an expanded function definition into a Lambda. original is the
+    // definition before expansion.
+    fn compile_definition(&mut self, lst: Vec<LispObject>, original: &LispObject)
+                          -> Result<LispObject, EvalError> {
+        let (params, patterns, keys, is_macro) = {
+            let fn_def = self.parse_function_def(&lst)?;
+            (fn_def.params, fn_def.patterns, fn_def.keys, fn_def.is_macro)
+        };
+        let body = lst[2..].iter()
+            .map(|form| self.resolve_special_forms(form))
+            .collect::<Vec<LispObject>>();
+        let source_map = match original.as_slice() {
+            Ok(original) if original.len() == lst.len() => {
+                let mut calls = vec![];
+                for (index, (original, expanded)) in original.iter().zip(lst.iter()).enumerate().skip(2) {
+                    macro_calls(original, expanded, &mut vec![index - 2], &mut calls);
+                }
+                (!calls.is_empty()).then(|| SourceMap { body: original[2..].to_vec(), calls })
+            },
+            _ => None,
+        };
+        Ok(LispObject::Lambda(Rc::new(Lambda {
+            params,
+            patterns,
+            keys,
+            body,
+            source: lst,
+            is_macro,
+            scope: self.env.scope(),
+            source_map,
+            name: OnceCell::new(),
+        })))
+    }
+
+    // Replace symbols in head position that name special forms by the special
+    // form itself. Quoted and quasiquoted forms are left alone, as are the
+    // bodies of nested fn and macro forms, which are compiled when the closure
+    // is created.
+    fn resolve_special_forms(&self, object: &LispObject) -> LispObject {
+        match object {
+            LispObject::List(l) if !l.is_empty() => {
+                let head = match &l[0] {
+                    LispObject::Symbol(s) => match self.env.resolve(s) {
+                        Some(LispObject::SpecialForm(sf)) => LispObject::SpecialForm(sf),
+                        _ => l[0].clone(),
+                    },
+                    head => self.resolve_special_forms(head),
+                };
+                if let LispObject::SpecialForm(SpecialForm::Quote | SpecialForm::Quasiquote
+                                               | SpecialForm::Fn | SpecialForm::Macro) = head {
+                    return LispObject::List(iter::once(head).chain(l[1..].iter().cloned()).collect())
+                }
+                // Patterns of match clauses are not evaluated
+                if let LispObject::SpecialForm(SpecialForm::Match) = head {
+                    let clauses = l.iter().skip(2).map(|clause| match clause {
+                        LispObject::List(c) if !c.is_empty() => LispObject::List(
+                            iter::once(c[0].clone())
+                                .chain(c[1..].iter().map(|o| self.resolve_special_forms(o)))
+                                .collect()),
+                        clause => clause.clone(),
+                    });
+                    return LispObject::List(iter::once(head)
+                                            .chain(l.get(1).map(|o| self.resolve_special_forms(o)))
+                                            .chain(clauses)
+                                            .collect())
+                }
+                LispObject::List(iter::once(head)
+                                 .chain(l[1..].iter().map(|o| self.resolve_special_forms(o)))
+                                 .collect())
+            },
+            object => object.clone(),
+        }
+    }
+
+    fn is_function_def(&self, lst: &[LispObject]) -> bool {
+        match lst.first() {
+            Some(LispObject::Symbol(s)) => lst.len() >= 2
+                && (*s == self.symbols.sym_fn || *s == self.symbols.sym_macro),
+            _ => false,
+        }
+    }
+
+    // Evaluate object where a single value is expected, taking the first of
+    // multiple values, or nil if there are none.
+    fn eval(&mut self, object: &LispObject) -> Result<LispObject, EvalError> {
+        match self.eval_values(object)? {
+            LispObject::Values(values) => Ok(values.first().cloned().unwrap_or(LispObject::Nil)),
+            value => Ok(value),
+        }
+    }
+
+    // Evaluate object, keeping multiple values returned from tail position.
+    fn eval_values(&mut self, object: &LispObject) -> Result<LispObject, EvalError> {
+        match object {
+            LispObject::List(_) => {
+                if let Some(max_depth) = self.max_depth.filter(|max_depth| self.depth >= *max_depth) {
+                    return Err(exc::stack_overflow(max_depth))
+                }
+                if self.stack_limit.is_some_and(|limit| stack::position() < limit) {
+                    return Err(exc::stack_exhausted(self.depth))
+                }
+                // A new top-level evaluation
+                if self.depth == 0 {
+                    self.steps = 0;
+                }
+                let scope = self.env.scope();
+                let loops = self.loops;
+                self.depth += 1;
+                let result = self.eval_tail(object);
+                self.depth -= 1;
+                self.loops = loops;
+                self.env.enter(scope);
+                result
+            },
+            atom => self.eval_atom(atom),
+        }
+    }
+
+    // Evaluate object, continuing with forms in tail position in this loop
+    // instead of a nested call. Scopes entered on the way and the loops
+    // left by calls are restored by eval. Context of calls replaced by a
+    // tail call is not kept for errors.
+    fn eval_tail(&mut self, object: &LispObject) -> Result<LispObject, EvalError> {
+        let mut root = Root::Form(object);
+        let mut path = vec![];
+        let mut contexts = vec![];
+        // The span of the function whose body is evaluated, ended by a tail
+        // call or the return.
+        let mut span = None;
+        loop {
+            self.count_step()
+                .map_err(|e| annotate(e, &contexts))?;
+            let step = self.eval_step(root.node(&path))
+                .map_err(|e| annotate(e, &contexts))?;
+            match step {
+                Step::Value(value) => return Ok(value),
+                Step::Descend(index) => {
+                    contexts.push(Context::Trace(index));
+                    path.push(index);
+                },
+                Step::DescendClause(clause, index) => {
+                    contexts.push(Context::Trace(clause));
+                    contexts.push(Context::Trace(index));
+                    path.push(clause);
+                    path.push(index);
+                },
+                Step::Call(lambda, index) => {
+                    trace::follow(&mut span, Kind::Call, || self.head_name(root.node(&path)));
+                    // recur in a function body can't rebind the loops of
+                    // its caller
+                    self.loops = 0;
+                    contexts.clear();
+                    contexts.push(Context::Lambda(lambda.clone()));
+                    contexts.push(Context::Trace(index));
+                    root = Root::Lambda(lambda);
+                    path = vec![index];
+                },
+                Step::CallForm(form, index) => {
+                    trace::follow(&mut span, Kind::Call, || self.head_name(root.node(&path)));
+                    self.loops = 0;
+                    contexts.clear();
+                    contexts.push(Context::Body(form.clone()));
+                    contexts.push(Context::Trace(index));
+                    root = Root::Owned(form);
+                    path = vec![index + 2];
+                },
+                Step::Expand(expansion) => {
+                    contexts.push(Context::Trace(0));
+                    contexts.push(Context::Frame(expansion.clone()));
+                    root = Root::Owned(expansion);
+                    path.clear();
+                },
+            }
+        }
+    }
+
+    fn count_step(&mut self) -> Result<(), EvalError> {
+        self.check_cancelled()?;
+        if let Some(fuel) = self.fuel {
+            self.steps += 1;
+            if self.steps > fuel {
+                return Err(exc::out_of_fuel(fuel))
+            }
+        }
+        let guard = match &mut self.step_guard {
+            Some(guard) => guard,
+            None => return Ok(()),
+        };
+        guard.steps += 1;
+        if guard.steps % guard.limit.get() != 0 {
+            return Ok(())
+        }
+        eprint!("Evaluated {} steps, continue? [y/N] ", guard.steps);
+        io::stderr().flush().ok();
+        let mut answer = String::new();
+        match io::stdin().read_line(&mut answer) {
+            Ok(_) if answer.trim().eq_ignore_ascii_case("y") => Ok(()),
+            _ => Err(exc::step_limit(guard.steps)),
+        }
+    }
+
+    fn eval_step(&mut self, object: &LispObject) -> Result<Step, EvalError> {
+        let l = match object {
+            LispObject::List(l) => l,
+            atom => return self.eval_atom(atom).map(Step::Value),
+        };
+        if l.is_empty() {
+            return Err(exc::apply_empty())
+        }
+
+        let tail = &l[1..];
+        let head = match &l[0] {
+            // Resolved when the surrounding function was compiled
+            LispObject::SpecialForm(sf) => LispObject::SpecialForm(sf.clone()),
+            head => self.eval(head)
+                .map_err(|e| e.trace(0))?,
+        };
+        let sym = l[0].as_symbol().ok();
+
+        if self.check_specs && matches!(head, LispObject::Native(..) | LispObject::Lambda(_)
+                                        | LispObject::List(_)) {
+            if let Some((name, spec)) = sym.and_then(|s| Some((s, self.specs.get(&s)?.clone()))) {
+                return self.eval_checked_call(&spec, name, &head, tail).map(Step::Value)
+            }
+        }
+
+        match head {
+            LispObject::SpecialForm(sf)
+                => self.eval_special_form(sf, tail),
+            LispObject::Native(params, func) => {
+                let args = self.bind_args(&params, tail, true, sym)?;
+                let _span = trace::span(Kind::Native, || self.callee_name(sym));
+                func(self, &args[..]).map(Step::Value)
+            }
+            LispObject::List(lst) => {
+                self.eval_form(lst, tail, sym)
+            }
+            LispObject::Lambda(lambda) => {
+                if lambda.is_macro {
+                    let expansion = self.call_lambda(&lambda, tail, false, sym)?;
+                    Ok(Step::Expand(Rc::new(expansion)))
+                } else {
+                    self.enter_lambda(lambda, tail, sym)
+                }
+            }
+            _ => Err(exc::apply_unimpl()
+                     .def_frame(&self.symbols, head, sym)
+                     .trace(0))
+        }
+    }
+
+    // Call func with the arguments in tail, checking them and the result
+    // against the spec of name. The call is not in tail position, as its
+    // result is checked after it returns.
+    fn eval_checked_call(&mut self, spec: &Spec, name: Symbol, func: &LispObject,
+                         tail: &[LispObject])
+                         -> Result<LispObject, EvalError> {
+        let name = self.symbols.as_string(&name).unwrap_or_default().to_string();
+        if spec.params.len() != tail.len() {
+            return Err(EvalError::new(format!("spec of {} requires {} arguments, got {}",
+                                              name, spec.params.len(), tail.len()))
+                       .with_condition(condition::SPEC_ERROR))
+        }
+        let args = tail.iter().enumerate()
+            .map(|(index, arg)| self.eval(arg)
+                 .map_err(|e| e.trace(index + 1)))
+            .collect::<Result<Vec<LispObject>, EvalError>>()?;
+        for (index, (arg, predicate)) in args.iter().zip(&spec.params).enumerate() {
+            self.check_spec(predicate, arg, || format!("argument {} of {}", index + 1, name))
+                .map_err(|e| e.trace(index + 1))?;
+        }
+        let result = self.apply(func, &args)?;
+        self.check_spec(&spec.result, &result, || format!("result of {}", name))?;
+        Ok(result)
+    }
+
+    fn check_spec(&mut self, (source, predicate): &(LispObject, LispObject), value: &LispObject,
+                  subject: impl FnOnce() -> String)
+                  -> Result<(), EvalError> {
+        let result = self.apply(predicate, std::slice::from_ref(value))?;
+        if self.is_true(&result)? {
+            Ok(())
+        } else {
+            Err(EvalError::new(format!("{} violates spec {}, got {}", subject(),
+                                       self.serialize(source), self.serialize(value)))
+                .with_condition(condition::SPEC_ERROR))
+        }
+    }
+
+    fn eval_atom(&mut self, object: &LispObject) -> Result<LispObject, EvalError> {
+        match object {
+            LispObject::Symbol(s) => match self.env.resolve(s) {
+                Some(object) => Ok(object),
+                None => {
+                    let name = self.symbols.as_string(s);
+                    let similar = name.and_then(|name| self.symbols.similar(
+                        name, |other| self.env.resolve(&other).is_some()));
+                    Err(exc::unbound_symbol(name, similar))
+                },
+            }
+            LispObject::Keyword(s) => Ok(LispObject::Keyword(*s)),
+            LispObject::String(s) => Ok(LispObject::String(s.clone())),
+            LispObject::Number(n) => Ok(LispObject::Number(*n)),
+            LispObject::Nil       => Ok(LispObject::Nil),
+            LispObject::Bool(b)   => Ok(LispObject::Bool(*b)),
+            LispObject::Native((p, r), f) => Ok(LispObject::Native((p.clone(), *r), *f)),
+            LispObject::Lambda(l) => Ok(LispObject::Lambda(l.clone())),
+            LispObject::Map(m) => Ok(LispObject::Map(m.clone())),
+            LispObject::Handle(h) => Ok(LispObject::Handle(h.clone())),
+            LispObject::Stream(s) => Ok(LispObject::Stream(s.clone())),
+            LispObject::Values(v) => Ok(LispObject::Values(v.clone())),
+            LispObject::List(_) => self.eval(object),
+            LispObject::SpecialForm(_)
+                => Err(exc::unexpected_special_form())
+        }
+    }
+
+    // The name of a span of a call to callee.
+    fn callee_name(&self, callee: Option<Symbol>) -> String {
+        callee.and_then(|s| self.symbols.as_string(&s))
+            .unwrap_or("<anonymous>")
+            .to_string()
+    }
+
+    // The name of a span of the call form.
+    fn head_name(&self, form: &LispObject) -> String {
+        let head = form.as_slice().ok()
+            .and_then(|l| l.first()?.as_symbol().ok());
+        self.callee_name(head)
+    }
+
+    // Bind the arguments of a call to lambda in a new scope on top of the one
+    // it captured and evaluate its body up to the last form.
+    fn enter_lambda(&mut self, lambda: Rc<Lambda>, tail: &[LispObject], callee: Option<Symbol>)
+                    -> Result<Step, EvalError> {
+        let binding = self.bind_param_list(&lambda.params, &lambda.patterns, &lambda.keys, tail, true, callee)?;
+        self.env.enter(lambda.scope.clone());
+        self.enter_scope(binding);
+        let last = match lambda.body.len() {
+            0 => return Ok(Step::Value(LispObject::Nil)),
+            len => len - 1,
+        };
+        self.eval_sequence(&lambda.body[..last])
+            .map_err(|(err, index)| lambda.frame(err.trace(index)))?;
+        Ok(Step::Call(lambda, last))
+    }
+
+    // Call a function or macro given as list, with its body evaluated in
+    // the scope of the caller.
+    fn eval_form(&mut self, lst: Sexpr, tail: &[LispObject], sym: Option<Symbol>)
+                 -> Result<Step, EvalError> {
+        let fn_def = self.parse_function_def(&lst)
+            .map_err(|e| e.def_frame(&self.symbols, LispObject::List(lst.clone()), sym)
+                          .trace(0))?;
+        if fn_def.is_macro {
+            let expansion = self.eval_lambda(&fn_def, tail, false, sym)?;
+            return Ok(Step::Expand(Rc::new(expansion)))
+        }
+        let binding = self.bind_param_list(&fn_def.params, &fn_def.patterns, &fn_def.keys, tail, true, sym)?;
+        self.enter_scope(binding);
+        let last = match fn_def.forms.len() {
+            0 => return Ok(Step::Value(LispObject::Nil)),
+            len => len - 1,
+        };
+        self.eval_sequence(&fn_def.forms[..last])
+            .map_err(|(err, index)| err.trace(index)
+                     .frame(LispObject::List(fn_def.forms.to_vec()), None))?;
+        Ok(Step::CallForm(Rc::new(LispObject::List(lst)), last))
+    }
+
+    // Apply a callable to already evaluated arguments. This is the protocol
+    // shared by the evaluator and natives receiving functions as arguments.
+    pub fn apply(&mut self, func: &LispObject, args: &[LispObject])
+                 -> Result<LispObject, EvalError> {
+        match func {
+            LispObject::Native(params, native) => {
+                let args = self.bind_args(params, args, false, None)?;
+                let _span = trace::span(Kind::Native, || self.callee_name(None));
+                native(self, &args[..])
+            },
+            LispObject::List(lst) => {
+                let fn_def = self.parse_function_def(lst)
+                    .map_err(|e| e.frame(func.clone(), None))?;
+                if fn_def.is_macro {
+                    Err(exc::apply_macro())
+                } else {
+                    self.eval_lambda(&fn_def, args, false, None)
+                }
+            },
+            LispObject::Lambda(lambda) => {
+                if lambda.is_macro {
+                    Err(exc::apply_macro())
+                } else {
+                    self.call_lambda(lambda, args, false, None)
+                }
+            },
+            // Special forms evaluating all of their arguments can be applied
+            // by quoting the already evaluated values.
+            LispObject::SpecialForm(sf @ SpecialForm::Begin) |
+            LispObject::SpecialForm(sf @ SpecialForm::If) |
+            LispObject::SpecialForm(sf @ SpecialForm::And) |
+            LispObject::SpecialForm(sf @ SpecialForm::Or) => {
+                let quoted = args.iter()
+                    .map(|arg| self.symbols.quote(arg.clone()))
+                    .collect::<Vec<LispObject>>();
+                match self.eval_special_form(sf.clone(), &quoted)? {
+                    Step::Descend(index) => self.eval(&quoted[index - 1]),
+                    Step::Value(value) => Ok(value),
+                    _ => unreachable!("begin, if, and and or only descend into their arguments"),
+                }
+            },
+            LispObject::SpecialForm(sf) => Err(exc::apply_special_form(sf)),
+            _ => Err(exc::apply_unimpl()),
+        }
+    }
+
+    fn eval_lambda(&mut self, fn_def: &FunctionDef, tail: &[LispObject], eval_args: bool,
+                   callee: Option<Symbol>)
+                   -> Result<LispObject, EvalError> {
+        let binding = self.bind_param_list(&fn_def.params, &fn_def.patterns, &fn_def.keys, tail, eval_args, callee)?;
+        let _span = trace::span(if fn_def.is_macro { Kind::Expand } else { Kind::Call },
+                                || self.callee_name(callee));
+        let loops = std::mem::replace(&mut self.loops, 0);
+        let result = self.eval_body(Some(binding), fn_def.forms);
+        self.loops = loops;
+        result
+            .map_err(|(err, index)| err.trace(index).frame(LispObject::List(fn_def.forms.to_vec()), None))
+    }
+
+    fn call_lambda(&mut self, lambda: &Lambda, tail: &[LispObject], eval_args: bool,
+                   callee: Option<Symbol>)
+                   -> Result<LispObject, EvalError> {
+        let binding = self.bind_param_list(&lambda.params, &lambda.patterns, &lambda.keys, tail, eval_args, callee)?;
+        let _span = trace::span(if lambda.is_macro { Kind::Expand } else { Kind::Call },
+                                || self.callee_name(callee));
+        let caller = self.env.enter(lambda.scope.clone());
+        let loops = std::mem::replace(&mut self.loops, 0);
+        let result = self.eval_body(Some(binding), &lambda.body);
+        self.loops = loops;
+        self.env.enter(caller);
+        result
+            .map_err(|(err, index)| lambda.frame(err.trace(index)))
+    }
+
+    fn eval_special_form(&mut self, sf: SpecialForm, tail: &[LispObject])
+                         -> Result<Step, EvalError> {
+        match sf {
+            SpecialForm::Quote => {
+                assert_args(Match::Exact, tail, 1, || "special form (quote obj)".to_string(),
+                            || self.symbols.serialize_call("quote", tail))?;
+                Ok(Step::Value(tail[0].clone()))
+            }
+            SpecialForm::Begin => {
+                assert_args(Match::Min, tail, 1, || "special form (begin &rest body)".to_string(),
+                            || self.symbols.serialize_call("begin", tail))?;
+                self.eval_sequence(&tail[..tail.len() - 1])
+                    .map_err(|(err, index)| err.trace(index + 1))?;
+                Ok(Step::Descend(tail.len()))
+            }
+            // The body is evaluated like begin if the situations include
+            // :execute, see expand_eval_when for :expand.
+            SpecialForm::EvalWhen => {
+                let (_, execute) = self.eval_when_situations(tail)?;
+                if !execute || tail.len() == 1 {
+                    return Ok(Step::Value(LispObject::Nil))
+                }
+                self.eval_sequence(&tail[1..tail.len() - 1])
+                    .map_err(|(err, index)| err.trace(index + 2))?;
+                Ok(Step::Descend(tail.len()))
+            },
+            // The cleanup forms are evaluated after the protected form, even
+            // if it fails, in which case its error is propagated after them.
+            SpecialForm::UnwindProtect => {
+                assert_args(Match::Min, tail, 1, || "special form (unwind-protect form &rest cleanup)".to_string(),
+                            || self.symbols.serialize_call("unwind-protect", tail))?;
+                let result = self.eval_values(&tail[0])
+                    .map_err(|e| e.trace(1));
+                self.eval_sequence(&tail[1..])
+                    .map_err(|(err, index)| err.trace(index + 2))?;
+                Ok(Step::Value(result?))
+            },
+            // Errors raised by the body are caught by the first catch clause
+            // whose types include the condition type of the error, a clause
+            // without types catches all errors. Its handler is evaluated with
+            // the symbol bound to a map of the :type, :message and :data of
+            // the error. Cancellation and escapes are not caught.
+            SpecialForm::Try => {
+                assert_args(Match::Min, tail, 2, || "special form (try &rest body (catch [types] sym &rest handler))".to_string(),
+                            || self.symbols.serialize_call("try", tail))?;
+                let first = tail.iter()
+                    .position(|o| catch_handler(&self.symbols, o).is_some())
+                    .ok_or_else(|| exc::try_without_catch().trace(tail.len()))?;
+                let clauses = tail[first..].iter().enumerate()
+                    .map(|(index, clause)| self.catch_clause(clause)
+                         .map_err(|e| e.trace(first + index + 1)))
+                    .collect::<Result<Vec<_>, EvalError>>()?;
+                let (err, index) = match self.eval_sequence(&tail[..first]) {
+                    Ok(value) => return Ok(Step::Value(value)),
+                    Err((err, index)) => (err, index),
+                };
+                let clause = clauses.iter().position(|(types, _, _)| {
+                    types.is_empty() || types.iter().any(|t| {
+                        condition::is_a(&err.condition, self.symbols.as_string(t).unwrap_or_default())
+                    })
+                });
+                let (clause, (_, sym, handler)) = match clause {
+                    Some(clause) if err.kind == ErrorKind::Error => (first + clause + 1, clauses[clause].clone()),
+                    _ => return Err(err.trace(index + 1)),
+                };
+                let condition = self.condition(err);
+                self.enter_scope(vec![(sym, condition)]);
+                let handler_forms = &tail[clause - 1].as_slice()?[handler..];
+                if handler_forms.is_empty() {
+                    return Ok(Step::Value(LispObject::Nil))
+                }
+                self.eval_sequence(&handler_forms[..handler_forms.len() - 1])
+                    .map_err(|(err, index)| err.trace(index + handler).trace(clause))?;
+                Ok(Step::DescendClause(clause, handler + handler_forms.len() - 1))
+            },
+            SpecialForm::Def | SpecialForm::Defconst => {
+                let name = sf.to_string();
+                assert_args(Match::Exact, tail, 2, || format!("special form ({} sym value)", name),
+                            || self.symbols.serialize_call(&name, tail))?;
+                match tail[0] {
+                    LispObject::Symbol(s) => {
+                        let value = self.eval(&tail[1])
+                            .map_err(|e| e.trace(2))?;
+                        if let LispObject::Lambda(lambda) = &value {
+                            let name = self.symbols.as_string(&s).unwrap_or_default();
+                            lambda.name.get_or_init(|| name.to_string());
+                        }
+                        let assigned = match sf {
+                            SpecialForm::Defconst => self.env.constant(s, value.clone()),
+                            _ => self.env.global(s, value.clone()),
+                        };
+                        self.check_assign(assigned, s)?;
+                        self.report_changes(sf, tail, s)?;
+                        Ok(Step::Value(value))
+                    },
+                    _ => Err(EvalError::new(format!("special form {} must have a symbol in 1st place",
+                                                    name))
+                             .with_condition(condition::SYNTAX_ERROR)
+                             .trace(1))
+                }
+            },
+            SpecialForm::Set => {
+                assert_args(Match::Exact, tail, 2, || "special form (set sym value)".to_string(),
+                            || self.symbols.serialize_call("set", tail))?;
+                match tail[0] {
+                    LispObject::Symbol(s) => {
+                        let value = self.eval(&tail[1])
+                            .map_err(|e| e.trace(2))?;
+                        let assigned = self.env.set(s, value.clone());
+                        self.check_assign(assigned, s)?;
+                        self.report_changes(sf, tail, s)?;
+                        Ok(Step::Value(value))
+                    },
+                    _ => Err(EvalError::new("special form set must have a symbol in 1st place"
+                                            .to_string())
+                             .with_condition(condition::SYNTAX_ERROR)
+                             .trace(1))
+                }
+            },
+            SpecialForm::Local => {
+                assert_args(Match::Exact, tail, 2, || "special form (local sym value)".to_string(),
+                            || self.symbols.serialize_call("local", tail))?;
+                match tail[0] {
+                    LispObject::Symbol(s) => {
+                        let value = self.eval(&tail[1])
+                            .map_err(|e| e.trace(2))?;
+                        let assigned = self.env.local(s, value.clone());
+                        self.check_assign(assigned, s)?;
+                        self.report_changes(sf, tail, s)?;
+                        Ok(Step::Value(value))
+                    },
+                    _ => Err(EvalError::new("special form local must have a symbol in 1st place"
+                                            .to_string())
+                             .with_condition(condition::SYNTAX_ERROR)
+                             .trace(1))
+                }
+            },
+            SpecialForm::If => {
+                if self.legacy_if {
+                    return self.eval_legacy_if(tail)
+                }
+                let signature = || "special form (if test then [[else] alt])".to_string();
+                let call = || self.symbols.serialize_call("if", tail);
+                assert_args(Match::Min, tail, 2, signature, call)?;
+                assert_args(Match::Max, tail, 4, signature, call)?;
+                let alt_index = match tail.len() {
+                    4 => match tail[2] {
+                        LispObject::Symbol(s) if s == self.symbols.sym_else => Ok(Some(3)),
+                        _ => Err(EvalError::new("special form if expects `else` before its 4th argument"
+                                                .to_string())
+                                 .with_condition(condition::SYNTAX_ERROR)
+                                 .trace(3)),
+                    },
+                    3 => Ok(Some(2)),
+                    _ => Ok(None),
+                }?;
+                let predicate = self.eval(&tail[0])
+                    .and_then(|object| self.is_true(&object))
+                    .map_err(|e| e.trace(1))?;
+                if predicate {
+                    Ok(Step::Descend(2))
+                } else if let Some(index) = alt_index {
+                    Ok(Step::Descend(index + 1))
+                } else {
+                    Ok(Step::Value(LispObject::Nil))
+                }
+            },
+            SpecialForm::Let => {
+                assert_args(Match::Min, tail, 2, || "special form (let bindings &rest body)".to_string(),
+                            || self.symbols.serialize_call("let", tail))?;
+                let binding_forms = tail[0].as_list()
+                    .map_err(|e| e.trace(1))?;
+                let mut binding = vec![];
+                for (index, b) in binding_forms.iter().enumerate() {
+                    let (binder, v) = self.binder_form(b)
+                        .map_err(|e| e.trace(index).trace(1))?;
+                    let v = self.eval(v)
+                        .map_err(|e| e.trace(1).trace(index).trace(1))?;
+                    self.destructure(binder, v, &mut binding)
+                        .map_err(|e| e.trace(0).trace(index).trace(1))?;
+                }
+                self.enter_scope(binding);
+                self.eval_sequence(&tail[1..tail.len() - 1])
+                    .map_err(|(err, index)| err.trace(index + 2))?;
+                Ok(Step::Descend(tail.len()))
+            },
+            // Each value is evaluated in the scope of the previous bindings.
+            SpecialForm::LetStar => {
+                assert_args(Match::Min, tail, 2, || "special form (let* bindings &rest body)".to_string(),
+                            || self.symbols.serialize_call("let*", tail))?;
+                let binding_forms = tail[0].as_list()
+                    .map_err(|e| e.trace(1))?;
+                self.enter_scope(vec![]);
+                for (index, b) in binding_forms.iter().enumerate() {
+                    let (binder, v) = self.binder_form(b)
+                        .map_err(|e| e.trace(index).trace(1))?;
+                    let v = self.eval(v)
+                        .map_err(|e| e.trace(1).trace(index).trace(1))?;
+                    let mut binding = vec![];
+                    self.destructure(binder, v, &mut binding)
+                        .map_err(|e| e.trace(0).trace(index).trace(1))?;
+                    for (s, v) in binding {
+                        self.env.local(s, v);
+                    }
+                }
+                self.eval_sequence(&tail[1..tail.len() - 1])
+                    .map_err(|(err, index)| err.trace(index + 2))?;
+                Ok(Step::Descend(tail.len()))
+            },
+            // All names are bound to () before the values are evaluated in
+            // their scope, so functions created there can call each other.
+            SpecialForm::Letrec => {
+                assert_args(Match::Min, tail, 2, || "special form (letrec bindings &rest body)".to_string(),
+                            || self.symbols.serialize_call("letrec", tail))?;
+                let binding_forms = tail[0].as_list()
+                    .map_err(|e| e.trace(1))?;
+                let binding = binding_forms.iter().enumerate()
+                    .map(|(index, b)| self.binding_form(b)
+                         .map_err(|e| e.trace(index).trace(1)))
+                    .collect::<Result<Vec<(Symbol, &LispObject)>, EvalError>>()?;
+                self.enter_scope(binding.iter()
+                                 .map(|(s, _)| (*s, LispObject::List(vec![])))
+                                 .collect());
+                for (index, (s, v)) in binding.into_iter().enumerate() {
+                    let v = self.eval(v)
+                        .map_err(|e| e.trace(1).trace(index).trace(1))?;
+                    self.env.local(s, v);
+                }
+                self.eval_sequence(&tail[1..tail.len() - 1])
+                    .map_err(|(err, index)| err.trace(index + 2))?;
+                Ok(Step::Descend(tail.len()))
+            },
+            // Binders are patterns matched against the list of values
+            // returned by the value form, (values) binds the empty list.
+            SpecialForm::LetValues => {
+                assert_args(Match::Min, tail, 2, || "special form (let-values bindings &rest body)".to_string(),
+                            || self.symbols.serialize_call("let-values", tail))?;
+                let binding_forms = tail[0].as_list()
+                    .map_err(|e| e.trace(1))?;
+                let mut binding = vec![];
+                for (index, b) in binding_forms.iter().enumerate() {
+                    let (binder, v) = self.binder_form(b)
+                        .map_err(|e| e.trace(index).trace(1))?;
+                    let values = match self.eval_values(v)
+                        .map_err(|e| e.trace(1).trace(index).trace(1))? {
+                        LispObject::Values(values) => values.to_vec(),
+                        value => vec![value],
+                    };
+                    self.destructure(binder, LispObject::List(values), &mut binding)
+                        .map_err(|e| e.trace(0).trace(index).trace(1))?;
+                }
+                self.enter_scope(binding);
+                self.eval_sequence(&tail[1..tail.len() - 1])
+                    .map_err(|(err, index)| err.trace(index + 2))?;
+                Ok(Step::Descend(tail.len()))
+            },
+            // The body of the first clause whose pattern matches the value is
+            // evaluated with the pattern variables bound.
+            SpecialForm::Match => {
+                assert_args(Match::Min, tail, 1, || "special form (match value &rest clauses)".to_string(),
+                            || self.symbols.serialize_call("match", tail))?;
+                let value = self.eval(&tail[0])
+                    .map_err(|e| e.trace(1))?;
+                for (index, clause) in tail[1..].iter().enumerate() {
+                    let clause = clause.as_slice()
+                        .and_then(|c| {
+                            assert_args(Match::Min, c, 2, || "match clause (pattern &rest body)".to_string(),
+                                        || self.symbols.serialize_object(clause))?;
+                            Ok(c)
+                        })
+                        .map_err(|e| e.trace(index + 2))?;
+                    let pattern = pattern::compile(&self.symbols, &clause[0])
+                        .map_err(|e| e.trace(0).trace(index + 2))?;
+                    let mut binding = vec![];
+                    if pattern.bind(&value, &mut binding).is_ok() {
+                        self.enter_scope(binding);
+                        self.eval_sequence(&clause[1..clause.len() - 1])
+                            .map_err(|(err, i)| err.trace(i + 1).trace(index + 2))?;
+                        return Ok(Step::DescendClause(index + 2, clause.len() - 1))
+                    }
+                }
+                Err(exc::match_no_clause(&self.symbols.serialize_object(&value)))
+            },
+            // The body is evaluated again with the loop variables rebound
+            // each time its value comes from a recur in tail position.
+            SpecialForm::Loop => {
+                assert_args(Match::Min, tail, 2, || "special form (loop bindings &rest body)".to_string(),
+                            || self.symbols.serialize_call("loop", tail))?;
+                for (index, object) in tail[1..].iter().enumerate() {
+                    self.check_recur(object, index == tail.len() - 2)
+                        .map_err(|e| e.trace(index + 2))?;
+                }
+                let binding = self.eval_bindings(&tail[0])
+                    .map_err(|e| e.trace(1))?;
+                let vars = binding.iter()
+                    .map(|(sym, _)| *sym)
+                    .collect::<Vec<Symbol>>();
+                self.enter_scope(binding);
+                self.loops += 1;
+                let result = self.eval_loop(&vars, &tail[1..]);
+                self.loops -= 1;
+                self.recur = None;
+                result.map(Step::Value)
+                    .map_err(|(err, index)| err.trace(index + 2))
+            },
+            SpecialForm::Recur => {
+                if self.loops == 0 {
+                    return Err(exc::recur_outside_loop())
+                }
+                let values = tail.iter().enumerate()
+                    .map(|(index, object)| self.eval(object)
+                         .map_err(|e| e.trace(index + 1)))
+                    .collect::<Result<Vec<LispObject>, EvalError>>()?;
+                self.recur = Some(values);
+                Ok(Step::Value(LispObject::List(vec![])))
+            },
+            // Arguments but the last decide by being false for and, true for
+            // or, and are returned then. The last one is in tail position and
+            // its value is returned.
+            SpecialForm::And | SpecialForm::Or => {
+                let decisive = matches!(sf, SpecialForm::Or);
+                let last = match tail.len() {
+                    0 => return Ok(Step::Value(LispObject::Bool(!decisive))),
+                    len => len - 1,
+                };
+                for (index, object) in tail[..last].iter().enumerate() {
+                    let value = self.eval(object)
+                        .map_err(|e| e.trace(index + 1))?;
+                    if self.is_true(&value).map_err(|e| e.trace(index + 1))? == decisive {
+                        return Ok(Step::Value(value))
+                    }
+                }
+                Ok(Step::Descend(tail.len()))
+            },
+            SpecialForm::Quasiquote => {
+                assert_args(Match::Exact, tail, 1, || "special form (quasiquote template)".to_string(),
+                            || self.symbols.serialize_call("quasiquote", tail))?;
+                self.eval_quasiquote(&tail[0], 0)
+                    .map(Step::Value)
+                    .map_err(|e| e.trace(1))
+            },
+            SpecialForm::Defspec => {
+                assert_args(Match::Exact, tail, 4,
+                            || "special form (defspec sym params => result)".to_string(),
+                            || self.symbols.serialize_call("defspec", tail))?;
+                let name = tail[0].as_symbol()
+                    .map_err(|e| e.trace(1))?;
+                match tail[2] {
+                    LispObject::Symbol(s) if self.symbols.as_string(&s) == Some("=>") => (),
+                    _ => return Err(EvalError::new("special form defspec expects `=>` before the result predicate"
+                                                   .to_string())
+                                    .with_condition(condition::SYNTAX_ERROR)
+                                    .trace(3)),
+                }
+                let params = tail[1].as_list()
+                    .map_err(|e| e.trace(2))?
+                    .iter().enumerate()
+                    .map(|(index, predicate)| Ok((predicate.clone(), self.eval(predicate)
+                                                  .map_err(|e| e.trace(index).trace(2))?)))
+                    .collect::<Result<Vec<(LispObject, LispObject)>, EvalError>>()?;
+                let result = self.eval(&tail[3])
+                    .map_err(|e| e.trace(4))?;
+                self.specs.insert(name, Rc::new(Spec {
+                    params,
+                    result: (tail[3].clone(), result),
+                }));
+                Ok(Step::Value(tail[0].clone()))
+            },
+            SpecialForm::Fn | SpecialForm::Macro => {
+                let name = sf.to_string();
+                assert_args(Match::Min, tail, 1, || format!("special form ({} params &rest body)", name),
+                            || self.symbols.serialize_call(&name, tail))?;
+                let head = match sf {
+                    SpecialForm::Macro => self.symbols.sym_macro,
+                    _ => self.symbols.sym_fn,
+                };
+                let definition = iter::once(LispObject::Symbol(head))
+                    .chain(tail.iter().cloned())
+                    .collect();
+                let original = LispObject::List(definition);
+                let definition = self.expand_definition(original.clone(), &mut vec![])?
+                    .into_list()?;
+                self.compile_definition(definition, &original).map(Step::Value)
+            },
+        }
+    }
+
+    // Build the value of a quasiquoted template, evaluating unquoted forms and
+    // splicing the lists of unquote-splice into the surrounding list. Unquotes
+    // in nested quasiquotes belong to the inner template, depth counts the
+    // levels of nesting.
+    fn eval_quasiquote(&mut self, template: &LispObject, depth: usize)
+                       -> Result<LispObject, EvalError> {
+        let l = match template {
+            LispObject::List(l) => l,
+            atom => return Ok(atom.clone()),
+        };
+        match self.as_quasiquote_form(l) {
+            Some(s) if s == self.symbols.sym_unquote && depth == 0 =>
+                return self.eval(&l[1])
+                    .map_err(|e| e.trace(1)),
+            Some(s) if s == self.symbols.sym_unquote_splice && depth == 0 =>
+                return Err(exc::unquote_splice_outside_list()),
+            Some(s) => {
+                let depth = if s == self.symbols.sym_quasiquote { depth + 1 } else { depth - 1 };
+                let inner = self.eval_quasiquote(&l[1], depth)
+                    .map_err(|e| e.trace(1))?;
+                return Ok(LispObject::List(vec![l[0].clone(), inner]))
+            },
+            None => (),
+        }
+
+        let mut result = vec![];
+        for (index, element) in l.iter().enumerate() {
+            match element {
+                LispObject::List(e) if depth == 0 && self.as_quasiquote_form(e)
+                    == Some(self.symbols.sym_unquote_splice) => {
+                    let spliced = self.eval(&e[1])
+                        .and_then(|object| object.into_list())
+                        .map_err(|err| err.trace(1).trace(index))?;
+                    result.extend(spliced);
+                },
+                element => result.push(self.eval_quasiquote(element, depth)
+                                       .map_err(|e| e.trace(index))?),
+            }
+        }
+        Ok(LispObject::List(result))
+    }
+
+    // The head of l if it is a (quasiquote x), (unquote x) or
+    // (unquote-splice x) form.
+    fn as_quasiquote_form(&self, l: &[LispObject]) -> Option<Symbol> {
+        match l {
+            [LispObject::Symbol(s), _] if *s == self.symbols.sym_quasiquote
+                || *s == self.symbols.sym_unquote
+                || *s == self.symbols.sym_unquote_splice => Some(*s),
+            _ => None,
+        }
+    }
+
+    // Report the changes of bindings after the special form sf with tail
+    // assigned s. While recording, its change of the global s is added to the
+    // history of s. Handlers of watched bindings are called with the symbol,
+    // the old value, nil if it was unbound, and the new value.
+    fn report_changes(&mut self, sf: SpecialForm, tail: &[LispObject], s: Symbol) -> Result<(), EvalError> {
+        for change in self.env.take_changes() {
+            if change.global && change.key == s && self.env.is_recording() {
+                let mut form = vec![LispObject::Symbol(self.symbols.intern(&sf.to_string()))];
+                form.extend_from_slice(tail);
+                self.assignments.entry(change.key).or_default()
+                    .push((change.new.clone(), LispObject::List(form)));
+            }
+            let old = change.old.unwrap_or(LispObject::Nil);
+            match self.env.watcher(change.key) {
+                Some(Some(handler)) => {
+                    self.apply(&handler, &[LispObject::Symbol(change.key), old, change.new])?;
+                },
+                Some(None) => eprintln!("watch: {} {} -> {}",
+                                        self.symbols.as_string(&change.key).unwrap_or_default(),
+                                        self.serialize(&old), self.serialize(&change.new)),
+                None => (),
+            }
+        }
+        Ok(())
+    }
+
+    // The values recorded for the global key with the forms assigning them,
+    // oldest first.
+    pub(crate) fn history_of(&self, key: Symbol) -> &[(LispObject, LispObject)] {
+        self.assignments.get(&key).map_or(&[], |assignments| assignments.as_slice())
+    }
+
+    // Report changes of the binding of key by set and definitions to
+    // handler, or print them if None.
+    pub(crate) fn watch(&mut self, key: Symbol, handler: Option<LispObject>) {
+        self.env.watch(key, handler);
+    }
+
+    pub(crate) fn unwatch(&mut self, key: Symbol) -> bool {
+        self.env.unwatch(key)
+    }
+
+    fn check_assign(&self, assigned: Assign, s: Symbol) -> Result<(), EvalError> {
+        match assigned {
+            Assign::Done => Ok(()),
+            Assign::Unbound => Err(exc::set_unbound(self.symbols.as_string(&s)).trace(1)),
+            Assign::Constant => Err(exc::assign_constant(self.symbols.as_string(&s)).trace(1)),
+        }
+    }
+
+    // Pre-1.0 behaviour of if, everything after the consequent is evaluated
+    // as an implicit begin. Enabled by --legacy-if.
+    fn eval_legacy_if(&mut self, tail: &[LispObject]) -> Result<Step, EvalError> {
+        assert_args(Match::Min, tail, 2, || "special form (if test then &rest alt)".to_string(),
+                    || self.symbols.serialize_call("if", tail))?;
+        let predicate = self.eval(&tail[0])
+            .and_then(|object| self.is_true(&object))
+            .map_err(|e| e.trace(1))?;
+        if predicate {
+            Ok(Step::Descend(2))
+        } else if tail.len() == 2 {
+            Ok(Step::Value(LispObject::Bool(false)))
+        } else {
+            self.eval_sequence(&tail[2..tail.len() - 1])
+                .map_err(|(err, index)| err.trace(3 + index))?;
+            Ok(Step::Descend(tail.len()))
+        }
+    }
+
+    // Evaluate the values of let style bindings ((symbol value) ...).
+    fn eval_bindings(&mut self, bindings: &LispObject)
+                     -> Result<Vec<(Symbol, LispObject)>, EvalError> {
+        bindings.as_list()?.iter().enumerate()
+            .map(|(index, b)| {
+                let (s, v) = self.binding_form(b)
+                    .map_err(|e| e.trace(index))?;
+                let v = self.eval(v)
+                    .map_err(|e| e.trace(1).trace(index))?;
+                Ok((s, v))
+            })
+            .collect()
+    }
+
+    // The symbol and value form of a binding (symbol value).
+    fn binding_form<'a>(&self, binding: &'a LispObject) -> Result<(Symbol, &'a LispObject), EvalError> {
+        let (binder, value) = self.binder_form(binding)?;
+        let s = binder.as_symbol()
+            .map_err(|e| e.trace(0))?;
+        Ok((s, value))
+    }
+
+    // The binder and value form of a binding (binder value).
+    fn binder_form<'a>(&self, binding: &'a LispObject) -> Result<(&'a LispObject, &'a LispObject), EvalError> {
+        let b = binding.as_slice()?;
+        assert_args(Match::Exact, b, 2, || "binding (sym value)".to_string(),
+                    || self.symbols.serialize_object(binding))?;
+        Ok((&b[0], &b[1]))
+    }
+
+    // Bind value to binder, which is a symbol or a list pattern destructuring
+    // value, failing if value does not have the shape of the pattern.
+    fn destructure(&self, binder: &LispObject, value: LispObject, binding: &mut Vec<(Symbol, LispObject)>)
+                   -> Result<(), EvalError> {
+        match binder {
+            LispObject::Symbol(s) => {
+                binding.push((*s, value));
+                Ok(())
+            },
+            LispObject::List(_) => pattern::compile(&self.symbols, binder)?
+                .bind(&value, binding)
+                .map_err(|m| m.error(&self.symbols)),
+            _ => Err(EvalError::new("Expected a symbol or a list pattern".to_string())
+                     .with_condition(condition::SYNTAX_ERROR)),
+        }
+    }
+
+    // The types caught by a clause (catch [types] sym &rest handler), its
+    // symbol and the index of its first handler form. types is a keyword or
+    // a list of keywords, and empty if the clause catches all errors.
+    fn catch_clause(&self, clause: &LispObject) -> Result<(Vec<Symbol>, Symbol, usize), EvalError> {
+        let handler = catch_handler(&self.symbols, clause)
+            .ok_or_else(exc::try_without_catch)?;
+        let clause = clause.as_slice()?;
+        let types = match &clause[1..handler - 1] {
+            [] => vec![],
+            [LispObject::Keyword(t)] => vec![*t],
+            [LispObject::List(l)] => l.iter().enumerate()
+                .map(|(index, t)| match t {
+                    LispObject::Keyword(t) => Ok(*t),
+                    _ => Err(exc::catch_type_expected(self.serialize(t)).trace(index).trace(1)),
+                })
+                .collect::<Result<Vec<Symbol>, EvalError>>()?,
+            [t] => return Err(exc::catch_type_expected(self.serialize(t)).trace(1)),
+            _ => unreachable!(),
+        };
+        Ok((types, clause[handler - 1].as_symbol()?, handler))
+    }
+
+    // The value a caught error is bound to in a catch clause.
+    fn condition(&mut self, err: EvalError) -> LispObject {
+        let typ = LispObject::Keyword(self.symbols.intern("type"));
+        let message = LispObject::Keyword(self.symbols.intern("message"));
+        let data = LispObject::Keyword(self.symbols.intern("data"));
+        LispObject::Map(Rc::new(vec![
+            (typ, LispObject::Keyword(self.symbols.intern(&err.condition))),
+            (message, LispObject::String(self.symbols.string(&err.message))),
+            (data, LispObject::List(err.data)),
+        ]))
+    }
+
+    fn eval_loop(&mut self, vars: &[Symbol], body: &[LispObject])
+                 -> Result<LispObject, (EvalError, usize)> {
+        loop {
+            let value = self.eval_sequence(body)?;
+            match self.recur.take() {
+                Some(values) if values.len() == vars.len() => {
+                    for (sym, value) in vars.iter().zip(values) {
+                        self.env.local(*sym, value);
+                    }
+                },
+                Some(values) => return Err((exc::recur_arity(vars.len(), values.len()), body.len() - 1)),
+                None => return Ok(value),
+            }
+        }
+    }
+
+    // Fail if a recur in object, part of a loop body, is not in tail
+    // position of the loop. Nested loops and functions are checked when
+    // they are evaluated.
+    fn check_recur(&mut self, object: &LispObject, tail: bool) -> Result<(), EvalError> {
+        let l = match object {
+            LispObject::List(l) if !l.is_empty() => l,
+            _ => return Ok(()),
+        };
+        let head = match &l[0] {
+            LispObject::Symbol(s) => self.env.resolve(s),
+            head => Some(head.clone()),
+        };
+        let sequence = |interp: &mut Interpreter, start: usize, tail: bool| {
+            (start..l.len()).try_for_each(|index| {
+                interp.check_recur(&l[index], tail && index == l.len() - 1)
+                    .map_err(|e| e.trace(index))
+            })
+        };
+        match head {
+            Some(LispObject::SpecialForm(SpecialForm::Recur)) if !tail =>
+                Err(exc::recur_not_in_tail_position()),
+            Some(LispObject::SpecialForm(SpecialForm::Quote)) |
+            Some(LispObject::SpecialForm(SpecialForm::Quasiquote)) |
+            Some(LispObject::SpecialForm(SpecialForm::Fn)) |
+            Some(LispObject::SpecialForm(SpecialForm::Macro)) |
+            Some(LispObject::SpecialForm(SpecialForm::Loop)) => Ok(()),
+            Some(LispObject::SpecialForm(SpecialForm::If)) => {
+                (1..l.len()).try_for_each(|index| {
+                    self.check_recur(&l[index], tail && index > 1)
+                        .map_err(|e| e.trace(index))
+                })
+            },
+            Some(LispObject::SpecialForm(SpecialForm::EvalWhen)) => sequence(self, 2, tail),
+            Some(LispObject::SpecialForm(SpecialForm::UnwindProtect)) => sequence(self, 1, false),
+            Some(LispObject::SpecialForm(SpecialForm::Try)) => {
+                (1..l.len()).try_for_each(|index| match catch_handler(&self.symbols, &l[index]) {
+                    Some(handler) => {
+                        let c = l[index].as_slice()?;
+                        (handler..c.len()).try_for_each(|i| {
+                            self.check_recur(&c[i], tail && i == c.len() - 1)
+                                .map_err(|e| e.trace(i).trace(index))
+                        })
+                    },
+                    None => self.check_recur(&l[index], false)
+                        .map_err(|e| e.trace(index)),
+                })
+            },
+            Some(LispObject::SpecialForm(SpecialForm::Begin)) |
+            Some(LispObject::SpecialForm(SpecialForm::And)) |
+            Some(LispObject::SpecialForm(SpecialForm::Or)) => sequence(self, 1, tail),
+            Some(LispObject::SpecialForm(SpecialForm::Match)) => {
+                if let Some(value) = l.get(1) {
+                    self.check_recur(value, false)
+                        .map_err(|e| e.trace(1))?;
+                }
+                for (index, clause) in l.iter().enumerate().skip(2) {
+                    if let LispObject::List(c) = clause {
+                        (1..c.len()).try_for_each(|i| {
+                            self.check_recur(&c[i], tail && i == c.len() - 1)
+                                .map_err(|e| e.trace(i).trace(index))
+                        })?;
+                    }
+                }
+                Ok(())
+            },
+            Some(LispObject::SpecialForm(SpecialForm::Let | SpecialForm::LetStar | SpecialForm::Letrec |
+                                         SpecialForm::LetValues)) => {
+                if let Some(LispObject::List(bindings)) = l.get(1) {
+                    for (index, binding) in bindings.iter().enumerate() {
+                        if let LispObject::List(b) = binding {
+                            (1..b.len()).try_for_each(|i| {
+                                self.check_recur(&b[i], false)
+                                    .map_err(|e| e.trace(i).trace(index).trace(1))
+                            })?;
+                        }
+                    }
+                }
+                sequence(self, 2, tail)
+            },
+            _ => match self.macroexpand_1(object)? {
+                // Errors in the expansion are reported at the macro call
+                (expansion, true) => self.check_recur(&expansion, tail)
+                    .map_err(|mut e| {
+                        e.trace.clear();
+                        e
+                    }),
+                _ => sequence(self, 0, false),
+            },
+        }
+    }
+
+    fn enter_scope(&mut self, binding: Vec<(Symbol, LispObject)>) {
+        self.env.push_scope(binding);
+    }
+
+    fn eval_body(&mut self, binding: Option<Vec<(Symbol,LispObject)>>, forms: &[LispObject])
+                 -> Result<LispObject, (EvalError, usize)> {
+        self.enter_scope(binding.unwrap_or_default());
+
+        let result = self.eval_sequence(forms);
+
+        self.env.pop_scope();
+        result
+    }
+
+    // Evaluate forms in order, only keeping the value of the last one alive.
+    // Errors carry the index of the failing form.
+    fn eval_sequence(&mut self, forms: &[LispObject])
+                     -> Result<LispObject, (EvalError, usize)> {
+        let mut result = LispObject::Nil;
+        for (index, object) in forms.iter().enumerate() {
+            result = self.eval(object)
+                .map_err(|e| (e, index))?;
+        }
+        Ok(result)
+    }
+
+    fn parse_function_def<'a>(&mut self, lst: &'a [LispObject])
+                              -> Result<FunctionDef<'a>, EvalError> {
+        assert_args(Match::Min, lst, 2, || "fn definition (fn params &rest body)".to_string(),
+                    || self.symbols.serialize_object(&LispObject::List(lst.to_vec())))?;
+
+        let is_macro = match lst[0] {
+            LispObject::Symbol(x) if x == self.symbols.sym_fn =>
+                Ok(false),
+            LispObject::Symbol(x) if x == self.symbols.sym_macro =>
+                Ok(true),
+            _ => Err(EvalError::new(format!("Expected `fn` or `macro` symbol, got `{}`",
+                                            self.symbols.serialize_object(&lst[0])))
+                     .with_condition(condition::SYNTAX_ERROR)
+                     .trace(0))
+        }?;
+
+        // TODO mention param-list in err message
+        let param_list = lst[1].as_list()
+            .map_err(|e| e.trace(1))?;
+        let (params, patterns, keys) = self.parse_param_list(param_list)
+            .map_err(|e| e.trace(1))?;
+        let forms = &lst[2..];
+
+        Ok(FunctionDef {
+            params,
+            patterns,
+            keys,
+            forms,
+            is_macro,
+        })
+    }
+
+    // Parameters given as lists are patterns destructuring their argument,
+    // they take the place of a `_` parameter in the returned ParamList.
+    // Parameters following &key are returned separately.
+    fn parse_param_list(&mut self, mut lst: Vec<LispObject>)
+                        -> Result<ParsedParams, EvalError> {
+        let key_index = lst.iter().position(|o| o.as_symbol().ok() == Some(self.symbols.sym_key));
+        let keys = match key_index {
+            Some(key_index) => lst.split_off(key_index).iter().enumerate().skip(1)
+                .map(|(index, o)| o.as_symbol().map_err(|e| e.trace(key_index + index)))
+                .collect::<Result<Vec<Symbol>, EvalError>>()?,
+            None => vec![],
+        };
+        let mut patterns = vec![];
+        let mut vars = vec![];
+        let mut params = Vec::with_capacity(lst.len());
+        for (index, object) in lst.iter().enumerate() {
+            match object {
+                LispObject::List(_) => {
+                    patterns.push((index, pattern::compile_into(&self.symbols, object, &mut vars)
+                                   .map_err(|e| e.trace(index))?));
+                    params.push(self.symbols.intern("_"));
+                },
+                object => params.push(object.as_symbol()
+                                      .map_err(|e| e.trace(index))?),
+            }
+        }
+        let mut rest_indices = params.iter().enumerate()
+            .filter(|(_, sym)| **sym == self.symbols.sym_rest)
+            .map(|(index, _)| index);
+        let rest_index = rest_indices.next();
+        if let Some(index) = rest_indices.next() {
+            return Err(exc::duplicate_rest().trace(index))
+        }
+        if let (Some(rest_index), Some(_)) = (rest_index, key_index) {
+            return Err(exc::key_with_rest().trace(rest_index))
+        }
+        if let Some(index) = keys.iter().position(|sym| *sym == self.symbols.sym_rest ||
+                                                  *sym == self.symbols.sym_key) {
+            return Err(exc::key_with_rest().trace(key_index.unwrap_or(0) + index + 1))
+        }
+        let is_pattern = |index| patterns.iter().any(|(i, _)| *i == index);
+        for (index, sym) in params.iter().enumerate().filter(|(index, _)| !is_pattern(*index)) {
+            let earlier = params[..index].iter().enumerate()
+                .any(|(i, s)| s == sym && !is_pattern(i));
+            if earlier || vars.contains(sym) {
+                return Err(exc::duplicate_param(self.symbols.as_string(sym)).trace(index))
+            }
+        }
+        for (index, sym) in keys.iter().enumerate() {
+            if keys[..index].contains(sym) || params.contains(sym) || vars.contains(sym) {
+                return Err(exc::duplicate_param(self.symbols.as_string(sym))
+                           .trace(params.len() + index + 1))
+            }
+        }
+        let rest = split_param_list(&mut params, rest_index)?;
+        // The rest parameter moves into the place of &rest
+        for (index, _) in patterns.iter_mut() {
+            if rest_index.is_some_and(|rest_index| *index > rest_index) {
+                *index -= 1;
+            }
+        }
+        Ok(((params, rest), patterns, keys))
+    }
+
+    // Check and evaluate the arguments of a call, collecting the arguments
+    // for a rest parameter into a list.
+    fn bind_args(&mut self, params: &ParamList, tail: &[LispObject], eval_args: bool,
+                 callee: Option<Symbol>)
+                 -> Result<Vec<LispObject>, EvalError> {
+        // Check Validity of Arguments
+        let m = match params.1 {
+            None    => Match::Exact,
+            Some(_) => Match::Min,
+        };
+        let head = callee.and_then(|sym| self.symbols.as_string(&sym)).unwrap_or("~fn~");
+        assert_args(m, tail, params.0.len(),
+                    || format!("param list {}", self.symbols.serialize_param_list(params)),
+                    || self.symbols.serialize_call(head, tail))?;
+
+        // Evaluate Arguments
+        let mut args = if eval_args {
+            tail.iter().enumerate()
+                .map(|(index, object)| self.eval(object)
+                     // TODO this assumes param list always at position 1
+                     // Return index and error and process in caller
+                     .map_err(|e| e.trace(index + 1)))
+                .collect::<Result<Vec<LispObject>, EvalError>>()?
+        } else {
+            tail.to_vec()
+        };
+
+        if params.1.is_some() {
+            let rest_args = args.split_off(params.0.len());
+            args.push(LispObject::List(rest_args));
+        }
+        Ok(args)
+    }
+
+    fn bind_param_list(&mut self, params: &ParamList, patterns: &[(usize, Pattern)], keys: &[Symbol],
+                       tail: &[LispObject], eval_args: bool, callee: Option<Symbol>)
+                   -> Result<Vec<(Symbol, LispObject)>, EvalError> {
+        // Keyword arguments follow the positional ones
+        let (tail, keyword_tail) = match keys.is_empty() {
+            true => (tail, &[][..]),
+            false => tail.split_at(params.0.len().min(tail.len())),
+        };
+        let args = self.bind_args(params, tail, eval_args, callee)?;
+        let mut binding = Vec::with_capacity(args.len());
+        let mut patterns = patterns.iter().peekable();
+        for (index, (sym, arg)) in params.0.iter().chain(params.1.iter()).zip(args).enumerate() {
+            match patterns.next_if(|(i, _)| *i == index) {
+                Some((_, pattern)) => pattern.bind(&arg, &mut binding)
+                    .map_err(|m| exc::argument_mismatch(index, m.describe(&self.symbols))
+                             .trace((index + 1).min(tail.len())))?,
+                None => binding.push((*sym, arg)),
+            }
+        }
+        if !keys.is_empty() {
+            self.bind_keywords(keys, keyword_tail, tail.len(), eval_args, &mut binding)?;
+        }
+        Ok(binding)
+    }
+
+    // Bind the :key value pairs in tail, which starts at offset in the
+    // arguments of the call. Keys not given are bound to nil.
+    fn bind_keywords(&mut self, keys: &[Symbol], tail: &[LispObject], offset: usize,
+                     eval_args: bool, binding: &mut Vec<(Symbol, LispObject)>)
+                     -> Result<(), EvalError> {
+        let mut values: Vec<Option<LispObject>> = vec![None; keys.len()];
+        for (pair_index, pair) in tail.chunks(2).enumerate() {
+            // Position of the key in the call, after its head
+            let index = offset + 2 * pair_index + 1;
+            let key = match eval_args {
+                true => self.eval(&pair[0]).map_err(|e| e.trace(index))?,
+                false => pair[0].clone(),
+            };
+            let key = match key {
+                LispObject::Keyword(key) => key,
+                other => return Err(exc::keyword_expected(self.serialize(&other)).trace(index)),
+            };
+            let name = self.symbols.as_string(&key);
+            let position = keys.iter().position(|sym| *sym == key)
+                .ok_or_else(|| exc::unknown_keyword(name).trace(index))?;
+            if values[position].is_some() {
+                return Err(exc::duplicate_keyword(name).trace(index))
+            }
+            let value = match pair.get(1) {
+                Some(value) if eval_args => self.eval(value).map_err(|e| e.trace(index + 1))?,
+                Some(value) => value.clone(),
+                None => return Err(exc::keyword_without_value(name).trace(index)),
+            };
+            values[position] = Some(value);
+        }
+        binding.extend(keys.iter().zip(values)
+                       .map(|(key, value)| (*key, value.unwrap_or(LispObject::Nil))));
+        Ok(())
+    }
+}
+
+// Collect the traces of the forms in original that were replaced by macro
+// expansions in expanded, path leading to both from the body. Lists keeping
+// their head and length are taken to have been expanded in place.
+fn macro_calls(original: &LispObject, expanded: &LispObject, path: &mut Vec<usize>,
+               calls: &mut Vec<Trace>) {
+    if original.equals(expanded) {
+        return
+    }
+    match (original, expanded) {
+        (LispObject::List(o), LispObject::List(e))
+            if o.len() == e.len() && o.first().zip(e.first()).is_some_and(|(o, e)| o.equals(e)) => {
+            for (index, (o, e)) in o.iter().zip(e.iter()).enumerate() {
+                path.push(index);
+                macro_calls(o, e, path, calls);
+                path.pop();
+            }
+        },
+        _ => calls.push(path.iter().rev().copied().collect()),
+    }
+}
+
+fn split_param_list(lst: &mut Vec<Symbol>, rest_index: Option<usize>)
+                    -> Result<Option<Symbol>, EvalError> {
+    match rest_index {
+        None => Ok(None),
+        Some(rest_index) => if rest_index + 1 == lst.len() {
+            Err(EvalError::new("&rest must be followed by a symbol".to_string())
+                .with_condition(condition::SYNTAX_ERROR)
+                .trace(rest_index))
+        } else if rest_index + 2 == lst.len() {
+            let rest = lst.split_off(rest_index)[1];
+            Ok(Some(rest))
+        } else {
+            Err(EvalError::new("&rest must be second to last in parameter list".to_string())
+                .with_condition(condition::SYNTAX_ERROR)
+                .trace(rest_index + 2))
+        }
+    }
+}
+
+// Collect the symbols a binder or parameter list may bind in symbols.
+fn binder_symbols(binder: &LispObject, symbols: &mut Vec<Symbol>) {
+    match binder {
+        LispObject::Symbol(s) => symbols.push(*s),
+        LispObject::List(l) => l.iter().for_each(|o| binder_symbols(o, symbols)),
+        _ => (),
+    }
+}
@@ -0,0 +1,59 @@
+// Ctrl-C at the REPL. While a Guard is alive, SIGINT cancels the evaluation
+// running in the interpreter owning the token instead of ending the process.
+// Elsewhere than on Unix, Ctrl-C keeps its default behaviour.
+
+use crate::interpreter::CancellationToken;
+
+pub struct Guard {
+    #[cfg(unix)]
+    previous: libc::sighandler_t,
+}
+
+#[cfg(unix)]
+mod handler {
+    use std::ptr;
+    use std::sync::atomic::{AtomicPtr, Ordering};
+
+    use crate::interpreter::CancellationToken;
+
+    // The token of the guarded interpreter, only touched by the handler
+    // through atomic operations, which are async-signal-safe.
+    static TOKEN: AtomicPtr<CancellationToken> = AtomicPtr::new(ptr::null_mut());
+
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        if let Some(token) = unsafe { TOKEN.load(Ordering::SeqCst).as_ref() } {
+            token.cancel();
+        }
+    }
+
+    pub fn install(token: CancellationToken) -> libc::sighandler_t {
+        let previous = TOKEN.swap(Box::into_raw(Box::new(token)), Ordering::SeqCst);
+        if !previous.is_null() {
+            drop(unsafe { Box::from_raw(previous) });
+        }
+        unsafe { libc::signal(libc::SIGINT, on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t) }
+    }
+
+    pub fn uninstall(previous: libc::sighandler_t) {
+        unsafe { libc::signal(libc::SIGINT, previous) };
+        let token = TOKEN.swap(ptr::null_mut(), Ordering::SeqCst);
+        if !token.is_null() {
+            drop(unsafe { Box::from_raw(token) });
+        }
+    }
+}
+
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub fn guard(token: CancellationToken) -> Guard {
+    #[cfg(unix)]
+    return Guard { previous: handler::install(token) };
+    #[cfg(not(unix))]
+    return Guard {};
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        handler::uninstall(self.previous);
+    }
+}
@@ -0,0 +1,146 @@
+use ansi_term::Colour::{Green, Yellow};
+use rustyline::{error::ReadlineError, Editor};
+
+use crate::{
+    env::Symbols,
+    err::paint,
+    interpreter::Interpreter,
+    lisp_object::{LispObject, SerializeSymbol},
+    reader::Reader,
+};
+
+const LESSONS: &[&str] = &[
+    include_str!("../lessons/01-arithmetic.lisp"),
+    include_str!("../lessons/02-lists.lisp"),
+    include_str!("../lessons/03-definitions.lisp"),
+];
+
+pub struct Exercise {
+    pub prompt: String,
+    pub expected: String,   // Source of the reference solution
+}
+
+pub struct Lesson {
+    pub title: String,
+    pub text: String,
+    pub exercises: Vec<Exercise>,
+}
+
+enum Outcome {
+    Continue,
+    Quit,
+}
+
+fn as_string(object: &LispObject) -> Result<String, String> {
+    match object {
+        LispObject::String(s) => Ok(s.to_string()),
+        _ => Err("Expected a string".to_string()),
+    }
+}
+
+fn parse_exercise(symbols: &Symbols, object: &LispObject) -> Result<Exercise, String> {
+    match object {
+        LispObject::List(l) if l.len() == 3
+            && l[0].as_symbol().ok().and_then(|s| symbols.as_string(&s)) == Some("exercise") =>
+            Ok(Exercise {
+                prompt: as_string(&l[1])?,
+                expected: symbols.serialize_object(&l[2]),
+            }),
+        _ => Err(format!("Expected (exercise prompt expected), got {}",
+                         symbols.serialize_object(object))),
+    }
+}
+
+fn parse_lesson(symbols: &Symbols, object: &LispObject) -> Result<Lesson, String> {
+    match object {
+        LispObject::List(l) if l.len() >= 3
+            && l[0].as_symbol().ok().and_then(|s| symbols.as_string(&s)) == Some("lesson") =>
+            Ok(Lesson {
+                title: as_string(&l[1])?,
+                text: as_string(&l[2])?,
+                exercises: l[3..].iter()
+                    .map(|e| parse_exercise(symbols, e))
+                    .collect::<Result<Vec<Exercise>, String>>()?,
+            }),
+        _ => Err(format!("Expected (lesson title text &rest exercises), got {}",
+                         symbols.serialize_object(object))),
+    }
+}
+
+// Read the lessons embedded in the binary. Lessons are plain Lisp data and
+// are never evaluated themselves.
+pub fn lessons() -> Result<Vec<Lesson>, String> {
+    let mut symbols = Symbols::new();
+    let mut reader = Reader::new();
+    let mut prog: Vec<LispObject> = vec![];
+    for source in LESSONS {
+        for line in source.lines() {
+            reader.partial(&mut symbols, &mut prog, line)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    prog.iter()
+        .map(|object| parse_lesson(&symbols, object))
+        .collect()
+}
+
+// Evaluate source in a fresh interpreter, so answers can neither see nor
+// modify the state of previous exercises.
+fn evaluate(source: &str) -> Result<String, ()> {
+    let mut child = Interpreter::new();
+    match child.eval_str(source) {
+        Ok(result) => Ok(child.serialize(&result)),
+        Err(e) => {
+            child.handle_exec_error(source, Err(e)).ok();
+            Err(())
+        },
+    }
+}
+
+fn run_exercise(color: bool, rl: &mut Editor<()>, exercise: &Exercise) -> Result<Outcome, String> {
+    let expected = evaluate(&exercise.expected)
+        .map_err(|()| format!("Reference solution {} failed", exercise.expected))?;
+    println!("\n{}", exercise.prompt);
+    loop {
+        let line = match rl.readline("? ") {
+            Ok(line) => line,
+            Err(ReadlineError::Eof) | Err(ReadlineError::Interrupted) => return Ok(Outcome::Quit),
+            Err(e) => return Err(e.to_string()),
+        };
+        match line.trim() {
+            "" => continue,
+            ":quit" => return Ok(Outcome::Quit),
+            ":skip" => {
+                println!("A possible solution is {}", exercise.expected);
+                return Ok(Outcome::Continue)
+            },
+            _ => (),
+        }
+        rl.add_history_entry(line.as_str());
+        if let Ok(actual) = evaluate(&line) {
+            if actual == expected {
+                println!("{} {}", paint(color, Green, "Correct!"), actual);
+                return Ok(Outcome::Continue)
+            }
+            println!("{} got {}, try again or type :skip.",
+                     paint(color, Yellow, "Not quite:"), actual);
+        }
+    }
+}
+
+pub fn run(color: bool) -> Result<(), String> {
+    let lessons = lessons()?;
+    let mut rl = Editor::<()>::new();
+    println!("Type your answers at the prompt, :skip to see a solution, :quit to leave.");
+    for (index, lesson) in lessons.iter().enumerate() {
+        println!("\n{} {}/{}: {}\n\n{}", paint(color, Green, "Lesson"),
+                 index + 1, lessons.len(), lesson.title, lesson.text);
+        for exercise in &lesson.exercises {
+            if let Outcome::Quit = run_exercise(color, &mut rl, exercise)? {
+                return Ok(())
+            }
+        }
+    }
+    println!("\nYou finished all lessons.");
+    Ok(())
+}
@@ -1,91 +1,148 @@
-use logos::{Logos, Lexer as LLexer};
-
-#[derive(Logos, Clone, Debug, PartialEq)]
-pub enum ObjectT {
-    #[token("'", priority = 5)]
-    Quote,
-    #[token("`", priority = 5)]
-    QuasiQuote,
-    #[token(",", priority = 5)]
-    Unquote,
-    #[token(",@", priority = 5)]
-    UnquoteSplice,
-    #[token("#t", priority = 5)]
-    True,
-    #[token("#f", priority = 5)]
-    False,
-    #[token("(", priority = 4)]
-    LBrace,
-    #[token(")", priority = 4)]
-    RBrace,
-    #[regex("-?([0-9]*\\.?[0-9]+)", |lex| lex.slice().parse(), priority = 3)]
-    Number(f64),
-    #[token("\"", priority = 2)]
-    StartString,
-    #[regex("[^'`,\"\\s\\(\\)][^\\s\\(\\)]*", |lex| lex.slice().to_string(), priority = 1)]
-    Symbol(String),
-    #[error]
-    #[regex(r"[ \t\n\f]+", logos::skip)]
-    Error,
-}
-
-#[derive(Logos, Clone, Debug, PartialEq)]
-pub enum StringT {
-    #[error]
-    Error,
-    #[regex(r#"[^\\"]+"#, |lex| lex.slice().to_string())]
-    Text(String),
-    #[token("\"")]
-    EndString,
-}
-
-enum Modes<'a> {
-    Object(LLexer<'a, ObjectT>),
-    String(LLexer<'a, StringT>),
-}
-
-pub enum Tokens {
-    Object(ObjectT),
-    String(StringT),
-}
-
-pub struct Lexer<'a> {
-    mode: Modes<'a>,
-}
-
-impl<'a> Iterator for Lexer<'a> {
-    type Item = Tokens;
-    fn next(&mut self) -> Option<Self::Item> {
-        match &mut self.mode {
-            Modes::Object(lex) => {
-                let t = lex.next();
-                if t == Some(ObjectT::StartString) {
-                    self.mode = Modes::String(lex.to_owned().morph());
-                }
-                t.map(Tokens::Object)
-            },
-            Modes::String(lex) => {
-                let t = lex.next();
-                if t == Some(StringT::EndString) {
-                    self.mode = Modes::Object(lex.to_owned().morph());
-                }
-                t.map(Tokens::String)
-            }
-        }
-    }
-}
-
-impl<'a> Lexer<'a> {
-    pub fn new(input: &str) -> Lexer {
-        Lexer {
-            mode: Modes::Object(ObjectT::lexer(input))
-        }
-    }
-
-    pub fn span(&self) -> (usize, usize) {
-        match &self.mode {
-            Modes::Object(lex) => (lex.span().start, lex.span().end),
-            Modes::String(lex) => (lex.span().start, lex.span().end),
-        }
-    }
-}
+use logos::{Logos, Lexer as LLexer};
+
+#[derive(Logos, Clone, Debug, PartialEq)]
+pub enum ObjectT {
+    #[token("'", priority = 5)]
+    Quote,
+    #[token("`", priority = 5)]
+    QuasiQuote,
+    #[token(",", priority = 5)]
+    Unquote,
+    #[token(",@", priority = 5)]
+    UnquoteSplice,
+    #[token("#t", priority = 5)]
+    True,
+    #[token("#f", priority = 5)]
+    False,
+    #[token("nil", priority = 5)]
+    Nil,
+    #[token("(", priority = 4)]
+    LBrace,
+    #[token(")", priority = 4)]
+    RBrace,
+    #[regex("-?([0-9]*\\.?[0-9]+)", |lex| lex.slice().parse(), priority = 3)]
+    Number(f64),
+    #[token("\"", priority = 2)]
+    StartString,
+    #[regex(":[^;'`,\"|\\s\\(\\)][^;\\s\\(\\)]*", |lex| lex.slice()[1..].to_string(), priority = 2)]
+    Keyword(String),
+    #[regex("[^;'`,\"|\\s\\(\\)][^;\\s\\(\\)]*", |lex| lex.slice().to_string(), priority = 1)]
+    #[regex(r"\|([^|\\]|\\.)*\|", |lex| unescape_symbol(lex.slice()))]
+    Symbol(String),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    #[regex(";[^\n]*", logos::skip)]
+    Error,
+}
+
+// The name of an escaped symbol |name|, in which \ escapes the next character.
+fn unescape_symbol(slice: &str) -> String {
+    let mut name = String::new();
+    let mut chars = slice[1..slice.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => name.extend(chars.next()),
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+// Print name so it is read back as the same symbol, escaped as |name| if
+// it would be read as something else.
+pub fn print_symbol(name: &str) -> String {
+    let mut lexer = ObjectT::lexer(name);
+    match (lexer.next(), lexer.next()) {
+        (Some(ObjectT::Symbol(s)), None) if s == name => name.to_string(),
+        _ => format!("|{}|", name.replace('\\', "\\\\").replace('|', "\\|")),
+    }
+}
+
+#[derive(Logos, Clone, Debug, PartialEq)]
+pub enum StringT {
+    #[error]
+    Error,
+    #[regex(r#"[^\\"]+"#, |lex| lex.slice().to_string())]
+    Text(String),
+    #[regex(r#"\\[nt\\"]"#, |lex| unescape_char(lex.slice()))]
+    Escape(char),
+    #[token("\"")]
+    EndString,
+}
+
+// The character of an escape \n, \t, \\ or \" in a string.
+fn unescape_char(slice: &str) -> char {
+    match &slice[1..] {
+        "n" => '\n',
+        "t" => '\t',
+        other => other.chars().next().unwrap_or_default(),
+    }
+}
+
+// Print string so it is read back as the same string, quoted and escaped.
+pub fn print_string(string: &str) -> String {
+    let mut printed = String::with_capacity(string.len() + 2);
+    printed.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => printed.push_str("\\\""),
+            '\\' => printed.push_str("\\\\"),
+            '\n' => printed.push_str("\\n"),
+            '\t' => printed.push_str("\\t"),
+            c => printed.push(c),
+        }
+    }
+    printed.push('"');
+    printed
+}
+
+enum Modes<'a> {
+    Object(LLexer<'a, ObjectT>),
+    String(LLexer<'a, StringT>),
+}
+
+pub enum Tokens {
+    Object(ObjectT),
+    String(StringT),
+}
+
+pub struct Lexer<'a> {
+    mode: Modes<'a>,
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Tokens;
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.mode {
+            Modes::Object(lex) => {
+                let t = lex.next();
+                if t == Some(ObjectT::StartString) {
+                    self.mode = Modes::String(lex.to_owned().morph());
+                }
+                t.map(Tokens::Object)
+            },
+            Modes::String(lex) => {
+                let t = lex.next();
+                if t == Some(StringT::EndString) {
+                    self.mode = Modes::Object(lex.to_owned().morph());
+                }
+                t.map(Tokens::String)
+            }
+        }
+    }
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &str) -> Lexer<'_> {
+        Lexer {
+            mode: Modes::Object(ObjectT::lexer(input))
+        }
+    }
+
+    pub fn span(&self) -> (usize, usize) {
+        match &self.mode {
+            Modes::Object(lex) => (lex.span().start, lex.span().end),
+            Modes::String(lex) => (lex.span().start, lex.span().end),
+        }
+    }
+}
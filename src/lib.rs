@@ -1,9 +1,48 @@
-mod lexer;
-pub mod lisp_object;
-pub mod lisp_object_util;
-mod native;
-pub mod reader;
-pub mod env;
-pub mod interpreter;
-mod err;
-mod exc;
+mod lexer;
+pub mod lisp_object;
+pub mod lisp_object_util;
+mod native;
+pub mod reader;
+pub mod env;
+pub mod interpreter;
+pub mod learn;
+pub mod examples;
+pub mod lint;
+pub mod pool;
+mod collections;
+mod condition;
+mod err;
+mod exc;
+mod format;
+mod fuzzy;
+mod generator;
+mod graph;
+mod inspect;
+mod interrupt;
+mod matrix;
+mod notify;
+mod pattern;
+mod persist;
+mod promise;
+mod prop;
+mod stack;
+mod stats;
+mod stream;
+mod syntax;
+mod template;
+mod timer;
+pub mod trace;
+mod watch;
+mod xml;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "net")]
+mod net;
+#[cfg(feature = "desktop")]
+mod clipboard;
+#[cfg(feature = "collation")]
+mod collation;
+#[cfg(feature = "markdown")]
+mod markdown;
+#[cfg(feature = "graphics")]
+mod graphics;
@@ -0,0 +1,190 @@
+// Static checks of source files, run by the lint subcommand. Findings point
+// into the top level form they were found in with a trace, like the frames
+// of evaluation errors.
+
+use std::fs;
+
+use crate::{
+    env::Symbols,
+    err::{print_span, print_warning},
+    lisp_object::{LispObject, SerializeSymbol, Symbol, Trace},
+    lisp_object_util::catch_handler,
+    reader::Reader,
+};
+
+pub struct Finding {
+    pub message: String,
+    pub form: LispObject,
+    pub trace: Trace,
+}
+
+struct TailCalls<'a> {
+    symbols: &'a Symbols,
+    name: Symbol,
+    path: Vec<usize>,
+    found: Vec<Trace>,
+}
+
+impl TailCalls<'_> {
+    fn head_name(&self, l: &[LispObject]) -> Option<&str> {
+        match l.first() {
+            Some(LispObject::Symbol(s)) => self.symbols.as_string(s),
+            _ => None,
+        }
+    }
+
+    fn visit(&mut self, object: &LispObject, index: usize, tail: bool) {
+        self.path.push(index);
+        self.walk(object, tail);
+        self.path.pop();
+    }
+
+    // Visit elements from start on, the last one in tail position if tail is.
+    fn visit_sequence(&mut self, l: &[LispObject], start: usize, tail: bool) {
+        for (index, object) in l.iter().enumerate().skip(start) {
+            self.visit(object, index, tail && index == l.len() - 1);
+        }
+    }
+
+    fn walk(&mut self, object: &LispObject, tail: bool) {
+        let l = match object {
+            LispObject::List(l) if !l.is_empty() => l,
+            _ => return,
+        };
+        match self.head_name(l) {
+            // Not evaluated, or a new function with a frame of its own
+            Some("quote") | Some("quasiquote") | Some("fn") | Some("macro") => (),
+            Some("if") => {
+                for (index, object) in l.iter().enumerate().skip(1) {
+                    self.visit(object, index, tail && index > 1);
+                }
+            },
+            Some("begin") | Some("and") | Some("or") => self.visit_sequence(l, 1, tail),
+            Some("eval-when") => self.visit_sequence(l, 2, tail),
+            Some("unwind-protect") => self.visit_sequence(l, 1, false),
+            Some("try") => {
+                for (index, object) in l.iter().enumerate().skip(1) {
+                    match (catch_handler(self.symbols, object), object) {
+                        (Some(handler), LispObject::List(c)) => {
+                            self.path.push(index);
+                            self.visit_sequence(c, handler, tail);
+                            self.path.pop();
+                        },
+                        _ => self.visit(object, index, false),
+                    }
+                }
+            },
+            Some("match") => {
+                if let Some(value) = l.get(1) {
+                    self.visit(value, 1, false);
+                }
+                for (index, clause) in l.iter().enumerate().skip(2) {
+                    if let LispObject::List(c) = clause {
+                        self.path.push(index);
+                        self.visit_sequence(c, 1, tail);
+                        self.path.pop();
+                    }
+                }
+            },
+            Some("let") | Some("let*") | Some("letrec") | Some("let-values") => {
+                if let Some(LispObject::List(bindings)) = l.get(1) {
+                    self.path.push(1);
+                    for (index, binding) in bindings.iter().enumerate() {
+                        self.path.push(index);
+                        if let LispObject::List(b) = binding {
+                            self.visit_sequence(b, 1, false);
+                        }
+                        self.path.pop();
+                    }
+                    self.path.pop();
+                }
+                self.visit_sequence(l, 2, tail);
+            },
+            _ => {
+                if !tail && l[0].as_symbol().ok() == Some(self.name) {
+                    self.found.push(self.path.iter().rev().copied().collect());
+                }
+                self.visit_sequence(l, 0, false);
+            },
+        }
+    }
+}
+
+// The name of the function defined by form and the index its (fn ...)
+// definition is found at, for (defun name ...), (def name '(fn ...)) and
+// (def name (fn ...)).
+fn function_def(symbols: &Symbols, form: &LispObject) -> Option<(Symbol, Vec<usize>)> {
+    let l = form.as_slice().ok()?;
+    let head = symbols.as_string(&l.first()?.as_symbol().ok()?)?;
+    let name = l.get(1)?.as_symbol().ok()?;
+    match head {
+        "defun" => Some((name, vec![])),
+        "def" | "defconst" => {
+            let value = l.get(2)?.as_slice().ok()?;
+            match symbols.as_string(&value.first()?.as_symbol().ok()?)? {
+                "fn" => Some((name, vec![2])),
+                "quote" => match value.get(1)?.as_slice().ok()?.first()?.as_symbol() {
+                    Ok(s) if s == symbols.sym_fn => Some((name, vec![2, 1])),
+                    _ => None,
+                },
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+// Calls of functions to themselves which are not in tail position and thus
+// grow the stack.
+pub fn non_tail_self_calls(symbols: &Symbols, form: &LispObject) -> Vec<Finding> {
+    let (name, path) = match function_def(symbols, form) {
+        Some(def) => def,
+        None => return vec![],
+    };
+    let definition = path.iter()
+        .try_fold(form, |object, index| object.as_slice().ok()?.get(*index))
+        .and_then(|definition| definition.as_slice().ok());
+    let definition = match definition {
+        Some(definition) => definition,
+        None => return vec![],
+    };
+    // The body starts after the parameter list, which for defun is preceded
+    // by the name, for fn by the fn symbol.
+    let body_start = 2 + usize::from(path.is_empty());
+    let mut calls = TailCalls { symbols, name, path, found: vec![] };
+    calls.visit_sequence(definition, body_start, true);
+
+    let name = symbols.as_string(&name).unwrap_or_default();
+    calls.found.into_iter()
+        .map(|trace| Finding {
+            message: format!("call of {} to itself is not in tail position", name),
+            form: form.clone(),
+            trace,
+        })
+        .collect()
+}
+
+pub fn lint_source(symbols: &mut Symbols, source: &str) -> Result<Vec<Finding>, String> {
+    let mut reader = Reader::new();
+    let mut prog: Vec<LispObject> = vec![];
+    for line in source.lines() {
+        reader.partial(symbols, &mut prog, line)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(prog.iter()
+       .flat_map(|form| non_tail_self_calls(symbols, form))
+       .collect())
+}
+
+// Print the findings for file f, in color if color is set, returns how many
+// there were.
+pub fn run(f: &str, color: bool) -> Result<usize, String> {
+    let source = fs::read_to_string(f).map_err(|e| e.to_string())?;
+    let mut symbols = Symbols::new();
+    let findings = lint_source(&mut symbols, &source)?;
+    for finding in &findings {
+        print_warning(color, &finding.message);
+        print_span(color, &symbols, &finding.form, &finding.trace, Some(f.to_string()));
+    }
+    Ok(findings.len())
+}
@@ -1,142 +1,428 @@
-use std::fmt;
-
-#[derive(Clone)]
-pub enum SpecialForm {
-    Def,
-    Set,
-    If,
-    Let,
-    Begin,
-    Quote,
-}
-
-impl fmt::Display for SpecialForm {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", match self {
-            SpecialForm::Def => "def",
-            SpecialForm::Set => "set",
-            SpecialForm::If => "if",
-            SpecialForm::Let => "let",
-            SpecialForm::Begin => "begin",
-            SpecialForm::Quote => "quote",
-        })
-    }
-}
-
-pub type Symbol = u64;
-
-pub type Sexpr = Vec<LispObject>;
-
-pub type ParamList = (Vec<Symbol>, Option<Symbol>);
-
-#[derive(Clone)]
-pub enum LispObject {
-    Bool(bool),
-    SpecialForm(SpecialForm),
-    Symbol(Symbol),
-    String(String),
-    Number(f64),
-    List(Sexpr),
-    Native(ParamList, Native),
-}
-
-// When an error occurs during evaluation an Err(EvalError) is returned.
-// - frames contains the s-expressions that eval processed, resolved from
-//   function definitions.
-// - trace contains the position in the current frame where the error is
-//   occurred.
-
-pub type Trace = Vec<usize>;
-pub type Frame = (LispObject, Trace, Option<String>);
-
-pub struct EvalError {
-    pub message: String,      // Message describing the error
-    pub frames: Vec<Frame>,   // Already handled frames
-    pub trace: Trace,         // Current trace
-}
-
-pub trait SerializeSymbol {
-    fn as_string(&self, sym: &Symbol) -> Option<&str>;
-}
-
-impl fmt::Display for EvalError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
-    }
-}
-
-impl EvalError {
-    pub fn new(message: String) -> EvalError {
-        EvalError {
-            message: message,
-            trace: vec![],
-            frames: vec![],
-        }
-    }
-
-    pub fn trace(mut self, index: usize) -> EvalError {
-        self.trace.push(index);
-        self
-    }
-
-    pub fn frame(mut self, expr: LispObject, place: Option<String>) -> EvalError {
-        self.frames.push((expr, self.trace, place));
-        self.trace = vec![];
-        self
-    }
-
-    pub fn def_frame<S>(self, serializer: &S, expr: LispObject, place: Option<Symbol>)
-                        -> EvalError
-    where S: SerializeSymbol {
-        self.frame(expr, place.map_or_else(
-            || None,
-            |sym| Some(serializer.as_string(&sym).unwrap().to_string())))
-
-    }
-}
-
-pub type Native = fn(&[LispObject]) -> Result<LispObject, EvalError>;
-
-pub struct NativeDef {
-    pub name: &'static str,
-    pub positional: &'static [&'static str],
-    pub rest: Option<&'static str>,
-    pub func: Native,
-}
-
-impl LispObject {
-    pub fn as_bool(&self) -> Result<bool, EvalError> {
-        match self {
-            LispObject::Bool(b) => Ok(*b),
-            _ => Err(EvalError::new("Expected a bool".to_string())),
-        }
-    }
-
-    pub fn as_number(&self) -> Result<f64, EvalError> {
-        match self {
-            LispObject::Number(n) => Ok(*n),
-            _ => Err(EvalError::new("Expected a number".to_string())),
-        }
-    }
-
-    pub fn as_symbol(&self) -> Result<Symbol, EvalError> {
-        match self {
-            LispObject::Symbol(s) => Ok(*s),
-            _ => Err(EvalError::new("Expected a symbol".to_string())),
-        }
-    }
-
-    pub fn as_list(&self) -> Result<Sexpr, EvalError> {
-        match self {
-            LispObject::List(l) => Ok(l.clone()),
-            _ => Err(EvalError::new("Expected a list".to_string())),
-        }
-    }
-
-    pub fn into_list(self) -> Result<Sexpr, EvalError> {
-        match self {
-            LispObject::List(l) => Ok(l),
-            _ => Err(EvalError::new("Expected a list".to_string())),
-        }
-    }
-}
+use std::any::Any;
+use std::cell::{OnceCell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{
+    condition,
+    env::Scope,
+    interpreter::Interpreter,
+    pattern::Pattern,
+};
+
+#[derive(Clone)]
+pub enum SpecialForm {
+    Def,
+    Set,
+    Local,
+    If,
+    Let,
+    LetStar,
+    Letrec,
+    LetValues,
+    Begin,
+    Quote,
+    Quasiquote,
+    Fn,
+    Macro,
+    Defspec,
+    Defconst,
+    And,
+    Or,
+    Loop,
+    Recur,
+    Match,
+    EvalWhen,
+    UnwindProtect,
+    Try,
+}
+
+impl fmt::Display for SpecialForm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            SpecialForm::Def => "def",
+            SpecialForm::Set => "set",
+            SpecialForm::Local => "local",
+            SpecialForm::If => "if",
+            SpecialForm::Let => "let",
+            SpecialForm::LetStar => "let*",
+            SpecialForm::Letrec => "letrec",
+            SpecialForm::LetValues => "let-values",
+            SpecialForm::Begin => "begin",
+            SpecialForm::Quote => "quote",
+            SpecialForm::Quasiquote => "quasiquote",
+            SpecialForm::Fn => "fn",
+            SpecialForm::Macro => "macro",
+            SpecialForm::Defspec => "defspec",
+            SpecialForm::Defconst => "defconst",
+            SpecialForm::And => "and",
+            SpecialForm::Or => "or",
+            SpecialForm::Loop => "loop",
+            SpecialForm::Recur => "recur",
+            SpecialForm::Match => "match",
+            SpecialForm::EvalWhen => "eval-when",
+            SpecialForm::UnwindProtect => "unwind-protect",
+            SpecialForm::Try => "try",
+        })
+    }
+}
+
+pub type Symbol = u64;
+
+pub type Sexpr = Vec<LispObject>;
+
+pub type ParamList = (Vec<Symbol>, Option<Symbol>);
+
+// Maps keep their entries in insertion order.
+pub type Map = Vec<(LispObject, LispObject)>;
+
+// Booleans, numbers and symbols are stored inline and the empty list does
+// not allocate, so evaluating these literals only copies a few words. String
+// literals are shared through Symbols::string.
+#[derive(Clone)]
+pub enum LispObject {
+    // The value of forms with nothing meaningful to return, false in tests
+    Nil,
+    Bool(bool),
+    SpecialForm(SpecialForm),
+    Symbol(Symbol),
+    // :name, evaluates to itself and names keyword arguments
+    Keyword(Symbol),
+    String(Rc<str>),
+    Number(f64),
+    List(Sexpr),
+    Native(ParamList, Native),
+    Lambda(Rc<Lambda>),
+    Map(Rc<Map>),
+    Handle(Rc<Handle>),
+    // A cell of a lazy stream, the empty stream is the empty list
+    Stream(Rc<Stream>),
+    // Several values returned by values, see Interpreter::eval_values
+    Values(Rc<Sexpr>),
+}
+
+// Host resources like sockets, owned by the objects referencing them.
+pub struct Handle {
+    pub kind: &'static str,
+    pub value: RefCell<Box<dyn Any>>,
+}
+
+impl Handle {
+    pub fn object<T: Any>(kind: &'static str, value: T) -> LispObject {
+        LispObject::Handle(Rc::new(Handle {
+            kind,
+            value: RefCell::new(Box::new(value)),
+        }))
+    }
+}
+
+// A cell of a possibly infinite stream, see stream.rs. Its rest is computed
+// when it is first needed and then remembered, so the printer can show the
+// cells computed so far.
+pub struct Stream {
+    pub head: LispObject,
+    pub tail: RefCell<StreamTail>,
+}
+
+// How the rest of a stream is computed.
+#[derive(Clone)]
+pub enum StreamTail {
+    // By calling a thunk, the tail of stream-cons
+    Delayed(LispObject),
+    // By mapping a function over the rest of a stream
+    Map(LispObject, Rc<Stream>),
+    // By filtering the rest of a stream with a predicate
+    Filter(LispObject, Rc<Stream>),
+    Forced(LispObject),
+}
+
+impl Stream {
+    pub fn object(head: LispObject, tail: StreamTail) -> LispObject {
+        LispObject::Stream(Rc::new(Stream { head, tail: RefCell::new(tail) }))
+    }
+
+    // The cells forced so far from this one, and whether the stream ends
+    // after them.
+    pub fn forced(self: &Rc<Stream>) -> (Vec<Rc<Stream>>, bool) {
+        let mut cells = vec![self.clone()];
+        loop {
+            let (next, ends) = match &*cells[cells.len() - 1].tail.borrow() {
+                StreamTail::Forced(LispObject::Stream(next)) => (Some(next.clone()), false),
+                StreamTail::Forced(_) => (None, true),
+                _ => (None, false),
+            };
+            match next {
+                Some(next) => cells.push(next),
+                None => return (cells, ends),
+            }
+        }
+    }
+}
+
+// Drop forced cells no other stream shares one by one, instead of
+// recursively, which overflows the stack for long streams.
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let mut tail = self.tail.replace(StreamTail::Forced(LispObject::List(vec![])));
+        while let StreamTail::Forced(LispObject::Stream(next)) = tail {
+            tail = match Rc::try_unwrap(next) {
+                Ok(next) => next.tail.replace(StreamTail::Forced(LispObject::List(vec![]))),
+                Err(_) => break,
+            };
+        }
+    }
+}
+
+// A function or macro compiled when its fn or macro form is evaluated, e.g.
+// by defn or defmacro. Its parameter list is parsed, macros in the body are
+// expanded and list heads naming special forms are resolved, so calls don't
+// have to repeat this work. Quoted (fn ...) lists are data and stay lists.
+// source is the definition as written, used for printing and error frames.
+// scope is the local scope the lambda was created in, its body is evaluated
+// in it. name is set by the first def binding the lambda and shown in its
+// error frames.
+pub struct Lambda {
+    pub params: ParamList,
+    // Parameters given as list patterns, by index into params
+    pub(crate) patterns: Vec<(usize, Pattern)>,
+    // Parameters following &key, bound by name
+    pub keys: Vec<Symbol>,
+    pub body: Sexpr,
+    pub source: Sexpr,
+    pub is_macro: bool,
+    pub scope: Option<Rc<Scope>>,
+    pub(crate) source_map: Option<SourceMap>,
+    pub name: OnceCell<String>,
+}
+
+// The body of a lambda as written, before the macro calls at calls were
+// expanded. Calls are given as traces into the body.
+pub(crate) struct SourceMap {
+    pub body: Sexpr,
+    pub calls: Vec<Trace>,
+}
+
+impl Lambda {
+    // Add the frame of the body to err. If err occurred in the expansion of a
+    // macro call, the call in the body as written is added as well.
+    pub(crate) fn frame(&self, mut err: EvalError) -> EvalError {
+        let call = self.source_map.as_ref().and_then(|map| map.calls.iter()
+            .find(|call| err.trace.ends_with(call))
+            .map(|call| (map, call.clone())));
+        err = err.frame(LispObject::List(self.source[2..].to_vec()), self.name.get().cloned());
+        match call {
+            Some((map, call)) => {
+                err.trace = call;
+                err.frame(LispObject::List(map.body.clone()), Some("~>".to_string()))
+            },
+            None => err,
+        }
+    }
+}
+
+// When an error occurs during evaluation an Err(EvalError) is returned.
+// - frames contains the s-expressions that eval processed, resolved from
+//   function definitions.
+// - trace contains the position in the current frame where the error is
+//   occurred.
+
+pub type Trace = Vec<usize>;
+pub type Frame = (LispObject, Trace, Option<String>);
+
+pub struct EvalError {
+    pub message: String,      // Message describing the error
+    pub frames: Vec<Frame>,   // Already handled frames
+    pub trace: Trace,         // Current trace
+    pub kind: ErrorKind,      // Why evaluation stopped
+    pub data: Sexpr,          // Values signalled along with the message
+    pub condition: Rc<String>, // Type of the error, see condition
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorKind {
+    // Raised while evaluating
+    Error,
+    // Aborted through a CancellationToken
+    Cancelled,
+    // Unwinding to the call/ec whose id is the first element of data
+    Escape,
+}
+
+pub trait SerializeSymbol {
+    fn as_string(&self, sym: &Symbol) -> Option<&str>;
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl EvalError {
+    pub fn new(message: String) -> EvalError {
+        EvalError {
+            message,
+            trace: vec![],
+            frames: vec![],
+            kind: ErrorKind::Error,
+            data: vec![],
+            condition: Rc::new(condition::ERROR.to_string()),
+        }
+    }
+
+    pub fn with_condition(mut self, condition: &str) -> EvalError {
+        self.condition = Rc::new(condition.to_string());
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.kind == ErrorKind::Cancelled
+    }
+
+    pub fn trace(mut self, index: usize) -> EvalError {
+        self.trace.push(index);
+        self
+    }
+
+    pub fn frame(mut self, expr: LispObject, place: Option<String>) -> EvalError {
+        self.frames.push((expr, self.trace, place));
+        self.trace = vec![];
+        self
+    }
+
+    pub fn def_frame<S>(self, serializer: &S, expr: LispObject, place: Option<Symbol>)
+                        -> EvalError
+    where S: SerializeSymbol {
+        self.frame(expr, place.map_or_else(
+            || None,
+            |sym| Some(serializer.as_string(&sym).unwrap().to_string())))
+
+    }
+}
+
+// Natives receive the interpreter so they can call back into it, e.g. to
+// apply functions passed as arguments.
+pub type Native = fn(&mut Interpreter, &[LispObject]) -> Result<LispObject, EvalError>;
+
+pub struct NativeDef {
+    pub name: &'static str,
+    pub positional: &'static [&'static str],
+    pub rest: Option<&'static str>,
+    pub func: Native,
+}
+
+impl LispObject {
+    // Structural equality. Functions are only equal to themselves.
+    pub fn equals(&self, other: &LispObject) -> bool {
+        match (self, other) {
+            (LispObject::Nil, LispObject::Nil) => true,
+            (LispObject::Bool(a), LispObject::Bool(b)) => a == b,
+            (LispObject::Symbol(a), LispObject::Symbol(b)) => a == b,
+            (LispObject::Keyword(a), LispObject::Keyword(b)) => a == b,
+            (LispObject::String(a), LispObject::String(b)) => Rc::ptr_eq(a, b) || a == b,
+            (LispObject::Number(a), LispObject::Number(b)) => a == b,
+            (LispObject::List(a), LispObject::List(b)) =>
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.equals(b)),
+            (LispObject::Values(a), LispObject::Values(b)) =>
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.equals(b)),
+            (LispObject::Map(a), LispObject::Map(b)) =>
+                a.len() == b.len() && a.iter().all(|(k, v)| b.iter()
+                    .any(|(bk, bv)| k.equals(bk) && v.equals(bv))),
+            (LispObject::Lambda(a), LispObject::Lambda(b)) => Rc::ptr_eq(a, b),
+            (LispObject::Handle(a), LispObject::Handle(b)) => Rc::ptr_eq(a, b),
+            (LispObject::Stream(a), LispObject::Stream(b)) => Rc::ptr_eq(a, b),
+            (LispObject::Native(_, a), LispObject::Native(_, b)) => *a as usize == *b as usize,
+            (LispObject::SpecialForm(a), LispObject::SpecialForm(b)) => a.to_string() == b.to_string(),
+            _ => false,
+        }
+    }
+
+    // Identity. Atoms are identical if they are equal, shared objects if they
+    // are the same object. Lists are copied, so only empty lists are
+    // identical.
+    pub fn is_identical(&self, other: &LispObject) -> bool {
+        match (self, other) {
+            (LispObject::String(a), LispObject::String(b)) => Rc::ptr_eq(a, b),
+            (LispObject::List(a), LispObject::List(b)) => a.is_empty() && b.is_empty(),
+            (LispObject::Map(a), LispObject::Map(b)) => Rc::ptr_eq(a, b),
+            (LispObject::Values(a), LispObject::Values(b)) => Rc::ptr_eq(a, b),
+            _ => self.equals(other),
+        }
+    }
+
+    pub fn map_get(&self, key: &LispObject) -> Result<Option<&LispObject>, EvalError> {
+        Ok(self.as_map()?.iter()
+           .find(|(k, _)| k.equals(key))
+           .map(|(_, v)| v))
+    }
+
+    pub fn as_handle(&self, kind: &str) -> Result<&Handle, EvalError> {
+        match self {
+            LispObject::Handle(h) if h.kind == kind => Ok(h),
+            _ => Err(EvalError::new(format!("Expected a {} handle", kind)).with_condition(condition::TYPE_ERROR)),
+        }
+    }
+
+    pub fn as_stream(&self) -> Result<&Rc<Stream>, EvalError> {
+        match self {
+            LispObject::Stream(s) => Ok(s),
+            _ => Err(EvalError::new("Expected a stream".to_string()).with_condition(condition::TYPE_ERROR)),
+        }
+    }
+
+    pub fn as_map(&self) -> Result<&Map, EvalError> {
+        match self {
+            LispObject::Map(m) => Ok(m),
+            _ => Err(EvalError::new("Expected a map".to_string()).with_condition(condition::TYPE_ERROR)),
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, EvalError> {
+        match self {
+            LispObject::Bool(b) => Ok(*b),
+            _ => Err(EvalError::new("Expected a bool".to_string()).with_condition(condition::TYPE_ERROR)),
+        }
+    }
+
+    pub fn as_number(&self) -> Result<f64, EvalError> {
+        match self {
+            LispObject::Number(n) => Ok(*n),
+            _ => Err(EvalError::new("Expected a number".to_string()).with_condition(condition::TYPE_ERROR)),
+        }
+    }
+
+    pub fn as_symbol(&self) -> Result<Symbol, EvalError> {
+        match self {
+            LispObject::Symbol(s) => Ok(*s),
+            _ => Err(EvalError::new("Expected a symbol".to_string()).with_condition(condition::TYPE_ERROR)),
+        }
+    }
+
+    pub fn as_str(&self) -> Result<&str, EvalError> {
+        match self {
+            LispObject::String(s) => Ok(s),
+            _ => Err(EvalError::new("Expected a string".to_string()).with_condition(condition::TYPE_ERROR)),
+        }
+    }
+
+    pub fn as_slice(&self) -> Result<&[LispObject], EvalError> {
+        match self {
+            LispObject::List(l) => Ok(l),
+            _ => Err(EvalError::new("Expected a list".to_string()).with_condition(condition::TYPE_ERROR)),
+        }
+    }
+
+    pub fn as_list(&self) -> Result<Sexpr, EvalError> {
+        match self {
+            LispObject::List(l) => Ok(l.clone()),
+            _ => Err(EvalError::new("Expected a list".to_string()).with_condition(condition::TYPE_ERROR)),
+        }
+    }
+
+    pub fn into_list(self) -> Result<Sexpr, EvalError> {
+        match self {
+            LispObject::List(l) => Ok(l),
+            _ => Err(EvalError::new("Expected a list".to_string()).with_condition(condition::TYPE_ERROR)),
+        }
+    }
+}
@@ -1,54 +1,102 @@
-use crate::lisp_object::{
-    EvalError,
-    LispObject,
-    Symbol,
-};
-
-pub enum Match {
-    Exact,
-    Min,
-}
-
-pub fn assert_args<T>(m: Match, form: &[LispObject], len: usize, description: T)
-                      -> Result<(), EvalError>
-where T: Fn() -> String {
-    let actual_len = form.len();
-    let pred = match m {
-        Match::Exact => actual_len != len,
-        Match::Min => actual_len < len,
-    };
-    if pred {
-        let s = match m {
-            Match::Exact => "exactly",
-            Match::Min   => "at least",
-        };
-        Err(EvalError::new(format!("{} requires {} {} arguments, got {}",
-                                   description(), s, len, actual_len)))
-    } else {
-        Ok(())
-    }
-}
-
-pub fn as_numbers(objects: &[LispObject]) -> Result<Vec<f64>, (EvalError, usize)> {
-    objects
-        .iter().enumerate()
-        .map(|(index, object)| {
-            match object.as_number() {
-                Err(e) => Err((e, index)),
-                Ok(n) => Ok(n),
-            }
-        })
-        .collect()
-}
-
-pub fn as_symbols(objects: &[LispObject]) -> Result<Vec<Symbol>, (EvalError, usize)> {
-    objects
-        .iter().enumerate()
-        .map(|(index, object)| {
-            match object.as_symbol() {
-                Err(e) => Err((e, index)),
-                Ok(n) => Ok(n),
-            }
-        })
-        .collect()
-}
+use crate::{
+    condition,
+    env::Symbols,
+    exc,
+    lisp_object::{
+        EvalError,
+        LispObject,
+        SerializeSymbol,
+        Symbol,
+    },
+};
+
+pub enum Match {
+    Exact,
+    Min,
+    Max,
+}
+
+// Check the number of arguments in form. signature describes what was
+// expected, call the offending call, both are only rendered on failure.
+pub fn assert_args(m: Match, form: &[LispObject], len: usize,
+                   signature: impl FnOnce() -> String, call: impl FnOnce() -> String)
+                   -> Result<(), EvalError> {
+    let actual_len = form.len();
+    let pred = match m {
+        Match::Exact => actual_len != len,
+        Match::Min => actual_len < len,
+        Match::Max => actual_len > len,
+    };
+    if pred {
+        let s = match m {
+            Match::Exact => "exactly",
+            Match::Min   => "at least",
+            Match::Max   => "at most",
+        };
+        Err(EvalError::new(format!("{} requires {} {} arguments, got {} in {}",
+                                   signature(), s, len, actual_len, call()))
+            .with_condition(condition::ARITY_ERROR))
+    } else {
+        Ok(())
+    }
+}
+
+// The index of the first handler form if clause is a catch clause of try,
+// either (catch sym &rest handler) or (catch types sym &rest handler).
+pub fn catch_handler(symbols: &Symbols, clause: &LispObject) -> Option<usize> {
+    match clause.as_slice() {
+        Ok([LispObject::Symbol(head), LispObject::Symbol(_), ..])
+            if symbols.as_string(head) == Some("catch") => Some(2),
+        Ok([LispObject::Symbol(head), _, LispObject::Symbol(_), ..])
+            if symbols.as_string(head) == Some("catch") => Some(3),
+        _ => None,
+    }
+}
+
+// A keyword argument as name, without the colon, and value.
+pub type KeywordArg<'a> = (Option<&'a str>, &'a LispObject);
+
+// The keyword arguments in options, the rest arguments of a native. Errors
+// come with the index of the offending argument.
+pub fn keyword_args<'a>(symbols: &'a Symbols, options: &'a [LispObject])
+                        -> Result<Vec<KeywordArg<'a>>, (EvalError, usize)> {
+    let mut args: Vec<KeywordArg> = vec![];
+    for (index, pair) in options.chunks(2).enumerate() {
+        let name = match &pair[0] {
+            LispObject::Keyword(k) => symbols.as_string(k),
+            other => return Err((exc::keyword_expected(symbols.serialize_object(other)), 2 * index)),
+        };
+        if args.iter().any(|(other, _)| *other == name) {
+            return Err((exc::duplicate_keyword(name), 2 * index))
+        }
+        match pair.get(1) {
+            Some(value) => args.push((name, value)),
+            None => return Err((exc::keyword_without_value(name), 2 * index)),
+        }
+    }
+    Ok(args)
+}
+
+pub fn as_numbers(objects: &[LispObject]) -> Result<Vec<f64>, (EvalError, usize)> {
+    objects
+        .iter().enumerate()
+        .map(|(index, object)| {
+            match object.as_number() {
+                Err(e) => Err((e, index)),
+                Ok(n) => Ok(n),
+            }
+        })
+        .collect()
+}
+
+pub fn as_symbols(objects: &[LispObject]) -> Result<Vec<Symbol>, (EvalError, usize)> {
+    objects
+        .iter().enumerate()
+        .map(|(index, object)| {
+            match object.as_symbol() {
+                Err(e) => Err((e, index)),
+                Ok(n) => Ok(n),
+            }
+        })
+        .collect()
+}
@@ -1,8 +1,39 @@
-use clap::{Arg, App};
-use lisp::interpreter::Interpreter;
+use clap::{Arg, App, SubCommand};
+use std::num::NonZeroU64;
+use std::path::Path;
+use lisp::{interpreter::{Interpreter, InterpreterConfig, STACK_SIZE}, learn, lint, examples};
 
+fn run_examples(name: Option<&str>) {
+    match name {
+        None => for example in examples::EXAMPLES {
+            println!("{:14} {}", example.name, example.description());
+        },
+        Some(name) => match examples::find(name) {
+            None => println!("Unknown example {}", name),
+            Some(example) => {
+                println!("{}", example.source);
+                let mut interpreter = Interpreter::new();
+                let result = example.run(&mut interpreter)
+                    .map(|object| println!("=> {}", interpreter.serialize(&object)));
+                interpreter.handle_exec_error(example.source, result).ok();
+            },
+        },
+    }
+}
+
+// Run on a thread with a stack large enough for the default depth limit.
 fn main() {
-    let matches = App::new("lisp")
+    let cli = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("cannot start interpreter thread");
+    if cli.join().is_err() {
+        std::process::exit(101);
+    }
+}
+
+fn run() {
+    let app = App::new("lisp")
         .version("0.1.0")
         .author("Christoph Landgraf <christoph.landgraf@googlemail.com>")
         .about("Simple Lisp Interpreter in Rust")
@@ -10,17 +41,128 @@ fn main() {
              .short("i")
              .long("interactive")
              .help("If a file is provided, go to interpreter after that."))
+        .arg(Arg::with_name("legacy-if")
+             .long("legacy-if")
+             .help("Evaluate all forms after the consequent of if as else body."))
+        .arg(Arg::with_name("relaxed-predicates")
+             .long("relaxed-predicates")
+             .help("Accept any value as test of if, and, or and predicates, only #f and () are false."))
+        .arg(Arg::with_name("persist")
+             .long("persist")
+             .takes_value(true)
+             .value_name("FILE")
+             .help("Load global definitions from FILE and save them to it on exit of the REPL."))
+        .arg(Arg::with_name("step-limit")
+             .long("step-limit")
+             .takes_value(true)
+             .value_name("STEPS")
+             .help("Ask whether to continue every STEPS steps of evaluating a form in the REPL."))
+        .arg(Arg::with_name("max-depth")
+             .long("max-depth")
+             .takes_value(true)
+             .value_name("DEPTH")
+             .help("Fail with a stack-overflow error when evaluations nest deeper than DEPTH levels."))
+        .arg(Arg::with_name("record-history")
+             .long("record-history")
+             .help("Record every value assigned to a global with the form assigning it, see history-of."))
+        .arg(Arg::with_name("no-color")
+             .long("no-color")
+             .help("Print messages, the inspector and lessons without ANSI colors."))
         .arg(Arg::with_name("file")
              .help("If provided run the file.")
              .index(1))
-        .get_matches();
+        .subcommand(SubCommand::with_name("learn")
+                    .about("Interactive tutorial with exercises."))
+        .subcommand(SubCommand::with_name("examples")
+                    .about("List bundled example programs or run one of them.")
+                    .arg(Arg::with_name("name")
+                         .help("If provided run the example.")
+                         .index(1)))
+        .subcommand(SubCommand::with_name("lint")
+                    .about("Report self calls not in tail position, which grow the stack.")
+                    .arg(Arg::with_name("file")
+                         .required(true)
+                         .index(1)));
+    #[cfg(feature = "trace")]
+    let app = app.arg(Arg::with_name("trace")
+                      .long("trace")
+                      .help("Write spans of reading, macro expansion and calls with their durations to stderr."));
+    let matches = app.get_matches();
+
+    #[cfg(feature = "trace")]
+    if matches.is_present("trace") {
+        lisp::trace::log_to_stderr().expect("no logger is set yet");
+    }
+
+    let color = !matches.is_present("no-color");
+    if matches.subcommand_matches("learn").is_some() {
+        if let Err(e) = learn::run(color) {
+            println!("{}", e);
+        }
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("examples") {
+        run_examples(matches.value_of("name"));
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("lint") {
+        match lint::run(matches.value_of("file").unwrap(), color) {
+            Ok(0) => (),
+            Ok(_) => std::process::exit(1),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(2);
+            },
+        }
+        return;
+    }
 
-    let mut interpreter = Interpreter::new();
+    let max_depth = match matches.value_of("max-depth").map(|depth| depth.parse::<usize>()) {
+        Some(Ok(depth)) if depth > 0 => Some(depth),
+        Some(_) => {
+            println!("--max-depth requires a positive number");
+            return;
+        },
+        None => InterpreterConfig::default().max_depth,
+    };
+    let mut interpreter = Interpreter::new_with(InterpreterConfig {
+        legacy_if: matches.is_present("legacy-if"),
+        strict_predicates: !matches.is_present("relaxed-predicates"),
+        max_depth,
+        record_history: matches.is_present("record-history"),
+        color,
+        ..InterpreterConfig::default()
+    });
+    let persist = matches.value_of("persist");
+    if let Some(f) = persist.filter(|f| Path::new(f).exists()) {
+        if let Err(e) = interpreter.read_file(f) {
+            println!("{}", e);
+            return;
+        }
+    }
     if let Some(f) = matches.value_of("file") {
-        if let Err(e) = interpreter.read_file(&f) {
+        if let Err(e) = interpreter.read_file(f) {
             println!("{}:", e);
             return;
         }
     }
+    match matches.value_of("step-limit").map(|steps| steps.parse::<NonZeroU64>()) {
+        Some(Ok(steps)) => interpreter.set_step_limit(Some(steps)),
+        Some(_) => {
+            println!("--step-limit requires a positive number");
+            return;
+        },
+        None => (),
+    }
     interpreter.interactive();
+    if let Some(f) = persist {
+        match interpreter.save_globals(f) {
+            Ok(skipped) => for name in skipped {
+                println!("Not saving {}, its value cannot be read back", name);
+            },
+            Err(e) => println!("Could not save globals to {}: {}", f, e),
+        }
+    }
 }
@@ -0,0 +1,27 @@
+// Rendering Markdown to HTML with pulldown-cmark, for documentation and
+// static site scripts. Besides CommonMark, tables, footnotes, strikethrough
+// and task lists are recognized, as on GitHub.
+
+use pulldown_cmark::{html, Options, Parser};
+
+use crate::{
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef},
+};
+
+fn markdown_to_html(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let source = args[0].as_str()
+        .map_err(|e| e.trace(1))?;
+    let options = Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let mut out = String::new();
+    html::push_html(&mut out, Parser::new_ext(source, options));
+    Ok(LispObject::String(out.into()))
+}
+
+pub const MARKDOWN_TO_HTML: NativeDef = NativeDef {
+    name: "markdown->html",
+    positional: &["source"],
+    rest: None,
+    func: markdown_to_html,
+};
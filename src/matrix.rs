@@ -0,0 +1,348 @@
+// Matrices of numbers stored row by row in one Vec<f64>, so numeric code
+// doesn't pay for lists of lists. Vectors are matrices with one row or
+// column. Operations return new matrices, except mat-set!, which modifies
+// its matrix in place.
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, Handle, LispObject, NativeDef},
+};
+
+const MATRIX: &str = "matrix";
+
+// Elements make-matrix allocates at most, 128MB of numbers.
+const MAX_ELEMENTS: usize = 1 << 24;
+
+#[derive(Clone)]
+struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    fn shape(&self) -> String {
+        format!("{}x{}", self.rows, self.cols)
+    }
+
+    fn index(&self, row: &LispObject, col: &LispObject) -> Result<usize, EvalError> {
+        let row = dimension(row)
+            .map_err(|e| e.trace(2))?;
+        let col = dimension(col)
+            .map_err(|e| e.trace(3))?;
+        if row >= self.rows || col >= self.cols {
+            return Err(EvalError::new(format!("Index ({} {}) out of range for a {} matrix",
+                                              row, col, self.shape()))
+                       .with_condition(condition::PROGRAM_ERROR))
+        }
+        Ok(row * self.cols + col)
+    }
+}
+
+fn matrix(object: &LispObject) -> Result<Matrix, EvalError> {
+    object.as_handle(MATRIX)?.value.borrow().downcast_ref::<Matrix>()
+        .cloned()
+        .ok_or_else(|| EvalError::new("Invalid matrix handle".to_string()))
+}
+
+// A number of rows or columns, or an index.
+fn dimension(object: &LispObject) -> Result<usize, EvalError> {
+    match object.as_number()? {
+        n if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+        n => Err(EvalError::new(format!("Expected a non-negative integer, got {}", n))
+                 .with_condition(condition::TYPE_ERROR)),
+    }
+}
+
+fn mismatch(operation: &str, a: &Matrix, b: &Matrix) -> EvalError {
+    EvalError::new(format!("Cannot {} a {} and a {} matrix", operation, a.shape(), b.shape()))
+        .with_condition(condition::PROGRAM_ERROR)
+}
+
+fn make_matrix(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let rows = dimension(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let cols = dimension(&args[1])
+        .map_err(|e| e.trace(2))?;
+    let fill = match args[2].as_slice()? {
+        [] => 0.0,
+        [fill, ..] => fill.as_number()
+            .map_err(|e| e.trace(3))?,
+    };
+    let size = rows.checked_mul(cols)
+        .filter(|size| *size <= MAX_ELEMENTS)
+        .ok_or_else(|| EvalError::new(format!("Cannot make a {}x{} matrix, it may have at most {} elements",
+                                              rows, cols, MAX_ELEMENTS))
+                    .with_condition(condition::PROGRAM_ERROR))?;
+    Ok(Handle::object(MATRIX, Matrix { rows, cols, data: vec![fill; size] }))
+}
+
+pub const MAKE_MATRIX: NativeDef = NativeDef {
+    name: "make-matrix",
+    positional: &["rows", "cols"],
+    rest: Some("fill"),
+    func: make_matrix,
+};
+
+// A matrix of a list of rows of equal length. A list of numbers is a matrix
+// of one row.
+fn list_to_matrix(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let items = args[0].as_slice()
+        .map_err(|e| e.trace(1))?;
+    let rows = match items.first() {
+        Some(LispObject::Number(_)) => vec![items],
+        _ => items.iter()
+            .map(|row| row.as_slice())
+            .collect::<Result<Vec<&[LispObject]>, EvalError>>()
+            .map_err(|e| e.trace(1))?,
+    };
+    let cols = rows.first().map_or(0, |row| row.len());
+    let mut data = Vec::with_capacity(rows.len() * cols);
+    for row in &rows {
+        if row.len() != cols {
+            return Err(EvalError::new("Expected rows of equal length".to_string())
+                       .with_condition(condition::TYPE_ERROR)
+                       .trace(1))
+        }
+        for item in row.iter() {
+            data.push(item.as_number()
+                      .map_err(|e| e.trace(1))?);
+        }
+    }
+    Ok(Handle::object(MATRIX, Matrix { rows: rows.len(), cols, data }))
+}
+
+pub const LIST_TO_MATRIX: NativeDef = NativeDef {
+    name: "list->matrix",
+    positional: &["rows"],
+    rest: None,
+    func: list_to_matrix,
+};
+
+fn matrix_to_list(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let m = matrix(&args[0])
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::List((0..m.rows)
+                        .map(|i| LispObject::List(m.data[i * m.cols..(i + 1) * m.cols].iter()
+                                                  .map(|n| LispObject::Number(*n))
+                                                  .collect()))
+                        .collect()))
+}
+
+pub const MATRIX_TO_LIST: NativeDef = NativeDef {
+    name: "matrix->list",
+    positional: &["m"],
+    rest: None,
+    func: matrix_to_list,
+};
+
+fn is_matrix(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(args[0].as_handle(MATRIX).is_ok()))
+}
+
+pub const MATRIX_P: NativeDef = NativeDef {
+    name: "matrix?",
+    positional: &["obj"],
+    rest: None,
+    func: is_matrix,
+};
+
+// The numbers of rows and columns as values.
+fn mat_shape(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let m = matrix(&args[0])
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::Values(vec![LispObject::Number(m.rows as f64),
+                               LispObject::Number(m.cols as f64)].into()))
+}
+
+pub const MAT_SHAPE: NativeDef = NativeDef {
+    name: "mat-shape",
+    positional: &["m"],
+    rest: None,
+    func: mat_shape,
+};
+
+fn mat_ref(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let m = matrix(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let index = m.index(&args[1], &args[2])?;
+    Ok(LispObject::Number(m.data[index]))
+}
+
+pub const MAT_REF: NativeDef = NativeDef {
+    name: "mat-ref",
+    positional: &["m", "row", "col"],
+    rest: None,
+    func: mat_ref,
+};
+
+fn mat_set(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let value = args[3].as_number()
+        .map_err(|e| e.trace(4))?;
+    let handle = args[0].as_handle(MATRIX)
+        .map_err(|e| e.trace(1))?;
+    let mut m = handle.value.borrow_mut();
+    let m = m.downcast_mut::<Matrix>()
+        .ok_or_else(|| EvalError::new("Invalid matrix handle".to_string()).trace(1))?;
+    let index = m.index(&args[1], &args[2])?;
+    m.data[index] = value;
+    Ok(args[0].clone())
+}
+
+pub const MAT_SET: NativeDef = NativeDef {
+    name: "mat-set!",
+    positional: &["m", "row", "col", "value"],
+    rest: None,
+    func: mat_set,
+};
+
+fn mat_mul(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let a = matrix(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let b = matrix(&args[1])
+        .map_err(|e| e.trace(2))?;
+    if a.cols != b.rows {
+        return Err(mismatch("multiply", &a, &b))
+    }
+    let mut data = vec![0.0; a.rows * b.cols];
+    for i in 0..a.rows {
+        for k in 0..a.cols {
+            let x = a.data[i * a.cols + k];
+            for j in 0..b.cols {
+                data[i * b.cols + j] += x * b.data[k * b.cols + j];
+            }
+        }
+    }
+    Ok(Handle::object(MATRIX, Matrix { rows: a.rows, cols: b.cols, data }))
+}
+
+pub const MAT_MUL: NativeDef = NativeDef {
+    name: "mat-mul",
+    positional: &["a", "b"],
+    rest: None,
+    func: mat_mul,
+};
+
+fn transpose(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let m = matrix(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let mut data = Vec::with_capacity(m.data.len());
+    for j in 0..m.cols {
+        for i in 0..m.rows {
+            data.push(m.data[i * m.cols + j]);
+        }
+    }
+    Ok(Handle::object(MATRIX, Matrix { rows: m.cols, cols: m.rows, data }))
+}
+
+pub const TRANSPOSE: NativeDef = NativeDef {
+    name: "transpose",
+    positional: &["m"],
+    rest: None,
+    func: transpose,
+};
+
+// Combine the elements of matrices of the same shape with op.
+fn elementwise(operation: &str, args: &[LispObject], op: fn(f64, f64) -> f64)
+               -> Result<LispObject, EvalError> {
+    let a = matrix(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let b = matrix(&args[1])
+        .map_err(|e| e.trace(2))?;
+    if a.rows != b.rows || a.cols != b.cols {
+        return Err(mismatch(operation, &a, &b))
+    }
+    let data = a.data.iter().zip(&b.data).map(|(x, y)| op(*x, *y)).collect();
+    Ok(Handle::object(MATRIX, Matrix { data, ..a }))
+}
+
+fn mat_add(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    elementwise("add", args, |x, y| x + y)
+}
+
+pub const MAT_ADD: NativeDef = NativeDef {
+    name: "mat-add",
+    positional: &["a", "b"],
+    rest: None,
+    func: mat_add,
+};
+
+fn mat_sub(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    elementwise("subtract", args, |x, y| x - y)
+}
+
+pub const MAT_SUB: NativeDef = NativeDef {
+    name: "mat-sub",
+    positional: &["a", "b"],
+    rest: None,
+    func: mat_sub,
+};
+
+// The elementwise, or Hadamard, product.
+fn mat_mul_elements(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    elementwise("multiply the elements of", args, |x, y| x * y)
+}
+
+pub const MAT_MUL_ELEMENTS: NativeDef = NativeDef {
+    name: "mat-mul-elements",
+    positional: &["a", "b"],
+    rest: None,
+    func: mat_mul_elements,
+};
+
+fn mat_scale(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let m = matrix(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let factor = args[1].as_number()
+        .map_err(|e| e.trace(2))?;
+    let data = m.data.iter().map(|x| x * factor).collect();
+    Ok(Handle::object(MATRIX, Matrix { data, ..m }))
+}
+
+pub const MAT_SCALE: NativeDef = NativeDef {
+    name: "mat-scale",
+    positional: &["m", "factor"],
+    rest: None,
+    func: mat_scale,
+};
+
+// The matrix of f applied to every element.
+fn mat_map(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let m = matrix(&args[1])
+        .map_err(|e| e.trace(2))?;
+    let mut data = Vec::with_capacity(m.data.len());
+    for x in &m.data {
+        data.push(interp.apply(&args[0], &[LispObject::Number(*x)])?
+                  .as_number()
+                  .map_err(|e| e.trace(1))?);
+    }
+    Ok(Handle::object(MATRIX, Matrix { data, ..m }))
+}
+
+pub const MAT_MAP: NativeDef = NativeDef {
+    name: "mat-map",
+    positional: &["f", "m"],
+    rest: None,
+    func: mat_map,
+};
+
+// The sum of the products of the elements of two matrices of the same shape,
+// the dot product of vectors.
+fn dot(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let a = matrix(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let b = matrix(&args[1])
+        .map_err(|e| e.trace(2))?;
+    if a.rows != b.rows || a.cols != b.cols {
+        return Err(mismatch("take the dot product of", &a, &b))
+    }
+    Ok(LispObject::Number(a.data.iter().zip(&b.data).map(|(x, y)| x * y).sum()))
+}
+
+pub const DOT: NativeDef = NativeDef {
+    name: "dot",
+    positional: &["a", "b"],
+    rest: None,
+    func: dot,
+};
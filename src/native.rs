@@ -1,157 +1,1067 @@
-use crate::{
-    lisp_object::{
-        EvalError,
-        LispObject,
-        NativeDef,
-    },
-    lisp_object_util::{
-        as_numbers,
-    },
-};
-
-fn add(args: &[LispObject]) -> Result<LispObject, EvalError> {
-    let terms = args[0].as_list()?;
-    as_numbers(&terms)
-        .map(|args| LispObject::Number(args.iter().fold(0.0, |sum, a| sum + a)))
-        .map_err(|(err, index)| err.trace(index + 1))
-}
-
-pub const ADD: NativeDef = NativeDef {
-    name: "+",
-    positional: &[],
-    rest: Some("terms"),
-    func: add,
-};
-
-fn multiply(args: &[LispObject]) -> Result<LispObject, EvalError> {
-    let factors = args[0].as_list()?;
-    as_numbers(&factors)
-        .map(|args| LispObject::Number(args.iter().fold(1.0, |sum, a| sum * a)))
-        .map_err(|(err, index)| err.trace(index + 1))
-}
-
-pub const MULTIPLY: NativeDef = NativeDef {
-    name: "*",
-    positional: &[],
-    rest: Some("factors"),
-    func: multiply
-};
-
-fn subtract(args: &[LispObject]) -> Result<LispObject, EvalError> {
-    let min = args[0].as_number()
-        .map_err(|err| err.trace(1))?;
-    let subs = args[1].as_list()?;
-    let sub = as_numbers(&subs)
-        .map(|args| args.iter().fold(0.0, |sum, a| sum + a))
-        .map_err(|(err, index)| err.trace(index + 2))?;
-    Ok(LispObject::Number(min - sub))
-}
-
-pub const SUBTRACT: NativeDef = NativeDef {
-    name: "-",
-    positional: &["min"],
-    rest: Some("subs"),
-    func: subtract,
-};
-
-fn equal(args: &[LispObject]) -> Result<LispObject, EvalError> {
-    match args[0] {
-        LispObject::Number(op0) => {
-            let op1 = args[1].as_number()
-                .map_err(|e| e.trace(2))?;
-            Ok(LispObject::Bool(op0 == op1))
-        }
-        LispObject::Symbol(op0) => {
-            let op1 = args[1].as_symbol()
-                .map_err(|e| e.trace(2))?;
-            Ok(LispObject::Bool(op0 == op1))
-        }
-        _ => Err(EvalError::new("equal not implemented for type".to_string()).trace(1)),
-    }
-}
-
-pub const EQUAL: NativeDef = NativeDef {
-    name: "=",
-    positional: &["o1", "o2"],
-    rest: None,
-    func: equal,
-};
-
-fn first(args: &[LispObject]) -> Result<LispObject, EvalError> {
-    let lst = args[0].as_list()?;
-    Ok(lst[0].clone())
-}
-
-pub const FIRST: NativeDef = NativeDef {
-    name: "first",
-    positional: &["lst"],
-    rest: None,
-    func: first,
-};
-
-fn rest(args: &[LispObject]) -> Result<LispObject, EvalError> {
-    let lst = args[0].as_list()?;
-    let res = if lst.len() > 0 {
-        lst[1..].to_vec()
-    } else {
-        vec![]
-    };
-    Ok(LispObject::List(res))
-}
-
-pub const REST: NativeDef = NativeDef {
-    name: "rest",
-    positional: &["lst"],
-    rest: None,
-    func: rest,
-};
-
-fn list(args: &[LispObject]) -> Result<LispObject, EvalError> {
-    Ok(LispObject::List(args[0].as_list()?))
-}
-
-pub const LIST: NativeDef = NativeDef {
-    name: "list",
-    positional: &[],
-    rest: Some("elems"),
-    func: list,
-};
-
-fn concat(args: &[LispObject]) -> Result<LispObject, EvalError> {
-    Ok(LispObject::List(
-        args[0].as_list()?.into_iter().enumerate()
-            .map(|(index, elem)| elem.into_list()
-                 .map_err(|e| e.trace(index + 1)))
-            .collect::<Result<Vec<Vec<LispObject>>, EvalError>>()?
-            .concat()
-    ))
-}
-
-pub const CONCAT: NativeDef = NativeDef {
-    name: "concat",
-    positional: &[],
-    rest: Some("lsts"),
-    func: concat,
-};
-
-fn is_list(args: &[LispObject]) -> Result<LispObject, EvalError> {
-    Ok(LispObject::Bool(matches!(args[0], LispObject::List(_))))
-}
-
-pub const IS_LIST: NativeDef = NativeDef {
-    name: "is-list",
-    positional: &["lst"],
-    rest: None,
-    func: is_list,
-};
-
-fn length(args: &[LispObject]) -> Result<LispObject, EvalError> {
-    Ok(LispObject::Number(args[0].as_list()?.len() as f64))
-}
-
-pub const LENGTH: NativeDef = NativeDef {
-    name: "length",
-    positional: &["lst"],
-    rest: None,
-    func: length,
-};
+use std::iter;
+use std::rc::Rc;
+
+use crate::{
+    condition,
+    exc,
+    interpreter::{ExecError, Interpreter},
+    lisp_object::{
+        ErrorKind,
+        EvalError,
+        LispObject,
+        Map,
+        NativeDef,
+        SerializeSymbol,
+    },
+    lisp_object_util::{
+        Match,
+        as_numbers,
+        assert_args,
+    },
+};
+
+fn add(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let terms = args[0].as_slice()?;
+    as_numbers(terms)
+        .map(|args| LispObject::Number(args.iter().fold(0.0, |sum, a| sum + a)))
+        .map_err(|(err, index)| err.trace(index + 1))
+}
+
+pub const ADD: NativeDef = NativeDef {
+    name: "+",
+    positional: &[],
+    rest: Some("terms"),
+    func: add,
+};
+
+fn multiply(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let factors = args[0].as_slice()?;
+    as_numbers(factors)
+        .map(|args| LispObject::Number(args.iter().fold(1.0, |sum, a| sum * a)))
+        .map_err(|(err, index)| err.trace(index + 1))
+}
+
+pub const MULTIPLY: NativeDef = NativeDef {
+    name: "*",
+    positional: &[],
+    rest: Some("factors"),
+    func: multiply
+};
+
+fn subtract(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let min = args[0].as_number()
+        .map_err(|err| err.trace(1))?;
+    let subs = args[1].as_slice()?;
+    let sub = as_numbers(subs)
+        .map(|args| args.iter().fold(0.0, |sum, a| sum + a))
+        .map_err(|(err, index)| err.trace(index + 2))?;
+    Ok(LispObject::Number(min - sub))
+}
+
+pub const SUBTRACT: NativeDef = NativeDef {
+    name: "-",
+    positional: &["min"],
+    rest: Some("subs"),
+    func: subtract,
+};
+
+// Any object may be compared to nil.
+fn equal(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    match args[0] {
+        _ if matches!(args[1], LispObject::Nil) => Ok(LispObject::Bool(matches!(args[0], LispObject::Nil))),
+        LispObject::Nil => Ok(LispObject::Bool(false)),
+        LispObject::Number(op0) => {
+            let op1 = args[1].as_number()
+                .map_err(|e| e.trace(2))?;
+            Ok(LispObject::Bool(op0 == op1))
+        }
+        LispObject::Symbol(op0) => {
+            let op1 = args[1].as_symbol()
+                .map_err(|e| e.trace(2))?;
+            Ok(LispObject::Bool(op0 == op1))
+        }
+        LispObject::String(ref op0) => {
+            let op1 = args[1].as_str()
+                .map_err(|e| e.trace(2))?;
+            Ok(LispObject::Bool(**op0 == *op1))
+        }
+        LispObject::Bool(op0) => {
+            let op1 = args[1].as_bool()
+                .map_err(|e| e.trace(2))?;
+            Ok(LispObject::Bool(op0 == op1))
+        }
+        LispObject::Keyword(op0) => match args[1] {
+            LispObject::Keyword(op1) => Ok(LispObject::Bool(op0 == op1)),
+            _ => Err(EvalError::new("Expected a keyword".to_string())
+                     .with_condition(condition::TYPE_ERROR)
+                     .trace(2)),
+        },
+        _ => Err(EvalError::new("= compares numbers, symbols, keywords, strings and bools, use equal? \
+                                 for other objects".to_string())
+                 .with_condition(condition::TYPE_ERROR)
+                 .trace(1)),
+    }
+}
+
+pub const EQUAL: NativeDef = NativeDef {
+    name: "=",
+    positional: &["o1", "o2"],
+    rest: None,
+    func: equal,
+};
+
+fn less(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let op0 = args[0].as_number()
+        .map_err(|e| e.trace(1))?;
+    let op1 = args[1].as_number()
+        .map_err(|e| e.trace(2))?;
+    Ok(LispObject::Bool(op0 < op1))
+}
+
+pub const LESS: NativeDef = NativeDef {
+    name: "<",
+    positional: &["n1", "n2"],
+    rest: None,
+    func: less,
+};
+
+// Structural equality, for objects of any type.
+fn is_equal(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(args[0].equals(&args[1])))
+}
+
+pub const EQUAL_P: NativeDef = NativeDef {
+    name: "equal?",
+    positional: &["o1", "o2"],
+    rest: None,
+    func: is_equal,
+};
+
+fn is_eq(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(args[0].is_identical(&args[1])))
+}
+
+pub const EQ_P: NativeDef = NativeDef {
+    name: "eq?",
+    positional: &["o1", "o2"],
+    rest: None,
+    func: is_eq,
+};
+
+// Negation of a test, see the strict-predicates option.
+fn not(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let value = interp.is_true(&args[0])
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::Bool(!value))
+}
+
+pub const NOT: NativeDef = NativeDef {
+    name: "not",
+    positional: &["obj"],
+    rest: None,
+    func: not,
+};
+
+fn first(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let lst = args[0].as_slice()?;
+    lst.first().cloned()
+        .ok_or_else(|| EvalError::new("first of empty list".to_string())
+                    .with_condition(condition::PROGRAM_ERROR)
+                    .trace(1))
+}
+
+pub const FIRST: NativeDef = NativeDef {
+    name: "first",
+    positional: &["lst"],
+    rest: None,
+    func: first,
+};
+
+fn rest(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let lst = args[0].as_slice()?;
+    let res = if !lst.is_empty() {
+        lst[1..].to_vec()
+    } else {
+        vec![]
+    };
+    Ok(LispObject::List(res))
+}
+
+pub const REST: NativeDef = NativeDef {
+    name: "rest",
+    positional: &["lst"],
+    rest: None,
+    func: rest,
+};
+
+fn list(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(args[0].clone())
+}
+
+pub const LIST: NativeDef = NativeDef {
+    name: "list",
+    positional: &[],
+    rest: Some("elems"),
+    func: list,
+};
+
+fn concat(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::List(
+        args[0].as_slice()?.iter().enumerate()
+            .map(|(index, elem)| elem.as_slice()
+                 .map_err(|e| e.trace(index + 1)))
+            .collect::<Result<Vec<&[LispObject]>, EvalError>>()?
+            .concat()
+    ))
+}
+
+pub const CONCAT: NativeDef = NativeDef {
+    name: "concat",
+    positional: &[],
+    rest: Some("lsts"),
+    func: concat,
+};
+
+fn is_list(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(matches!(args[0], LispObject::List(_))))
+}
+
+pub const IS_LIST: NativeDef = NativeDef {
+    name: "is-list",
+    positional: &["lst"],
+    rest: None,
+    func: is_list,
+};
+
+pub const LIST_P: NativeDef = NativeDef {
+    name: "list?",
+    positional: &["obj"],
+    rest: None,
+    func: is_list,
+};
+
+fn is_number(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(matches!(args[0], LispObject::Number(_))))
+}
+
+pub const NUMBER_P: NativeDef = NativeDef {
+    name: "number?",
+    positional: &["obj"],
+    rest: None,
+    func: is_number,
+};
+
+fn is_string(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(matches!(args[0], LispObject::String(_))))
+}
+
+pub const STRING_P: NativeDef = NativeDef {
+    name: "string?",
+    positional: &["obj"],
+    rest: None,
+    func: is_string,
+};
+
+fn is_symbol(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(matches!(args[0], LispObject::Symbol(_))))
+}
+
+pub const SYMBOL_P: NativeDef = NativeDef {
+    name: "symbol?",
+    positional: &["obj"],
+    rest: None,
+    func: is_symbol,
+};
+
+// A single value is returned as is, other counts as Values, of which
+// contexts expecting a single value take the first.
+fn values(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    match args[0].as_slice()? {
+        [value] => Ok(value.clone()),
+        values => Ok(LispObject::Values(Rc::new(values.to_vec()))),
+    }
+}
+
+pub const VALUES: NativeDef = NativeDef {
+    name: "values",
+    positional: &[],
+    rest: Some("values"),
+    func: values,
+};
+
+fn is_keyword(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(matches!(args[0], LispObject::Keyword(_))))
+}
+
+pub const KEYWORD_P: NativeDef = NativeDef {
+    name: "keyword?",
+    positional: &["obj"],
+    rest: None,
+    func: is_keyword,
+};
+
+fn is_bool(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(matches!(args[0], LispObject::Bool(_))))
+}
+
+pub const BOOL_P: NativeDef = NativeDef {
+    name: "bool?",
+    positional: &["obj"],
+    rest: None,
+    func: is_bool,
+};
+
+fn is_nil(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(matches!(args[0], LispObject::Nil)))
+}
+
+pub const NIL_P: NativeDef = NativeDef {
+    name: "nil?",
+    positional: &["obj"],
+    rest: None,
+    func: is_nil,
+};
+
+fn is_map(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(matches!(args[0], LispObject::Map(_))))
+}
+
+pub const MAP_P: NativeDef = NativeDef {
+    name: "map?",
+    positional: &["obj"],
+    rest: None,
+    func: is_map,
+};
+
+fn is_fn(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(matches!(args[0], LispObject::Native(_, _) | LispObject::Lambda(_))))
+}
+
+pub const FN_P: NativeDef = NativeDef {
+    name: "fn?",
+    positional: &["obj"],
+    rest: None,
+    func: is_fn,
+};
+
+fn length(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Number(args[0].as_slice()?.len() as f64))
+}
+
+pub const LENGTH: NativeDef = NativeDef {
+    name: "length",
+    positional: &["lst"],
+    rest: None,
+    func: length,
+};
+
+// Call f with the elements of lst as arguments.
+fn apply(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let lst = args[1].as_slice()
+        .map_err(|e| e.trace(2))?;
+    interp.apply(&args[0], lst)
+}
+
+pub const APPLY: NativeDef = NativeDef {
+    name: "apply",
+    positional: &["f", "lst"],
+    rest: None,
+    func: apply,
+};
+
+// Call f with the elements at each index of the lists in args[1], up to the
+// length of the shortest.
+fn map_lists<'a>(interp: &'a mut Interpreter, args: &'a [LispObject])
+                 -> Result<impl Iterator<Item = Result<LispObject, EvalError>> + 'a, EvalError> {
+    let lsts = args[1].as_slice()?.iter().enumerate()
+        .map(|(index, elem)| elem.as_slice()
+             .map_err(|e| e.trace(index + 2)))
+        .collect::<Result<Vec<&[LispObject]>, EvalError>>()?;
+    let len = lsts.iter().map(|lst| lst.len()).min().unwrap_or(0);
+    Ok((0..len)
+        .map(move |index| {
+            let call_args = lsts.iter()
+                .map(|lst| lst[index].clone())
+                .collect::<Vec<LispObject>>();
+            interp.apply(&args[0], &call_args)
+        }))
+}
+
+fn map(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    map_lists(interp, args)?
+        .collect::<Result<Vec<LispObject>, EvalError>>()
+        .map(LispObject::List)
+}
+
+pub const MAP: NativeDef = NativeDef {
+    name: "map",
+    positional: &["f"],
+    rest: Some("lsts"),
+    func: map,
+};
+
+fn reduce(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let lst = args[1].as_list()
+        .map_err(|e| e.trace(2))?;
+    let mut elems = lst.into_iter();
+    let mut acc = elems.next()
+        .ok_or_else(|| EvalError::new("reduce of empty list".to_string()).trace(2))?;
+    for elem in elems {
+        acc = interp.apply(&args[0], &[acc, elem])?;
+    }
+    Ok(acc)
+}
+
+pub const REDUCE: NativeDef = NativeDef {
+    name: "reduce",
+    positional: &["f", "lst"],
+    rest: None,
+    func: reduce,
+};
+
+// The elements of lst for which pred is true, in order.
+fn filter(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let lst = args[1].as_slice()
+        .map_err(|e| e.trace(2))?;
+    let mut kept = vec![];
+    for elem in lst {
+        let test = interp.apply(&args[0], std::slice::from_ref(elem))?;
+        if interp.is_true(&test)? {
+            kept.push(elem.clone());
+        }
+    }
+    Ok(LispObject::List(kept))
+}
+
+pub const FILTER: NativeDef = NativeDef {
+    name: "filter",
+    positional: &["pred", "lst"],
+    rest: None,
+    func: filter,
+};
+
+// Like map, but only for the effects of f.
+fn for_each(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    for result in map_lists(interp, args)? {
+        result?;
+    }
+    Ok(LispObject::Nil)
+}
+
+pub const FOR_EACH: NativeDef = NativeDef {
+    name: "for-each",
+    positional: &["f"],
+    rest: Some("lsts"),
+    func: for_each,
+};
+
+// Rebuild form with inner applied to its children, the elements of a list or
+// the keys and values of a map. Other objects have no children.
+fn walk_children(form: &LispObject,
+                 mut inner: impl FnMut(&LispObject) -> Result<LispObject, EvalError>)
+                 -> Result<LispObject, EvalError> {
+    match form {
+        LispObject::List(l) => l.iter()
+            .map(inner)
+            .collect::<Result<Vec<LispObject>, EvalError>>()
+            .map(LispObject::List),
+        LispObject::Map(m) => m.iter()
+            .map(|(k, v)| Ok((inner(k)?, inner(v)?)))
+            .collect::<Result<Map, EvalError>>()
+            .map(|m| LispObject::Map(Rc::new(m))),
+        _ => Ok(form.clone()),
+    }
+}
+
+fn walk_post(interp: &mut Interpreter, f: &LispObject, form: &LispObject)
+             -> Result<LispObject, EvalError> {
+    let walked = walk_children(form, |child| walk_post(interp, f, child))?;
+    interp.apply(f, &[walked])
+}
+
+fn walk_pre(interp: &mut Interpreter, f: &LispObject, form: &LispObject)
+            -> Result<LispObject, EvalError> {
+    let replaced = interp.apply(f, std::slice::from_ref(form))?;
+    walk_children(&replaced, |child| walk_pre(interp, f, child))
+}
+
+// Replace every node of form by f applied to it, after its children were
+// replaced.
+fn postwalk(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    walk_post(interp, &args[0], &args[1])
+}
+
+pub const POSTWALK: NativeDef = NativeDef {
+    name: "postwalk",
+    positional: &["f", "form"],
+    rest: None,
+    func: postwalk,
+};
+
+// Replace every node of form by f applied to it, then walk the children of
+// the replacement.
+fn prewalk(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    walk_pre(interp, &args[0], &args[1])
+}
+
+pub const PREWALK: NativeDef = NativeDef {
+    name: "prewalk",
+    positional: &["f", "form"],
+    rest: None,
+    func: prewalk,
+};
+
+fn load(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let path = args[0].as_str()
+        .map_err(|e| e.trace(1))?;
+    interp.load(path)
+        .map_err(|e| match e {
+            ExecError::Eval(e) => e,
+            ExecError::Read(e) => EvalError::new(format!("{}: {}", path, e))
+                .with_condition(condition::SYNTAX_ERROR),
+            ExecError::Io(e) => EvalError::new(e)
+                .with_condition(condition::IO_ERROR),
+        })
+}
+
+pub const LOAD: NativeDef = NativeDef {
+    name: "load",
+    positional: &["path"],
+    rest: None,
+    func: load,
+};
+
+fn make_map(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let kvs = args[0].as_slice()?;
+    if kvs.len() % 2 != 0 {
+        return Err(EvalError::new("make-map requires an even number of arguments".to_string()))
+    }
+    let mut map: Map = vec![];
+    for kv in kvs.chunks(2) {
+        match map.iter_mut().find(|(k, _)| k.equals(&kv[0])) {
+            Some(entry) => entry.1 = kv[1].clone(),
+            None => map.push((kv[0].clone(), kv[1].clone())),
+        }
+    }
+    Ok(LispObject::Map(Rc::new(map)))
+}
+
+pub const MAKE_MAP: NativeDef = NativeDef {
+    name: "make-map",
+    positional: &[],
+    rest: Some("kvs"),
+    func: make_map,
+};
+
+// The value of a key that was not found, default if given, otherwise nil.
+// Shared by get, alist-get and plist-get, whose first parameter is named
+// collection.
+fn not_found(interp: &Interpreter, name: &str, collection: &str, args: &[LispObject])
+             -> Result<LispObject, EvalError> {
+    let default = args[2].as_slice()?;
+    assert_args(Match::Max, default, 1, || format!("({} {} key &rest default)", name, collection),
+                || interp.symbols().serialize_call(name, default))?;
+    Ok(default.first().cloned().unwrap_or(LispObject::Nil))
+}
+
+fn get(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let value = args[0].map_get(&args[1])
+        .map_err(|e| e.trace(1))?;
+    match value {
+        Some(value) => Ok(value.clone()),
+        None => not_found(interp, "get", "m", args),
+    }
+}
+
+pub const GET: NativeDef = NativeDef {
+    name: "get",
+    positional: &["m", "key"],
+    rest: Some("default"),
+    func: get,
+};
+
+fn assoc(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let mut map = args[0].as_map()
+        .map_err(|e| e.trace(1))?
+        .clone();
+    match map.iter_mut().find(|(k, _)| k.equals(&args[1])) {
+        Some(entry) => entry.1 = args[2].clone(),
+        None => map.push((args[1].clone(), args[2].clone())),
+    }
+    Ok(LispObject::Map(Rc::new(map)))
+}
+
+pub const ASSOC: NativeDef = NativeDef {
+    name: "assoc",
+    positional: &["m", "key", "value"],
+    rest: None,
+    func: assoc,
+};
+
+fn keys(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let map = args[0].as_map()
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::List(map.iter().map(|(k, _)| k.clone()).collect()))
+}
+
+pub const KEYS: NativeDef = NativeDef {
+    name: "keys",
+    positional: &["m"],
+    rest: None,
+    func: keys,
+};
+
+// The entries of an association list of (key value) lists.
+fn alist_entries(alist: &LispObject) -> Result<Vec<(&LispObject, &LispObject)>, EvalError> {
+    alist.as_slice()?.iter()
+        .map(|entry| match entry.as_slice() {
+            Ok([key, value]) => Ok((key, value)),
+            _ => Err(EvalError::new("Expected entries (key value) in association list".to_string())
+                     .with_condition(condition::TYPE_ERROR)),
+        })
+        .collect()
+}
+
+// The entries of a property list of alternating keys and values.
+fn plist_entries(plist: &LispObject) -> Result<Vec<(&LispObject, &LispObject)>, EvalError> {
+    let elements = plist.as_slice()?;
+    if elements.len() % 2 != 0 {
+        return Err(EvalError::new("Property list requires an even number of elements".to_string())
+                   .with_condition(condition::TYPE_ERROR))
+    }
+    Ok(elements.chunks(2).map(|kv| (&kv[0], &kv[1])).collect())
+}
+
+// A map of the entries, of which the first one for a key is kept, like
+// lookups find it.
+fn entries_to_map(entries: Vec<(&LispObject, &LispObject)>) -> LispObject {
+    let mut map: Map = vec![];
+    for (key, value) in entries {
+        if !map.iter().any(|(k, _)| k.equals(key)) {
+            map.push((key.clone(), value.clone()));
+        }
+    }
+    LispObject::Map(Rc::new(map))
+}
+
+fn alist_get(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let entries = alist_entries(&args[0])
+        .map_err(|e| e.trace(1))?;
+    match entries.iter().find(|(key, _)| key.equals(&args[1])) {
+        Some((_, value)) => Ok((*value).clone()),
+        None => not_found(interp, "alist-get", "alist", args),
+    }
+}
+
+pub const ALIST_GET: NativeDef = NativeDef {
+    name: "alist-get",
+    positional: &["alist", "key"],
+    rest: Some("default"),
+    func: alist_get,
+};
+
+// Like assoc, the entry of key is replaced or a new one added at the end.
+fn alist_set(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let entries = alist_entries(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let entry = LispObject::List(vec![args[1].clone(), args[2].clone()]);
+    let mut alist = vec![];
+    let mut replaced = false;
+    for (key, value) in entries {
+        if !replaced && key.equals(&args[1]) {
+            alist.push(entry.clone());
+            replaced = true;
+        } else {
+            alist.push(LispObject::List(vec![key.clone(), value.clone()]));
+        }
+    }
+    if !replaced {
+        alist.push(entry);
+    }
+    Ok(LispObject::List(alist))
+}
+
+pub const ALIST_SET: NativeDef = NativeDef {
+    name: "alist-set",
+    positional: &["alist", "key", "value"],
+    rest: None,
+    func: alist_set,
+};
+
+fn alist_to_map(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    alist_entries(&args[0])
+        .map(entries_to_map)
+        .map_err(|e| e.trace(1))
+}
+
+pub const ALIST_TO_MAP: NativeDef = NativeDef {
+    name: "alist->map",
+    positional: &["alist"],
+    rest: None,
+    func: alist_to_map,
+};
+
+fn plist_get(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let entries = plist_entries(&args[0])
+        .map_err(|e| e.trace(1))?;
+    match entries.iter().find(|(key, _)| key.equals(&args[1])) {
+        Some((_, value)) => Ok((*value).clone()),
+        None => not_found(interp, "plist-get", "plist", args),
+    }
+}
+
+pub const PLIST_GET: NativeDef = NativeDef {
+    name: "plist-get",
+    positional: &["plist", "key"],
+    rest: Some("default"),
+    func: plist_get,
+};
+
+fn plist_to_map(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    plist_entries(&args[0])
+        .map(entries_to_map)
+        .map_err(|e| e.trace(1))
+}
+
+pub const PLIST_TO_MAP: NativeDef = NativeDef {
+    name: "plist->map",
+    positional: &["plist"],
+    rest: None,
+    func: plist_to_map,
+};
+
+fn difference(path: &[LispObject], fields: Vec<(&str, &LispObject)>) -> LispObject {
+    LispObject::Map(Rc::new(
+        iter::once(("path", &LispObject::List(path.to_vec())))
+            .chain(fields)
+            .map(|(k, v)| (LispObject::String(k.into()), v.clone()))
+            .collect()))
+}
+
+// Collect the places where actual differs from expected. Elements or entries
+// missing on one side are reported with only the other side.
+fn differences(path: &mut Vec<LispObject>, expected: &LispObject, actual: &LispObject,
+               result: &mut Vec<LispObject>) {
+    match (expected, actual) {
+        (LispObject::List(a), LispObject::List(b)) => {
+            for index in 0..a.len().max(b.len()) {
+                path.push(LispObject::Number(index as f64));
+                match (a.get(index), b.get(index)) {
+                    (Some(a), Some(b)) => differences(path, a, b, result),
+                    (Some(a), None) => result.push(difference(path, vec![("expected", a)])),
+                    (None, Some(b)) => result.push(difference(path, vec![("actual", b)])),
+                    (None, None) => (),
+                }
+                path.pop();
+            }
+        },
+        (LispObject::Map(a), LispObject::Map(b)) => {
+            for (key, value) in a.iter() {
+                path.push(key.clone());
+                match b.iter().find(|(k, _)| k.equals(key)) {
+                    Some((_, other)) => differences(path, value, other, result),
+                    None => result.push(difference(path, vec![("expected", value)])),
+                }
+                path.pop();
+            }
+            for (key, value) in b.iter().filter(|(k, _)| !a.iter().any(|(ak, _)| ak.equals(k))) {
+                path.push(key.clone());
+                result.push(difference(path, vec![("actual", value)]));
+                path.pop();
+            }
+        },
+        (a, b) if !a.equals(b) =>
+            result.push(difference(path, vec![("expected", a), ("actual", b)])),
+        _ => (),
+    }
+}
+
+fn diff(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let mut result = vec![];
+    differences(&mut vec![], &args[0], &args[1], &mut result);
+    Ok(LispObject::List(result))
+}
+
+pub const DIFF: NativeDef = NativeDef {
+    name: "diff",
+    positional: &["expected", "actual"],
+    rest: None,
+    func: diff,
+};
+
+fn describe_difference(interp: &Interpreter, difference: &LispObject) -> String {
+    let field = |name: &str| difference.map_get(&LispObject::String(name.into()))
+        .ok()
+        .flatten()
+        .map(|value| interp.serialize(value));
+    let path = field("path").unwrap_or_default();
+    match (field("expected"), field("actual")) {
+        (Some(expected), Some(actual)) =>
+            format!("at {}: expected {}, got {}", path, expected, actual),
+        (Some(expected), None) => format!("at {}: missing {}", path, expected),
+        (None, Some(actual)) => format!("at {}: unexpected {}", path, actual),
+        (None, None) => format!("at {}", path),
+    }
+}
+
+fn assert_equal(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let differences = diff(interp, args)?.into_list()?;
+    if differences.is_empty() {
+        return Ok(LispObject::Bool(true))
+    }
+    let description = differences.iter()
+        .map(|d| describe_difference(interp, d))
+        .collect::<Vec<String>>()
+        .join("; ");
+    Err(EvalError::new(format!("assert-equal failed: {}", description)))
+}
+
+pub const ASSERT_EQUAL: NativeDef = NativeDef {
+    name: "assert-equal",
+    positional: &["expected", "actual"],
+    rest: None,
+    func: assert_equal,
+};
+
+// Signal an error with message, which try can catch along with data. The
+// message may be preceded by a keyword naming the condition type.
+fn error(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let rest = args[1].as_slice()?;
+    let (condition, index, message, data) = match (&args[0], rest) {
+        (LispObject::Keyword(t), [message, data @ ..]) =>
+            (interp.symbols().as_string(t).unwrap_or_default(), 2, message, data),
+        (LispObject::Keyword(_), []) => return Err(exc::error_without_message()),
+        (message, data) => (condition::ERROR, 1, message, data),
+    };
+    let message = message.as_str()
+        .map_err(|e| e.trace(index))?;
+    Err(EvalError {
+        data: data.to_vec(),
+        ..EvalError::new(message.to_string())
+            .with_condition(condition)
+    })
+}
+
+pub const ERROR: NativeDef = NativeDef {
+    name: "error",
+    positional: &["message"],
+    rest: Some("data"),
+    func: error,
+};
+
+fn check_specs(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let enabled = args[0].as_bool()
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::Bool(interp.set_check_specs(enabled)))
+}
+
+pub const CHECK_SPECS: NativeDef = NativeDef {
+    name: "check-specs",
+    positional: &["enabled"],
+    rest: None,
+    func: check_specs,
+};
+
+// A fresh symbol for macros to bind, named after an optional prefix.
+fn gensym(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let rest = args[0].as_slice()?;
+    assert_args(Match::Max, rest, 1, || "(gensym &rest prefix)".to_string(),
+                || interp.symbols().serialize_call("gensym", rest))?;
+    let prefix = match rest.first() {
+        Some(prefix) => prefix.as_str()
+            .map_err(|e| e.trace(1))?,
+        None => "g",
+    };
+    Ok(LispObject::Symbol(interp.symbols_mut().gensym(prefix)))
+}
+
+pub const GENSYM: NativeDef = NativeDef {
+    name: "gensym",
+    positional: &[],
+    rest: Some("prefix"),
+    func: gensym,
+};
+
+// The symbols bound where it is called, sorted by name so that the listing is
+// the same across runs.
+fn bound_symbols(interp: &mut Interpreter, _: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::List(interp.env_view().bound_symbols().into_iter()
+                        .map(LispObject::Symbol)
+                        .collect()))
+}
+
+pub const BOUND_SYMBOLS: NativeDef = NativeDef {
+    name: "bound-symbols",
+    positional: &[],
+    rest: None,
+    func: bound_symbols,
+};
+
+// The forms read from s as multiple values, so a single form is returned
+// as is.
+fn read_string(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let source = args[0].as_str()
+        .map_err(|e| e.trace(1))?;
+    match interp.read_source(source) {
+        Ok((forms, true)) => values(interp, &[LispObject::List(forms)]),
+        Ok((_, false)) => Err(exc::incomplete_input().trace(1)),
+        Err(e) => Err(EvalError::new(e.to_string())
+                      .with_condition(condition::SYNTAX_ERROR)
+                      .trace(1)),
+    }
+}
+
+pub const READ_STRING: NativeDef = NativeDef {
+    name: "read-string",
+    positional: &["s"],
+    rest: None,
+    func: read_string,
+};
+
+// Evaluate form in the global environment.
+fn eval(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    interp.eval_global(&args[0])
+}
+
+pub const EVAL: NativeDef = NativeDef {
+    name: "eval",
+    positional: &["form"],
+    rest: None,
+    func: eval,
+};
+
+// Change an interpreter option named by a keyword, returning its previous
+// value.
+fn set_option(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let option = match &args[0] {
+        LispObject::Keyword(k) => interp.symbols().as_string(k).unwrap_or_default().to_string(),
+        other => return Err(exc::keyword_expected(interp.serialize(other)).trace(1)),
+    };
+    interp.set_option(&option, &args[1])
+        .map_err(|(e, index)| e.trace(index + 1))
+}
+
+pub const SET_OPTION: NativeDef = NativeDef {
+    name: "set-option!",
+    positional: &["option", "value"],
+    rest: None,
+    func: set_option,
+};
+
+// Report changes of the binding of sym by def and set, by calling handler
+// with sym, the old and the new value, or printing them without one.
+fn watch(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let sym = args[0].as_symbol()
+        .map_err(|e| e.trace(1))?;
+    let handler = args[1].as_slice()?;
+    assert_args(Match::Max, handler, 1, || "(watch sym &rest handler)".to_string(),
+                || interp.symbols().serialize_call("watch", handler))?;
+    interp.watch(sym, handler.first().cloned());
+    Ok(LispObject::Nil)
+}
+
+pub const WATCH: NativeDef = NativeDef {
+    name: "watch",
+    positional: &["sym"],
+    rest: Some("handler"),
+    func: watch,
+};
+
+// Stop watching sym, returns whether it was watched.
+fn unwatch(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let sym = args[0].as_symbol()
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::Bool(interp.unwatch(sym)))
+}
+
+pub const UNWATCH: NativeDef = NativeDef {
+    name: "unwatch",
+    positional: &["sym"],
+    rest: None,
+    func: unwatch,
+};
+
+// The values the global sym had while recording history, as (value form)
+// pairs with the form that assigned it, oldest first.
+fn history_of(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let sym = args[0].as_symbol()
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::List(
+        interp.history_of(sym).iter()
+            .map(|(value, form)| LispObject::List(vec![value.clone(), form.clone()]))
+            .collect()))
+}
+
+pub const HISTORY_OF: NativeDef = NativeDef {
+    name: "history-of",
+    positional: &["sym"],
+    rest: None,
+    func: history_of,
+};
+
+// Past inputs of the REPL as (n input) pairs.
+fn history(interp: &mut Interpreter, _: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::List(
+        interp.inputs().iter().enumerate()
+            .map(|(i, input)| LispObject::List(vec![
+                LispObject::Number((i + 1) as f64),
+                LispObject::String(input.as_str().into()),
+            ]))
+            .collect()))
+}
+
+pub const HISTORY: NativeDef = NativeDef {
+    name: "history",
+    positional: &[],
+    rest: None,
+    func: history,
+};
+
+fn call_ec(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    interp.call_with_escape(&args[0])
+}
+
+pub const CALL_EC: NativeDef = NativeDef {
+    name: "call/ec",
+    positional: &["f"],
+    rest: None,
+    func: call_ec,
+};
+
+// Called by the escape continuations of call/ec with their id and values.
+pub(crate) fn escape(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Err(EvalError {
+        kind: ErrorKind::Escape,
+        data: args.to_vec(),
+        ..EvalError::new("Escape continuation called after its call/ec returned".to_string())
+    })
+}
+
+fn macroexpand_1(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    interp.macroexpand_1(&args[0])
+        .map(|(expansion, _)| expansion)
+}
+
+pub const MACROEXPAND_1: NativeDef = NativeDef {
+    name: "macroexpand-1",
+    positional: &["form"],
+    rest: None,
+    func: macroexpand_1,
+};
+
+fn macroexpand(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let mut form = args[0].clone();
+    loop {
+        match interp.macroexpand_1(&form)? {
+            (expansion, true) => form = expansion,
+            (expansion, false) => return Ok(expansion),
+        }
+    }
+}
+
+pub const MACROEXPAND: NativeDef = NativeDef {
+    name: "macroexpand",
+    positional: &["form"],
+    rest: None,
+    func: macroexpand,
+};
@@ -0,0 +1,424 @@
+// Networking natives, enabled by the net feature. Only std::net is used, all
+// natives block the interpreter while they run.
+
+use std::collections::hash_map::RandomState;
+use std::convert::TryFrom;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{prelude::*, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::rc::Rc;
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{ErrorKind, EvalError, Handle, LispObject, Map, NativeDef},
+};
+
+// Largest request body http-serve reads, larger ones are answered with 413.
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+fn string(s: &str) -> LispObject {
+    LispObject::String(Rc::from(s))
+}
+
+fn io_error(e: std::io::Error) -> EvalError {
+    EvalError::new(format!("IO error: {}", e))
+        .with_condition(condition::IO_ERROR)
+}
+
+// Read a request from stream. Requests that cannot be read fail with the
+// status and message to answer them with.
+fn read_request(stream: &mut BufReader<TcpStream>) -> Result<LispObject, (u16, String)> {
+    let bad_request = |e: std::io::Error| (400, format!("IO error: {}", e));
+    let mut line = String::new();
+    stream.read_line(&mut line).map_err(bad_request)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("/");
+    let (path, query) = match target.find('?') {
+        Some(index) => (&target[..index], &target[index + 1..]),
+        None => (target, ""),
+    };
+
+    let mut headers: Map = vec![];
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if stream.read_line(&mut header).map_err(bad_request)? == 0 {
+            break
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break
+        }
+        if let Some(index) = header.find(':') {
+            let name = header[..index].trim().to_lowercase();
+            let value = header[index + 1..].trim();
+            if name == "content-length" {
+                content_length = value.parse().unwrap_or(0);
+            }
+            headers.push((string(&name), string(value)));
+        }
+    }
+
+    if content_length > MAX_BODY {
+        return Err((413, format!("Request body exceeds {} bytes", MAX_BODY)))
+    }
+    let mut body = vec![];
+    stream.by_ref().take(content_length as u64).read_to_end(&mut body).map_err(bad_request)?;
+    if body.len() < content_length {
+        return Err((400, "Request body is shorter than its Content-Length".to_string()))
+    }
+
+    Ok(LispObject::Map(Rc::new(vec![
+        (string("method"), string(method)),
+        (string("path"), string(path)),
+        (string("query"), string(query)),
+        (string("headers"), LispObject::Map(Rc::new(headers))),
+        (string("body"), string(&String::from_utf8_lossy(&body))),
+    ])))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        400 => "Bad Request",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
+
+// A handler may return a string used as body of a 200 response, or a map with
+// optional "status", "headers" and "body" entries.
+fn write_response(stream: &mut TcpStream, response: &LispObject) -> Result<(), EvalError> {
+    let (status, headers, body) = match response {
+        LispObject::String(s) => (200, vec![], s.to_string()),
+        LispObject::Map(_) => {
+            let status = match response.map_get(&string("status"))? {
+                Some(status) => status.as_number()? as u16,
+                None => 200,
+            };
+            let headers = match response.map_get(&string("headers"))? {
+                Some(headers) => headers.as_map()?.iter()
+                    .map(|(k, v)| Ok((k.as_str()?.to_string(), v.as_str()?.to_string())))
+                    .collect::<Result<Vec<(String, String)>, EvalError>>()?,
+                None => vec![],
+            };
+            let body = match response.map_get(&string("body"))? {
+                Some(body) => body.as_str()?.to_string(),
+                None => String::new(),
+            };
+            (status, headers, body)
+        },
+        _ => return Err(EvalError::new("Handler must return a string or a map".to_string())),
+    };
+
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, reason(status));
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+    stream.write_all(head.as_bytes())
+        .and_then(|()| stream.write_all(body.as_bytes()))
+        .map_err(io_error)
+}
+
+fn error_response(status: u16, message: &str) -> LispObject {
+    LispObject::Map(Rc::new(vec![
+        (string("status"), LispObject::Number(status as f64)),
+        (string("body"), string(message)),
+    ]))
+}
+
+fn http_serve(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let port = args[0].as_number()
+        .map_err(|e| e.trace(1))? as u16;
+    let limit = match args[2].as_slice()?.first() {
+        Some(limit) => Some(limit.as_number().map_err(|e| e.trace(3))? as usize),
+        None => None,
+    };
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(io_error)?;
+
+    let mut served = 0;
+    for stream in listener.incoming() {
+        let mut stream = stream.map_err(io_error)?;
+        let request = read_request(&mut BufReader::new(stream.try_clone().map_err(io_error)?));
+        // Only errors raised by the handler are answered, cancellations and
+        // escapes stop the server.
+        let response = match request {
+            Ok(request) => match interp.apply(&args[1], &[request]) {
+                Ok(response) => response,
+                Err(e) if e.kind == ErrorKind::Error => error_response(500, &e.message),
+                Err(e) => return Err(e),
+            },
+            Err((status, message)) => error_response(status, &message),
+        };
+        if let Err(e) = write_response(&mut stream, &response) {
+            write_response(&mut stream, &error_response(500, &e.message)).ok();
+        }
+        served += 1;
+        if Some(served) == limit {
+            break
+        }
+    }
+    Ok(LispObject::Number(served as f64))
+}
+
+pub const HTTP_SERVE: NativeDef = NativeDef {
+    name: "http-serve",
+    positional: &["port", "handler"],
+    rest: Some("limit"),
+    func: http_serve,
+};
+
+// WebSocket client (RFC 6455) over plain TCP, wss:// is not supported.
+
+const WS_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const WEBSOCKET: &str = "websocket";
+// Largest message ws-recv accepts, in one frame or fragmented.
+const MAX_PAYLOAD: usize = 16 * 1024 * 1024;
+
+fn random_bytes(n: usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    while bytes.len() < n {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_usize(bytes.len());
+        bytes.extend_from_slice(&hasher.finish().to_le_bytes());
+    }
+    bytes.truncate(n);
+    bytes
+}
+
+fn base64(data: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([block[4 * i], block[4 * i + 1],
+                                       block[4 * i + 2], block[4 * i + 3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e)
+                .wrapping_add(k).wrapping_add(*wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (hi, v) in h.iter_mut().zip([a, b, c, d, e].iter()) {
+            *hi = hi.wrapping_add(*v);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (i, hi) in h.iter().enumerate() {
+        digest[4 * i..4 * i + 4].copy_from_slice(&hi.to_be_bytes());
+    }
+    digest
+}
+
+fn parse_ws_url(url: &str) -> Result<(String, String), EvalError> {
+    let rest = url.strip_prefix("ws://")
+        .ok_or_else(|| EvalError::new("Only ws:// urls are supported".to_string()))?;
+    let (host, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+    let host = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+    Ok((host, path.to_string()))
+}
+
+fn ws_connect(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let url = args[0].as_str()
+        .map_err(|e| e.trace(1))?;
+    let (host, path) = parse_ws_url(url)
+        .map_err(|e| e.trace(1))?;
+    let mut stream = TcpStream::connect(&host).map_err(io_error)?;
+
+    let key = base64(&random_bytes(16));
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\n\
+                           Connection: Upgrade\r\nSec-WebSocket-Key: {}\r\n\
+                           Sec-WebSocket-Version: 13\r\n\r\n", path, host, key);
+    stream.write_all(request.as_bytes()).map_err(io_error)?;
+
+    // Read the response head byte by byte, so no frame data is consumed.
+    let mut head = vec![];
+    while !head.ends_with(b"\r\n\r\n") {
+        let mut byte = [0u8];
+        stream.read_exact(&mut byte).map_err(io_error)?;
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let expected = base64(&sha1(format!("{}{}", key, WS_GUID).as_bytes()));
+    let accepted = head.lines().next().is_some_and(|status| status.contains(" 101"))
+        && head.lines().any(|line| line.to_lowercase().starts_with("sec-websocket-accept:")
+                            && line[21..].trim() == expected);
+    if !accepted {
+        return Err(EvalError::new(format!("WebSocket handshake with {} failed", url)))
+    }
+    Ok(Handle::object(WEBSOCKET, stream))
+}
+
+pub const WS_CONNECT: NativeDef = NativeDef {
+    name: "ws-connect",
+    positional: &["url"],
+    rest: None,
+    func: ws_connect,
+};
+
+fn write_frame(stream: &mut TcpStream, opcode: u8, payload: &[u8]) -> Result<(), EvalError> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => frame.push(0x80 | len as u8),
+        len if len < 65536 => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        },
+        len => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        },
+    }
+    let mask = random_bytes(4);
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    stream.write_all(&frame).map_err(io_error)
+}
+
+fn payload_too_large() -> EvalError {
+    EvalError::new(format!("WebSocket message exceeds {} bytes", MAX_PAYLOAD))
+        .with_condition(condition::IO_ERROR)
+}
+
+// Read a frame, returning whether it is final, its opcode and its payload.
+fn read_frame(stream: &mut TcpStream) -> Result<(bool, u8, Vec<u8>), EvalError> {
+    let mut head = [0u8; 2];
+    stream.read_exact(&mut head).map_err(io_error)?;
+    let len = match head[1] & 0x7f {
+        126 => {
+            let mut len = [0u8; 2];
+            stream.read_exact(&mut len).map_err(io_error)?;
+            u16::from_be_bytes(len) as usize
+        },
+        127 => {
+            let mut len = [0u8; 8];
+            stream.read_exact(&mut len).map_err(io_error)?;
+            usize::try_from(u64::from_be_bytes(len)).unwrap_or(usize::MAX)
+        },
+        len => len as usize,
+    };
+    if len > MAX_PAYLOAD {
+        return Err(payload_too_large())
+    }
+    let mask = if head[1] & 0x80 != 0 {
+        let mut mask = [0u8; 4];
+        stream.read_exact(&mut mask).map_err(io_error)?;
+        Some(mask)
+    } else {
+        None
+    };
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).map_err(io_error)?;
+    if let Some(mask) = mask {
+        payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i % 4]);
+    }
+    Ok((head[0] & 0x80 != 0, head[0] & 0x0f, payload))
+}
+
+fn ws_send(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let handle = args[0].as_handle(WEBSOCKET)
+        .map_err(|e| e.trace(1))?;
+    let message = args[1].as_str()
+        .map_err(|e| e.trace(2))?;
+    let mut value = handle.value.borrow_mut();
+    let stream = value.downcast_mut::<TcpStream>()
+        .ok_or_else(|| EvalError::new("Invalid websocket handle".to_string()).trace(1))?;
+    write_frame(stream, 0x1, message.as_bytes())?;
+    Ok(LispObject::Bool(true))
+}
+
+pub const WS_SEND: NativeDef = NativeDef {
+    name: "ws-send",
+    positional: &["ws", "message"],
+    rest: None,
+    func: ws_send,
+};
+
+// Wait for the next text or binary message. Pings are answered, a close frame
+// is acknowledged and results in ().
+fn ws_recv(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let handle = args[0].as_handle(WEBSOCKET)
+        .map_err(|e| e.trace(1))?;
+    let mut value = handle.value.borrow_mut();
+    let stream = value.downcast_mut::<TcpStream>()
+        .ok_or_else(|| EvalError::new("Invalid websocket handle".to_string()).trace(1))?;
+    let mut message = vec![];
+    loop {
+        let (fin, opcode, payload) = read_frame(stream)?;
+        match opcode {
+            0x8 => {
+                write_frame(stream, 0x8, &payload)?;
+                return Ok(LispObject::List(vec![]))
+            },
+            0x9 => write_frame(stream, 0xA, &payload)?,
+            0xA => (),
+            _ => {
+                if message.len() + payload.len() > MAX_PAYLOAD {
+                    return Err(payload_too_large())
+                }
+                message.extend(payload);
+                if fin {
+                    return Ok(string(&String::from_utf8_lossy(&message)))
+                }
+            },
+        }
+    }
+}
+
+pub const WS_RECV: NativeDef = NativeDef {
+    name: "ws-recv",
+    positional: &["ws"],
+    rest: None,
+    func: ws_recv,
+};
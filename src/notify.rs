@@ -0,0 +1,69 @@
+// Alerting the user, e.g. when a long-running script finishes. beep rings
+// the terminal bell. With the desktop feature, notify shows a desktop
+// notification through osascript on macOS or notify-send elsewhere.
+
+use std::io::Write;
+#[cfg(feature = "desktop")]
+use std::process::{Command, Stdio};
+
+#[cfg(feature = "desktop")]
+use crate::condition;
+use crate::{
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef},
+};
+
+// The bell goes to stderr, so it sounds when stdout is redirected.
+fn beep(_: &mut Interpreter, _: &[LispObject]) -> Result<LispObject, EvalError> {
+    let mut stderr = std::io::stderr();
+    stderr.write_all(b"\x07").and_then(|()| stderr.flush()).ok();
+    Ok(LispObject::Nil)
+}
+
+pub const BEEP: NativeDef = NativeDef {
+    name: "beep",
+    positional: &[],
+    rest: None,
+    func: beep,
+};
+
+// A string literal of AppleScript.
+#[cfg(feature = "desktop")]
+fn apple_script_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(feature = "desktop")]
+fn notify(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let title = args[0].as_str()
+        .map_err(|e| e.trace(1))?;
+    let message = args[1].as_str()
+        .map_err(|e| e.trace(2))?;
+    let script = format!("display notification {} with title {}",
+                         apple_script_string(message), apple_script_string(title));
+    let tools: &[(&str, Vec<&str>)] = &[
+        ("osascript", vec!["-e", &script]),
+        ("notify-send", vec!["--", title, message]),
+    ];
+    for (program, tool_args) in tools {
+        if let Ok(status) = Command::new(program).args(tool_args)
+            .stdout(Stdio::null()).stderr(Stdio::null()).status() {
+            return if status.success() {
+                Ok(LispObject::Nil)
+            } else {
+                Err(EvalError::new(format!("{} failed with {}", program, status))
+                    .with_condition(condition::IO_ERROR))
+            }
+        }
+    }
+    Err(EvalError::new("No notification tool found, install libnotify".to_string())
+        .with_condition(condition::IO_ERROR))
+}
+
+#[cfg(feature = "desktop")]
+pub const NOTIFY: NativeDef = NativeDef {
+    name: "notify",
+    positional: &["title", "message"],
+    rest: None,
+    func: notify,
+};
@@ -0,0 +1,148 @@
+use crate::{
+    condition,
+    env::Symbols,
+    lisp_object::{EvalError, LispObject, SerializeSymbol, SpecialForm, Symbol},
+};
+
+// Patterns destructure values in match clauses, let bindings and parameters:
+//   _                 matches anything
+//   sym               matches anything and binds it to sym
+//   'datum, literals  match equal values
+//   (p ... &rest r)   match lists element by element, r binds the remaining
+//                     elements as a list
+pub enum Pattern {
+    Wildcard,
+    Bind(Symbol),
+    Literal(LispObject),
+    List(Vec<Pattern>, Option<Box<Pattern>>),
+}
+
+enum Expected {
+    Value(LispObject),
+    List(usize, bool),
+}
+
+// A value not matching a pattern. The path leads from the innermost
+// sub-pattern that failed to the pattern itself.
+pub struct Mismatch {
+    path: Vec<usize>,
+    expected: Expected,
+    actual: LispObject,
+}
+
+impl Mismatch {
+    fn at(mut self, index: usize) -> Mismatch {
+        self.path.push(index);
+        self
+    }
+
+    pub fn describe(&self, symbols: &Symbols) -> String {
+        let expected = match &self.expected {
+            Expected::Value(value) => symbols.serialize_object(value),
+            Expected::List(len, false) => format!("a list of {} elements", len),
+            Expected::List(len, true) => format!("a list of at least {} elements", len),
+        };
+        format!("Pattern expected {}, got {}", expected, symbols.serialize_object(&self.actual))
+    }
+
+    // Error traced to the failing sub-pattern.
+    pub fn error(self, symbols: &Symbols) -> EvalError {
+        let err = EvalError::new(self.describe(symbols))
+            .with_condition(condition::PATTERN_ERROR);
+        self.path.into_iter().fold(err, |err, index| err.trace(index))
+    }
+}
+
+// Quote may already be resolved when the pattern is part of a compiled
+// function, as in let bindings.
+fn is_quote(symbols: &Symbols, head: &LispObject) -> bool {
+    match head {
+        LispObject::Symbol(s) => *s == symbols.sym_quote,
+        LispObject::SpecialForm(SpecialForm::Quote) => true,
+        _ => false,
+    }
+}
+
+pub fn compile(symbols: &Symbols, object: &LispObject) -> Result<Pattern, EvalError> {
+    compile_into(symbols, object, &mut vec![])
+        .map_err(|e| e.with_condition(condition::SYNTAX_ERROR))
+}
+
+// Compile object, collecting bound symbols in vars to reject duplicates.
+pub fn compile_into(symbols: &Symbols, object: &LispObject, vars: &mut Vec<Symbol>)
+                -> Result<Pattern, EvalError> {
+    match object {
+        LispObject::Symbol(s) if symbols.as_string(s) == Some("_") => Ok(Pattern::Wildcard),
+        LispObject::Symbol(s) if *s == symbols.sym_rest =>
+            Err(EvalError::new("&rest may only appear before the last pattern of a list".to_string())),
+        LispObject::Symbol(s) if vars.contains(s) =>
+            Err(EvalError::new(format!("Duplicate pattern variable {}", symbols.serialize_object(object)))),
+        LispObject::Symbol(s) => {
+            vars.push(*s);
+            Ok(Pattern::Bind(*s))
+        },
+        LispObject::List(l) if l.len() == 2 && is_quote(symbols, &l[0]) =>
+            Ok(Pattern::Literal(l[1].clone())),
+        LispObject::List(l) => {
+            let (elements, rest) = match l.iter().position(|o| o.as_symbol().ok() == Some(symbols.sym_rest)) {
+                Some(index) if index == l.len() - 2 => (&l[..index], Some(&l[index + 1])),
+                Some(index) => return Err(EvalError::new(
+                    "&rest must be followed by exactly one pattern".to_string()).trace(index)),
+                None => (&l[..], None),
+            };
+            let elements = elements.iter().enumerate()
+                .map(|(index, o)| compile_into(symbols, o, vars)
+                     .map_err(|e| e.trace(index)))
+                .collect::<Result<Vec<Pattern>, EvalError>>()?;
+            let rest = match rest {
+                Some(o) => Some(Box::new(compile_into(symbols, o, vars)
+                                         .map_err(|e| e.trace(l.len() - 1))?)),
+                None => None,
+            };
+            Ok(Pattern::List(elements, rest))
+        },
+        LispObject::Nil | LispObject::Number(_) | LispObject::String(_) | LispObject::Bool(_) =>
+            Ok(Pattern::Literal(object.clone())),
+        _ => Err(EvalError::new("Expected a symbol, literal or list as pattern".to_string())),
+    }
+}
+
+impl Pattern {
+    // Match value, adding the bindings of pattern variables to binding.
+    pub fn bind(&self, value: &LispObject, binding: &mut Vec<(Symbol, LispObject)>)
+                -> Result<(), Mismatch> {
+        match self {
+            Pattern::Wildcard => Ok(()),
+            Pattern::Bind(s) => {
+                binding.push((*s, value.clone()));
+                Ok(())
+            },
+            Pattern::Literal(literal) if literal.equals(value) => Ok(()),
+            Pattern::Literal(literal) => Err(Mismatch {
+                path: vec![],
+                expected: Expected::Value(literal.clone()),
+                actual: value.clone(),
+            }),
+            Pattern::List(elements, rest) => {
+                let mismatch = || Mismatch {
+                    path: vec![],
+                    expected: Expected::List(elements.len(), rest.is_some()),
+                    actual: value.clone(),
+                };
+                let l = value.as_slice().map_err(|_| mismatch())?;
+                if l.len() < elements.len() || (rest.is_none() && l.len() > elements.len()) {
+                    return Err(mismatch())
+                }
+                for (index, (pattern, value)) in elements.iter().zip(l).enumerate() {
+                    pattern.bind(value, binding)
+                        .map_err(|m| m.at(index))?;
+                }
+                match rest {
+                    Some(pattern) => pattern.bind(&LispObject::List(l[elements.len()..].to_vec()), binding)
+                        .map_err(|m| m.at(elements.len() + 1)),
+                    None => Ok(()),
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::fs;
+
+use crate::{
+    env::Symbols,
+    lisp_object::{LispObject, SerializeSymbol, Symbol},
+};
+
+// Strings containing these cannot be read back, the reader has no escapes
+// and reads files line by line, stripping comments.
+fn readable(s: &str) -> bool {
+    !s.contains(['"', '\\', ';', '\n'])
+}
+
+// Values written as they are serialized, quoted.
+fn is_data(value: &LispObject) -> bool {
+    match value {
+        LispObject::Nil | LispObject::Number(_) | LispObject::Bool(_) | LispObject::Symbol(_) |
+        LispObject::Keyword(_) => true,
+        LispObject::String(s) => readable(s),
+        LispObject::List(l) => l.iter().all(is_data),
+        _ => false,
+    }
+}
+
+// Source of an expression evaluating to value, None for values that cannot
+// be written, like natives and handles. Functions are written as their
+// definition, closures lose the scope they captured.
+fn expression(symbols: &Symbols, value: &LispObject) -> Option<String> {
+    match value {
+        LispObject::Nil | LispObject::Number(_) | LispObject::Bool(_) | LispObject::String(_) if is_data(value) =>
+            Some(symbols.serialize_object(value)),
+        LispObject::Symbol(_) | LispObject::List(_) if is_data(value) =>
+            Some(format!("'{}", symbols.serialize_object(value))),
+        LispObject::Lambda(l) if l.source.iter().all(is_data) =>
+            Some(format!("'{}", symbols.serialize_object(value))),
+        LispObject::List(l) => l.iter()
+            .map(|object| expression(symbols, object))
+            .collect::<Option<Vec<String>>>()
+            .map(|elements| format!("(list {})", elements.join(" "))),
+        LispObject::Map(m) => m.iter()
+            .map(|(k, v)| Some(format!("{} {}", expression(symbols, k)?, expression(symbols, v)?)))
+            .collect::<Option<Vec<String>>>()
+            .map(|entries| format!("(make-map {})", entries.join(" "))),
+        _ => None,
+    }
+}
+
+// Write bindings (symbol, value, constant) to path as definitions read_file
+// restores, returns the names of bindings that could not be written.
+pub fn save(symbols: &Symbols, path: &str, bindings: Vec<(Symbol, LispObject, bool)>)
+            -> Result<Vec<String>, String> {
+    let mut bindings = bindings.into_iter()
+        .map(|(sym, value, constant)| (symbols.as_string(&sym).unwrap_or("~~uninterned~~"), value, constant))
+        .collect::<Vec<_>>();
+    bindings.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut source = String::from(";; Global definitions saved by --persist\n");
+    let mut skipped = vec![];
+    for (name, value, constant) in bindings {
+        match expression(symbols, &value) {
+            Some(expr) => source.push_str(&format!("({} {} {})\n",
+                                                   if constant { "defconst" } else { "def" },
+                                                   name, expr)),
+            None => skipped.push(name.to_string()),
+        }
+    }
+    fs::write(path, source).map_err(|e| e.to_string())?;
+    Ok(skipped)
+}
@@ -0,0 +1,147 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{
+    Arc, Mutex,
+    mpsc::{channel, sync_channel, Receiver, Sender, SyncSender},
+};
+use std::thread::{self, JoinHandle};
+
+use crate::interpreter::{Interpreter, STACK_SIZE};
+
+type Reply = Sender<Result<String, String>>;
+type Job = (String, Reply);
+
+// A fixed number of interpreters on worker threads evaluating requests
+// concurrently. Every worker evaluates the same setup source once, each
+// request then runs on a fresh copy of the resulting globals, so definitions
+// made by a request are not seen by later ones. The copy is shallow: the
+// scopes closures of the setup captured and the values held by its handles
+// are shared by all requests a worker evaluates, so state kept there carries
+// over between them. Values cross threads in serialized form, as LispObjects
+// are bound to the thread that created them.
+pub struct RispPool {
+    sender: Option<SyncSender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+fn worker(root: Interpreter, jobs: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let job = jobs.lock().ok().and_then(|jobs| jobs.recv().ok());
+        let (source, reply) = match job {
+            Some(job) => job,
+            None => return,
+        };
+        let mut interpreter = root.clone();
+        // A panicking native fails its request, the worker lives on with a
+        // fresh copy for the next one
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            interpreter.eval_source(&source)
+                .map(|object| interpreter.serialize(&object))
+                .map_err(|e| e.to_string())
+        })).unwrap_or_else(|panic| Err(format!("Evaluation panicked: {}", panic_message(&*panic))));
+        reply.send(result).ok();
+    }
+}
+
+// The message a panic was raised with.
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => panic.downcast_ref::<String>().map_or("unknown cause", |message| message),
+    }
+}
+
+impl RispPool {
+    // Start size workers, at most size requests wait for a free worker
+    // before eval and call block.
+    pub fn new(size: usize, setup: &str) -> Result<RispPool, String> {
+        RispPool::with_init(size, |_| (), setup)
+    }
+
+    // Like new, but init is called on each worker's interpreter before the
+    // setup is evaluated, e.g. to define natives with define_globals.
+    pub fn with_init(size: usize, init: fn(&mut Interpreter), setup: &str)
+                     -> Result<RispPool, String> {
+        let (sender, receiver) = sync_channel::<Job>(size);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let (ready, started) = channel::<Result<(), String>>();
+
+        let workers = (0..size)
+            .map(|_| {
+                let jobs = receiver.clone();
+                let ready = ready.clone();
+                let setup = setup.to_string();
+                thread::Builder::new().stack_size(STACK_SIZE).spawn(move || {
+                    let root = panic::catch_unwind(AssertUnwindSafe(|| {
+                        let mut root = Interpreter::new();
+                        init(&mut root);
+                        root.eval_source(&setup)
+                            .map(|_| root)
+                            .map_err(|e| e.to_string())
+                    })).unwrap_or_else(|panic| Err(format!("panicked: {}", panic_message(&*panic))));
+                    match root {
+                        Ok(root) => {
+                            ready.send(Ok(())).ok();
+                            worker(root, jobs);
+                        },
+                        Err(e) => {
+                            ready.send(Err(e)).ok();
+                        },
+                    }
+                })
+            })
+            .collect::<Result<Vec<JoinHandle<()>>, std::io::Error>>()
+            .map_err(|e| e.to_string())?;
+        // Only the workers hold senders now, so recv fails rather than
+        // blocking if one of them exits without reporting
+        drop(ready);
+
+        let mut pool = RispPool {
+            sender: Some(sender),
+            workers,
+        };
+        for _ in 0..size {
+            match started.recv() {
+                Ok(Ok(())) => (),
+                Ok(Err(e)) => {
+                    pool.shutdown();
+                    return Err(format!("Setup failed: {}", e))
+                },
+                Err(e) => {
+                    pool.shutdown();
+                    return Err(e.to_string())
+                },
+            }
+        }
+        Ok(pool)
+    }
+
+    // Evaluate source on the next free worker and return the serialized
+    // value of its last form.
+    pub fn eval(&self, source: &str) -> Result<String, String> {
+        let (reply, result) = channel();
+        self.sender.as_ref()
+            .ok_or_else(|| "Pool is shut down".to_string())?
+            .send((source.to_string(), reply))
+            .map_err(|e| e.to_string())?;
+        result.recv().map_err(|e| e.to_string())?
+    }
+
+    // Call the function bound to name with arguments given as source.
+    pub fn call(&self, name: &str, args: &[&str]) -> Result<String, String> {
+        self.eval(&format!("({} {})", name, args.join(" ")))
+    }
+
+    fn shutdown(&mut self) {
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            worker.join().ok();
+        }
+    }
+}
+
+impl Drop for RispPool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,68 @@
+// Promises for lazy evaluation. (delay expr), a prelude macro, wraps expr in a
+// thunk passed to make-promise. force calls the thunk the first time and
+// returns its memoized value after that. If the thunk fails, the promise
+// stays delayed and the next force calls it again.
+
+use crate::{
+    interpreter::Interpreter,
+    lisp_object::{EvalError, Handle, LispObject, NativeDef},
+};
+
+const PROMISE: &str = "promise";
+
+enum Promise {
+    Delayed(LispObject),
+    Forced(LispObject),
+}
+
+fn make_promise(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(Handle::object(PROMISE, Promise::Delayed(args[0].clone())))
+}
+
+pub const MAKE_PROMISE: NativeDef = NativeDef {
+    name: "make-promise",
+    positional: &["thunk"],
+    rest: None,
+    func: make_promise,
+};
+
+// Objects other than promises are returned as they are. The thunk may force
+// its own promise, in which case the value forced first is kept.
+fn force(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let handle = match args[0].as_handle(PROMISE) {
+        Ok(handle) => handle,
+        Err(_) => return Ok(args[0].clone()),
+    };
+    let thunk = match handle.value.borrow().downcast_ref::<Promise>() {
+        Some(Promise::Delayed(thunk)) => thunk.clone(),
+        Some(Promise::Forced(value)) => return Ok(value.clone()),
+        None => return Err(EvalError::new("Invalid promise handle".to_string()).trace(1)),
+    };
+    let value = interp.apply(&thunk, &[])?;
+    let mut promise = handle.value.borrow_mut();
+    match promise.downcast_ref::<Promise>() {
+        Some(Promise::Forced(first)) => Ok(first.clone()),
+        _ => {
+            *promise = Box::new(Promise::Forced(value.clone()));
+            Ok(value)
+        },
+    }
+}
+
+pub const FORCE: NativeDef = NativeDef {
+    name: "force",
+    positional: &["promise"],
+    rest: None,
+    func: force,
+};
+
+fn is_promise(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(args[0].as_handle(PROMISE).is_ok()))
+}
+
+pub const PROMISE_P: NativeDef = NativeDef {
+    name: "promise?",
+    positional: &["obj"],
+    rest: None,
+    func: is_promise,
+};
@@ -0,0 +1,348 @@
+// Property based testing. Generators draw their random choices from a
+// Source, which records them. A failing case is shrunk by simplifying the
+// recorded choices, deleting or reducing them, and replaying the generator,
+// so values built by any combination of generators shrink the same way.
+
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    interpreter::Interpreter,
+    lisp_object::{EvalError, Handle, LispObject, NativeDef},
+};
+
+const GENERATOR: &str = "generator";
+const DEFAULT_MAX_LENGTH: usize = 10;
+const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 ";
+const MAX_SHRINKS: usize = 1000;
+
+enum Gen {
+    Int(i64, i64),
+    Bool,
+    List(LispObject, usize),
+    String(usize),
+    Elements(Vec<LispObject>),
+    OneOf(Vec<LispObject>),
+    Tuple(Vec<LispObject>),
+    Map(LispObject, LispObject),
+}
+
+// xorshift64*, good enough for test data.
+struct Rng(u64);
+
+impl Rng {
+    fn from_time() -> Rng {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Rng(nanos | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+// Choices are drawn from rng and recorded when generating, and replayed when
+// shrinking. Choices beyond the recorded ones are 0, the simplest choice.
+struct Source<'a> {
+    choices: Vec<u64>,
+    index: usize,
+    rng: Option<&'a mut Rng>,
+}
+
+impl Source<'_> {
+    fn draw(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            return 0
+        }
+        if self.index == self.choices.len() {
+            let choice = self.rng.as_mut().map_or(0, |rng| rng.next() % bound);
+            self.choices.push(choice);
+        }
+        self.index += 1;
+        self.choices[self.index - 1] % bound
+    }
+}
+
+fn generator(gen: Gen) -> LispObject {
+    Handle::object(GENERATOR, Rc::new(gen))
+}
+
+fn as_generator(object: &LispObject) -> Result<Rc<Gen>, EvalError> {
+    object.as_handle(GENERATOR)?
+        .value.borrow()
+        .downcast_ref::<Rc<Gen>>()
+        .cloned()
+        .ok_or_else(|| EvalError::new("Invalid generator handle".to_string()))
+}
+
+fn generate(interp: &mut Interpreter, gen: &LispObject, source: &mut Source)
+            -> Result<LispObject, EvalError> {
+    Ok(match &*as_generator(gen)? {
+        Gen::Int(lo, hi) => {
+            // Ranges containing 0 draw a sign and magnitude, so values
+            // shrink towards 0, other ranges towards their bound nearest 0.
+            let value = if *lo >= 0 {
+                lo + source.draw((hi - lo + 1) as u64) as i64
+            } else if *hi <= 0 {
+                hi - source.draw((hi - lo + 1) as u64) as i64
+            } else if source.draw(2) == 1 {
+                -(source.draw(-lo as u64 + 1) as i64)
+            } else {
+                source.draw(*hi as u64 + 1) as i64
+            };
+            LispObject::Number(value as f64)
+        },
+        Gen::Bool => LispObject::Bool(source.draw(2) == 1),
+        Gen::List(element, max) => {
+            let len = source.draw(*max as u64 + 1);
+            LispObject::List((0..len)
+                             .map(|_| generate(interp, element, source))
+                             .collect::<Result<Vec<LispObject>, EvalError>>()?)
+        },
+        Gen::String(max) => {
+            let len = source.draw(*max as u64 + 1);
+            let s = (0..len)
+                .map(|_| ALPHABET[source.draw(ALPHABET.len() as u64) as usize] as char)
+                .collect::<String>();
+            LispObject::String(s.into())
+        },
+        Gen::Elements(values) => values[source.draw(values.len() as u64) as usize].clone(),
+        Gen::OneOf(gens) => {
+            let gen = &gens[source.draw(gens.len() as u64) as usize];
+            generate(interp, gen, source)?
+        },
+        Gen::Tuple(gens) => LispObject::List(
+            gens.iter()
+                .map(|gen| generate(interp, gen, source))
+                .collect::<Result<Vec<LispObject>, EvalError>>()?),
+        Gen::Map(f, gen) => {
+            let value = generate(interp, gen, source)?;
+            interp.apply(f, &[value])?
+        },
+    })
+}
+
+fn as_max_length(rest: &LispObject, index: usize) -> Result<usize, EvalError> {
+    match rest.as_slice()?.first() {
+        Some(max) => Ok(max.as_number().map_err(|e| e.trace(index))? as usize),
+        None => Ok(DEFAULT_MAX_LENGTH),
+    }
+}
+
+fn as_generators(objects: &[LispObject]) -> Result<Vec<LispObject>, EvalError> {
+    for (index, object) in objects.iter().enumerate() {
+        as_generator(object).map_err(|e| e.trace(index + 1))?;
+    }
+    Ok(objects.to_vec())
+}
+
+fn gen_int(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let lo = args[0].as_number().map_err(|e| e.trace(1))? as i64;
+    let hi = args[1].as_number().map_err(|e| e.trace(2))? as i64;
+    if lo > hi {
+        return Err(EvalError::new(format!("gen-int requires lo <= hi, got {} and {}", lo, hi)))
+    }
+    Ok(generator(Gen::Int(lo, hi)))
+}
+
+pub const GEN_INT: NativeDef = NativeDef {
+    name: "gen-int",
+    positional: &["lo", "hi"],
+    rest: None,
+    func: gen_int,
+};
+
+fn gen_bool(_: &mut Interpreter, _: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(generator(Gen::Bool))
+}
+
+pub const GEN_BOOL: NativeDef = NativeDef {
+    name: "gen-bool",
+    positional: &[],
+    rest: None,
+    func: gen_bool,
+};
+
+fn gen_list(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    as_generator(&args[0]).map_err(|e| e.trace(1))?;
+    Ok(generator(Gen::List(args[0].clone(), as_max_length(&args[1], 2)?)))
+}
+
+pub const GEN_LIST: NativeDef = NativeDef {
+    name: "gen-list",
+    positional: &["gen"],
+    rest: Some("max-length"),
+    func: gen_list,
+};
+
+fn gen_string(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(generator(Gen::String(as_max_length(&args[0], 1)?)))
+}
+
+pub const GEN_STRING: NativeDef = NativeDef {
+    name: "gen-string",
+    positional: &[],
+    rest: Some("max-length"),
+    func: gen_string,
+};
+
+fn gen_elements(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let values = args[0].as_slice()?;
+    if values.is_empty() {
+        return Err(EvalError::new("gen-elements requires at least one value".to_string()))
+    }
+    Ok(generator(Gen::Elements(values.to_vec())))
+}
+
+pub const GEN_ELEMENTS: NativeDef = NativeDef {
+    name: "gen-elements",
+    positional: &[],
+    rest: Some("values"),
+    func: gen_elements,
+};
+
+fn gen_one_of(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let gens = as_generators(args[0].as_slice()?)?;
+    if gens.is_empty() {
+        return Err(EvalError::new("gen-one-of requires at least one generator".to_string()))
+    }
+    Ok(generator(Gen::OneOf(gens)))
+}
+
+pub const GEN_ONE_OF: NativeDef = NativeDef {
+    name: "gen-one-of",
+    positional: &[],
+    rest: Some("gens"),
+    func: gen_one_of,
+};
+
+fn gen_tuple(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(generator(Gen::Tuple(as_generators(args[0].as_slice()?)?)))
+}
+
+pub const GEN_TUPLE: NativeDef = NativeDef {
+    name: "gen-tuple",
+    positional: &[],
+    rest: Some("gens"),
+    func: gen_tuple,
+};
+
+fn gen_map(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    as_generator(&args[1]).map_err(|e| e.trace(2))?;
+    Ok(generator(Gen::Map(args[0].clone(), args[1].clone())))
+}
+
+pub const GEN_MAP: NativeDef = NativeDef {
+    name: "gen-map",
+    positional: &["f", "gen"],
+    rest: None,
+    func: gen_map,
+};
+
+fn gen_sample(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let mut rng = Rng::from_time();
+    let mut source = Source { choices: vec![], index: 0, rng: Some(&mut rng) };
+    generate(interp, &args[0], &mut source)
+        .map_err(|e| e.trace(1))
+}
+
+pub const GEN_SAMPLE: NativeDef = NativeDef {
+    name: "gen-sample",
+    positional: &["gen"],
+    rest: None,
+    func: gen_sample,
+};
+
+// Run prop on the value generated from choices, returns the value, the
+// choices consumed and why the property failed, if it did.
+fn run_case(interp: &mut Interpreter, gen: &LispObject, prop: &LispObject,
+            choices: Vec<u64>, rng: Option<&mut Rng>)
+            -> Result<(LispObject, Vec<u64>, Option<String>), EvalError> {
+    let mut source = Source { choices, index: 0, rng };
+    let value = generate(interp, gen, &mut source)?;
+    source.choices.truncate(source.index);
+    let failure = match interp.apply(prop, std::slice::from_ref(&value))
+        .and_then(|result| interp.is_true(&result)) {
+            Ok(true) => None,
+            Ok(false) => Some("returned #f".to_string()),
+            Err(e) => Some(e.message),
+        };
+    Ok((value, source.choices, failure))
+}
+
+// Simpler variants of choices: with chunks removed, then with single choices
+// reduced towards 0.
+fn simplifications(choices: &[u64]) -> Vec<Vec<u64>> {
+    let mut result = vec![];
+    for size in [8, 4, 2, 1] {
+        for start in 0..choices.len().saturating_sub(size - 1) {
+            result.push([&choices[..start], &choices[start + size..]].concat());
+        }
+    }
+    for (index, &choice) in choices.iter().enumerate() {
+        for smaller in [0, choice / 2, choice.saturating_sub(1)] {
+            if smaller < choice {
+                let mut candidate = choices.to_vec();
+                candidate[index] = smaller;
+                result.push(candidate);
+            }
+        }
+    }
+    result
+}
+
+// Shorter choices are simpler, choices of the same length are compared
+// element by element.
+fn simpler(a: &[u64], b: &[u64]) -> bool {
+    (a.len(), a) < (b.len(), b)
+}
+
+fn check_prop(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let runs = args[0].as_number().map_err(|e| e.trace(1))? as usize;
+    as_generator(&args[1]).map_err(|e| e.trace(2))?;
+    let (gen, prop) = (&args[1], &args[2]);
+
+    let mut rng = Rng::from_time();
+    for run in 1..=runs {
+        let (value, choices, failure) = run_case(interp, gen, prop, vec![], Some(&mut rng))?;
+        let mut failure = match failure {
+            Some(failure) => (value, choices, failure),
+            None => continue,
+        };
+
+        let mut shrinks = 0;
+        let mut attempts = 0;
+        'shrinking: while attempts < MAX_SHRINKS {
+            for candidate in simplifications(&failure.1) {
+                attempts += 1;
+                if let (value, choices, Some(reason)) = run_case(interp, gen, prop, candidate, None)? {
+                    if simpler(&choices, &failure.1) {
+                        failure = (value, choices, reason);
+                        shrinks += 1;
+                        continue 'shrinking
+                    }
+                }
+                if attempts == MAX_SHRINKS {
+                    break
+                }
+            }
+            break
+        }
+        return Err(EvalError::new(format!(
+            "property failed after {} tests and {} shrinks with {}: {}",
+            run, shrinks, interp.serialize(&failure.0), failure.2)))
+    }
+    Ok(LispObject::Bool(true))
+}
+
+pub const CHECK_PROP: NativeDef = NativeDef {
+    name: "check-prop",
+    positional: &["n", "gen", "prop"],
+    rest: None,
+    func: check_prop,
+};
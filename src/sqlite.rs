@@ -0,0 +1,225 @@
+// SQLite access through the sqlite3 command line shell, which has to be on
+// the PATH. Rows are read in json mode, an array of objects from column name
+// to value for each statement returning rows, so the values keep their
+// types: NULL is nil, INTEGER and REAL are numbers and TEXT stays a string.
+
+use std::iter::Peekable;
+use std::process::Command;
+use std::rc::Rc;
+use std::str::Chars;
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef},
+};
+
+// Run sql on the database at path, returning the rows of all its statements.
+fn run(path: &str, sql: &str) -> Result<Vec<LispObject>, EvalError> {
+    let output = Command::new("sqlite3")
+        .args(["-batch", "-bail", "-json", "--", path, sql])
+        .output()
+        .map_err(|e| EvalError::new(format!("Could not run sqlite3: {}", e))
+                 .with_condition(condition::IO_ERROR))?;
+    if output.status.success() {
+        rows(&String::from_utf8_lossy(&output.stdout))
+    } else {
+        Err(EvalError::new(String::from_utf8_lossy(&output.stderr).trim().to_string())
+            .with_condition(condition::IO_ERROR))
+    }
+}
+
+fn sql_literal(object: &LispObject) -> Result<String, EvalError> {
+    match object {
+        LispObject::Number(n) => Ok(n.to_string()),
+        LispObject::String(s) => Ok(format!("'{}'", s.replace('\'', "''"))),
+        LispObject::Bool(b) => Ok(if *b { "1" } else { "0" }.to_string()),
+        LispObject::Nil => Ok("NULL".to_string()),
+        LispObject::List(l) if l.is_empty() => Ok("NULL".to_string()),
+        _ => Err(EvalError::new("Expected a number, string, bool, nil or () as parameter"
+                                .to_string())),
+    }
+}
+
+// Replace the ? placeholders in sql by params. Placeholders in string
+// literals are left alone.
+fn bind(sql: &str, params: &[LispObject]) -> Result<String, EvalError> {
+    let mut params = params.iter().enumerate();
+    let mut result = String::new();
+    let mut in_literal = false;
+    for c in sql.chars() {
+        match c {
+            '\'' => {
+                in_literal = !in_literal;
+                result.push(c);
+            },
+            '?' if !in_literal => match params.next() {
+                Some((index, param)) => result.push_str(
+                    &sql_literal(param).map_err(|e| e.trace(index))?),
+                None => return Err(EvalError::new("Not enough parameters for query".to_string())),
+            },
+            c => result.push(c),
+        }
+    }
+    match params.next() {
+        Some(_) => Err(EvalError::new("Too many parameters for query".to_string())),
+        None => Ok(result),
+    }
+}
+
+fn malformed_output() -> EvalError {
+    EvalError::new("Could not read the output of sqlite3".to_string())
+        .with_condition(condition::IO_ERROR)
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), EvalError> {
+    skip_whitespace(chars);
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        _ => Err(malformed_output()),
+    }
+}
+
+fn json_string(chars: &mut Peekable<Chars>) -> Result<String, EvalError> {
+    expect(chars, '"')?;
+    let mut result = String::new();
+    loop {
+        match chars.next().ok_or_else(malformed_output)? {
+            '"' => return Ok(result),
+            '\\' => result.push(match chars.next().ok_or_else(malformed_output)? {
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => {
+                    let code = chars.by_ref().take(4).collect::<String>();
+                    u32::from_str_radix(&code, 16).ok()
+                        .and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER)
+                },
+                c => c,
+            }),
+            c => result.push(c),
+        }
+    }
+}
+
+// A value of a row, sqlite3 only writes strings, numbers and null.
+fn json_value(chars: &mut Peekable<Chars>) -> Result<LispObject, EvalError> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('"') => Ok(LispObject::String(Rc::from(json_string(chars)?))),
+        Some('n') => {
+            let null = chars.by_ref().take(4).collect::<String>();
+            if null == "null" { Ok(LispObject::Nil) } else { Err(malformed_output()) }
+        },
+        _ => {
+            let mut number = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+                number.push(c);
+            }
+            number.parse().map(LispObject::Number).map_err(|_| malformed_output())
+        },
+    }
+}
+
+// Parse a sequence of json arrays of objects into a list of maps.
+fn rows(output: &str) -> Result<Vec<LispObject>, EvalError> {
+    let mut chars = output.chars().peekable();
+    let mut rows = vec![];
+    loop {
+        skip_whitespace(&mut chars);
+        if chars.peek().is_none() {
+            return Ok(rows)
+        }
+        expect(&mut chars, '[')?;
+        loop {
+            let mut row = vec![];
+            expect(&mut chars, '{')?;
+            loop {
+                let column = json_string(&mut chars)?;
+                expect(&mut chars, ':')?;
+                row.push((LispObject::String(Rc::from(column)), json_value(&mut chars)?));
+                skip_whitespace(&mut chars);
+                if chars.next_if_eq(&',').is_none() {
+                    break
+                }
+            }
+            expect(&mut chars, '}')?;
+            rows.push(LispObject::Map(Rc::new(row)));
+            skip_whitespace(&mut chars);
+            if chars.next_if_eq(&',').is_none() {
+                break
+            }
+        }
+        expect(&mut chars, ']')?;
+    }
+}
+
+fn connection(object: &LispObject) -> Result<String, EvalError> {
+    let path = object.map_get(&LispObject::String(Rc::from("database")))?
+        .ok_or_else(|| EvalError::new("Expected a connection from db-open".to_string()))?;
+    Ok(path.as_str()?.to_string())
+}
+
+fn db_open(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let path = args[0].as_str()
+        .map_err(|e| e.trace(1))?;
+    run(path, "select 1;")?;
+    Ok(LispObject::Map(Rc::new(vec![
+        (LispObject::String(Rc::from("database")), args[0].clone()),
+    ])))
+}
+
+pub const DB_OPEN: NativeDef = NativeDef {
+    name: "db-open",
+    positional: &["path"],
+    rest: None,
+    func: db_open,
+};
+
+fn db_query(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let path = connection(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let sql = args[1].as_str()
+        .map_err(|e| e.trace(2))?;
+    let params = args[2].as_slice()
+        .map_err(|e| e.trace(3))?;
+    let sql = bind(sql, params)
+        .map_err(|e| e.trace(3))?;
+    Ok(LispObject::List(run(&path, &sql)?))
+}
+
+pub const DB_QUERY: NativeDef = NativeDef {
+    name: "db-query",
+    positional: &["conn", "sql", "params"],
+    rest: None,
+    func: db_query,
+};
+
+fn db_exec(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let path = connection(&args[0])
+        .map_err(|e| e.trace(1))?;
+    let sql = args[1].as_str()
+        .map_err(|e| e.trace(2))?;
+    let sql = bind(sql, args[2].as_slice()?)
+        .map_err(|e| e.trace(3))?;
+    let rows = run(&path, &format!("{};\nselect changes() as changes;", sql.trim_end_matches(';')))?;
+    let changes = match rows.last() {
+        Some(row) => row.map_get(&LispObject::String(Rc::from("changes")))?.cloned(),
+        None => None,
+    };
+    Ok(changes.unwrap_or(LispObject::Number(0.0)))
+}
+
+pub const DB_EXEC: NativeDef = NativeDef {
+    name: "db-exec",
+    positional: &["conn", "sql", "params"],
+    rest: None,
+    func: db_exec,
+};
@@ -0,0 +1,49 @@
+// Bounds of the stack of the current thread, so evaluations can fail with a
+// stack-overflow error before the stack runs out, whatever its size. Only
+// known on Linux and macOS, elsewhere nesting is bounded by max-depth alone.
+
+// Stack kept free below the limit for the frames between two checks and the
+// guard page.
+const RESERVE: usize = 256 * 1024;
+
+// The address below which the stack of the current thread is about to run
+// out. Stacks grow downwards on the supported platforms.
+pub fn limit() -> Option<usize> {
+    lowest().map(|lowest| lowest + RESERVE)
+}
+
+// An address in the current frame of the stack.
+#[inline(always)]
+pub fn position() -> usize {
+    let marker = 0u8;
+    &marker as *const u8 as usize
+}
+
+#[cfg(target_os = "linux")]
+fn lowest() -> Option<usize> {
+    unsafe {
+        let mut attr = std::mem::MaybeUninit::<libc::pthread_attr_t>::uninit();
+        if libc::pthread_getattr_np(libc::pthread_self(), attr.as_mut_ptr()) != 0 {
+            return None
+        }
+        let mut addr = std::ptr::null_mut();
+        let mut size = 0;
+        let result = libc::pthread_attr_getstack(attr.as_ptr(), &mut addr, &mut size);
+        libc::pthread_attr_destroy(attr.as_mut_ptr());
+        (result == 0).then_some(addr as usize)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn lowest() -> Option<usize> {
+    unsafe {
+        let thread = libc::pthread_self();
+        let top = libc::pthread_get_stackaddr_np(thread) as usize;
+        Some(top - libc::pthread_get_stacksize_np(thread))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn lowest() -> Option<usize> {
+    None
+}
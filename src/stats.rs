@@ -0,0 +1,173 @@
+// Descriptive statistics over lists of numbers for data analysis scripts.
+// variance and stddev are those of a sample unless :population is true.
+// Percentiles interpolate linearly between the closest ranks.
+
+use crate::{
+    condition,
+    exc,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef},
+    lisp_object_util::keyword_args,
+};
+
+// The numbers in xs, which must have at least min of them.
+fn numbers(xs: &LispObject, min: usize) -> Result<Vec<f64>, EvalError> {
+    let numbers = xs.as_slice()?.iter()
+        .map(LispObject::as_number)
+        .collect::<Result<Vec<f64>, EvalError>>()?;
+    if numbers.len() < min {
+        let plural = if min == 1 { "" } else { "s" };
+        return Err(EvalError::new(format!("Expected at least {} number{}, got {}", min, plural, numbers.len()))
+                   .with_condition(condition::PROGRAM_ERROR))
+    }
+    Ok(numbers)
+}
+
+fn sorted(mut xs: Vec<f64>) -> Vec<f64> {
+    xs.sort_by(f64::total_cmp);
+    xs
+}
+
+fn mean_of(xs: &[f64]) -> f64 {
+    xs.iter().sum::<f64>() / xs.len() as f64
+}
+
+// The value below which p percent of the sorted numbers xs fall.
+fn percentile_of(xs: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (xs.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    xs[lower] + (xs[upper] - xs[lower]) * (rank - lower as f64)
+}
+
+// The variance of xs, with the options following them as arguments.
+fn variance_of(interp: &Interpreter, args: &[LispObject]) -> Result<f64, EvalError> {
+    let mut population = false;
+    let options = keyword_args(interp.symbols(), args[1].as_slice()?)
+        .map_err(|(e, index)| e.trace(index + 2))?;
+    for ((name, value), index) in options.into_iter().zip((2..).step_by(2)) {
+        match name {
+            Some("population") => population = value.as_bool()
+                .map_err(|e| e.trace(index + 1))?,
+            _ => return Err(exc::unknown_keyword(name).trace(index)),
+        }
+    }
+    let xs = numbers(&args[0], if population { 1 } else { 2 })
+        .map_err(|e| e.trace(1))?;
+    let mean = mean_of(&xs);
+    let squares = xs.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>();
+    Ok(squares / (xs.len() - if population { 0 } else { 1 }) as f64)
+}
+
+fn mean(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let xs = numbers(&args[0], 1)
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::Number(mean_of(&xs)))
+}
+
+pub const MEAN: NativeDef = NativeDef {
+    name: "mean",
+    positional: &["xs"],
+    rest: None,
+    func: mean,
+};
+
+fn median(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let xs = numbers(&args[0], 1)
+        .map_err(|e| e.trace(1))?;
+    Ok(LispObject::Number(percentile_of(&sorted(xs), 50.0)))
+}
+
+pub const MEDIAN: NativeDef = NativeDef {
+    name: "median",
+    positional: &["xs"],
+    rest: None,
+    func: median,
+};
+
+fn variance(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Number(variance_of(interp, args)?))
+}
+
+pub const VARIANCE: NativeDef = NativeDef {
+    name: "variance",
+    positional: &["xs"],
+    rest: Some("options"),
+    func: variance,
+};
+
+fn stddev(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Number(variance_of(interp, args)?.sqrt()))
+}
+
+pub const STDDEV: NativeDef = NativeDef {
+    name: "stddev",
+    positional: &["xs"],
+    rest: Some("options"),
+    func: stddev,
+};
+
+fn percentile(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let xs = numbers(&args[0], 1)
+        .map_err(|e| e.trace(1))?;
+    let p = match args[1].as_number()
+        .map_err(|e| e.trace(2))? {
+        p if (0.0..=100.0).contains(&p) => p,
+        p => return Err(EvalError::new(format!("Expected a percentile from 0 to 100, got {}", p))
+                        .with_condition(condition::TYPE_ERROR)
+                        .trace(2)),
+    };
+    Ok(LispObject::Number(percentile_of(&sorted(xs), p)))
+}
+
+pub const PERCENTILE: NativeDef = NativeDef {
+    name: "percentile",
+    positional: &["xs", "p"],
+    rest: None,
+    func: percentile,
+};
+
+// Bins histogram counts in at most.
+const MAX_BINS: usize = 1 << 20;
+
+// The counts of xs in bins of equal width from their minimum to their
+// maximum, as a list of (lower upper count). The last bin includes its upper
+// bound.
+fn histogram(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let xs = numbers(&args[0], 1)
+        .map_err(|e| e.trace(1))?;
+    let bins = match args[1].as_number()
+        .map_err(|e| e.trace(2))? {
+        n if n >= 1.0 && n.fract() == 0.0 => n as usize,
+        n => return Err(EvalError::new(format!("Expected a positive integer number of bins, got {}", n))
+                        .with_condition(condition::TYPE_ERROR)
+                        .trace(2)),
+    };
+    if bins > MAX_BINS {
+        return Err(EvalError::new(format!("Cannot count in {} bins, histogram takes at most {}",
+                                          bins, MAX_BINS))
+                   .with_condition(condition::PROGRAM_ERROR)
+                   .trace(2))
+    }
+    let min = xs.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = xs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let width = (max - min) / bins as f64;
+    let mut counts = vec![0; bins];
+    for x in &xs {
+        let bin = if width > 0.0 { ((x - min) / width) as usize } else { 0 };
+        counts[bin.min(bins - 1)] += 1;
+    }
+    Ok(LispObject::List(counts.into_iter().enumerate()
+                        .map(|(bin, count)| LispObject::List(vec![
+                            LispObject::Number(min + width * bin as f64),
+                            LispObject::Number(if bin == bins - 1 { max } else { min + width * (bin + 1) as f64 }),
+                            LispObject::Number(count as f64),
+                        ]))
+                        .collect()))
+}
+
+pub const HISTOGRAM: NativeDef = NativeDef {
+    name: "histogram",
+    positional: &["xs", "bins"],
+    rest: None,
+    func: histogram,
+};
@@ -0,0 +1,184 @@
+// Lazy, possibly infinite streams. (stream-cons head tail), a prelude macro,
+// delays tail, which evaluates to the rest of the stream, a stream or the
+// empty list ending it. stream-map and stream-filter are lazy as well, they
+// only compute the cells that are taken. Forced cells are remembered, which
+// lets the printer show the part of a stream computed so far.
+
+use std::rc::Rc;
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef, Stream, StreamTail},
+};
+
+fn is_end(object: &LispObject) -> bool {
+    matches!(object, LispObject::List(l) if l.is_empty())
+}
+
+fn expect_stream(interp: &Interpreter, object: &LispObject) -> Result<(), EvalError> {
+    match object {
+        LispObject::Stream(_) => Ok(()),
+        _ if is_end(object) => Ok(()),
+        _ => Err(EvalError::new(format!("Expected a stream or (), got {}", interp.serialize(object)))
+                 .with_condition(condition::TYPE_ERROR)),
+    }
+}
+
+// The rest of the stream after cell, computed the first time it is needed.
+// If computing it forces cell again, the rest computed first is kept.
+fn rest(interp: &mut Interpreter, cell: &Rc<Stream>) -> Result<LispObject, EvalError> {
+    let tail = cell.tail.borrow().clone();
+    let rest = match tail {
+        StreamTail::Forced(rest) => return Ok(rest),
+        StreamTail::Delayed(thunk) => {
+            let rest = interp.apply(&thunk, &[])?;
+            expect_stream(interp, &rest)?;
+            rest
+        },
+        StreamTail::Map(f, source) => {
+            let source = rest(interp, &source)?;
+            map(interp, &f, &source)?
+        },
+        StreamTail::Filter(pred, source) => {
+            let source = rest(interp, &source)?;
+            filter(interp, &pred, source)?
+        },
+    };
+    let mut tail = cell.tail.borrow_mut();
+    match &*tail {
+        StreamTail::Forced(first) => Ok(first.clone()),
+        _ => {
+            *tail = StreamTail::Forced(rest.clone());
+            Ok(rest)
+        },
+    }
+}
+
+fn map(interp: &mut Interpreter, f: &LispObject, stream: &LispObject) -> Result<LispObject, EvalError> {
+    match stream {
+        LispObject::Stream(cell) => {
+            let head = interp.apply(f, std::slice::from_ref(&cell.head))?;
+            Ok(Stream::object(head, StreamTail::Map(f.clone(), cell.clone())))
+        },
+        _ => Ok(LispObject::List(vec![])),
+    }
+}
+
+// The stream from the first element of stream satisfying pred on. Elements
+// are tested until one is found, so this doesn't return for infinite streams
+// without any.
+fn filter(interp: &mut Interpreter, pred: &LispObject, mut stream: LispObject)
+          -> Result<LispObject, EvalError> {
+    while let LispObject::Stream(cell) = stream {
+        let test = interp.apply(pred, std::slice::from_ref(&cell.head))?;
+        if interp.is_true(&test)? {
+            return Ok(Stream::object(cell.head.clone(), StreamTail::Filter(pred.clone(), cell)))
+        }
+        stream = rest(interp, &cell)?;
+    }
+    Ok(LispObject::List(vec![]))
+}
+
+fn make_stream(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(Stream::object(args[0].clone(), StreamTail::Delayed(args[1].clone())))
+}
+
+pub const MAKE_STREAM: NativeDef = NativeDef {
+    name: "make-stream",
+    positional: &["head", "thunk"],
+    rest: None,
+    func: make_stream,
+};
+
+fn stream_first(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let cell = args[0].as_stream()
+        .map_err(|e| e.trace(1))?;
+    Ok(cell.head.clone())
+}
+
+pub const STREAM_FIRST: NativeDef = NativeDef {
+    name: "stream-first",
+    positional: &["stream"],
+    rest: None,
+    func: stream_first,
+};
+
+fn stream_rest(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let cell = args[0].as_stream()
+        .map_err(|e| e.trace(1))?;
+    rest(interp, cell)
+}
+
+pub const STREAM_REST: NativeDef = NativeDef {
+    name: "stream-rest",
+    positional: &["stream"],
+    rest: None,
+    func: stream_rest,
+};
+
+fn is_stream(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    Ok(LispObject::Bool(matches!(args[0], LispObject::Stream(_))))
+}
+
+pub const STREAM_P: NativeDef = NativeDef {
+    name: "stream?",
+    positional: &["obj"],
+    rest: None,
+    func: is_stream,
+};
+
+fn stream_map(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    expect_stream(interp, &args[1])
+        .map_err(|e| e.trace(2))?;
+    map(interp, &args[0], &args[1])
+}
+
+pub const STREAM_MAP: NativeDef = NativeDef {
+    name: "stream-map",
+    positional: &["f", "stream"],
+    rest: None,
+    func: stream_map,
+};
+
+fn stream_filter(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    expect_stream(interp, &args[1])
+        .map_err(|e| e.trace(2))?;
+    filter(interp, &args[0], args[1].clone())
+}
+
+pub const STREAM_FILTER: NativeDef = NativeDef {
+    name: "stream-filter",
+    positional: &["pred", "stream"],
+    rest: None,
+    func: stream_filter,
+};
+
+// A list of the first n elements of stream, or all of them if it is shorter.
+fn stream_take(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let n = args[0].as_number()
+        .map_err(|e| e.trace(1))?;
+    expect_stream(interp, &args[1])
+        .map_err(|e| e.trace(2))?;
+    let mut items = vec![];
+    let mut stream = args[1].clone();
+    while (items.len() as f64) < n {
+        let cell = match &stream {
+            LispObject::Stream(cell) => cell.clone(),
+            _ => break,
+        };
+        items.push(cell.head.clone());
+        // Don't compute the rest unless more elements are taken
+        if (items.len() as f64) < n {
+            stream = rest(interp, &cell)?;
+        }
+    }
+    Ok(LispObject::List(items))
+}
+
+pub const STREAM_TAKE: NativeDef = NativeDef {
+    name: "stream-take",
+    positional: &["n", "stream"],
+    rest: None,
+    func: stream_take,
+};
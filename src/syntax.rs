@@ -0,0 +1,175 @@
+// Hygienic macros defined by defsyntax as rules of a pattern and a template.
+// The arguments of a call are matched against the pattern of each rule in
+// turn, and the template of the first matching rule is instantiated with the
+// bindings of the pattern variables. Symbols the template binds itself, e.g.
+// with let or fn, are renamed to fresh symbols for every expansion, so they
+// never capture the variables of the caller. Other symbols of the template
+// refer to globals by name.
+//
+// A trailing `var ...` in a pattern binds the remaining arguments to var,
+// like &rest, and `var ...` in a template splices them.
+
+use std::collections::HashMap;
+
+use crate::{
+    condition,
+    env::Symbols,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef, SerializeSymbol, Symbol},
+    lisp_object_util::catch_handler,
+    pattern,
+};
+
+const ELLIPSIS: &str = "...";
+
+fn is_named(symbols: &Symbols, object: &LispObject, name: &str) -> bool {
+    match object {
+        LispObject::Symbol(s) => symbols.as_string(s) == Some(name),
+        _ => false,
+    }
+}
+
+// The pattern of a rule without the macro name, with a trailing ellipsis
+// turned into &rest.
+fn rule_pattern(symbols: &Symbols, pattern: &[LispObject]) -> LispObject {
+    let mut elements = pattern.iter().skip(1).cloned().collect::<Vec<LispObject>>();
+    let len = elements.len();
+    if len >= 2 && is_named(symbols, &elements[len - 1], ELLIPSIS) {
+        elements[len - 1] = elements[len - 2].clone();
+        elements[len - 2] = LispObject::Symbol(symbols.sym_rest);
+    }
+    LispObject::List(elements)
+}
+
+// Add the symbols bound by the binding pattern to binders.
+fn pattern_symbols(symbols: &Symbols, pattern: &LispObject, binders: &mut Vec<Symbol>) {
+    match pattern {
+        LispObject::Symbol(s) if *s == symbols.sym_rest || *s == symbols.sym_key => (),
+        LispObject::Symbol(s) if symbols.as_string(s) == Some("_") => (),
+        LispObject::Symbol(s) => binders.push(*s),
+        LispObject::List(l) if l.first().is_some_and(|head| is_named(symbols, head, "quote")) => (),
+        LispObject::List(l) => l.iter().for_each(|p| pattern_symbols(symbols, p, binders)),
+        _ => (),
+    }
+}
+
+// Add the symbols template binds with let, fn, match and the like to binders.
+fn template_binders(symbols: &Symbols, template: &LispObject, binders: &mut Vec<Symbol>) {
+    let l = match template {
+        LispObject::List(l) => l,
+        _ => return,
+    };
+    let head = match l.first() {
+        Some(LispObject::Symbol(s)) => symbols.as_string(s).unwrap_or_default(),
+        _ => "",
+    };
+    match (head, l.get(1)) {
+        ("quote", _) => return,
+        ("let" | "let*" | "letrec" | "let-values" | "loop", Some(LispObject::List(bindings))) =>
+            for binding in bindings {
+                if let Ok([binder, ..]) = binding.as_slice() {
+                    pattern_symbols(symbols, binder, binders);
+                }
+            },
+        ("fn" | "macro", Some(params)) | ("local", Some(params)) =>
+            pattern_symbols(symbols, params, binders),
+        ("match", _) =>
+            for clause in l.iter().skip(2) {
+                if let Ok([pattern, ..]) = clause.as_slice() {
+                    pattern_symbols(symbols, pattern, binders);
+                }
+            },
+        ("try", _) =>
+            for clause in l.iter().skip(1) {
+                if let (Some(handler), Ok(c)) = (catch_handler(symbols, clause), clause.as_slice()) {
+                    pattern_symbols(symbols, &c[handler - 1], binders);
+                }
+            },
+        _ => (),
+    }
+    l.iter().for_each(|object| template_binders(symbols, object, binders));
+}
+
+// Instantiate template with the values of the pattern variables in binding
+// and the renamed binders of the template. Quoted symbols are not renamed.
+fn instantiate(symbols: &Symbols, template: &LispObject, binding: &[(Symbol, LispObject)],
+               renamed: &HashMap<Symbol, Symbol>, quoted: bool)
+               -> Result<LispObject, EvalError> {
+    let l = match template {
+        LispObject::Symbol(s) => return Ok(match binding.iter().rfind(|(var, _)| var == s) {
+            Some((_, value)) => value.clone(),
+            None if quoted => template.clone(),
+            None => LispObject::Symbol(*renamed.get(s).unwrap_or(s)),
+        }),
+        LispObject::List(l) => l,
+        _ => return Ok(template.clone()),
+    };
+    let quoted = quoted || l.first().is_some_and(|head| is_named(symbols, head, "quote"));
+    let mut result = vec![];
+    for (index, object) in l.iter().enumerate() {
+        if is_named(symbols, object, ELLIPSIS) {
+            continue
+        }
+        let value = instantiate(symbols, object, binding, renamed, quoted)
+            .map_err(|e| e.trace(index))?;
+        if l.get(index + 1).is_some_and(|next| is_named(symbols, next, ELLIPSIS)) {
+            let spliced = value.as_slice()
+                .map_err(|_| EvalError::new(format!("Only variables bound to lists can be followed by {}, got {}",
+                                                    ELLIPSIS, symbols.serialize_object(object)))
+                         .with_condition(condition::SYNTAX_ERROR)
+                         .trace(index))?;
+            result.extend_from_slice(spliced);
+        } else {
+            result.push(value);
+        }
+    }
+    Ok(LispObject::List(result))
+}
+
+// Expand a call of the syntax name with args by the first matching of rules.
+pub fn expand(symbols: &mut Symbols, name: &LispObject, rules: &[LispObject], args: &[LispObject])
+              -> Result<LispObject, EvalError> {
+    let call = LispObject::List(args.to_vec());
+    for rule in rules {
+        let (pattern, template) = match rule.as_slice() {
+            Ok([LispObject::List(pattern), template]) => (pattern, template),
+            _ => return Err(EvalError::new(format!("Expected a rule (pattern template), got {}",
+                                                   symbols.serialize_object(rule)))
+                            .with_condition(condition::SYNTAX_ERROR)),
+        };
+        let mut binding = vec![];
+        if pattern::compile(symbols, &rule_pattern(symbols, pattern))?
+            .bind(&call, &mut binding).is_err() {
+            continue
+        }
+        let mut binders = vec![];
+        template_binders(symbols, template, &mut binders);
+        let renamed = binders.into_iter()
+            .filter(|s| !binding.iter().any(|(var, _)| var == s))
+            .map(|s| {
+                let name = symbols.as_string(&s).unwrap_or_default().to_string();
+                (s, symbols.gensym(&name))
+            })
+            .collect::<HashMap<Symbol, Symbol>>();
+        return instantiate(symbols, template, &binding, &renamed, false)
+    }
+    Err(EvalError::new(format!("No rule of {} matches {}", symbols.serialize_object(name),
+                               symbols.serialize_call(&symbols.serialize_object(name), args)))
+        .with_condition(condition::SYNTAX_ERROR))
+}
+
+fn syntax_expand(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let rules = args[1].as_slice()
+        .map_err(|e| e.trace(2))?;
+    let form = args[2].as_slice()
+        .map_err(|e| e.trace(3))?;
+    expand(interp.symbols_mut(), &args[0], rules, form)
+}
+
+// Used by the expansions of defsyntax.
+pub const SYNTAX_EXPAND: NativeDef = NativeDef {
+    name: "syntax-expand",
+    positional: &["name", "rules", "form"],
+    rest: None,
+    func: syntax_expand,
+};
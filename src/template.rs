@@ -0,0 +1,186 @@
+// A small template language for generating text from data:
+//   {{name}}                   the value of name, strings without quotes
+//   {{user.name}}              a path through nested maps
+//   {{#each items}}..{{/each}} the body for every element of a list
+//   {{#if ready}}..{{else}}..{{/if}}, and #unless, by the value of ready
+// Names are looked up as keyword, symbol or string keys of the maps in
+// scope, innermost first: the elements of each loop and the data. {{.}} is
+// the current element, {{@index}} its index, and @first and @last are true
+// for the first and last element. Missing values, nil, #f and () are false,
+// everything else is true.
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, NativeDef, SerializeSymbol},
+};
+
+enum Node {
+    Text(String),
+    Value(String),
+    Each(String, Vec<Node>),
+    If(String, Vec<Node>, Vec<Node>),
+}
+
+fn syntax_error(message: String) -> EvalError {
+    EvalError::new(message).with_condition(condition::SYNTAX_ERROR)
+}
+
+// Parse template up to the closing tag of the section named by end, if any.
+// Returns the nodes of the section and of its else branch.
+fn parse(template: &mut &str, end: Option<&str>) -> Result<(Vec<Node>, Vec<Node>), EvalError> {
+    let mut nodes = vec![];
+    let mut alt = None;
+    loop {
+        let (text, tag) = match template.find("{{") {
+            Some(start) => {
+                let close = template[start..].find("}}")
+                    .ok_or_else(|| syntax_error("Unclosed {{ in template".to_string()))?;
+                let tag = template[start + 2..start + close].trim();
+                let text = &template[..start];
+                *template = &template[start + close + 2..];
+                (text, Some(tag))
+            },
+            None => (std::mem::take(template), None),
+        };
+        if !text.is_empty() {
+            nodes.push(Node::Text(text.to_string()));
+        }
+        let tag = match tag {
+            Some(tag) => tag,
+            None => match end {
+                Some(end) => return Err(syntax_error(format!("Missing {{{{/{}}}}} in template", end))),
+                None => return Ok((nodes, vec![])),
+            },
+        };
+        if let Some(closing) = tag.strip_prefix('/') {
+            return match end {
+                Some(end) if end == closing => Ok(match alt {
+                    Some(then) => (then, nodes),
+                    None => (nodes, vec![]),
+                }),
+                _ => Err(syntax_error(format!("Unexpected {{{{/{}}}}} in template", closing))),
+            }
+        }
+        if tag == "else" && matches!(end, Some("if") | Some("unless")) && alt.is_none() {
+            alt = Some(std::mem::take(&mut nodes));
+            continue
+        }
+        let section = tag.strip_prefix('#')
+            .map(|section| section.split_once(char::is_whitespace).unwrap_or((section, "")));
+        match section {
+            Some(("each", path)) => {
+                let (body, _) = parse(template, Some("each"))?;
+                nodes.push(Node::Each(path.trim().to_string(), body));
+            },
+            Some((kind @ ("if" | "unless"), path)) => {
+                let (then, alt) = parse(template, Some(kind))?;
+                nodes.push(match kind {
+                    "if" => Node::If(path.trim().to_string(), then, alt),
+                    _ => Node::If(path.trim().to_string(), alt, then),
+                });
+            },
+            Some((other, _)) => return Err(syntax_error(format!("Unknown section #{} in template", other))),
+            None => nodes.push(Node::Value(tag.to_string())),
+        }
+    }
+}
+
+// The elements in scope, innermost last, with the index of loop elements and
+// the length of their list.
+struct Scope {
+    frames: Vec<(LispObject, Option<(usize, usize)>)>,
+}
+
+impl Scope {
+    fn is_key(interp: &Interpreter, key: &LispObject, name: &str) -> bool {
+        match key {
+            LispObject::Keyword(k) | LispObject::Symbol(k) => interp.symbols().as_string(k) == Some(name),
+            LispObject::String(s) => &**s == name,
+            _ => false,
+        }
+    }
+
+    fn field(interp: &Interpreter, object: &LispObject, name: &str) -> Option<LispObject> {
+        match object {
+            LispObject::Map(map) => map.iter()
+                .find(|(key, _)| Scope::is_key(interp, key, name))
+                .map(|(_, value)| value.clone()),
+            _ => None,
+        }
+    }
+
+    fn lookup(&self, interp: &Interpreter, path: &str) -> Option<LispObject> {
+        let (current, position) = self.frames.last()?;
+        match (path, position) {
+            (".", _) => return Some(current.clone()),
+            ("@index", Some((index, _))) => return Some(LispObject::Number(*index as f64)),
+            ("@first", Some((index, _))) => return Some(LispObject::Bool(*index == 0)),
+            ("@last", Some((index, length))) => return Some(LispObject::Bool(index + 1 == *length)),
+            _ => (),
+        }
+        let mut names = path.split('.');
+        let first = names.next()?;
+        let value = self.frames.iter().rev()
+            .find_map(|(object, _)| Scope::field(interp, object, first))?;
+        names.try_fold(value, |value, name| Scope::field(interp, &value, name))
+    }
+}
+
+fn is_true(value: &Option<LispObject>) -> bool {
+    match value {
+        None | Some(LispObject::Nil) | Some(LispObject::Bool(false)) => false,
+        Some(LispObject::List(l)) => !l.is_empty(),
+        _ => true,
+    }
+}
+
+fn render(interp: &Interpreter, nodes: &[Node], scope: &mut Scope, out: &mut String) -> Result<(), EvalError> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value(path) => match scope.lookup(interp, path) {
+                Some(LispObject::String(s)) => out.push_str(&s),
+                Some(value) => out.push_str(&interp.serialize(&value)),
+                None => return Err(EvalError::new(format!("{} is not in the template data", path))
+                                   .with_condition(condition::PROGRAM_ERROR)),
+            },
+            Node::Each(path, body) => {
+                let items = scope.lookup(interp, path).unwrap_or_else(|| LispObject::List(vec![]));
+                let items = items.as_slice()
+                    .map_err(|e| EvalError::new(format!("Cannot loop over {} in template: {}", path, e.message))
+                             .with_condition(condition::TYPE_ERROR))?;
+                for (index, item) in items.iter().enumerate() {
+                    scope.frames.push((item.clone(), Some((index, items.len()))));
+                    let rendered = render(interp, body, scope, out);
+                    scope.frames.pop();
+                    rendered?;
+                }
+            },
+            Node::If(path, then, alt) => {
+                let branch = if is_true(&scope.lookup(interp, path)) { then } else { alt };
+                render(interp, branch, scope, out)?;
+            },
+        }
+    }
+    Ok(())
+}
+
+fn render_template(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let mut template = args[0].as_str()
+        .map_err(|e| e.trace(1))?;
+    let (nodes, _) = parse(&mut template, None)
+        .map_err(|e| e.trace(1))?;
+    let mut scope = Scope { frames: vec![(args[1].clone(), None)] };
+    let mut out = String::new();
+    render(interp, &nodes, &mut scope, &mut out)
+        .map_err(|e| e.trace(2))?;
+    Ok(LispObject::String(out.into()))
+}
+
+pub const RENDER_TEMPLATE: NativeDef = NativeDef {
+    name: "render-template",
+    positional: &["template", "data"],
+    rest: None,
+    func: render_template,
+};
@@ -0,0 +1,330 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::rc::Rc;
+use std::sync::{
+    Arc, Weak,
+    atomic::{AtomicBool, Ordering},
+    mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, Handle, LispObject, NativeDef},
+};
+
+const TIMER: &str = "timer";
+pub const WATCHER: &str = "watcher";
+
+// Longest time sleep waits before checking for cancellation
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+// Fields of an event posted by a background thread, passed to the handler
+// as a map with string keys.
+pub type Event = Vec<(&'static str, String)>;
+
+// Sends the id of a due timer or subscription together with its event.
+pub type Poster = Sender<(u64, Option<Event>)>;
+
+struct Timer {
+    thunk: LispObject,
+    repeat: bool,
+    cancelled: Arc<AtomicBool>,
+}
+
+// A timer as known to the background thread: when it is due next, its id,
+// the interval it repeats at and whether it was cancelled.
+type Schedule = (Instant, u64, Option<Duration>, Arc<AtomicBool>);
+
+// Timers are driven by one background thread, started with the first timer,
+// posting the id of a timer when it is due. The thunks run on the
+// interpreter's thread when it polls between top-level evaluations or while
+// sleeping. Other background threads, like file watchers, subscribe to post
+// events the same way.
+pub struct Timers {
+    next_id: u64,
+    pending: HashMap<u64, Timer>,
+    sender: Poster,
+    receiver: Receiver<(u64, Option<Event>)>,
+    // Passes new timers to the background thread, which ends when this is
+    // dropped
+    scheduler: Option<Sender<Schedule>>,
+    // Only referenced weakly by subscriptions, which end when it is dropped
+    alive: Arc<()>,
+}
+
+// The side of a subscription held by a background thread.
+pub struct Subscription {
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+    poster: Poster,
+    alive: Weak<()>,
+}
+
+impl Subscription {
+    // Whether events are still wanted, it was neither cancelled nor was its
+    // interpreter dropped.
+    pub fn active(&self) -> bool {
+        !self.cancelled.load(Ordering::Relaxed) && self.alive.strong_count() > 0
+    }
+
+    // Post event to the handler, returns whether the interpreter still
+    // receives events.
+    pub fn post(&self, event: Event) -> bool {
+        self.poster.send((self.id, Some(event))).is_ok()
+    }
+}
+
+impl Default for Timers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A cloned interpreter starts without timers, the background threads post to
+// the original one.
+impl Clone for Timers {
+    fn clone(&self) -> Self {
+        Timers::new()
+    }
+}
+
+impl Timers {
+    pub fn new() -> Timers {
+        let (sender, receiver) = channel();
+        Timers {
+            next_id: 0,
+            pending: HashMap::new(),
+            sender,
+            receiver,
+            scheduler: None,
+            alive: Arc::new(()),
+        }
+    }
+
+    fn register(&mut self, thunk: LispObject, repeat: bool) -> (u64, Arc<AtomicBool>) {
+        self.next_id += 1;
+        let id = self.next_id;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.pending.insert(id, Timer { thunk, repeat, cancelled: cancelled.clone() });
+        (id, cancelled)
+    }
+
+    // Thunks of the pending timers and subscriptions.
+    pub fn thunks(&self) -> impl Iterator<Item = &LispObject> {
+        self.pending.values().map(|timer| &timer.thunk)
+    }
+
+    fn schedule(&mut self, ms: u64, thunk: LispObject, repeat: bool) -> LispObject {
+        let (id, cancelled) = self.register(thunk, repeat);
+        let delay = Duration::from_millis(ms);
+        let schedule = (Instant::now() + delay, id, repeat.then_some(delay), cancelled.clone());
+        let scheduler = match &self.scheduler {
+            Some(scheduler) => scheduler.clone(),
+            None => {
+                let (scheduler, schedules) = channel();
+                let poster = self.sender.clone();
+                thread::spawn(move || run_scheduler(schedules, poster));
+                self.scheduler.insert(scheduler).clone()
+            },
+        };
+        scheduler.send(schedule).ok();
+        Handle::object(TIMER, cancelled)
+    }
+
+    // Register handler to be called with every event posted through the
+    // returned subscription, until the flag is set by cancel.
+    pub fn subscribe(&mut self, handler: LispObject) -> (Subscription, Arc<AtomicBool>) {
+        let (id, cancelled) = self.register(handler, true);
+        let subscription = Subscription {
+            id,
+            cancelled: cancelled.clone(),
+            poster: self.sender.clone(),
+            alive: Arc::downgrade(&self.alive),
+        };
+        (subscription, cancelled)
+    }
+
+    // Take the thunk of a due timer, dropping timers which are done.
+    fn fire(&mut self, id: u64) -> Option<LispObject> {
+        let timer = self.pending.get(&id)?;
+        if timer.cancelled.load(Ordering::Relaxed) {
+            self.pending.remove(&id);
+            None
+        } else if timer.repeat {
+            Some(timer.thunk.clone())
+        } else {
+            self.pending.remove(&id).map(|timer| timer.thunk)
+        }
+    }
+
+    fn due(&self) -> Vec<(u64, Option<Event>)> {
+        self.receiver.try_iter().collect()
+    }
+
+    fn wait(&self, timeout: Duration) -> Option<(u64, Option<Event>)> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(id) => Some(id),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+}
+
+// The background thread, posting timers as they become due until the
+// interpreter drops its Timers.
+fn run_scheduler(schedules: Receiver<Schedule>, poster: Poster) {
+    let mut queue = BinaryHeap::<Reverse<(Instant, u64)>>::new();
+    let mut timers = HashMap::new();
+    loop {
+        let received = match queue.peek() {
+            Some(Reverse((due, _))) =>
+                schedules.recv_timeout(due.saturating_duration_since(Instant::now())),
+            None => schedules.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok((due, id, interval, cancelled)) => {
+                queue.push(Reverse((due, id)));
+                timers.insert(id, (interval, cancelled));
+            },
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        while let Some(Reverse((due, id))) = queue.peek().copied()
+            .filter(|Reverse((due, _))| *due <= Instant::now()) {
+            queue.pop();
+            let (interval, cancelled) = match timers.remove(&id) {
+                Some(timer) => timer,
+                None => continue,
+            };
+            if cancelled.load(Ordering::Relaxed) {
+                continue
+            }
+            if poster.send((id, None)).is_err() {
+                return
+            }
+            // A repeating timer that fell behind is not posted again for
+            // each missed interval
+            if let Some(interval) = interval {
+                queue.push(Reverse(((due + interval).max(Instant::now()), id)));
+                timers.insert(id, (Some(interval), cancelled));
+            }
+        }
+    }
+}
+
+impl Interpreter {
+    // Run the thunks of all timers that are due.
+    pub fn run_timers(&mut self) -> Result<(), EvalError> {
+        for (id, event) in self.timers().due() {
+            self.run_timer(id, event)?;
+        }
+        Ok(())
+    }
+
+    // Block for duration, running timers as they become due.
+    pub fn sleep(&mut self, duration: Duration) -> Result<(), EvalError> {
+        let deadline = Instant::now() + duration;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(())
+            }
+            // Wake up regularly to notice cancellation
+            if let Some((id, event)) = self.timers().wait((deadline - now).min(CANCEL_POLL)) {
+                self.run_timer(id, event)?;
+            }
+            self.check_cancelled()?;
+        }
+    }
+
+    fn run_timer(&mut self, id: u64, event: Option<Event>) -> Result<(), EvalError> {
+        let thunk = match self.timers().fire(id) {
+            Some(thunk) => thunk,
+            None => return Ok(()),
+        };
+        match event {
+            Some(fields) => {
+                let map = fields.into_iter()
+                    .map(|(k, v)| (LispObject::String(k.into()), LispObject::String(v.into())))
+                    .collect();
+                self.apply(&thunk, &[LispObject::Map(Rc::new(map))]).map(|_| ())
+            },
+            None => self.apply(&thunk, &[]).map(|_| ()),
+        }
+    }
+}
+
+fn schedule(interp: &mut Interpreter, args: &[LispObject], repeat: bool)
+            -> Result<LispObject, EvalError> {
+    let ms = args[0].as_number()
+        .map_err(|e| e.trace(1))?;
+    // Repeating without an interval would flood the interpreter with events
+    if repeat && ms < 1.0 {
+        return Err(EvalError::new(format!("Expected an interval of at least 1 millisecond, got {}", ms))
+                   .with_condition(condition::TYPE_ERROR)
+                   .trace(1))
+    }
+    if ms < 0.0 {
+        return Err(EvalError::new("Expected a non-negative number of milliseconds".to_string())
+                   .with_condition(condition::TYPE_ERROR)
+                   .trace(1))
+    }
+    Ok(interp.timers().schedule(ms as u64, args[1].clone(), repeat))
+}
+
+fn after(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    schedule(interp, args, false)
+}
+
+pub const AFTER: NativeDef = NativeDef {
+    name: "after",
+    positional: &["ms", "thunk"],
+    rest: None,
+    func: after,
+};
+
+fn every(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    schedule(interp, args, true)
+}
+
+pub const EVERY: NativeDef = NativeDef {
+    name: "every",
+    positional: &["ms", "thunk"],
+    rest: None,
+    func: every,
+};
+
+fn cancel(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let handle = args[0].as_handle(TIMER)
+        .or_else(|_| args[0].as_handle(WATCHER))
+        .map_err(|e| e.trace(1))?;
+    let value = handle.value.borrow();
+    let cancelled = value.downcast_ref::<Arc<AtomicBool>>()
+        .ok_or_else(|| EvalError::new("Invalid timer handle".to_string())
+                    .with_condition(condition::TYPE_ERROR)
+                    .trace(1))?;
+    Ok(LispObject::Bool(!cancelled.swap(true, Ordering::Relaxed)))
+}
+
+pub const CANCEL: NativeDef = NativeDef {
+    name: "cancel",
+    positional: &["timer"],
+    rest: None,
+    func: cancel,
+};
+
+fn sleep(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let ms = args[0].as_number()
+        .map_err(|e| e.trace(1))?;
+    interp.sleep(Duration::from_millis(ms.max(0.0) as u64))?;
+    Ok(LispObject::Bool(true))
+}
+
+pub const SLEEP: NativeDef = NativeDef {
+    name: "sleep",
+    positional: &["ms"],
+    rest: None,
+    func: sleep,
+};
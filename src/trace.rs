@@ -0,0 +1,205 @@
+// Spans of reading, loading, macro expansion and calls of functions and
+// natives. With the trace feature they are tracing spans at trace level with
+// the target risp, named by their Kind and carrying the name of what is read
+// or called in the field name. Spans entered while another one is open are
+// its children. Without the feature spans compile to nothing.
+
+#[cfg(feature = "trace")]
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Write},
+    sync::{Mutex, atomic::{AtomicU64, Ordering}},
+    time::Instant,
+};
+
+#[cfg(feature = "trace")]
+use tracing::{
+    Event, Level, Metadata, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+
+#[cfg(feature = "trace")]
+const TARGET: &str = "risp";
+
+#[derive(Clone, Copy)]
+pub enum Kind {
+    Read,
+    Load,
+    Call,
+    Native,
+    Expand,
+}
+
+#[cfg(feature = "trace")]
+pub struct Span {
+    _entered: tracing::span::EnteredSpan,
+}
+
+#[cfg(not(feature = "trace"))]
+pub struct Span;
+
+// Enter a span of kind named by name. name is only computed if the span is
+// enabled.
+#[cfg(feature = "trace")]
+pub fn span(kind: Kind, name: impl FnOnce() -> String) -> Option<Span> {
+    if !tracing::enabled!(target: TARGET, Level::TRACE) {
+        return None
+    }
+    let name = name();
+    let span = match kind {
+        Kind::Read => tracing::span!(target: TARGET, Level::TRACE, "read", name = %name),
+        Kind::Load => tracing::span!(target: TARGET, Level::TRACE, "load", name = %name),
+        Kind::Call => tracing::span!(target: TARGET, Level::TRACE, "call", name = %name),
+        Kind::Native => tracing::span!(target: TARGET, Level::TRACE, "native", name = %name),
+        Kind::Expand => tracing::span!(target: TARGET, Level::TRACE, "expand", name = %name),
+    };
+    Some(Span { _entered: span.entered() })
+}
+
+#[cfg(not(feature = "trace"))]
+#[inline(always)]
+pub fn span(_kind: Kind, _name: impl FnOnce() -> String) -> Option<Span> {
+    None
+}
+
+// Leave span, if any, and enter a new one in its place.
+pub fn follow(span: &mut Option<Span>, kind: Kind, name: impl FnOnce() -> String) {
+    *span = None;
+    *span = self::span(kind, name);
+}
+
+// Formats the fields of a span as name=value pairs.
+#[cfg(feature = "trace")]
+struct Fields(String);
+
+#[cfg(feature = "trace")]
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        write!(self.0, " {}={:?}", field.name(), value).ok();
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        write!(self.0, " {}={}", field.name(), value).ok();
+    }
+}
+
+#[cfg(feature = "trace")]
+struct SpanData {
+    name: &'static str,
+    fields: String,
+    refs: usize,
+    start: Option<Instant>,
+}
+
+#[cfg(feature = "trace")]
+thread_local! {
+    // Spans entered on this thread, innermost last
+    static ENTERED: RefCell<Vec<u64>> = const { RefCell::new(vec![]) };
+}
+
+// Writes the spans of the target risp to stderr as they are entered and left,
+// indented by their nesting on the thread.
+#[cfg(feature = "trace")]
+struct StderrSubscriber {
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, SpanData>>,
+}
+
+#[cfg(feature = "trace")]
+impl StderrSubscriber {
+    fn print(&self, id: &Id, action: &str) {
+        let depth = ENTERED.with(|entered| entered.borrow().len());
+        let spans = self.spans.lock().unwrap();
+        if let Some(span) = spans.get(&id.into_u64()) {
+            let elapsed = match (action, span.start) {
+                ("leave", Some(start)) => format!(" after {:?}", start.elapsed()),
+                _ => String::new(),
+            };
+            eprintln!("[{}] {}{} {}{}{}", TARGET, "  ".repeat(depth), action, span.name,
+                      span.fields, elapsed);
+        }
+    }
+}
+
+#[cfg(feature = "trace")]
+impl Subscriber for StderrSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == TARGET
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut fields = Fields(String::new());
+        attributes.record(&mut fields);
+        self.spans.lock().unwrap().insert(id, SpanData {
+            name: attributes.metadata().name(),
+            fields: fields.0,
+            refs: 1,
+            start: None,
+        });
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            let mut fields = Fields(std::mem::take(&mut span.fields));
+            values.record(&mut fields);
+            span.fields = fields.0;
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(String::new());
+        event.record(&mut fields);
+        eprintln!("[{}]{}", TARGET, fields.0);
+    }
+
+    fn enter(&self, span: &Id) {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            data.start = Some(Instant::now());
+        }
+        self.print(span, "enter");
+        ENTERED.with(|entered| entered.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        ENTERED.with(|entered| entered.borrow_mut().pop());
+        self.print(span, "leave");
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(data) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            data.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans.lock().unwrap();
+        let closed = match spans.get_mut(&span.into_u64()) {
+            Some(data) => {
+                data.refs -= 1;
+                data.refs == 0
+            },
+            None => false,
+        };
+        if closed {
+            spans.remove(&span.into_u64());
+        }
+        closed
+    }
+}
+
+// Write the spans to stderr, for --trace. Fails if a global subscriber is
+// already set, embedders install their own subscriber instead.
+#[cfg(feature = "trace")]
+pub fn log_to_stderr() -> Result<(), tracing::subscriber::SetGlobalDefaultError> {
+    tracing::subscriber::set_global_default(StderrSubscriber {
+        next_id: AtomicU64::new(1),
+        spans: Mutex::new(HashMap::new()),
+    })
+}
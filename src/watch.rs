@@ -0,0 +1,111 @@
+// watch-path reports files created, modified or removed below a path to a
+// handler, which runs between top-level evaluations like timer thunks.
+//
+// There is no portable file notification API in std, so watched paths are
+// polled and compared against the previous snapshot. The notify crate would
+// bring a backend per platform (inotify, kqueue, FSEvents,
+// ReadDirectoryChangesW) and its dependencies into every build. Like the
+// other natives reaching outside the interpreter, which use std or the
+// platform's tools, watch-path keeps to std instead and pays with latency of
+// up to POLL_INTERVAL and a scan of the tree per poll.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, Handle, LispObject, NativeDef},
+    timer::{Event, WATCHER},
+};
+
+// Time between two scans of a watched path.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+type Snapshot = HashMap<PathBuf, SystemTime>;
+
+// Record the files below the watched path, which may be a symbolic link.
+// Links below it are recorded themselves and not followed, so a loop of links
+// is not scanned forever.
+fn scan(path: &Path, snapshot: &mut Snapshot) {
+    if let Ok(metadata) = fs::metadata(path) {
+        record(path, &metadata, snapshot);
+    }
+}
+
+fn record(path: &Path, metadata: &fs::Metadata, snapshot: &mut Snapshot) {
+    if metadata.is_dir() {
+        if let Ok(entries) = fs::read_dir(path) {
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if let Ok(metadata) = fs::symlink_metadata(&path) {
+                    record(&path, &metadata, snapshot);
+                }
+            }
+        }
+    } else if let Ok(modified) = metadata.modified() {
+        snapshot.insert(path.to_path_buf(), modified);
+    }
+}
+
+fn event(kind: &str, path: &Path) -> Event {
+    vec![
+        ("kind", kind.to_string()),
+        ("path", path.to_string_lossy().to_string()),
+    ]
+}
+
+fn changes(old: &Snapshot, new: &Snapshot) -> Vec<Event> {
+    let mut events = vec![];
+    for (path, modified) in new {
+        match old.get(path) {
+            None => events.push(event("create", path)),
+            Some(previous) if previous != modified => events.push(event("modify", path)),
+            _ => (),
+        }
+    }
+    for path in old.keys().filter(|path| !new.contains_key(*path)) {
+        events.push(event("remove", path));
+    }
+    events.sort();
+    events
+}
+
+fn watch_path(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let path = PathBuf::from(args[0].as_str().map_err(|e| e.trace(1))?);
+    if !path.exists() {
+        return Err(EvalError::new(format!("No such file or directory {}", path.display()))
+                   .with_condition(condition::IO_ERROR)
+                   .trace(1))
+    }
+
+    let (subscription, cancelled) = interp.timers().subscribe(args[1].clone());
+    thread::spawn(move || {
+        let mut snapshot = Snapshot::new();
+        scan(&path, &mut snapshot);
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            if !subscription.active() {
+                return
+            }
+            let mut current = Snapshot::new();
+            scan(&path, &mut current);
+            for event in changes(&snapshot, &current) {
+                if !subscription.post(event) {
+                    return
+                }
+            }
+            snapshot = current;
+        }
+    });
+    Ok(Handle::object(WATCHER, cancelled))
+}
+
+pub const WATCH_PATH: NativeDef = NativeDef {
+    name: "watch-path",
+    positional: &["path", "handler"],
+    rest: None,
+    func: watch_path,
+};
@@ -0,0 +1,567 @@
+// Reading, writing and querying XML and HTML documents. An element is the
+// list (tag attrs children) of its tag as a string, a map of its attributes
+// by keyword and the list of its child nodes, text is a string. xml-parse
+// returns the list of top-level nodes, dropping comments, declarations and
+// text of only whitespace. It accepts common HTML: void elements like <br>,
+// unquoted and valueless attributes, the raw text of script and style, and
+// elements left open, which end with their parent.
+// select finds elements by CSS-like selectors of tags, .classes, #ids and
+// [attributes] or [attributes=values], combined for descendants by spaces,
+// for children by >, and as alternatives by commas.
+
+use crate::{
+    condition,
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject, Map, NativeDef, SerializeSymbol},
+};
+
+const VOID: &[&str] = &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
+                        "param", "source", "track", "wbr"];
+const RAW_TEXT: &[&str] = &["script", "style"];
+
+fn is_one_of(tag: &str, tags: &[&str]) -> bool {
+    tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+}
+
+fn is_tag_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn syntax_error(message: String) -> EvalError {
+    EvalError::new(message).with_condition(condition::SYNTAX_ERROR)
+}
+
+// Replace character and entity references in text.
+fn decode(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let c = reference.and_then(|name| match name {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => name.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+            }.and_then(char::from_u32),
+        });
+        match (reference, c) {
+            (Some(name), Some(c)) => {
+                decoded.push(c);
+                rest = &rest[name.len() + 2..];
+            },
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+pub(crate) fn escape(text: &str, quotes: bool) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if quotes => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+struct Element {
+    tag: String,
+    attrs: Map,
+    children: Vec<LispObject>,
+}
+
+impl Element {
+    fn into_object(self) -> LispObject {
+        LispObject::List(vec![
+            LispObject::String(self.tag.into()),
+            LispObject::Map(self.attrs.into()),
+            LispObject::List(self.children),
+        ])
+    }
+}
+
+struct Parser<'a> {
+    interp: &'a mut Interpreter,
+    source: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    fn error(&self, message: &str) -> EvalError {
+        let line = self.source[..self.pos].matches('\n').count() + 1;
+        syntax_error(format!("{} in line {} of the document", message, line))
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.source.len() - self.rest().trim_start().len();
+    }
+
+    // Advance past the next occurrence of end, returning the text before it.
+    fn until(&mut self, end: &str, message: &str) -> Result<&'a str, EvalError> {
+        let rest = self.rest();
+        match rest.find(end) {
+            Some(index) => {
+                self.pos += index + end.len();
+                Ok(&rest[..index])
+            },
+            None => Err(self.error(message)),
+        }
+    }
+
+    fn name(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c: char| c.is_whitespace() || matches!(c, '>' | '/' | '='))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    // The attributes of a start tag after its name, and whether it closes
+    // itself.
+    fn attributes(&mut self) -> Result<(Map, bool), EvalError> {
+        let mut attrs: Map = vec![];
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                return Ok((attrs, true))
+            } else if rest.starts_with('>') {
+                self.pos += 1;
+                return Ok((attrs, false))
+            } else if rest.is_empty() {
+                return Err(self.error("Unclosed tag"))
+            } else if rest.starts_with('/') {
+                self.pos += 1;
+                continue
+            }
+            let name = self.name().to_string();
+            if name.is_empty() {
+                return Err(self.error("Expected an attribute name"))
+            }
+            self.skip_whitespace();
+            let value = if self.rest().starts_with('=') {
+                self.pos += 1;
+                self.skip_whitespace();
+                match self.rest().chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        self.pos += 1;
+                        decode(self.until(&quote.to_string(), "Unclosed attribute value")?)
+                    },
+                    _ => {
+                        let rest = self.rest();
+                        let len = rest.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(rest.len());
+                        self.pos += len;
+                        decode(&rest[..len])
+                    },
+                }
+            } else {
+                String::new()
+            };
+            let key = LispObject::Keyword(self.interp.symbols_mut().intern(&name));
+            attrs.retain(|(k, _)| !k.equals(&key));
+            attrs.push((key, LispObject::String(value.into())));
+        }
+    }
+
+    fn parse(&mut self) -> Result<Vec<LispObject>, EvalError> {
+        let mut top = vec![];
+        let mut open: Vec<Element> = vec![];
+        let add = |open: &mut Vec<Element>, top: &mut Vec<LispObject>, node| match open.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => top.push(node),
+        };
+        while !self.rest().is_empty() {
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.until("-->", "Unclosed comment")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                let text = self.until("]]>", "Unclosed CDATA section")?.to_string();
+                add(&mut open, &mut top, LispObject::String(text.into()));
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                self.until(">", "Unclosed declaration")?;
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let name = self.name().to_string();
+                self.until(">", "Unclosed end tag")?;
+                let index = open.iter().rposition(|e| e.tag.eq_ignore_ascii_case(&name))
+                    .ok_or_else(|| self.error(&format!("Unexpected </{}>", name)))?;
+                while open.len() > index {
+                    let element = open.pop().unwrap_or_else(|| unreachable!()).into_object();
+                    add(&mut open, &mut top, element);
+                }
+            } else if rest.starts_with('<') && rest[1..].starts_with(is_tag_start) {
+                self.pos += 1;
+                let tag = self.name().to_string();
+                let (attrs, closed) = self.attributes()?;
+                let mut element = Element { tag, attrs, children: vec![] };
+                if closed || is_one_of(&element.tag, VOID) {
+                    add(&mut open, &mut top, element.into_object());
+                } else if is_one_of(&element.tag, RAW_TEXT) {
+                    let end = self.rest().to_ascii_lowercase().find(&format!("</{}", element.tag.to_ascii_lowercase()))
+                        .ok_or_else(|| self.error(&format!("Unclosed <{}>", element.tag)))?;
+                    let text = &self.rest()[..end];
+                    if !text.trim().is_empty() {
+                        element.children.push(LispObject::String(text.into()));
+                    }
+                    self.pos += end;
+                    self.until(">", "Unclosed end tag")?;
+                    add(&mut open, &mut top, element.into_object());
+                } else {
+                    open.push(element);
+                }
+            } else {
+                // A < not starting markup is part of the text
+                let len = rest.match_indices('<')
+                    .find(|(index, _)| rest[index + 1..].starts_with(|c| is_tag_start(c) || matches!(c, '/' | '!' | '?')))
+                    .map_or(rest.len(), |(index, _)| index);
+                self.pos += len;
+                if !rest[..len].trim().is_empty() {
+                    add(&mut open, &mut top, LispObject::String(decode(&rest[..len]).into()));
+                }
+            }
+        }
+        while let Some(element) = open.pop() {
+            add(&mut open, &mut top, element.into_object());
+        }
+        Ok(top)
+    }
+}
+
+fn xml_parse(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let source = args[0].as_str()
+        .map_err(|e| e.trace(1))?;
+    let mut parser = Parser { interp, source, pos: 0 };
+    Ok(LispObject::List(parser.parse()
+                        .map_err(|e| e.trace(1))?))
+}
+
+pub const XML_PARSE: NativeDef = NativeDef {
+    name: "xml-parse",
+    positional: &["source"],
+    rest: None,
+    func: xml_parse,
+};
+
+// The tag, attributes and children of an element.
+type Parts<'a> = (&'a str, &'a [(LispObject, LispObject)], &'a [LispObject]);
+
+// The parts of node if it is an element. Attributes may also be given as the
+// empty list.
+fn element(node: &LispObject) -> Option<Parts<'_>> {
+    match node {
+        LispObject::List(l) => match &l[..] {
+            [LispObject::String(tag), LispObject::Map(attrs), LispObject::List(children)] =>
+                Some((tag, attrs, children)),
+            [LispObject::String(tag), LispObject::List(attrs), LispObject::List(children)] if attrs.is_empty() =>
+                Some((tag, &[], children)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// The nodes of an element or text, or of a list of them.
+fn nodes(object: &LispObject) -> &[LispObject] {
+    match object {
+        LispObject::List(l) if element(object).is_none() => l,
+        _ => std::slice::from_ref(object),
+    }
+}
+
+fn not_a_node(interp: &Interpreter, node: &LispObject) -> EvalError {
+    EvalError::new(format!("Expected text or an element (tag attrs children), got {}", interp.serialize(node)))
+        .with_condition(condition::TYPE_ERROR)
+}
+
+fn attribute_name<'a>(interp: &'a Interpreter, key: &'a LispObject) -> Option<&'a str> {
+    match key {
+        LispObject::Keyword(s) | LispObject::Symbol(s) => interp.symbols().as_string(s),
+        LispObject::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+fn write(interp: &Interpreter, node: &LispObject, out: &mut String) -> Result<(), EvalError> {
+    if let LispObject::String(text) = node {
+        out.push_str(&escape(text, false));
+        return Ok(())
+    }
+    let (tag, attrs, children) = element(node).ok_or_else(|| not_a_node(interp, node))?;
+    out.push('<');
+    out.push_str(tag);
+    for (key, value) in attrs {
+        let name = attribute_name(interp, key)
+            .ok_or_else(|| EvalError::new(format!("Expected a keyword as attribute name, got {}",
+                                                  interp.serialize(key)))
+                        .with_condition(condition::TYPE_ERROR))?;
+        let value = match value {
+            LispObject::String(s) => s.to_string(),
+            value => interp.serialize(value),
+        };
+        out.push_str(&format!(" {}=\"{}\"", name, escape(&value, true)));
+    }
+    if children.is_empty() && is_one_of(tag, VOID) {
+        out.push_str("/>");
+        return Ok(())
+    }
+    out.push('>');
+    for child in children {
+        match child {
+            LispObject::String(text) if is_one_of(tag, RAW_TEXT) => out.push_str(text),
+            child => write(interp, child, out)?,
+        }
+    }
+    out.push_str(&format!("</{}>", tag));
+    Ok(())
+}
+
+// The source of a node or a list of nodes.
+fn xml_write(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let mut out = String::new();
+    for node in nodes(&args[0]) {
+        write(interp, node, &mut out)
+            .map_err(|e| e.trace(1))?;
+    }
+    Ok(LispObject::String(out.into()))
+}
+
+pub const XML_WRITE: NativeDef = NativeDef {
+    name: "xml-write",
+    positional: &["nodes"],
+    rest: None,
+    func: xml_write,
+};
+
+fn text(node: &LispObject, out: &mut String) {
+    match (node, element(node)) {
+        (LispObject::String(s), _) => out.push_str(s),
+        (_, Some((_, _, children))) => children.iter().for_each(|child| text(child, out)),
+        _ => (),
+    }
+}
+
+// The text of a node or a list of nodes, without the markup.
+fn xml_text(_: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let mut out = String::new();
+    nodes(&args[0]).iter().for_each(|node| text(node, &mut out));
+    Ok(LispObject::String(out.into()))
+}
+
+pub const XML_TEXT: NativeDef = NativeDef {
+    name: "xml-text",
+    positional: &["nodes"],
+    rest: None,
+    func: xml_text,
+};
+
+// A selector for one element, like div.item#main[href].
+#[derive(Default)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attrs: Vec<(String, Option<String>)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | ':')
+}
+
+fn name_at(chars: &[char], pos: &mut usize) -> String {
+    let start = *pos;
+    while *pos < chars.len() && is_name_char(chars[*pos]) {
+        *pos += 1;
+    }
+    chars[start..*pos].iter().collect()
+}
+
+// The compounds of each alternative with the combinator relating them to
+// the compound before them.
+fn parse_selector(selector: &str) -> Result<Vec<Vec<(Combinator, Compound)>>, EvalError> {
+    let invalid = || syntax_error(format!("Invalid selector \"{}\"", selector));
+    let mut alternatives = vec![];
+    for alternative in selector.split(',') {
+        let chars = alternative.chars().collect::<Vec<char>>();
+        let mut compounds = vec![];
+        let mut pos = 0;
+        let mut combinator = Combinator::Descendant;
+        loop {
+            while pos < chars.len() && chars[pos].is_whitespace() {
+                pos += 1;
+            }
+            if pos == chars.len() {
+                break
+            }
+            if chars[pos] == '>' {
+                if compounds.is_empty() || combinator == Combinator::Child {
+                    return Err(invalid())
+                }
+                combinator = Combinator::Child;
+                pos += 1;
+                continue
+            }
+            let mut compound = Compound::default();
+            let start = pos;
+            while pos < chars.len() && !chars[pos].is_whitespace() && chars[pos] != '>' {
+                match chars[pos] {
+                    '*' if pos == start => pos += 1,
+                    '.' | '#' => {
+                        let kind = chars[pos];
+                        pos += 1;
+                        let name = name_at(&chars, &mut pos);
+                        match (kind, name.is_empty()) {
+                            (_, true) => return Err(invalid()),
+                            ('.', _) => compound.classes.push(name),
+                            _ => compound.id = Some(name),
+                        }
+                    },
+                    '[' => {
+                        pos += 1;
+                        let name = name_at(&chars, &mut pos);
+                        let close = chars[pos..].iter().position(|c| *c == ']').ok_or_else(invalid)?;
+                        let value = match chars[pos..pos + close].iter().collect::<String>().as_str() {
+                            "" => None,
+                            value => Some(value.strip_prefix('=').ok_or_else(invalid)?
+                                          .trim_matches(|c| c == '"' || c == '\'').to_string()),
+                        };
+                        if name.is_empty() {
+                            return Err(invalid())
+                        }
+                        compound.attrs.push((name, value));
+                        pos += close + 1;
+                    },
+                    c if pos == start && is_name_char(c) => compound.tag = Some(name_at(&chars, &mut pos)),
+                    _ => return Err(invalid()),
+                }
+            }
+            compounds.push((combinator, compound));
+            combinator = Combinator::Descendant;
+        }
+        if compounds.is_empty() || combinator == Combinator::Child {
+            return Err(invalid())
+        }
+        alternatives.push(compounds);
+    }
+    Ok(alternatives)
+}
+
+struct Selector<'a> {
+    interp: &'a Interpreter,
+    alternatives: Vec<Vec<(Combinator, Compound)>>,
+}
+
+impl Selector<'_> {
+    fn attribute<'b>(&self, attrs: &'b [(LispObject, LispObject)], name: &str) -> Option<&'b LispObject> {
+        attrs.iter()
+            .find(|(key, _)| attribute_name(self.interp, key).is_some_and(|key| key.eq_ignore_ascii_case(name)))
+            .map(|(_, value)| value)
+    }
+
+    fn attribute_value(&self, attrs: &[(LispObject, LispObject)], name: &str) -> Option<String> {
+        self.attribute(attrs, name).map(|value| match value {
+            LispObject::String(s) => s.to_string(),
+            value => self.interp.serialize(value),
+        })
+    }
+
+    fn compound_matches(&self, compound: &Compound, node: &LispObject) -> bool {
+        let (tag, attrs, _) = match element(node) {
+            Some(element) => element,
+            None => return false,
+        };
+        let classes = self.attribute_value(attrs, "class").unwrap_or_default();
+        compound.tag.as_ref().is_none_or(|t| t.eq_ignore_ascii_case(tag))
+            && compound.id.as_ref().is_none_or(|id| self.attribute_value(attrs, "id").as_ref() == Some(id))
+            && compound.classes.iter().all(|class| classes.split_whitespace().any(|c| c == class))
+            && compound.attrs.iter().all(|(name, value)| match value {
+                Some(value) => self.attribute_value(attrs, name).as_ref() == Some(value),
+                None => self.attribute(attrs, name).is_some(),
+            })
+    }
+
+    // Whether node, below ancestors, matches compounds.
+    fn matches_at(&self, compounds: &[(Combinator, Compound)], node: &LispObject,
+                  ancestors: &[&LispObject]) -> bool {
+        let ((combinator, last), before) = match compounds.split_last() {
+            Some(split) => split,
+            None => return true,
+        };
+        if !self.compound_matches(last, node) {
+            return false
+        }
+        if before.is_empty() {
+            return true
+        }
+        match combinator {
+            Combinator::Child => ancestors.split_last()
+                .is_some_and(|(parent, above)| self.matches_at(before, parent, above)),
+            Combinator::Descendant => (0..ancestors.len()).rev()
+                .any(|index| self.matches_at(before, ancestors[index], &ancestors[..index])),
+        }
+    }
+
+    fn collect<'b>(&self, node: &'b LispObject, ancestors: &mut Vec<&'b LispObject>, found: &mut Vec<LispObject>) {
+        let children = match element(node) {
+            Some((_, _, children)) => children,
+            None => return,
+        };
+        if self.alternatives.iter().any(|compounds| self.matches_at(compounds, node, ancestors)) {
+            found.push(node.clone());
+        }
+        ancestors.push(node);
+        for child in children {
+            self.collect(child, ancestors, found);
+        }
+        ancestors.pop();
+    }
+}
+
+// The elements among nodes and their descendants matching a selector, in
+// document order.
+fn select(interp: &mut Interpreter, args: &[LispObject]) -> Result<LispObject, EvalError> {
+    let selector = args[1].as_str()
+        .map_err(|e| e.trace(2))?;
+    let alternatives = parse_selector(selector)
+        .map_err(|e| e.trace(2))?;
+    let selector = Selector { interp, alternatives };
+    let mut found = vec![];
+    for node in nodes(&args[0]) {
+        selector.collect(node, &mut vec![], &mut found);
+    }
+    Ok(LispObject::List(found))
+}
+
+pub const SELECT: NativeDef = NativeDef {
+    name: "select",
+    positional: &["nodes", "selector"],
+    rest: None,
+    func: select,
+};
@@ -0,0 +1,33 @@
+use lisp::interpreter::{ExecError, Interpreter};
+
+fn message(interpreter: &mut Interpreter, input: &str) -> String {
+    match interpreter.eval_str(input) {
+        Err(ExecError::Eval(e)) => {
+            assert_eq!(&*e.condition, "arity-error");
+            e.message
+        },
+        _ => panic!("expected {} to fail", input),
+    }
+}
+
+#[test]
+fn arity_errors_show_signature_and_call() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defn f (x y) x)").ok().unwrap();
+    assert_eq!(message(&mut interpreter, "(f 1 2 3)"),
+               "param list (x y) requires exactly 2 arguments, got 3 in (f 1 2 3)");
+    assert_eq!(message(&mut interpreter, "(first)"),
+               "param list (lst) requires exactly 1 arguments, got 0 in (first)");
+}
+
+#[test]
+fn arity_errors_of_varargs_state_the_minimum() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defn g (x &rest more) x)").ok().unwrap();
+    assert_eq!(message(&mut interpreter, "(g)"),
+               "param list (x &rest more) requires at least 1 arguments, got 0 in (g)");
+    assert_eq!(message(&mut interpreter, "((fn (a b) a) 1)"),
+               "param list (a b) requires exactly 2 arguments, got 1 in (~fn~ 1)");
+    let value = interpreter.eval_str("(g 1 2 3)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "1");
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use lisp::interpreter::Interpreter;
+use common::{eval, message};
+
+#[test]
+fn let_star_sees_earlier_bindings() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(let* ((a 1) (b (+ a 1)) (a (* b 10))) (list a b))"), "(20 2)");
+    assert_eq!(eval(&mut interpreter, "(let* () 5)"), "5");
+    assert_eq!(message(&mut interpreter, "(let ((a 1) (b (+ a 1))) b)"), "Unbound symbol 'a'");
+}
+
+#[test]
+fn let_star_bindings_do_not_outlive_the_form() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def a 0)").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(list (let* ((a 1) (b a)) b) a)"), "(1 0)");
+}
+
+#[test]
+fn let_star_rejects_malformed_bindings() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(message(&mut interpreter, "(let* ((a)) a)"),
+               "binding (sym value) requires exactly 2 arguments, got 1 in (a)");
+    assert_eq!(message(&mut interpreter, "(let* (a 1) a)"), "Expected a list");
+}
+
+#[test]
+fn letrec_functions_call_themselves_and_each_other() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(letrec ((fact (fn (n) (if (= n 0) 1 (* n (fact (- n 1))))))) \
+                                         (fact 5))"), "120");
+    assert_eq!(eval(&mut interpreter, "(letrec ((even? (fn (n) (if (= n 0) #t (odd? (- n 1))))) \
+                                                (odd? (fn (n) (if (= n 0) #f (even? (- n 1)))))) \
+                                         (list (even? 10) (odd? 7)))"), "(#t #t)");
+    assert!(interpreter.eval_str("fact").is_err());
+}
+
+#[test]
+fn letrec_rejects_malformed_bindings() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(message(&mut interpreter, "(letrec (x) 1)"), "Expected a list");
+    assert_eq!(message(&mut interpreter, "(letrec ((f)) f)"),
+               "binding (sym value) requires exactly 2 arguments, got 1 in (f)");
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use lisp::interpreter::{ExecError, Interpreter};
+use common::eval;
+
+#[test]
+fn natives_are_passed_like_functions() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(map + '(1 2) '(10 20))"), "(11 22)");
+    assert_eq!(eval(&mut interpreter, "(reduce * '(1 2 3 4))"), "24");
+    assert_eq!(eval(&mut interpreter, "(map first '((1) (2)))"), "(1 2)");
+    assert_eq!(eval(&mut interpreter, "(apply + '(1 2))"), "3");
+}
+
+#[test]
+fn special_forms_and_lambdas_are_passed_like_functions() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(map if '(#t #f) '(1 2) '(3 4))"), "(1 4)");
+    assert_eq!(eval(&mut interpreter, "(reduce (fn (a b) (+ a b)) '(1 2 3))"), "6");
+}
+
+#[test]
+fn map_stops_at_the_shortest_list() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(map + '(1 2 3) '(10))"), "(11)");
+    assert_eq!(eval(&mut interpreter, "(reduce + '(5))"), "5");
+}
+
+#[test]
+fn calling_values_that_are_not_functions_fails() {
+    let mut interpreter = Interpreter::new();
+    assert!(matches!(interpreter.eval_str("(map 1 '(1))"), Err(ExecError::Eval(_))));
+    match interpreter.eval_str("(reduce + '())") {
+        Err(ExecError::Eval(e)) => assert_eq!(e.message, "reduce of empty list"),
+        _ => panic!("expected reduce of an empty list to fail"),
+    }
+}
@@ -0,0 +1,44 @@
+use std::thread;
+use std::time::Duration;
+
+use lisp::interpreter::{ExecError, Interpreter};
+
+fn cancel_after(interpreter: &Interpreter, ms: u64) {
+    let token = interpreter.cancellation_token();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(ms));
+        token.cancel();
+    });
+}
+
+#[test]
+fn cancellation_aborts_infinite_loop() {
+    let mut interpreter = Interpreter::new();
+    cancel_after(&interpreter, 50);
+    match interpreter.eval_str("(begin (defun spin (n) (spin (+ n 1))) (spin 0))") {
+        Err(ExecError::Eval(e)) => assert!(e.is_cancelled()),
+        _ => panic!("expected a cancelled evaluation"),
+    }
+    // The token is consumed, later evaluations run normally
+    assert!(interpreter.eval_str("(+ 1 2)").is_ok());
+}
+
+#[test]
+fn cancellation_interrupts_sleep() {
+    let mut interpreter = Interpreter::new();
+    cancel_after(&interpreter, 50);
+    match interpreter.eval_str("(sleep 60000)") {
+        Err(ExecError::Eval(e)) => assert!(e.is_cancelled()),
+        _ => panic!("expected a cancelled evaluation"),
+    }
+}
+
+#[test]
+fn cancellation_runs_cleanup_forms() {
+    let mut interpreter = Interpreter::new();
+    cancel_after(&interpreter, 50);
+    let result = interpreter.eval_str("(unwind-protect (sleep 60000) (def cleaned #t))");
+    assert!(matches!(result, Err(ExecError::Eval(e)) if e.is_cancelled()));
+    let cleaned = interpreter.eval_str("cleaned").ok().unwrap();
+    assert_eq!(interpreter.serialize(&cleaned), "#t");
+}
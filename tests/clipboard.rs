@@ -0,0 +1,29 @@
+#![cfg(feature = "desktop")]
+
+use std::env;
+
+use lisp::interpreter::{ExecError, Interpreter};
+
+#[test]
+fn clipboard_set_requires_a_string() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval_str("(clipboard-set! 42)").is_err());
+    assert!(interpreter.eval_str("(clipboard-get 1)").is_err());
+}
+
+#[test]
+fn missing_clipboard_tools_fail_with_an_io_error() {
+    // No tool is found on an empty PATH, no other test in this binary runs
+    // programs.
+    env::set_var("PATH", "");
+    let mut interpreter = Interpreter::new();
+    for input in ["(clipboard-get)", "(clipboard-set! \"text\")"] {
+        match interpreter.eval_str(input) {
+            Err(ExecError::Eval(e)) => {
+                assert_eq!(&*e.condition, "io-error");
+                assert_eq!(e.message, "No clipboard tool found, install wl-clipboard, xclip or xsel");
+            },
+            _ => panic!("expected {} to fail without clipboard tools", input),
+        }
+    }
+}
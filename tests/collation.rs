@@ -0,0 +1,27 @@
+#![cfg(feature = "collation")]
+
+mod common;
+
+use lisp::interpreter::Interpreter;
+use common::eval;
+
+#[test]
+fn collate_ignores_accents_and_case_first() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(collate (list \"zebra\" \"Éclair\" \"apple\" \"eclair\" \"Apple\"))"),
+               "(\"apple\" \"Apple\" \"eclair\" \"Éclair\" \"zebra\")");
+    assert_eq!(eval(&mut interpreter, "(string-compare \"Apple\" \"apple\" :strength :primary)"), "0");
+    assert_eq!(eval(&mut interpreter, "(string-compare \"b\" \"Á\")"), "1");
+}
+
+#[test]
+fn collate_follows_locale_and_numeric_options() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(collate (list \"öl\" \"zebra\" \"ober\") :locale \"sv-SE\")"),
+               "(\"ober\" \"zebra\" \"öl\")");
+    assert_eq!(eval(&mut interpreter, "(collate (list \"öl\" \"zebra\" \"ober\") :locale \"de\")"),
+               "(\"ober\" \"öl\" \"zebra\")");
+    assert_eq!(eval(&mut interpreter, "(collate (list \"file10\" \"file2\") :numeric #t)"),
+               "(\"file2\" \"file10\")");
+    assert!(interpreter.eval_str("(collate (list \"a\") :strength :quaternary)").is_err());
+}
@@ -0,0 +1,33 @@
+// Helpers shared by the integration tests. Not every test uses every helper.
+
+#![allow(dead_code)]
+
+use lisp::interpreter::{ExecError, Interpreter};
+
+// The printed value input evaluates to.
+pub fn eval(interpreter: &mut Interpreter, input: &str) -> String {
+    let value = interpreter.eval_str(input).ok().unwrap();
+    interpreter.serialize(&value)
+}
+
+// The contents of the string input evaluates to.
+pub fn eval_string(interpreter: &mut Interpreter, input: &str) -> String {
+    let value = interpreter.eval_str(input).ok().unwrap();
+    value.as_str().ok().unwrap().to_string()
+}
+
+// The message of the error input fails with.
+pub fn message(interpreter: &mut Interpreter, input: &str) -> String {
+    match interpreter.eval_str(input) {
+        Err(ExecError::Eval(e)) => e.message,
+        _ => panic!("expected {} to fail", input),
+    }
+}
+
+// The condition and message of the error input fails with.
+pub fn error(interpreter: &mut Interpreter, input: &str) -> (String, String) {
+    match interpreter.eval_str(input) {
+        Err(ExecError::Eval(e)) => (e.condition.to_string(), e.message),
+        _ => panic!("expected {} to fail", input),
+    }
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use lisp::interpreter::{Interpreter, InterpreterConfig};
+use common::{eval, error};
+
+#[test]
+fn if_takes_an_optional_alternative() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(if #t 1 2)"), "1");
+    assert_eq!(eval(&mut interpreter, "(if #f 1 2)"), "2");
+    assert_eq!(eval(&mut interpreter, "(if #f 1)"), "nil");
+    assert_eq!(eval(&mut interpreter, "(if #f 1 (begin 2 3))"), "3");
+}
+
+#[test]
+fn if_accepts_else_before_the_alternative() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(if #t 1 else 2)"), "1");
+    assert_eq!(eval(&mut interpreter, "(if #f 1 else 2)"), "2");
+
+    let (condition, message) = error(&mut interpreter, "(if #f 1 2 3)");
+    assert_eq!(condition, "syntax-error");
+    assert_eq!(message, "special form if expects `else` before its 4th argument");
+}
+
+#[test]
+fn if_rejects_missing_and_extra_arguments() {
+    let mut interpreter = Interpreter::new();
+    let (condition, message) = error(&mut interpreter, "(if #t)");
+    assert_eq!(condition, "arity-error");
+    assert_eq!(message, "special form (if test then [[else] alt]) requires at least 2 arguments, \
+                         got 1 in (if #t)");
+    let (_, message) = error(&mut interpreter, "(if #f 1 else 2 3)");
+    assert_eq!(message, "special form (if test then [[else] alt]) requires at most 4 arguments, \
+                         got 5 in (if #f 1 else 2 3)");
+}
+
+#[test]
+fn legacy_if_evaluates_everything_after_the_consequent() {
+    let mut interpreter = Interpreter::new_with(InterpreterConfig {
+        legacy_if: true,
+        ..InterpreterConfig::default()
+    });
+    interpreter.eval_str("(def x 0)").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(if #f 1 (set x 5) (+ x 1))"), "6");
+    assert_eq!(eval(&mut interpreter, "(if #f 1)"), "#f");
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use lisp::{interpreter::Interpreter, lisp_object::LispObject};
+use common::eval;
+
+#[test]
+fn defined_functions_are_compiled() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defun square (x) (* x x))").ok().unwrap();
+    interpreter.eval_str("(defmacro unless (test then alt) (list 'if test alt then))").ok().unwrap();
+    assert!(matches!(interpreter.eval_str("square").ok().unwrap(), LispObject::Lambda(_)));
+    assert!(matches!(interpreter.eval_str("unless").ok().unwrap(), LispObject::Lambda(l) if l.is_macro));
+    assert_eq!(eval(&mut interpreter, "(unless (= 1 2) (square 3) 0)"), "9");
+}
+
+#[test]
+fn quoted_definitions_stay_data() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(def x '(fn 1))"), "(fn 1)");
+    interpreter.eval_str("(def y '(fn (a) a))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(first y)"), "fn");
+    assert_eq!(eval(&mut interpreter, "(y 3)"), "3");
+    assert!(interpreter.eval_str("(x)").is_err());
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use lisp::interpreter::Interpreter;
+use common::{eval, message};
+
+#[test]
+fn diff_reports_paths_into_nested_values() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(diff '(1 2 (3 4)) '(1 2 (3 4)))"), "()");
+    assert_eq!(eval(&mut interpreter, "(diff '(1 2 (3 4)) '(1 2 (3 5)))"),
+               "({\"path\" (2 1) \"expected\" 4 \"actual\" 5})");
+    assert_eq!(eval(&mut interpreter, "(diff (make-map :a 1 :b 2) (make-map :a 2 :c 3))"),
+               "({\"path\" (:a) \"expected\" 1 \"actual\" 2} {\"path\" (:b) \"expected\" 2} \
+                 {\"path\" (:c) \"actual\" 3})");
+}
+
+#[test]
+fn diff_reports_missing_elements_with_one_side() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, "(diff '(1 2) '(1))"), "({\"path\" (1) \"expected\" 2})");
+    assert_eq!(eval(&mut interpreter, "(diff '(1) '(1 2))"), "({\"path\" (1) \"actual\" 2})");
+}
+
+#[test]
+fn assert_equal_describes_the_first_difference() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(message(&mut interpreter, "(assert-equal '(1 2) '(1 3))"),
+               "assert-equal failed: at (1): expected 2, got 3");
+}
+
+#[test]
+fn diff_requires_two_values() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(message(&mut interpreter, "(diff 1)"),
+               "param list (expected actual) requires exactly 2 arguments, got 1 in (diff 1)");
+}
@@ -0,0 +1,228 @@
+use std::num::NonZeroU64;
+use std::rc::Rc;
+
+use lisp::{
+    env::{Printer, Symbols},
+    interpreter::{ExecError, Interpreter, InterpreterConfig},
+    lisp_object::LispObject,
+};
+
+#[test]
+fn define_globals_binds_names() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define_globals(&[("port", LispObject::Number(8080.0)),
+                                 ("host", LispObject::String("localhost".into()))])
+        .ok().unwrap();
+    let value = interpreter.eval_str("(list host port)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "(\"localhost\" 8080)");
+
+    interpreter.eval_str("(defconst limit 1)").ok().unwrap();
+    assert!(interpreter.define_globals(&[("limit", LispObject::Number(2.0))]).is_err());
+}
+
+#[test]
+fn with_scope_removes_bindings_after_errors() {
+    let mut interpreter = Interpreter::new();
+    let result = interpreter.with_scope(&[("request", LispObject::Number(1.0))], |interpreter| {
+        interpreter.eval_str("(def seen request)").ok().unwrap();
+        interpreter.eval_str("(undefined request)")
+    });
+    assert!(result.is_err());
+    assert!(interpreter.eval_str("request").is_err());
+    let seen = interpreter.eval_str("seen").ok().unwrap();
+    assert_eq!(interpreter.serialize(&seen), "1");
+}
+
+#[test]
+fn env_view_sees_visible_bindings() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def shadowed 1)").ok().unwrap();
+    interpreter.with_scope(&[("shadowed", LispObject::Number(2.0))], |interpreter| {
+        let view = interpreter.env_view();
+        let value = view.lookup_str("shadowed").unwrap();
+        assert_eq!(interpreter.serialize(&value), "2");
+        let bindings = view.iter_bindings()
+            .filter(|(name, _)| *name == "shadowed")
+            .count();
+        assert_eq!(bindings, 1);
+    });
+    let view = interpreter.env_view();
+    assert!(view.iter_bindings().any(|(name, _)| name == "map"));
+    assert!(view.lookup_str("never-interned").is_none());
+}
+
+#[test]
+fn listings_have_a_stable_order() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def zeta 1) (def alpha 2)").ok().unwrap();
+    let names = interpreter.env_view().iter_bindings()
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<String>>();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+
+    let bound = interpreter.eval_str("(let ((local 1)) (bound-symbols))").ok().unwrap();
+    let bound = interpreter.serialize(&bound);
+    assert!(bound.contains("alpha and"));
+    assert!(bound.contains("load local loop"));
+
+    let keys = interpreter.eval_str("(keys (assoc (make-map :b 1 :a 2) :c 3))").ok().unwrap();
+    assert_eq!(interpreter.serialize(&keys), "(:b :a :c)");
+}
+
+#[test]
+fn error_frames_show_function_names() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defn inner (x) (+ x \"a\")) (def alias inner)").ok().unwrap();
+    interpreter.eval_str("(defun outer (x) (list (alias x)))").ok().unwrap();
+    let places = match interpreter.eval_str("(outer 1)") {
+        Err(ExecError::Eval(e)) => e.frames.into_iter()
+            .map(|(_, _, place)| place)
+            .collect::<Vec<Option<String>>>(),
+        _ => panic!("outer should fail"),
+    };
+    assert_eq!(places[..2], [Some("inner".to_string()), Some("outer".to_string())]);
+}
+
+#[test]
+fn config_can_be_changed_from_lisp() {
+    let config = InterpreterConfig {
+        check_specs: false,
+        print_length: Some(10),
+        ..InterpreterConfig::default()
+    };
+    let mut interpreter = Interpreter::new_with(config.clone());
+    assert_eq!(interpreter.config(), config);
+
+    let previous = interpreter.eval_str("(set-option! :print-length 50)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&previous), "10");
+    interpreter.eval_str("(set-option! :check-specs #t)").ok().unwrap();
+    assert_eq!(interpreter.config(), InterpreterConfig {
+        print_length: Some(50),
+        ..InterpreterConfig::default()
+    });
+    assert!(interpreter.eval_str("(set-option! :print-length 0)").is_err());
+}
+
+#[test]
+fn fuel_bounds_each_evaluation() {
+    let mut interpreter = Interpreter::new_with(InterpreterConfig {
+        fuel: Some(1000),
+        ..InterpreterConfig::default()
+    });
+    interpreter.eval_str("(defn spin (n) (if (= n 0) 0 (spin (- n 1))))").ok().unwrap();
+    interpreter.eval_str("(spin 10)").ok().unwrap();
+    interpreter.eval_str("(spin 10)").ok().unwrap();
+    match interpreter.eval_str("(try (spin 100000) (catch :out-of-fuel e 0))") {
+        Err(ExecError::Eval(e)) =>
+            assert_eq!(e.to_string(), "Out of fuel, evaluation took more than 1000 steps"),
+        _ => panic!("expected to run out of fuel"),
+    }
+}
+
+#[test]
+fn float_precision_limits_displayed_digits() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(set-option! :float-precision 2)").ok().unwrap();
+    let value = interpreter.eval_str("(list 3.14159 4)").ok().unwrap();
+    assert_eq!(interpreter.display(&value), "(3.14 4)");
+    assert_eq!(interpreter.serialize(&value), "(3.14159 4)");
+    assert!(interpreter.eval_str("(set-option! :float-precision -1)").is_err());
+}
+
+#[test]
+fn features_select_bound_natives() {
+    let mut interpreter = Interpreter::new_with(InterpreterConfig {
+        features: Some(vec!["timers".to_string()]),
+        ..InterpreterConfig::default()
+    });
+    for name in ["load", "watch-path", "beep"] {
+        assert!(interpreter.eval_str(name).is_err());
+    }
+    interpreter.eval_str("sleep").ok().unwrap();
+    assert!(interpreter.eval_str("(set-option! :features '())").is_err());
+
+    interpreter.set_config(InterpreterConfig::default());
+    for name in ["load", "watch-path", "beep", "every"] {
+        interpreter.eval_str(name).ok().unwrap();
+    }
+}
+
+#[test]
+fn relaxed_predicates_accept_any_value() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval_str("(if (first '(1)) 1 2)").is_err());
+    assert!(interpreter.eval_str("(not 0)").is_err());
+
+    interpreter.eval_str("(set-option! :strict-predicates #f)").ok().unwrap();
+    let value = interpreter.eval_str("(list (if (first '(1)) 1 2) (if '() 1 2) (not 0) (not '()) \
+                                            (or '() \"default\") (and 1 #f 2))").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "(1 2 #f #t \"default\" #f)");
+}
+
+#[test]
+fn deep_recursion_fails_with_stack_overflow() {
+    let mut interpreter = Interpreter::new_with(InterpreterConfig {
+        max_depth: Some(50),
+        ..InterpreterConfig::default()
+    });
+    interpreter.eval_str("(defn depth (n) (if (= n 0) 0 (+ 1 (depth (- n 1)))))").ok().unwrap();
+    let value = interpreter.eval_str("(depth 20)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "20");
+
+    let value = interpreter.eval_str("(try (depth 1000) (catch :stack-overflow e (get e :message)))")
+        .ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "\"Stack overflow, evaluation nested deeper than 50 levels\"");
+    match interpreter.eval_str("(depth 1000)") {
+        Err(ExecError::Eval(e)) => assert!(e.frames.len() > 20),
+        _ => panic!("expected a stack overflow"),
+    }
+}
+
+// Test threads have a stack far smaller than the default depth limit needs.
+#[test]
+fn default_depth_limit_fails_gracefully_on_small_stacks() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defn depth (n) (if (= n 0) 0 (+ 1 (depth (- n 1)))))").ok().unwrap();
+    let condition = interpreter.eval_str("(try (depth 100000) (catch :stack-overflow e 'caught))")
+        .ok().unwrap();
+    assert_eq!(interpreter.serialize(&condition), "caught");
+    let value = interpreter.eval_str("(depth 50)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "50");
+}
+
+// Shows numbers in hex and leaves everything else alone.
+struct HexPrinter;
+
+impl Printer for HexPrinter {
+    fn print(&self, _: &Symbols, object: &LispObject) -> Option<String> {
+        match object {
+            LispObject::Number(n) => Some(format!("0x{:x}", *n as i64)),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn printer_renders_nested_values() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter.eval_str("(list 255 \"s\" (list 16))").ok().unwrap();
+    interpreter.set_printer(Some(Rc::new(HexPrinter)));
+    assert_eq!(interpreter.display(&value), "(0xff \"s\" (0x10))");
+    assert_eq!(interpreter.serialize(&value), "(255 \"s\" (16))");
+}
+
+#[test]
+fn step_limits_are_positive() {
+    let mut interpreter = Interpreter::new_with(InterpreterConfig {
+        step_limit: NonZeroU64::new(100_000),
+        ..InterpreterConfig::default()
+    });
+    let value = interpreter.eval_str("(+ 1 2)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "3");
+    assert!(interpreter.eval_str("(set-option! :step-limit 0)").is_err());
+    let previous = interpreter.eval_str("(set-option! :step-limit #f)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&previous), "100000");
+    assert_eq!(interpreter.config().step_limit, None);
+}
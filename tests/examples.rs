@@ -0,0 +1,13 @@
+use lisp::{examples::EXAMPLES, interpreter::Interpreter};
+
+#[test]
+fn examples_evaluate_to_expected_value() {
+    for example in EXAMPLES {
+        let mut interpreter = Interpreter::new();
+        let result = match example.run(&mut interpreter) {
+            Ok(object) => interpreter.serialize(&object),
+            Err(_) => panic!("example {} failed to evaluate", example.name),
+        };
+        assert_eq!(Some(result.as_str()), example.expected(), "example {}", example.name);
+    }
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "graphics")]
+
+mod common;
+
+use lisp::interpreter::Interpreter;
+use common::{eval, eval_string};
+
+const HEADER: &str = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"40\" height=\"40\" viewBox=\"0 0 40 40\">\n";
+
+#[test]
+fn shapes_render_as_svg() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def canvas (make-canvas 40 40))").ok().unwrap();
+    interpreter.eval_str("(draw-line canvas 0 0 40 40 :stroke \"red\" :width 2)").ok().unwrap();
+    interpreter.eval_str("(draw-circle (draw-rect canvas 1 2 3 4 :fill \"#0f0\") 20 20 5)").ok().unwrap();
+    assert_eq!(eval_string(&mut interpreter, "(render-svg canvas)"), format!(
+        "{}<line x1=\"0\" y1=\"0\" x2=\"40\" y2=\"40\" fill=\"none\" stroke=\"red\" stroke-width=\"2\"/>\n\
+         <rect x=\"1\" y=\"2\" width=\"3\" height=\"4\" fill=\"#0f0\" stroke=\"black\" stroke-width=\"1\"/>\n\
+         <circle cx=\"20\" cy=\"20\" r=\"5\" fill=\"none\" stroke=\"black\" stroke-width=\"1\"/>\n</svg>\n",
+        HEADER));
+    assert!(interpreter.eval_str("(draw-circle canvas 1 2 3 :colour \"red\")").is_err());
+    assert!(interpreter.eval_str("(make-canvas 0 10)").is_err());
+}
+
+#[test]
+fn turtle_draws_while_its_pen_is_down() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def canvas (make-canvas 40 40))").ok().unwrap();
+    interpreter.eval_str("(begin (turtle-forward canvas 10) (turtle-turn canvas 90) \
+                                 (turtle-pen canvas #f) (turtle-forward canvas 5) \
+                                 (turtle-pen canvas #t) (turtle-turn canvas -45) (turtle-forward canvas 10))")
+        .ok().unwrap();
+    assert_eq!(eval_string(&mut interpreter, "(render-svg canvas)"), format!(
+        "{}<line x1=\"20\" y1=\"20\" x2=\"20\" y2=\"10\" fill=\"none\" stroke=\"black\" stroke-width=\"1\"/>\n\
+         <line x1=\"25\" y1=\"10\" x2=\"32.071\" y2=\"2.929\" fill=\"none\" stroke=\"black\" stroke-width=\"1\"/>\n\
+         </svg>\n",
+        HEADER));
+}
+
+// A generation of Conway's game of life, drawn as one square per live cell.
+#[test]
+fn game_of_life_renders_generations() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_source("
+(defn neighbors (cells x y)
+  (reduce + (concat '(0) (map (fn (cell)
+                               (match cell
+                                 ((cx cy) (if (and (< (- x 2) cx) (< cx (+ x 2)) (< (- y 2) cy) (< cy (+ y 2))
+                                                   (not (and (= cx x) (= cy y))))
+                                              1 0))))
+                             cells))))
+(defn alive? (cells x y)
+  (< 0 (reduce + (concat '(0) (map (fn (cell) (if (equal? cell (list x y)) 1 0)) cells)))))
+(defn generation (cells)
+  (for ((x '(0 1 2 3 4)) (y '(0 1 2 3 4))
+        :when (or (= (neighbors cells x y) 3) (and (alive? cells x y) (= (neighbors cells x y) 2))))
+    (list x y)))
+(defn draw (cells)
+  (let ((canvas (make-canvas 40 40)))
+    (map (fn (cell) (match cell ((x y) (draw-rect canvas (* 8 x) (* 8 y) 8 8 :fill \"black\")))) cells)
+    (render-svg canvas)))
+(def blinker '((2 1) (2 2) (2 3)))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(generation blinker)"), "((1 2) (2 2) (3 2))");
+    assert_eq!(eval(&mut interpreter, "(generation (generation blinker))"), "((2 1) (2 2) (2 3))");
+    assert_eq!(eval_string(&mut interpreter, "(draw (generation blinker))"), format!(
+        "{}<rect x=\"8\" y=\"16\" width=\"8\" height=\"8\" fill=\"black\" stroke=\"black\" stroke-width=\"1\"/>\n\
+         <rect x=\"16\" y=\"16\" width=\"8\" height=\"8\" fill=\"black\" stroke=\"black\" stroke-width=\"1\"/>\n\
+         <rect x=\"24\" y=\"16\" width=\"8\" height=\"8\" fill=\"black\" stroke=\"black\" stroke-width=\"1\"/>\n\
+         </svg>\n",
+        HEADER));
+}
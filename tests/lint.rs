@@ -0,0 +1,19 @@
+use lisp::{env::Symbols, lint::lint_source};
+
+#[test]
+fn reports_self_calls_not_in_tail_position() {
+    let mut symbols = Symbols::new();
+    let source = "
+        (defun count (n acc) (if (= n 0) acc (count (- n 1) (+ acc 1))))
+        (defun sum (l) (if (is-empty l) 0 else (+ (first l) (sum (rest l)))))
+        (def loop (fn (n) (let ((m (loop n))) (begin (loop m) (or #f (loop 1))))))";
+    let traces = lint_source(&mut symbols, source).unwrap()
+        .into_iter()
+        .map(|finding| finding.trace)
+        .collect::<Vec<Vec<usize>>>();
+    assert_eq!(traces, vec![
+        vec![2, 4, 3],
+        vec![1, 0, 1, 2, 2],
+        vec![1, 2, 2, 2],
+    ]);
+}
@@ -0,0 +1,81 @@
+use std::cell::RefCell;
+use std::fs;
+use std::rc::Rc;
+
+use lisp::{
+    interpreter::{ExecError, Interpreter, LoadHook, Span},
+    lisp_object::{EvalError, LispObject},
+};
+
+// Records the spans of evaluated forms and skips definitions of 0.
+#[derive(Default)]
+struct Recorder {
+    evaluated: Vec<(usize, usize, bool)>,
+}
+
+impl LoadHook for Recorder {
+    fn before(&mut self, _file: &str, form: &LispObject, _span: Span) -> bool {
+        !matches!(form.as_slice(), Ok([_, LispObject::Symbol(_), LispObject::Number(n)]) if *n == 0.0)
+    }
+
+    fn after(&mut self, _file: &str, _form: &LispObject, span: Span,
+             result: Result<&LispObject, &EvalError>) {
+        self.evaluated.push((span.start, span.end, result.is_ok()));
+    }
+}
+
+#[test]
+fn load_hook_observes_top_level_forms() {
+    let path = std::env::temp_dir().join("risp-load-hook.lisp");
+    fs::write(&path, "(def a 1) (def b 2)\n\n(def c\n  3)\n(def skipped 0)\n(undefined)\n").unwrap();
+
+    let mut interpreter = Interpreter::new();
+    let recorder = Rc::new(RefCell::new(Recorder::default()));
+    interpreter.set_load_hook(Some(recorder.clone()));
+    assert!(interpreter.load(path.to_str().unwrap()).is_err());
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(recorder.borrow().evaluated, vec![(1, 1, true), (1, 1, true), (3, 4, true), (6, 6, false)]);
+    assert!(interpreter.eval_str("skipped").is_err());
+}
+
+#[test]
+fn circular_loads_fail_with_the_chain_of_files() {
+    let dir = std::env::temp_dir().join("risp-circular-load");
+    fs::create_dir_all(&dir).unwrap();
+    let a = dir.join("a.lisp");
+    let b = dir.join("b.lisp");
+    fs::write(&a, format!("(load {:?})\n", b.to_str().unwrap())).unwrap();
+    fs::write(&b, format!("(def loaded-b #t)\n(load {:?})\n", a.to_str().unwrap())).unwrap();
+
+    let mut interpreter = Interpreter::new();
+    let message = match interpreter.load(a.to_str().unwrap()) {
+        Err(ExecError::Eval(e)) => e.message,
+        _ => panic!("expected a circular load error"),
+    };
+    let chain = [&a, &b, &a].map(|p| p.to_str().unwrap()).join(" -> ");
+    assert_eq!(message, format!("Circular load of {}", chain));
+    assert!(interpreter.eval_str("loaded-b").is_ok());
+
+    // The failed loads are no longer in progress
+    fs::write(&a, "(def reloaded #t)\n").unwrap();
+    let reloaded = interpreter.load(a.to_str().unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(reloaded.is_ok());
+}
+
+#[test]
+fn loads_ending_in_an_incomplete_form_fail() {
+    let path = std::env::temp_dir().join("risp-incomplete-load.lisp");
+    fs::write(&path, "(def x 1)\n(def y (+ 1 2\n").unwrap();
+    let mut interpreter = Interpreter::new();
+    let result = interpreter.load(path.to_str().unwrap());
+    fs::remove_file(&path).unwrap();
+    match result {
+        Err(ExecError::Read(e)) =>
+            assert_eq!(e.to_string(), "Unexpected end of input, a list is missing its closing brace."),
+        _ => panic!("expected an incomplete form to fail"),
+    }
+    assert!(interpreter.eval_str("x").is_ok());
+    assert!(interpreter.eval_str("y").is_err());
+}
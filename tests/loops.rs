@@ -0,0 +1,20 @@
+use lisp::interpreter::{ExecError, Interpreter};
+
+#[test]
+fn recur_in_a_called_function_does_not_rebind_the_loop() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defn f () (recur 5))").ok().unwrap();
+    match interpreter.eval_str("(loop ((i 0)) (if (< i 3) (begin (f) i) i))") {
+        Err(ExecError::Eval(e)) => assert_eq!(&*e.condition, "syntax-error"),
+        _ => panic!("expected recur outside of a loop to fail"),
+    }
+    assert!(interpreter.eval_str("(loop ((i 0)) (if (< i 3) (f) i))").is_err());
+    assert!(interpreter.eval_str("(loop ((i 0)) (map (fn (x) (recur x)) '(1)))").is_err());
+
+    let value = interpreter.eval_str("(loop ((i 0)) (if (< i 3) (recur (+ i 1)) i))").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "3");
+    interpreter.eval_str("(defn count (n) (loop ((i 0)) (if (< i n) (recur (+ i 1)) i)))").ok().unwrap();
+    let value = interpreter.eval_str("(loop ((i 0) (acc 0)) (if (< i 3) (recur (+ i 1) (+ acc (count i))) acc))")
+        .ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "3");
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use lisp::interpreter::Interpreter;
+use common::eval;
+
+#[test]
+fn macros_in_function_bodies_expand_once() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def expansions 0)").ok().unwrap();
+    interpreter.eval_str("(defmacro twice (x) (set expansions (+ expansions 1)) (list '+ x x))").ok().unwrap();
+    interpreter.eval_str("(defn f (y) (twice y))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(list (f 1) (f 2) expansions)"), "(2 4 1)");
+}
+
+#[test]
+fn locally_bound_names_are_not_expanded() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defmacro m (x) x)").ok().unwrap();
+    interpreter.eval_str("(defn h () (let ((m 2)) m))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(h)"), "2");
+
+    interpreter.eval_str("(defn g (x) (let ((defn 3)) defn))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(g 1)"), "3");
+
+    interpreter.eval_str("(defn call (m) (m 5))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(call (fn (x) (+ x 1)))"), "6");
+    interpreter.eval_str("(defn loops () (loop ((m 0)) (if (< m 3) (recur (+ m 1)) m)))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(loops)"), "3");
+    interpreter.eval_str("(defn seq () (let* ((a 1) (m (fn (x) (+ x a)))) (m 1)))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(seq)"), "2");
+}
+
+#[test]
+fn macros_in_binding_values_are_expanded() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defmacro twice (x) (list '+ x x))").ok().unwrap();
+    interpreter.eval_str("(defn f (y) (let ((a (twice y))) (twice a)))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(f 1)"), "4");
+}
+
+#[test]
+fn macroexpand_1_expands_one_step() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defmacro swap (a b) `(list ,b ,a))").ok().unwrap();
+    interpreter.eval_str("(defmacro twice (x) `(swap ,x ,x))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(macroexpand-1 '(swap 1 2))"), "(list 2 1)");
+    assert_eq!(eval(&mut interpreter, "(macroexpand-1 '(twice 3))"), "(swap 3 3)");
+    assert_eq!(eval(&mut interpreter, "(macroexpand-1 5)"), "5");
+}
+
+#[test]
+fn macroexpand_expands_until_the_head_is_no_macro() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defmacro swap (a b) `(list ,b ,a))").ok().unwrap();
+    interpreter.eval_str("(defmacro twice (x) `(swap ,x ,x))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(macroexpand '(twice 3))"), "(list 3 3)");
+    assert_eq!(eval(&mut interpreter, "(macroexpand '(list (twice 3)))"), "(list (twice 3))");
+    assert!(interpreter.eval_str("(macroexpand)").is_err());
+}
@@ -0,0 +1,23 @@
+#![cfg(feature = "markdown")]
+
+mod common;
+
+use lisp::interpreter::Interpreter;
+use common::eval_string;
+
+#[test]
+fn markdown_renders_to_html() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval_string(&mut interpreter, "(markdown->html \"# Title\")"), "<h1>Title</h1>\n");
+    assert_eq!(eval_string(&mut interpreter, "(markdown->html \"Some *emphasis*, ~~struck~~ & <b>html</b>\")"),
+               "<p>Some <em>emphasis</em>, <del>struck</del> &amp; <b>html</b></p>\n");
+    assert_eq!(eval_string(&mut interpreter, "(markdown->html \"| a | b |\n|---|---|\n| 1 | 2 |\")"),
+               "<table><thead><tr><th>a</th><th>b</th></tr></thead><tbody>\n\
+                <tr><td>1</td><td>2</td></tr>\n</tbody></table>\n");
+}
+
+#[test]
+fn markdown_expects_a_string() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval_str("(markdown->html 1)").is_err());
+}
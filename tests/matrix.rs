@@ -0,0 +1,14 @@
+use lisp::interpreter::{ExecError, Interpreter};
+
+#[test]
+fn oversized_matrices_fail_with_a_program_error() {
+    let mut interpreter = Interpreter::new();
+    for size in ["1000000000 1000000000", "100000000000 100000000000", "18446744073709551616 2"] {
+        match interpreter.eval_str(&format!("(make-matrix {})", size)) {
+            Err(ExecError::Eval(e)) => assert_eq!(&*e.condition, "program-error"),
+            _ => panic!("expected make-matrix {} to fail", size),
+        }
+    }
+    let filled = interpreter.eval_str("(matrix->list (make-matrix 2 3 1))").ok().unwrap();
+    assert_eq!(interpreter.serialize(&filled), "((1 1 1) (1 1 1))");
+}
@@ -0,0 +1,251 @@
+#![cfg(feature = "net")]
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use lisp::interpreter::{ExecError, Interpreter};
+use common::{eval, message};
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+// Send request to port once the server listens, returning the response.
+fn request(port: u16, request: &'static str) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut stream = loop {
+            match TcpStream::connect(("127.0.0.1", port)) {
+                Ok(stream) => break stream,
+                Err(_) => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        stream.write_all(request.as_bytes()).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    })
+}
+
+#[test]
+fn http_serve_passes_requests_to_the_handler() {
+    let port = free_port();
+    let client = request(port, "POST /echo?x=1 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello");
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, &format!(
+        "(http-serve {} (fn (req) (make-map \"status\" 201 \"body\" (get req \"body\") \
+                                            \"headers\" (make-map \"X-Path\" (get req \"path\") \
+                                                                   \"X-Query\" (get req \"query\")))) 1)",
+        port)), "1");
+    let response = client.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 201 Created\r\n"));
+    assert!(response.contains("X-Path: /echo\r\nX-Query: x=1\r\n"));
+    assert!(response.ends_with("\r\n\r\nhello"));
+}
+
+#[test]
+fn http_serve_answers_handler_errors_with_500() {
+    let port = free_port();
+    let client = request(port, "GET / HTTP/1.1\r\n\r\n");
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(&format!("(http-serve {} (fn (req) 42) 1)", port)).ok().unwrap();
+    let response = client.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+    assert!(response.ends_with("Handler must return a string or a map"));
+}
+
+#[test]
+fn http_serve_answers_unreadable_requests_and_keeps_serving() {
+    let port = free_port();
+    let clients = thread::spawn(move || {
+        let too_large = request(port, "POST / HTTP/1.1\r\nContent-Length: 1000000000000000\r\n\r\n")
+            .join().unwrap();
+        let short = request(port, "POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc").join().unwrap();
+        let ok = request(port, "GET / HTTP/1.1\r\n\r\n").join().unwrap();
+        (too_large, short, ok)
+    });
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, &format!("(http-serve {} (fn (req) \"ok\") 3)", port)), "3");
+    let (too_large, short, ok) = clients.join().unwrap();
+    assert!(too_large.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+    assert!(short.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    assert!(ok.starts_with("HTTP/1.1 200 OK\r\n") && ok.ends_with("\r\n\r\nok"));
+}
+
+#[test]
+fn http_serve_stops_on_escapes_from_the_handler() {
+    let port = free_port();
+    let client = request(port, "GET / HTTP/1.1\r\n\r\n");
+    let mut interpreter = Interpreter::new();
+    assert_eq!(eval(&mut interpreter, &format!("(call/ec (fn (k) (http-serve {} (fn (req) (k 'stopped)))))",
+                                               port)), "stopped");
+    client.join().unwrap();
+}
+
+#[test]
+fn http_serve_validates_its_arguments() {
+    let mut interpreter = Interpreter::new();
+    assert!(interpreter.eval_str("(http-serve \"80\" (fn (req) \"\"))").is_err());
+    assert!(interpreter.eval_str("(http-serve 8080 (fn (req) \"\") \"once\")").is_err());
+    assert!(message(&mut interpreter, "(http-serve 1)").starts_with("param list"));
+}
+
+// Answer one connection on port with response after reading the request head.
+fn respond(listener: TcpListener, response: &'static str) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut head = vec![];
+        let mut byte = [0u8];
+        while !head.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            head.push(byte[0]);
+        }
+        stream.write_all(response.as_bytes()).unwrap();
+        String::from_utf8(head).unwrap()
+    })
+}
+
+#[test]
+fn ws_connect_rejects_failed_handshakes() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = respond(listener, "HTTP/1.1 101 Switching Protocols\r\nSec-WebSocket-Accept: wrong\r\n\r\n");
+    let mut interpreter = Interpreter::new();
+    let url = format!("ws://127.0.0.1:{}/chat", port);
+    assert_eq!(message(&mut interpreter, &format!("(ws-connect {:?})", url)),
+               format!("WebSocket handshake with {} failed", url));
+    let head = server.join().unwrap();
+    assert!(head.starts_with("GET /chat HTTP/1.1\r\n"));
+    assert!(head.contains("Sec-WebSocket-Key: ") && head.contains("Sec-WebSocket-Version: 13\r\n"));
+}
+
+#[test]
+fn ws_natives_validate_their_arguments() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(message(&mut interpreter, "(ws-connect \"wss://example.com\")"),
+               "Only ws:// urls are supported");
+    assert!(interpreter.eval_str("(ws-send \"socket\" \"hello\")").is_err());
+    assert!(interpreter.eval_str("(ws-recv 1)").is_err());
+
+    let port = free_port();
+    match interpreter.eval_str(&format!("(ws-connect \"ws://127.0.0.1:{}\")", port)) {
+        Err(ExecError::Eval(e)) => assert_eq!(&*e.condition, "io-error"),
+        _ => panic!("expected connecting to a closed port to fail"),
+    }
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..80 {
+            w[i] = if i < 16 {
+                u32::from_be_bytes([block[4 * i], block[4 * i + 1], block[4 * i + 2], block[4 * i + 3]])
+            } else {
+                (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1)
+            };
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (hi, v) in h.iter_mut().zip([a, b, c, d, e].iter()) {
+            *hi = hi.wrapping_add(*v);
+        }
+    }
+    let mut digest = [0u8; 20];
+    for (i, hi) in h.iter().enumerate() {
+        digest[4 * i..4 * i + 4].copy_from_slice(&hi.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    data.chunks(3).flat_map(|chunk| {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        (0..4).map(move |i| if i <= chunk.len() { CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char }
+                            else { '=' })
+    }).collect()
+}
+
+// Accept one websocket connection on listener and write frames to it.
+fn ws_server(listener: TcpListener, frames: Vec<Vec<u8>>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut head = vec![];
+        let mut byte = [0u8];
+        while !head.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            head.push(byte[0]);
+        }
+        let head = String::from_utf8(head).unwrap();
+        let key = head.lines()
+            .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+            .unwrap();
+        let accept = base64(&sha1(format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key).as_bytes()));
+        write!(stream, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                        Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept).unwrap();
+        for frame in frames {
+            stream.write_all(&frame).unwrap();
+        }
+        let mut rest = vec![];
+        stream.read_to_end(&mut rest).ok();
+    })
+}
+
+#[test]
+fn ws_recv_reads_text_messages() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = ws_server(listener, vec![
+        b"\x01\x03hel".to_vec(),
+        b"\x80\x02lo".to_vec(),
+    ]);
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(&format!("(def ws (ws-connect \"ws://127.0.0.1:{}\"))", port)).ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(ws-recv ws)"), "\"hello\"");
+    drop(interpreter);
+    server.join().unwrap();
+}
+
+#[test]
+fn ws_recv_rejects_oversized_frames_before_reading_them() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let mut frame = vec![0x81, 127];
+    frame.extend_from_slice(&u64::MAX.to_be_bytes());
+    let server = ws_server(listener, vec![frame]);
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(&format!("(def ws (ws-connect \"ws://127.0.0.1:{}\"))", port)).ok().unwrap();
+    match interpreter.eval_str("(ws-recv ws)") {
+        Err(ExecError::Eval(e)) => {
+            assert_eq!(&*e.condition, "io-error");
+            assert_eq!(e.message, "WebSocket message exceeds 16777216 bytes");
+        },
+        _ => panic!("expected an oversized frame to fail"),
+    }
+    drop(interpreter);
+    server.join().unwrap();
+}
@@ -0,0 +1,38 @@
+use lisp::interpreter::{ExecError, Interpreter};
+
+// The condition and message of the error evaluating input, and the argument
+// of the parameter list it points at.
+fn error(interpreter: &mut Interpreter, input: &str) -> (String, String, usize) {
+    match interpreter.eval_str(input) {
+        Err(ExecError::Eval(e)) => (e.condition.to_string(), e.message, e.frames[0].1[0]),
+        _ => panic!("expected {} to fail", input),
+    }
+}
+
+#[test]
+fn duplicate_parameters_are_rejected() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(error(&mut interpreter, "(fn (a b a) a)"),
+               ("syntax-error".to_string(), "Duplicate parameter 'a' in parameter list".to_string(), 2));
+    assert_eq!(error(&mut interpreter, "(fn (a &rest a) a)").1,
+               "Duplicate parameter 'a' in parameter list");
+    assert_eq!(error(&mut interpreter, "(fn (a &key a) a)").1,
+               "Duplicate parameter 'a' in parameter list");
+}
+
+#[test]
+fn malformed_rest_markers_are_rejected() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(error(&mut interpreter, "(fn (a &rest b &rest c) a)"),
+               ("syntax-error".to_string(), "&rest may only appear once in parameter list".to_string(), 3));
+    assert_eq!(error(&mut interpreter, "(fn (a &rest) a)").1, "&rest must be followed by a symbol");
+    assert_eq!(error(&mut interpreter, "(fn (a &rest b c) a)").1,
+               "&rest must be second to last in parameter list");
+}
+
+#[test]
+fn distinct_parameters_bind_their_arguments() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter.eval_str("((fn (a b &rest c) (list a b c)) 1 2 3 4)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "(1 2 (3 4))");
+}
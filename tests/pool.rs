@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use std::thread;
+
+use lisp::{
+    interpreter::Interpreter,
+    lisp_object::{EvalError, LispObject},
+    pool::RispPool,
+};
+
+#[test]
+fn pool_evaluates_requests_concurrently_on_shared_setup() {
+    let pool = Arc::new(RispPool::new(4, "(def square '(fn (x) (* x x)))").unwrap());
+    let handles = (0..16)
+        .map(|n| {
+            let pool = pool.clone();
+            thread::spawn(move || pool.call("square", &[&n.to_string()]))
+        })
+        .collect::<Vec<_>>();
+    for (n, handle) in handles.into_iter().enumerate() {
+        assert_eq!(handle.join().unwrap(), Ok((n * n).to_string()));
+    }
+}
+
+#[test]
+fn pool_requests_do_not_see_each_other() {
+    let pool = RispPool::new(1, "").unwrap();
+    assert_eq!(pool.eval("(def x 1)"), Ok("1".to_string()));
+    assert!(pool.eval("x").is_err());
+}
+
+#[test]
+fn pool_requests_share_state_captured_by_setup_closures() {
+    let pool = RispPool::new(1, "(def c (let ((n 0)) (fn () (set n (+ n 1)) n)))").unwrap();
+    assert_eq!(pool.eval("(c)"), Ok("1".to_string()));
+    assert_eq!(pool.eval("(c)"), Ok("2".to_string()));
+    assert_eq!(pool.eval("(def c 0)"), Ok("0".to_string()));
+    assert_eq!(pool.eval("(c)"), Ok("3".to_string()));
+}
+
+#[test]
+fn pool_reports_failing_setup() {
+    assert!(RispPool::new(2, "(undefined)").is_err());
+}
+
+fn explode(_: &mut Interpreter, _: &[LispObject]) -> Result<LispObject, EvalError> {
+    panic!("explode was called")
+}
+
+#[test]
+fn pool_survives_panicking_natives() {
+    let init = |interpreter: &mut Interpreter| {
+        interpreter.define_globals(&[("explode", LispObject::Native((vec![], None), explode))])
+            .ok().unwrap();
+    };
+    let pool = RispPool::with_init(1, init, "(def x 2)").unwrap();
+    assert_eq!(pool.eval("(explode)"), Err("Evaluation panicked: explode was called".to_string()));
+    assert!(pool.eval("(first '())").is_err());
+    assert_eq!(pool.eval("(+ x 1)"), Ok("3".to_string()));
+}
+
+#[test]
+fn pool_reports_panicking_init() {
+    let error = RispPool::with_init(2, |_| panic!("init failed"), "(def x 1)").err().unwrap();
+    assert_eq!(error, "Setup failed: panicked: init failed");
+}
@@ -0,0 +1,41 @@
+use std::fs;
+
+use lisp::interpreter::Interpreter;
+
+#[test]
+fn semicolons_in_strings_and_symbols_are_not_comments() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter.eval_str("(read-string \"\\\"a;b\\\"\")").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "\"a;b\"");
+    let value = interpreter.eval_source("(list \"x;y\" ; a comment\n  '|a;b|) ; another").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "(\"x;y\" |a;b|)");
+}
+
+#[test]
+fn comments_end_symbols_and_numbers() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter.eval_source("(list 'a;comment\n 1;comment\n :k)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "(a 1 :k)");
+}
+
+#[test]
+fn escaped_strings_round_trip() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter.eval_str(r#""quote \" backslash \\ tab \t newline \n""#).ok().unwrap();
+    let printed = interpreter.serialize(&value);
+    assert_eq!(printed, r#""quote \" backslash \\ tab \t newline \n""#);
+    let read = interpreter.eval_str(&printed).ok().unwrap();
+    assert!(read.equals(&value));
+    assert!(interpreter.eval_str(r#""unknown \q escape""#).is_err());
+}
+
+#[test]
+fn files_keep_semicolons_in_strings() {
+    let path = std::env::temp_dir().join("risp-reader-semicolons.lisp");
+    fs::write(&path, "; header\n(def s \"a;b\") ; trailing\n(def t '|c;d|)\n").unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.read_file(path.to_str().unwrap()).unwrap();
+    let value = interpreter.eval_str("(list s t)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "(\"a;b\" |c;d|)");
+    fs::remove_file(&path).ok();
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use lisp::interpreter::Interpreter;
+use common::{eval, error};
+
+#[test]
+fn specs_check_arguments_and_results() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defspec inc (number?) => number?) (defn inc (x) (+ x 1))").ok().unwrap();
+    interpreter.eval_str("(defspec wrap (number?) => string?) (defn wrap (x) x)").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(inc 2)"), "3");
+    assert_eq!(error(&mut interpreter, "(inc \"a\")"),
+               ("spec-error".to_string(), "argument 1 of inc violates spec number?, got \"a\"".to_string()));
+    assert_eq!(error(&mut interpreter, "(wrap 1)").1, "result of wrap violates spec string?, got 1");
+}
+
+#[test]
+fn specs_are_only_checked_while_enabled() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defspec inc (number?) => number?) (defn inc (x) x)").ok().unwrap();
+    interpreter.eval_str("(check-specs #f)").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(inc \"a\")"), "\"a\"");
+    interpreter.eval_str("(check-specs #t)").ok().unwrap();
+    assert!(interpreter.eval_str("(inc \"a\")").is_err());
+}
+
+#[test]
+fn malformed_specs_are_rejected() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(error(&mut interpreter, "(defspec f (number?) number?)").0, "arity-error");
+    assert_eq!(error(&mut interpreter, "(defspec f (number?) -> number?)"),
+               ("syntax-error".to_string(),
+                "special form defspec expects `=>` before the result predicate".to_string()));
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "sqlite")]
+
+mod common;
+
+use std::fs;
+
+use lisp::interpreter::{ExecError, Interpreter};
+use common::{eval, message};
+
+#[test]
+fn queries_bind_parameters_and_return_rows() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def db (db-open \":memory:\"))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(db-query db \"select ? + 1 as n, ? as s\" '(41 \"it's\"))"),
+               "({\"n\" 42 \"s\" \"it's\"})");
+    assert_eq!(eval(&mut interpreter, "(db-query db \"select '?' as q\" '())"), "({\"q\" \"?\"})");
+    assert_eq!(eval(&mut interpreter, "(db-query db \"select 1 where 0\" '())"), "()");
+}
+
+#[test]
+fn values_keep_their_sqlite_types() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def db (db-open \":memory:\"))").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(db-query db \"select '007' as t, '1e3' as e, 1.5 as r, 7 as i, \
+                                                         NULL as n, ? as q\" '(\"a\\\"b\\\\c\"))"),
+               "({\"t\" \"007\" \"e\" \"1e3\" \"r\" 1.5 \"i\" 7 \"n\" nil \"q\" \"a\\\"b\\\\c\"})");
+}
+
+#[test]
+fn paths_are_not_taken_as_options() {
+    let path = std::env::temp_dir().join("-version");
+    fs::remove_file(&path).ok();
+    let mut interpreter = Interpreter::new();
+    // The other tests in this binary use :memory: or absolute paths
+    let current = std::env::current_dir().unwrap();
+    std::env::set_current_dir(std::env::temp_dir()).unwrap();
+    let result = interpreter.eval_str("(db-query (db-open \"-version\") \"select 1 as one\" '())");
+    std::env::set_current_dir(current).unwrap();
+    let value = result.ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "({\"one\" 1})");
+    fs::remove_file(&path).ok();
+}
+
+#[test]
+fn executed_statements_report_changed_rows() {
+    let path = std::env::temp_dir().join("risp-sqlite.db");
+    fs::remove_file(&path).ok();
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(&format!("(def db (db-open {:?}))", path.to_str().unwrap())).ok().unwrap();
+    interpreter.eval_str("(db-exec db \"create table t (x)\" '())").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(db-exec db \"insert into t values (?), (?)\" '(1 2))"), "2");
+    assert_eq!(eval(&mut interpreter, "(db-query db \"select sum(x) as total from t\" '())"),
+               "({\"total\" 3})");
+    fs::remove_file(&path).ok();
+}
+
+#[test]
+fn invalid_arguments_are_rejected_before_running_sql() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def db (db-open \":memory:\"))").ok().unwrap();
+    assert_eq!(message(&mut interpreter, "(db-query db \"select ?, ?\" '(1))"),
+               "Not enough parameters for query");
+    assert_eq!(message(&mut interpreter, "(db-query db \"select ?\" '(1 2))"),
+               "Too many parameters for query");
+    assert_eq!(message(&mut interpreter, "(db-query db \"select ?\" '((1)))"),
+               "Expected a number, string, bool, nil or () as parameter");
+    assert_eq!(message(&mut interpreter, "(db-query (make-map) \"select 1\" '())"),
+               "Expected a connection from db-open");
+}
+
+#[test]
+fn sql_errors_fail_with_an_io_error() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def db (db-open \":memory:\"))").ok().unwrap();
+    match interpreter.eval_str("(db-query db \"select * from missing\" '())") {
+        Err(ExecError::Eval(e)) => assert_eq!(&*e.condition, "io-error"),
+        _ => panic!("expected querying a missing table to fail"),
+    }
+}
@@ -0,0 +1,14 @@
+use lisp::interpreter::{ExecError, Interpreter};
+
+#[test]
+fn histograms_with_too_many_bins_fail_with_a_program_error() {
+    let mut interpreter = Interpreter::new();
+    for bins in ["(* 1000000 1000000 1000000)", "2000000"] {
+        match interpreter.eval_str(&format!("(histogram (list 1 2) {})", bins)) {
+            Err(ExecError::Eval(e)) => assert_eq!(&*e.condition, "program-error"),
+            _ => panic!("expected histogram with {} bins to fail", bins),
+        }
+    }
+    let counts = interpreter.eval_str("(histogram (list 1 2 3 4) 2)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&counts), "((1 2.5 2) (2.5 4 2))");
+}
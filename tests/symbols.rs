@@ -0,0 +1,65 @@
+use lisp::interpreter::Interpreter;
+
+#[test]
+fn compact_drops_unreferenced_symbols() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defun tag (x) (list 'tagged x))").ok().unwrap();
+    let before = interpreter.symbols().len();
+    for n in 0..100 {
+        interpreter.eval_str(&format!("'input-{}", n)).ok().unwrap();
+    }
+    let kept = interpreter.eval_str("'kept").ok().unwrap();
+    assert_eq!(interpreter.symbols().len(), before + 101);
+
+    interpreter.compact_symbols(std::slice::from_ref(&kept));
+    assert_eq!(interpreter.symbols().len(), before + 1);
+    assert_eq!(interpreter.serialize(&kept), "kept");
+    let tagged = interpreter.eval_str("(tag 1)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&tagged), "(tagged 1)");
+}
+
+#[test]
+fn escaped_symbols_round_trip() {
+    let mut interpreter = Interpreter::new();
+    let names = "'(plain |with space| |(| |12| |#t| |a;b| |\\|x| |back\\\\slash| λ ||)";
+    let value = interpreter.eval_str(names).ok().unwrap();
+    let printed = interpreter.serialize(&value);
+    assert_eq!(printed, "(plain |with space| |(| |12| |#t| |a;b| |\\|x| back\\slash λ ||)");
+    let read = interpreter.eval_str(&format!("'{}", printed)).ok().unwrap();
+    assert!(read.equals(&value));
+}
+
+#[test]
+fn unbound_symbols_suggest_similar_names() {
+    let mut interpreter = Interpreter::new();
+    let error = interpreter.eval_str("(secnd '(1 2))").err().unwrap();
+    assert!(error.to_string().contains("did you mean `second`?"), "{}", error);
+    let error = interpreter.eval_str("(> 1 2)").err().unwrap();
+    assert!(!error.to_string().contains("did you mean"), "{}", error);
+    let error = interpreter.eval_str("completely-unrelated").err().unwrap();
+    assert!(!error.to_string().contains("did you mean"), "{}", error);
+}
+
+#[test]
+fn gensyms_are_distinct_from_symbols_of_the_same_name() {
+    let mut interpreter = Interpreter::new();
+    let gensym = interpreter.eval_str("(gensym \"tmp\")").ok().unwrap();
+    let name = interpreter.serialize(&gensym);
+    assert!(name.starts_with("tmp"));
+    let interned = interpreter.eval_str(&format!("'{}", name)).ok().unwrap();
+    assert!(!gensym.equals(&interned));
+    assert!(interpreter.eval_str("(gensym 1 2)").is_err());
+}
+
+#[test]
+fn compact_keeps_symbols_of_recorded_assignments() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(set-option! :record-history #t)").ok().unwrap();
+    interpreter.eval_str("(def x 'transient-name) (def x 1)").ok().unwrap();
+    interpreter.compact_symbols(&[]);
+    let history = interpreter.eval_str("(history-of 'x)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&history),
+               "((transient-name (def x (quote transient-name))) (1 (def x 1)))");
+    let first = interpreter.eval_str("(= (first (first (history-of 'x))) 'transient-name)").ok().unwrap();
+    assert_eq!(interpreter.serialize(&first), "#t");
+}
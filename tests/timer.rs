@@ -0,0 +1,41 @@
+mod common;
+
+use lisp::interpreter::{ExecError, Interpreter};
+use common::eval;
+
+#[test]
+fn timers_run_while_sleeping_in_due_order() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def fired '())").ok().unwrap();
+    interpreter.eval_str("(after 60 (fn () (set fired (concat fired '(late)))))").ok().unwrap();
+    interpreter.eval_str("(after 10 (fn () (set fired (concat fired '(early)))))").ok().unwrap();
+    interpreter.eval_str("(sleep 150)").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "fired"), "(early late)");
+}
+
+#[test]
+fn cancelled_timers_stop_repeating() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def ticks 0)").ok().unwrap();
+    interpreter.eval_str("(def ticker (every 10 (fn () (set ticks (+ ticks 1)))))").ok().unwrap();
+    interpreter.eval_str("(sleep 100)").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(cancel ticker)"), "#t");
+    let ticks = eval(&mut interpreter, "ticks");
+    assert!(ticks.parse::<u32>().unwrap() >= 2, "ticked {} times", ticks);
+    interpreter.eval_str("(sleep 50)").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "ticks"), ticks);
+    assert_eq!(eval(&mut interpreter, "(cancel ticker)"), "#f");
+}
+
+#[test]
+fn timers_reject_invalid_delays() {
+    let mut interpreter = Interpreter::new();
+    for input in ["(every 0 (fn () 1))", "(every -5 (fn () 1))", "(after -1 (fn () 1))",
+                  "(cancel 'timer)"] {
+        match interpreter.eval_str(input) {
+            Err(ExecError::Eval(e)) => assert_eq!(&*e.condition, "type-error", "{}", input),
+            _ => panic!("expected {} to fail", input),
+        }
+    }
+    interpreter.eval_str("(after 0 (fn () 1))").ok().unwrap();
+}
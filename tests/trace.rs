@@ -0,0 +1,72 @@
+#![cfg(feature = "trace")]
+
+use std::sync::{Arc, Mutex};
+
+use lisp::interpreter::Interpreter;
+use tracing::{
+    Event, Metadata, Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+
+// A span's name, name field and the index of its parent.
+type SpanRecord = (String, String, Option<usize>);
+
+// Records the spans created while it is the default subscriber.
+#[derive(Clone, Default)]
+struct Collector {
+    spans: Arc<Mutex<Vec<SpanRecord>>>,
+    entered: Arc<Mutex<Vec<usize>>>,
+}
+
+struct NameField(String);
+
+impl Visit for NameField {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "name" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
+
+impl Subscriber for Collector {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == "risp"
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut name = NameField(String::new());
+        attributes.record(&mut name);
+        let parent = self.entered.lock().unwrap().last().copied();
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((attributes.metadata().name().to_string(), name.0, parent));
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        self.entered.lock().unwrap().push(span.into_u64() as usize - 1);
+    }
+
+    fn exit(&self, _: &Id) {
+        self.entered.lock().unwrap().pop();
+    }
+}
+
+#[test]
+fn calls_are_nested_spans_with_their_names() {
+    let collector = Collector::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(defn square (x) (* x x))").ok().unwrap();
+    tracing::subscriber::with_default(collector.clone(), || {
+        interpreter.eval_str("(square 3)").ok().unwrap();
+    });
+    let spans = collector.spans.lock().unwrap();
+    let call = spans.iter().position(|(kind, name, _)| kind == "call" && name == "square").unwrap();
+    let native = spans.iter().position(|(kind, name, _)| kind == "native" && name == "*").unwrap();
+    assert_eq!(spans[native].2, Some(call));
+    assert!(spans.iter().any(|(kind, name, parent)| kind == "read" && name == "<string>" && parent.is_none()));
+}
@@ -0,0 +1,57 @@
+use lisp::interpreter::{ExecError, Interpreter};
+
+#[test]
+fn unwind_protect_cleans_up_after_errors() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def state 'idle)").ok().unwrap();
+    let result = interpreter.eval_str(
+        "(unwind-protect (begin (set state 'busy) (undefined)) (set state 'idle))");
+    assert!(result.is_err());
+    let state = interpreter.eval_str("state").ok().unwrap();
+    assert_eq!(interpreter.serialize(&state), "idle");
+}
+
+#[test]
+fn unwind_protect_returns_protected_value() {
+    let mut interpreter = Interpreter::new();
+    let value = interpreter.eval_str("(unwind-protect (+ 1 2) (def cleaned #t))").ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "3");
+    let cleaned = interpreter.eval_str("cleaned").ok().unwrap();
+    assert_eq!(interpreter.serialize(&cleaned), "#t");
+}
+
+#[test]
+fn try_rethrows_unmatched_conditions() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def state 'idle)").ok().unwrap();
+    let result = interpreter.eval_str(
+        "(try (unwind-protect (+ 1 \"one\") (set state 'done)) (catch :io-error e 'caught))");
+    match result {
+        Err(ExecError::Eval(err)) => assert_eq!(&*err.condition, "type-error"),
+        _ => panic!("expected the type error to propagate"),
+    }
+    let state = interpreter.eval_str("state").ok().unwrap();
+    assert_eq!(interpreter.serialize(&state), "done");
+}
+
+#[test]
+fn escapes_run_cleanups_on_the_way_out() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def state 'idle)").ok().unwrap();
+    let value = interpreter.eval_str(
+        "(call/ec (fn (k) (unwind-protect (begin (set state 'busy) (k 1) 2) (set state 'idle))))")
+        .ok().unwrap();
+    assert_eq!(interpreter.serialize(&value), "1");
+    let state = interpreter.eval_str("state").ok().unwrap();
+    assert_eq!(interpreter.serialize(&state), "idle");
+}
+
+#[test]
+fn first_of_empty_list_can_be_caught() {
+    let mut interpreter = Interpreter::new();
+    let caught = interpreter.eval_str("(try (first '()) (catch :program-error e (get e :message)))")
+        .ok().unwrap();
+    assert_eq!(interpreter.serialize(&caught), "\"first of empty list\"");
+    let first = interpreter.eval_str("(first '(1 2))").ok().unwrap();
+    assert_eq!(interpreter.serialize(&first), "1");
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use std::fs;
+use std::sync::Mutex;
+
+use lisp::interpreter::{ExecError, Interpreter};
+use common::eval;
+
+// Watchers run on threads of the test process, the tests take turns so
+// counting them is not disturbed by other tests.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[test]
+fn watchers_report_created_and_removed_files() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = std::env::temp_dir().join("risp-watch-path");
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("watched.txt");
+
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def kinds '())").ok().unwrap();
+    interpreter.eval_str(&format!("(def watcher (watch-path {:?} (fn (e) (set kinds (concat kinds (list (get e \"kind\")))))))",
+                                  dir.to_str().unwrap())).ok().unwrap();
+    interpreter.eval_str("(sleep 300)").ok().unwrap();
+    fs::write(&file, "changed").unwrap();
+    interpreter.eval_str("(sleep 500)").ok().unwrap();
+    fs::remove_file(&file).unwrap();
+    interpreter.eval_str("(sleep 500)").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "kinds"), "(\"create\" \"remove\")");
+
+    assert_eq!(eval(&mut interpreter, "(cancel watcher)"), "#t");
+    fs::write(&file, "ignored").unwrap();
+    interpreter.eval_str("(sleep 500)").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "(length kinds)"), "2");
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn watching_missing_paths_fails_with_an_io_error() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let mut interpreter = Interpreter::new();
+    match interpreter.eval_str("(watch-path \"/no/such/risp/path\" (fn (e) e))") {
+        Err(ExecError::Eval(e)) => assert_eq!(&*e.condition, "io-error"),
+        _ => panic!("expected watching a missing path to fail"),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn threads() -> usize {
+    fs::read_dir("/proc/self/task").unwrap().count()
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn watchers_stop_with_their_interpreter() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let before = threads();
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str(&format!("(watch-path {:?} (fn (e) e))", std::env::temp_dir().to_str().unwrap()))
+        .ok().unwrap();
+    assert_eq!(threads(), before + 1);
+    drop(interpreter);
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert!(threads() <= before);
+}
+
+#[cfg(unix)]
+#[test]
+fn watchers_do_not_follow_symbolic_links() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = std::env::temp_dir().join("risp-watch-links");
+    fs::remove_dir_all(&dir).ok();
+    fs::create_dir_all(&dir).unwrap();
+    std::os::unix::fs::symlink(&dir, dir.join("loop")).unwrap();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def paths '())").ok().unwrap();
+    interpreter.eval_str(&format!("(def watcher (watch-path {:?} (fn (e) (set paths (concat paths (list (get e \"path\")))))))",
+                                  dir.to_str().unwrap())).ok().unwrap();
+    interpreter.eval_str("(sleep 300)").ok().unwrap();
+    fs::write(dir.join("file"), "").unwrap();
+    interpreter.eval_str("(sleep 500)").ok().unwrap();
+    assert_eq!(eval(&mut interpreter, "paths"), format!("({:?})", dir.join("file").to_str().unwrap()));
+    interpreter.eval_str("(cancel watcher)").ok().unwrap();
+    fs::remove_dir_all(&dir).ok();
+}